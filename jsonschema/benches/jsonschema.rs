@@ -3,7 +3,8 @@ use bench_helpers::{
 };
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use jsonschema::JSONSchema;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
 
 macro_rules! jsonschema_rs_bench {
     ($c:tt, $name:expr, $schema:ident, $instance:ident) => {{
@@ -136,5 +137,143 @@ fn validate_invalid(c: &mut Criterion, name: &str, schema: &Value, instance: &Va
     );
 }
 
-criterion_group!(arbitrary, large_schemas, fast_schema, keywords);
+fn first_error(c: &mut Criterion) {
+    let schema = json!({
+        "items": {
+            "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+            }
+        },
+        "definitions": {
+            "Cat": {"properties": {"meows": {"type": "boolean"}}, "required": ["meows"]},
+            "Dog": {"properties": {"barks": {"type": "boolean"}}, "required": ["barks"]}
+        }
+    });
+    let instance = Value::Array(vec![json!({"petType": "dog", "barks": "loudly"}); 10_000]);
+    let compiled = JSONSchema::options()
+        .should_use_discriminator(true)
+        .compile(&schema)
+        .expect("Valid schema");
+    c.bench_function("large invalid jsonschema_rs/validate_once", |b| {
+        b.iter(|| {
+            compiled
+                .validate_once(&instance)
+                .expect_err("Invalid instance")
+        })
+    });
+    c.bench_function("large invalid jsonschema_rs/validate", |b| {
+        b.iter(|| {
+            let _: Vec<_> = compiled
+                .validate(&instance)
+                .expect_err("Invalid instance")
+                .collect();
+        })
+    });
+}
+
+fn exclusive_one_of(c: &mut Criterion) {
+    let schema = json!({
+        "items": {
+            "oneOf": [
+                {"type": "object", "properties": {"kind": {"const": "a"}}, "required": ["kind"]},
+                {"type": "string", "maxLength": 64},
+                {"type": "array", "items": {"type": "integer"}}
+            ]
+        }
+    });
+    let instance = Value::Array(vec![json!({"kind": "a"}); 10_000]);
+    for short_circuit in [false, true] {
+        let compiled = JSONSchema::options()
+            .should_short_circuit_exclusive_one_of(short_circuit)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&instance));
+        c.bench_with_input(
+            BenchmarkId::new("exclusive oneOf jsonschema_rs", short_circuit),
+            &instance,
+            |b, instance| b.iter(|| compiled.is_valid(instance)),
+        );
+    }
+}
+
+/// Items tagged with one of `variants` discriminator tags, and a schema for them.
+fn discriminated_items(variants: usize) -> (Value, Value) {
+    let mut definitions = serde_json::Map::new();
+    let mut mapping = serde_json::Map::new();
+    let mut one_of = Vec::new();
+    for idx in 0..variants {
+        let name = format!("Variant{}", idx);
+        definitions.insert(
+            name.clone(),
+            json!({"properties": {"value": {"type": "integer"}}, "required": ["value"]}),
+        );
+        mapping.insert(
+            format!("variant-{}", idx),
+            json!(format!("#/definitions/{}", name)),
+        );
+        one_of.push(json!({"$ref": format!("#/definitions/{}", name)}));
+    }
+    let schema = json!({
+        "items": {
+            "oneOf": one_of,
+            "discriminator": {"propertyName": "kind", "mapping": mapping}
+        },
+        "definitions": definitions
+    });
+    let instance = Value::Array(
+        (0..10_000)
+            .map(|idx| json!({"kind": format!("variant-{}", idx % variants), "value": idx}))
+            .collect(),
+    );
+    (schema, instance)
+}
+
+fn discriminator_mapping(c: &mut Criterion) {
+    let variants = 256;
+    let (schema, instance) = discriminated_items(variants);
+    let compiled = JSONSchema::options()
+        .should_use_discriminator(true)
+        .compile(&schema)
+        .expect("Valid schema");
+    assert!(compiled.is_valid(&instance));
+    c.bench_with_input(
+        BenchmarkId::new("discriminator mapping jsonschema_rs", variants),
+        &instance,
+        |b, instance| b.iter(|| compiled.is_valid(instance)),
+    );
+}
+
+fn result_cache_hasher(c: &mut Criterion) {
+    let (schema, instance) = discriminated_items(256);
+    let mut default = JSONSchema::options();
+    let mut sip_hash = JSONSchema::options();
+    sip_hash.with_result_cache_hasher(RandomState::new());
+    for (name, options) in [("ahash", &mut default), ("siphash", &mut sip_hash)] {
+        let compiled = options
+            .should_use_discriminator(true)
+            .with_result_cache(1)
+            .compile(&schema)
+            .expect("Valid schema");
+        // Every iteration hashes the instance to find its cached result
+        assert!(compiled.is_valid(&instance));
+        c.bench_with_input(
+            BenchmarkId::new("result cache jsonschema_rs", name),
+            &instance,
+            |b, instance| b.iter(|| compiled.is_valid(instance)),
+        );
+    }
+}
+
+criterion_group!(
+    arbitrary,
+    large_schemas,
+    fast_schema,
+    keywords,
+    first_error,
+    exclusive_one_of,
+    discriminator_mapping,
+    result_cache_hasher
+);
 criterion_main!(arbitrary);
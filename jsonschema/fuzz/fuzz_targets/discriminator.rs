@@ -0,0 +1,35 @@
+//! Compilation and validation of `oneOf` / `discriminator` schemas must never panic.
+//!
+//! The input is a JSON object: the `instance` member is validated against the rest of the object,
+//! which is used as the schema.
+#![no_main]
+use jsonschema::JSONSchema;
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let mut schema = match serde_json::from_slice::<Value>(data) {
+        Ok(Value::Object(schema)) => schema,
+        _ => return,
+    };
+    let instance = schema.remove("instance").unwrap_or(Value::Null);
+    let schema = Value::Object(schema);
+    for strict in [false, true] {
+        let compiled = match JSONSchema::options()
+            .should_use_strict_discriminator(strict)
+            .should_prune_unused_discriminator_branches(strict)
+            .should_resolve_implicit_discriminator_mapping(!strict)
+            .should_allow_partial_discriminated_instances(strict)
+            .compile(&schema)
+        {
+            Ok(compiled) => compiled,
+            Err(_) => continue,
+        };
+        let _ = compiled.is_valid(&instance);
+        if let Err(errors) = compiled.validate(&instance) {
+            for _ in errors {}
+        }
+        let _ = compiled.apply(&instance).basic();
+        let _ = compiled.one_of_matching_branches(&instance);
+    }
+});
@@ -0,0 +1,393 @@
+//! Caches of validation results, keyed by a hash of the instance.
+use super::options::{CompilationOptions, ResultCacheHasher};
+use ahash::{AHashMap, RandomState};
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::{
+    cell::RefCell,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Least recently used `is_valid` results.
+///
+/// Entries keep a copy of their instance, so a hash collision is a cache miss rather than a wrong
+/// result. They are linked in the order of their last use, so lookups and evictions take
+/// constant time.
+pub(crate) struct ResultCache {
+    capacity: usize,
+    hash_builder: RandomState,
+    // Builds the hashers instead of `hash_builder` if configured
+    custom_hasher: Option<ResultCacheHasher>,
+    entries: Mutex<Entries>,
+    hits: AtomicUsize,
+}
+
+impl fmt::Debug for ResultCache {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ResultCache")
+            .field("capacity", &self.capacity)
+            .field("entries", &self.entries)
+            .field("hits", &self.hits)
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    // Positions in `slots` by instance hash
+    map: AHashMap<u64, usize>,
+    slots: Vec<Entry>,
+    // The most and the least recently used slots
+    newest: Option<usize>,
+    oldest: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: u64,
+    instance: Value,
+    is_valid: bool,
+    // The next newer and older slots
+    newer: Option<usize>,
+    older: Option<usize>,
+}
+
+impl Entries {
+    /// Take `slot` out of the recency order.
+    fn unlink(&mut self, slot: usize) {
+        let (newer, older) = (self.slots[slot].newer, self.slots[slot].older);
+        match newer {
+            Some(newer) => self.slots[newer].older = older,
+            None => self.newest = older,
+        }
+        match older {
+            Some(older) => self.slots[older].newer = newer,
+            None => self.oldest = newer,
+        }
+    }
+
+    /// Make `slot` the most recently used one.
+    fn push_newest(&mut self, slot: usize) {
+        self.slots[slot].newer = None;
+        self.slots[slot].older = self.newest;
+        match self.newest {
+            Some(newest) => self.slots[newest].newer = Some(slot),
+            None => self.oldest = Some(slot),
+        }
+        self.newest = Some(slot);
+    }
+}
+
+impl ResultCache {
+    /// The cache configured by `CompilationOptions::with_result_cache`, if any.
+    pub(crate) fn for_options(config: &CompilationOptions) -> Option<ResultCache> {
+        let mut cache = ResultCache::new(config.result_cache_capacity()?);
+        cache.custom_hasher = config.result_cache_hasher().cloned();
+        Some(cache)
+    }
+
+    pub(crate) fn new(capacity: usize) -> ResultCache {
+        ResultCache {
+            capacity,
+            hash_builder: RandomState::new(),
+            custom_hasher: None,
+            entries: Mutex::new(Entries {
+                map: AHashMap::with_capacity(capacity),
+                slots: Vec::with_capacity(capacity),
+                newest: None,
+                oldest: None,
+            }),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// The cached result for `instance`, if any.
+    pub(crate) fn get(&self, instance: &Value) -> Option<bool> {
+        let key = self.hash(instance);
+        let mut entries = self.entries.lock();
+        let slot = *entries.map.get(&key)?;
+        if entries.slots[slot].instance != *instance {
+            return None;
+        }
+        entries.unlink(slot);
+        entries.push_newest(slot);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(entries.slots[slot].is_valid)
+    }
+
+    pub(crate) fn insert(&self, instance: &Value, is_valid: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = self.hash(instance);
+        let mut entries = self.entries.lock();
+        let entry = Entry {
+            key,
+            instance: instance.clone(),
+            is_valid,
+            newer: None,
+            older: None,
+        };
+        let slot = if let Some(&slot) = entries.map.get(&key) {
+            entries.unlink(slot);
+            entries.slots[slot] = entry;
+            slot
+        } else if entries.slots.len() < self.capacity {
+            entries.slots.push(entry);
+            entries.slots.len() - 1
+        } else {
+            // Full, the least recently used slot is reused
+            let slot = entries.oldest.expect("A full cache has entries");
+            entries.unlink(slot);
+            let evicted = entries.slots[slot].key;
+            entries.map.remove(&evicted);
+            entries.slots[slot] = entry;
+            slot
+        };
+        entries.map.insert(key, slot);
+        entries.push_newest(slot);
+    }
+
+    /// Number of lookups answered from the cache.
+    pub(crate) fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    fn hash(&self, instance: &Value) -> u64 {
+        match &self.custom_hasher {
+            Some(build_hasher) => {
+                let mut hasher = build_hasher();
+                hash_value(instance, &mut hasher);
+                hasher.finish()
+            }
+            None => hash_instance(&self.hash_builder, instance),
+        }
+    }
+}
+
+thread_local! {
+    static MEMO: RefCell<Option<ValidationMemo>> = RefCell::new(None);
+}
+
+/// `is_valid` results of discriminated subschemas during a single top-level validation, see
+/// `CompilationOptions::should_memoize_discriminated_instances`.
+#[derive(Default)]
+struct ValidationMemo {
+    hash_builder: RandomState,
+    // Keyed by the validator address and the instance hash, instances with the same hash are
+    // told apart by comparison
+    entries: AHashMap<(usize, u64), Vec<(Value, bool)>>,
+    hits: usize,
+}
+
+/// Keeps a memo for the current thread until dropped. Nested scopes share the outermost memo.
+pub(crate) struct MemoScope<'a> {
+    // Receives the hits of the memo if this scope created it
+    hits: Option<&'a AtomicUsize>,
+}
+
+impl<'a> MemoScope<'a> {
+    pub(crate) fn enter(hits: &'a AtomicUsize) -> MemoScope<'a> {
+        MEMO.with(|memo| {
+            let mut memo = memo.borrow_mut();
+            if memo.is_some() {
+                MemoScope { hits: None }
+            } else {
+                *memo = Some(ValidationMemo::default());
+                MemoScope { hits: Some(hits) }
+            }
+        })
+    }
+}
+
+impl Drop for MemoScope<'_> {
+    fn drop(&mut self) {
+        if let Some(hits) = self.hits {
+            if let Some(memo) = MEMO.with(|memo| memo.borrow_mut().take()) {
+                hits.fetch_add(memo.hits, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Whether a memo is active on the current thread.
+pub(crate) fn is_active() -> bool {
+    MEMO.with(|memo| memo.borrow().is_some())
+}
+
+/// The result of `is_valid` for `instance`, taken from the memo of the current thread if
+/// `validator` has already checked an equal instance.
+pub(crate) fn memoized(
+    validator: usize,
+    instance: &Value,
+    is_valid: impl FnOnce() -> bool,
+) -> bool {
+    // The instance hash and the memoized result, `None` if no memo is active
+    let lookup = MEMO.with(|memo| match memo.borrow_mut().as_mut() {
+        Some(memo) => {
+            let hash = hash_instance(&memo.hash_builder, instance);
+            let known = memo.entries.get(&(validator, hash)).and_then(|entries| {
+                entries
+                    .iter()
+                    .find(|(known, _)| known == instance)
+                    .map(|(_, is_valid)| *is_valid)
+            });
+            match known {
+                Some(is_valid) => {
+                    memo.hits += 1;
+                    Some((hash, Some(is_valid)))
+                }
+                None => Some((hash, None)),
+            }
+        }
+        None => None,
+    });
+    match lookup {
+        None => is_valid(),
+        Some((_, Some(is_valid))) => is_valid,
+        Some((hash, None)) => {
+            // The memo is not borrowed here, nested validators may use it
+            let is_valid = is_valid();
+            MEMO.with(|memo| {
+                if let Some(memo) = memo.borrow_mut().as_mut() {
+                    memo.entries
+                        .entry((validator, hash))
+                        .or_default()
+                        .push((instance.clone(), is_valid));
+                }
+            });
+            is_valid
+        }
+    }
+}
+
+fn hash_instance(hash_builder: &RandomState, instance: &Value) -> u64 {
+    let mut hasher = hash_builder.build_hasher();
+    hash_value(instance, &mut hasher);
+    hasher.finish()
+}
+
+fn hash_value<H: Hasher>(value: &Value, hasher: &mut H) {
+    // Distinguish types, e.g. `"1"` and `1`, and nesting levels
+    match value {
+        Value::Null => 0_u8.hash(hasher),
+        Value::Bool(value) => {
+            1_u8.hash(hasher);
+            value.hash(hasher);
+        }
+        Value::Number(value) => {
+            2_u8.hash(hasher);
+            if let Some(value) = value.as_u64() {
+                value.hash(hasher);
+            } else if let Some(value) = value.as_i64() {
+                value.hash(hasher);
+            } else if let Some(value) = value.as_f64() {
+                value.to_bits().hash(hasher);
+            }
+        }
+        Value::String(value) => {
+            3_u8.hash(hasher);
+            value.hash(hasher);
+        }
+        Value::Array(items) => {
+            4_u8.hash(hasher);
+            items.len().hash(hasher);
+            for item in items {
+                hash_value(item, hasher);
+            }
+        }
+        Value::Object(object) => {
+            5_u8.hash(hasher);
+            object.len().hash(hasher);
+            for (key, value) in object {
+                key.hash(hasher);
+                hash_value(value, hasher);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{memoized, MemoScope, ResultCache};
+    use serde_json::json;
+    use std::{
+        collections::hash_map::RandomState,
+        hash::BuildHasher,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        let cache = ResultCache::new(2);
+        cache.insert(&json!(1), true);
+        cache.insert(&json!(2), false);
+        assert_eq!(cache.get(&json!(1)), Some(true));
+        cache.insert(&json!(3), true);
+        assert_eq!(cache.get(&json!(2)), None);
+        assert_eq!(cache.get(&json!(1)), Some(true));
+        assert_eq!(cache.get(&json!(3)), Some(true));
+        assert_eq!(cache.get(&json!("1")), None);
+        assert_eq!(cache.hits(), 3);
+    }
+
+    #[test]
+    fn reinserted_entry_is_most_recent() {
+        let cache = ResultCache::new(2);
+        cache.insert(&json!(1), true);
+        cache.insert(&json!(2), true);
+        cache.insert(&json!(1), false);
+        cache.insert(&json!(3), true);
+        assert_eq!(cache.get(&json!(1)), Some(false));
+        assert_eq!(cache.get(&json!(2)), None);
+        assert_eq!(cache.get(&json!(3)), Some(true));
+    }
+
+    #[test]
+    fn custom_hasher() {
+        let mut cache = ResultCache::new(2);
+        let hash_builder = RandomState::new();
+        cache.custom_hasher = Some(Arc::new(move || Box::new(hash_builder.build_hasher())));
+        cache.insert(&json!(1), true);
+        cache.insert(&json!({"a": [1, 2]}), false);
+        assert_eq!(cache.get(&json!(1)), Some(true));
+        assert_eq!(cache.get(&json!({"a": [1, 2]})), Some(false));
+        assert_eq!(cache.get(&json!("1")), None);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let cache = ResultCache::new(0);
+        cache.insert(&json!(1), true);
+        assert_eq!(cache.get(&json!(1)), None);
+    }
+
+    #[test]
+    fn memo_is_scoped() {
+        let hits = AtomicUsize::new(0);
+        // No memo is active, every call validates
+        assert!(memoized(1, &json!(1), || true));
+        assert!(!memoized(1, &json!(1), || false));
+        {
+            let _scope = MemoScope::enter(&hits);
+            assert!(memoized(1, &json!(1), || true));
+            assert!(memoized(1, &json!(1), || unreachable!()));
+            // Validators and instances are told apart
+            assert!(!memoized(2, &json!(1), || false));
+            assert!(!memoized(1, &json!("1"), || false));
+            {
+                // Nested scopes share the outer memo
+                let _nested = MemoScope::enter(&hits);
+                assert!(memoized(1, &json!(1), || unreachable!()));
+            }
+            assert!(memoized(1, &json!(1), || unreachable!()));
+        }
+        assert_eq!(hits.load(Ordering::Relaxed), 3);
+        assert!(!memoized(1, &json!(1), || false));
+    }
+}
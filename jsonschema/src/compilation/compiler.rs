@@ -0,0 +1,166 @@
+//! Compilation of multiple schemas that share external documents.
+use crate::{
+    compilation::{options::CompilationOptions, JSONSchema},
+    resolver::{SchemaResolver, SchemaResolverError},
+    ValidationError,
+};
+use ahash::AHashMap;
+use parking_lot::RwLock;
+use serde_json::Value;
+use std::{fmt, sync::Arc};
+use url::Url;
+
+/// Compiles schemas with the same options, resolving every external document they reference
+/// at most once.
+///
+/// Documents are cached by URL when a `$ref` or a discriminator mapping value is first
+/// resolved by any of the compiled schemas, so schemas sharing e.g. OpenAPI components don't
+/// fetch and parse them again.
+///
+/// ```rust
+/// # use jsonschema::{JSONSchema, SchemaCompiler};
+/// # use serde_json::json;
+/// let compiler = SchemaCompiler::new(JSONSchema::options().should_validate_formats(true));
+/// let user = compiler
+///     .compile(&json!({"properties": {"email": {"format": "email"}}}))
+///     .expect("A valid schema");
+/// let order = compiler
+///     .compile(&json!({"required": ["id"]}))
+///     .expect("A valid schema");
+/// assert!(!user.is_valid(&json!({"email": "no"})));
+/// assert!(order.is_valid(&json!({"id": 1})));
+/// ```
+pub struct SchemaCompiler {
+    options: CompilationOptions,
+    documents: Arc<DocumentCache>,
+}
+
+impl SchemaCompiler {
+    /// Create a compiler using `options` for every schema.
+    #[must_use]
+    pub fn new(options: &CompilationOptions) -> SchemaCompiler {
+        let documents = Arc::new(DocumentCache {
+            resolver: Arc::clone(options.external_resolver()),
+            documents: RwLock::new(AHashMap::new()),
+        });
+        let mut options = options.clone();
+        options.with_resolver(SharedResolver(Arc::clone(&documents)));
+        SchemaCompiler { options, documents }
+    }
+
+    /// Compile `schema`, sharing the external documents with other schemas of this compiler.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`CompilationOptions::compile`], boxed.
+    pub fn compile<'a>(&self, schema: &'a Value) -> Result<JSONSchema, Box<ValidationError<'a>>> {
+        self.options.compile(schema).map_err(Box::new)
+    }
+
+    /// The number of cached external documents.
+    #[must_use]
+    pub fn cached_documents(&self) -> usize {
+        self.documents.documents.read().len()
+    }
+}
+
+impl fmt::Debug for SchemaCompiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaCompiler")
+            .field("options", &self.options)
+            .field("cached_documents", &self.cached_documents())
+            .finish()
+    }
+}
+
+impl Default for SchemaCompiler {
+    fn default() -> Self {
+        SchemaCompiler::new(&CompilationOptions::default())
+    }
+}
+
+struct DocumentCache {
+    resolver: Arc<dyn SchemaResolver>,
+    documents: RwLock<AHashMap<String, Arc<Value>>>,
+}
+
+/// Serves documents from the cache, which is shared by all schemas of a compiler.
+struct SharedResolver(Arc<DocumentCache>);
+
+impl SchemaResolver for SharedResolver {
+    fn resolve(
+        &self,
+        root_schema: &Value,
+        url: &Url,
+        original_reference: &str,
+    ) -> Result<Arc<Value>, SchemaResolverError> {
+        if let Some(document) = self.0.documents.read().get(url.as_str()) {
+            return Ok(Arc::clone(document));
+        }
+        let document = self
+            .0
+            .resolver
+            .resolve(root_schema, url, original_reference)?;
+        self.0
+            .documents
+            .write()
+            .insert(url.to_string(), Arc::clone(&document));
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SchemaCompiler;
+    use crate::{JSONSchema, SchemaResolver, SchemaResolverError};
+    use serde_json::{json, Value};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use url::Url;
+
+    struct CountingResolver(Arc<AtomicUsize>);
+
+    impl SchemaResolver for CountingResolver {
+        fn resolve(&self, _: &Value, _: &Url, _: &str) -> Result<Arc<Value>, SchemaResolverError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(Arc::new(json!({
+                "definitions": {
+                    "Cat": {"required": ["meows"]},
+                    "Dog": {"required": ["barks"]}
+                }
+            })))
+        }
+    }
+
+    #[test]
+    fn shared_documents() {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let compiler = SchemaCompiler::new(
+            JSONSchema::options().with_resolver(CountingResolver(Arc::clone(&fetched))),
+        );
+        let pets = compiler
+            .compile(&json!({
+                "oneOf": [
+                    {"$ref": "db://components#/definitions/Cat"},
+                    {"$ref": "db://components#/definitions/Dog"}
+                ],
+                "discriminator": {
+                    "propertyName": "petType",
+                    "mapping": {
+                        "cat": "db://components#/definitions/Cat",
+                        "dog": "db://components#/definitions/Dog"
+                    }
+                }
+            }))
+            .expect("Valid schema");
+        let owner = compiler
+            .compile(&json!({"properties": {"cat": {"$ref": "db://components#/definitions/Cat"}}}))
+            .expect("Valid schema");
+        assert!(pets.is_valid(&json!({"petType": "dog", "barks": true})));
+        assert!(!owner.is_valid(&json!({"cat": {}})));
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+        assert_eq!(compiler.cached_documents(), 1);
+    }
+}
@@ -1,12 +1,22 @@
 use super::options::CompilationOptions;
 use crate::{
-    compilation::DEFAULT_SCOPE,
+    compilation::{CompilationStats, DEFAULT_SCOPE},
+    error::ValidationError,
     paths::{InstancePath, JSONPointer, PathChunk},
     resolver::Resolver,
     schemas,
+    spans::SourceSpans,
 };
+use parking_lot::Mutex;
 use serde_json::Value;
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use url::{ParseError, Url};
 
 static DEFAULT_SCHEME: &str = "json-schema";
@@ -18,6 +28,55 @@ pub(crate) struct CompilationContext<'a> {
     pub(crate) config: Arc<CompilationOptions>,
     pub(crate) resolver: Arc<Resolver>,
     pub(crate) schema_path: InstancePath<'a>,
+    pub(crate) counters: Arc<CompilationCounters>,
+    // Spans of the root document, only while `schema_path` is relative to it
+    source_spans: Option<Arc<SourceSpans>>,
+}
+
+/// Counters shared by all contexts derived from the same root context.
+#[derive(Debug, Default)]
+pub(crate) struct CompilationCounters {
+    validators: AtomicUsize,
+    one_of_sites: AtomicUsize,
+    discriminator_sites: AtomicUsize,
+    // Problems found by the optional compile-time analyses
+    diagnostics: Mutex<Vec<String>>,
+}
+
+impl CompilationCounters {
+    pub(crate) fn add_validators(&self, count: usize) {
+        self.validators.fetch_add(count, Ordering::Relaxed);
+    }
+    pub(crate) fn add_one_of_site(&self) {
+        self.one_of_sites.fetch_add(1, Ordering::Relaxed);
+    }
+    pub(crate) fn add_discriminator_site(&self) {
+        self.discriminator_sites.fetch_add(1, Ordering::Relaxed);
+    }
+    pub(crate) fn add_diagnostic(&self, diagnostic: String) {
+        self.diagnostics.lock().push(diagnostic);
+    }
+    pub(crate) fn validators(&self) -> usize {
+        self.validators.load(Ordering::Relaxed)
+    }
+    pub(crate) fn one_of_sites(&self) -> usize {
+        self.one_of_sites.load(Ordering::Relaxed)
+    }
+    pub(crate) fn discriminator_sites(&self) -> usize {
+        self.discriminator_sites.load(Ordering::Relaxed)
+    }
+    pub(crate) fn diagnostics(&self) -> Vec<String> {
+        self.diagnostics.lock().clone()
+    }
+    /// The counts so far, without a duration.
+    pub(crate) fn stats(&self) -> CompilationStats {
+        CompilationStats {
+            validators: self.validators(),
+            one_of_sites: self.one_of_sites(),
+            discriminator_sites: self.discriminator_sites(),
+            duration: Duration::ZERO,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -75,7 +134,7 @@ impl<'a> From<&'a BaseUri<'a>> for Cow<'a, Url> {
 }
 
 impl<'a> CompilationContext<'a> {
-    pub(crate) const fn new(
+    pub(crate) fn new(
         scope: BaseUri<'a>,
         config: Arc<CompilationOptions>,
         resolver: Arc<Resolver>,
@@ -85,9 +144,17 @@ impl<'a> CompilationContext<'a> {
             config,
             resolver,
             schema_path: InstancePath::new(),
+            counters: Arc::default(),
+            source_spans: None,
         }
     }
 
+    /// Attach `spans` of the root document to the errors reported through [`Self::locate`].
+    pub(crate) fn with_source_spans(mut self, spans: Option<Arc<SourceSpans>>) -> Self {
+        self.source_spans = spans;
+        self
+    }
+
     #[allow(clippy::doc_markdown)]
     /// Push a new scope. All URLs built from the new context will have this scope in them.
     /// Before push:
@@ -106,6 +173,8 @@ impl<'a> CompilationContext<'a> {
                 config: Arc::clone(&self.config),
                 resolver: Arc::clone(&self.resolver),
                 schema_path: self.schema_path.clone(),
+                counters: Arc::clone(&self.counters),
+                source_spans: self.source_spans.clone(),
             })
         } else {
             Ok(CompilationContext {
@@ -113,6 +182,8 @@ impl<'a> CompilationContext<'a> {
                 config: Arc::clone(&self.config),
                 resolver: Arc::clone(&self.resolver),
                 schema_path: self.schema_path.clone(),
+                counters: Arc::clone(&self.counters),
+                source_spans: self.source_spans.clone(),
             })
         }
     }
@@ -125,6 +196,24 @@ impl<'a> CompilationContext<'a> {
             config: Arc::clone(&self.config),
             resolver: Arc::clone(&self.resolver),
             schema_path,
+            counters: Arc::clone(&self.counters),
+            source_spans: self.source_spans.clone(),
+        }
+    }
+
+    /// A context for compiling the branch referencing `reference`, with the options scoped to it.
+    pub(crate) fn for_branch(&self, reference: &str) -> CompilationContext<'a> {
+        let config = self
+            .config
+            .for_branch(reference)
+            .map_or_else(|| Arc::clone(&self.config), Arc::new);
+        CompilationContext {
+            base_uri: self.base_uri.clone(),
+            config,
+            resolver: Arc::clone(&self.resolver),
+            schema_path: self.schema_path.clone(),
+            counters: Arc::clone(&self.counters),
+            source_spans: self.source_spans.clone(),
         }
     }
 
@@ -148,6 +237,17 @@ impl<'a> CompilationContext<'a> {
             .parse(reference)
     }
 
+    /// Attach the source span of the invalid schema value to a compile-time error, which keeps
+    /// the location of that value in its `instance_path`.
+    pub(crate) fn locate<'e>(&self, mut error: ValidationError<'e>) -> ValidationError<'e> {
+        if error.source_span.is_none() {
+            if let Some(spans) = &self.source_spans {
+                error.source_span = spans.get(&error.instance_path.to_string());
+            }
+        }
+        error
+    }
+
     pub(crate) fn base_uri(&self) -> Option<Url> {
         match &self.base_uri {
             BaseUri::Known(u) => Some(u.as_ref().clone()),
@@ -0,0 +1,83 @@
+//! Deadlines of single validation calls, see [`crate::JSONSchema::validate_with_deadline`].
+//!
+//! Branch iteration checks the deadline of the current thread. Validation without a deadline
+//! only pays for reading a global counter of active deadlines. With one, the clock is only read
+//! on every `CHECK_INTERVAL`-th check of the thread. Once a deadline expired, its thread stops at
+//! every following check until the scope ends.
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+/// How many checks share a single read of the clock.
+const CHECK_INTERVAL: u32 = 32;
+
+/// Deadline scopes entered on any thread.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static DEADLINE: Cell<Option<Deadline>> = Cell::new(None);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Deadline {
+    at: Instant,
+    expired: bool,
+    // Checks since the clock was last read
+    checks: u32,
+}
+
+/// Sets the deadline of the current thread until dropped.
+#[derive(Debug)]
+pub(crate) struct DeadlineScope {
+    previous: Option<Deadline>,
+}
+
+impl DeadlineScope {
+    pub(crate) fn enter(at: Instant) -> DeadlineScope {
+        // A deadline that already passed stops validation at the first check
+        let deadline = Deadline {
+            at,
+            expired: Instant::now() >= at,
+            checks: 0,
+        };
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+        DeadlineScope {
+            previous: DEADLINE.with(|current| current.replace(Some(deadline))),
+        }
+    }
+
+    /// Whether the deadline passed during any check within this scope.
+    pub(crate) fn expired(&self) -> bool {
+        DEADLINE.with(|current| current.get().map_or(false, |deadline| deadline.expired))
+    }
+}
+
+impl Drop for DeadlineScope {
+    fn drop(&mut self) {
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+        DEADLINE.with(|current| current.set(self.previous));
+    }
+}
+
+/// Whether the deadline of the current thread has passed. Always `false` outside of a scope.
+#[inline]
+pub(crate) fn is_expired() -> bool {
+    if ACTIVE.load(Ordering::Relaxed) == 0 {
+        return false;
+    }
+    DEADLINE.with(|current| match current.get() {
+        None => false,
+        Some(Deadline { expired: true, .. }) => true,
+        Some(mut deadline) => {
+            deadline.checks += 1;
+            if deadline.checks == CHECK_INTERVAL {
+                deadline.checks = 0;
+                deadline.expired = Instant::now() >= deadline.at;
+            }
+            current.set(Some(deadline));
+            deadline.expired
+        }
+    })
+}
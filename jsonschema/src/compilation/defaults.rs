@@ -0,0 +1,98 @@
+//! Filling the `default` values declared by a schema into an instance.
+use crate::{
+    compilation::partial::{escape, item_location, local_pointer, MAX_DEPTH},
+    JSONSchema,
+};
+use serde_json::Value;
+
+/// Insert the `default` of every property that `instance` lacks, starting from the subschema at
+/// the JSON pointer `location` into `root`.
+///
+/// Defaults are taken from `properties` and followed through `allOf`, local `$ref`s and the
+/// discriminator mappings selected by the tags of `instance`. A mapping is only followed after
+/// the defaults of its own schema are filled in, so a `default` of the discriminator property
+/// selects the branch. Present values are never replaced.
+pub(crate) fn fill(root: &Value, location: &str, instance: &mut Value, compiled: &JSONSchema) {
+    fill_at(root, location, instance, compiled, 0);
+}
+
+fn fill_at(
+    root: &Value,
+    location: &str,
+    instance: &mut Value,
+    compiled: &JSONSchema,
+    depth: usize,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let schema = match root.pointer(location).and_then(Value::as_object) {
+        Some(schema) => schema,
+        None => return,
+    };
+    if let (Value::Object(object), Some(Value::Object(properties))) =
+        (&mut *instance, schema.get("properties"))
+    {
+        for (name, subschema) in properties {
+            if !object.contains_key(name) {
+                if let Some(default) = default_value(root, subschema) {
+                    object.insert(name.clone(), default.clone());
+                }
+            }
+        }
+    }
+    // Subschemas that apply to the same value
+    if let Some(reference) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(local_pointer)
+    {
+        fill_at(root, &reference, instance, compiled, depth + 1);
+    }
+    if let Some(Value::Array(all_of)) = schema.get("allOf") {
+        for idx in 0..all_of.len() {
+            let location = format!("{}/allOf/{}", location, idx);
+            fill_at(root, &location, instance, compiled, depth + 1);
+        }
+    }
+    if let Some(mapped) = compiled.mapped_location(root, location, instance) {
+        fill_at(root, &mapped, instance, compiled, depth + 1);
+    }
+    // Values within the instance
+    match instance {
+        Value::Object(object) => {
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                for (name, value) in object.iter_mut() {
+                    if properties.contains_key(name) {
+                        let location = format!("{}/properties/{}", location, escape(name));
+                        fill_at(root, &location, value, compiled, 0);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (idx, item) in items.iter_mut().enumerate() {
+                if let Some(location) = item_location(schema, location, idx) {
+                    fill_at(root, &location, item, compiled, 0);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The `default` of `schema`, following local `$ref`s.
+fn default_value<'a>(root: &'a Value, mut schema: &'a Value) -> Option<&'a Value> {
+    for _ in 0..MAX_DEPTH {
+        let object = schema.as_object()?;
+        if let Some(default) = object.get("default") {
+            return Some(default);
+        }
+        let reference = object
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(local_pointer)?;
+        schema = root.pointer(&reference)?;
+    }
+    None
+}
@@ -1,34 +1,131 @@
 //! Schema compilation.
 //! The main idea is to compile the input JSON Schema to a validators tree that will contain
 //! everything needed to perform such validation in runtime.
+pub(crate) mod cache;
+pub(crate) mod compiler;
 pub(crate) mod context;
+pub(crate) mod deadline;
+pub(crate) mod defaults;
 pub(crate) mod options;
+pub(crate) mod partial;
+pub(crate) mod streaming;
 
 use crate::{
-    error::ErrorIterator,
-    keywords,
-    output::Output,
+    error::{
+        error, BytesValidationError, ErrorIterator, LimitedErrors, LocatedValidationError,
+        ValidationErrors,
+    },
+    keywords::{
+        self,
+        any_of::AnyOfValidator,
+        discriminator::{
+            DiscriminatorError, DiscriminatorValidator, InheritedDiscriminatorValidator,
+            MappingSource, VariantInfo,
+        },
+        one_of::OneOfValidator,
+        ref_::RefValidator,
+        BoxedValidator,
+    },
+    offsets::value_offsets,
+    output::{Annotations, BasicOutput, Output, OutputUnit},
     paths::{InstancePath, JSONPointer},
     primitive_type::{PrimitiveType, PrimitiveTypesBitMap},
-    schema_node::SchemaNode,
+    resolver::Resolver,
+    schema_node::{self, SchemaNode},
     validator::Validate,
     Draft, ValidationError,
 };
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
+use cache::{MemoScope, ResultCache};
 use context::CompilationContext;
+use deadline::DeadlineScope;
 use once_cell::sync::Lazy;
 use options::CompilationOptions;
+use parking_lot::RwLock;
 use serde_json::Value;
-use std::sync::Arc;
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use url::Url;
 
 pub(crate) const DEFAULT_ROOT_URL: &str = "json-schema:///";
+/// How many references `JSONSchema::validate_ref` keeps compiled.
+const MAX_COMPILED_REFERENCES: usize = 1024;
 
 /// The structure that holds a JSON Schema compiled into a validation tree
 #[derive(Debug)]
 pub struct JSONSchema {
     pub(crate) node: SchemaNode,
     config: Arc<CompilationOptions>,
+    stats: CompilationStats,
+    diagnostics: Vec<String>,
+    result_cache: Option<ResultCache>,
+    // Summed up when the memo of a validation call is dropped
+    memo_hits: AtomicUsize,
+    // References validated against with `validate_ref`, compiled on first use
+    compiled_references: RwLock<AHashMap<String, Arc<BoxedValidator>>>,
+    // Subschemas by their location in the root document, compiled on first use to validate
+    // single values and to follow discriminators when locating them
+    subschemas: RwLock<AHashMap<String, Option<Arc<JSONSchema>>>>,
+    // Kept to compile subschemas on demand, see `subschema_at`
+    scope: Url,
+    resolver: Arc<Resolver>,
+}
+
+/// Statistics collected while compiling a `JSONSchema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompilationStats {
+    pub(crate) validators: usize,
+    pub(crate) one_of_sites: usize,
+    pub(crate) discriminator_sites: usize,
+    pub(crate) duration: Duration,
+}
+
+impl CompilationStats {
+    /// Total number of validators in the compiled tree, including those inside `oneOf` branches
+    /// and discriminator mappings. Lazily resolved `$ref` targets are not counted.
+    #[must_use]
+    pub const fn validators(&self) -> usize {
+        self.validators
+    }
+    /// Number of compiled `oneOf` keywords, with or without a discriminator.
+    #[must_use]
+    pub const fn one_of_sites(&self) -> usize {
+        self.one_of_sites
+    }
+    /// Number of compiled `discriminator` keywords, next to `oneOf` or `anyOf`.
+    #[must_use]
+    pub const fn discriminator_sites(&self) -> usize {
+        self.discriminator_sites
+    }
+    /// Wall-clock time spent in compilation, including meta-schema validation.
+    /// Always zero on `wasm32` targets where no monotonic clock is available.
+    #[must_use]
+    pub const fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The counts added since `earlier`, without a duration.
+    pub(crate) const fn since(&self, earlier: &CompilationStats) -> CompilationStats {
+        CompilationStats {
+            validators: self.validators - earlier.validators,
+            one_of_sites: self.one_of_sites - earlier.one_of_sites,
+            discriminator_sites: self.discriminator_sites - earlier.discriminator_sites,
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Add the counts of `other`, keeping the duration.
+    pub(crate) fn include(&mut self, other: &CompilationStats) {
+        self.validators += other.validators;
+        self.one_of_sites += other.one_of_sites;
+        self.discriminator_sites += other.discriminator_sites;
+    }
 }
 
 pub(crate) static DEFAULT_SCOPE: Lazy<Url> =
@@ -61,28 +158,1196 @@ impl JSONSchema {
         Self::options().compile(schema)
     }
 
+    /// Validate the input schema against its meta-schemas and compile it.
+    ///
+    /// The method is equivalent to `JSONSchema::options().compile_with_meta_validation(schema)`
+    ///
+    /// # Errors
+    ///
+    /// All meta-schema violations, or the compilation error if there are none.
+    pub fn compile_with_meta_validation(schema: &Value) -> Result<JSONSchema, ValidationErrors> {
+        Self::options().compile_with_meta_validation(schema)
+    }
+
     /// Run validation against `instance` and return an iterator over `ValidationError` in the error case.
     #[inline]
     pub fn validate<'instance>(
         &'instance self,
         instance: &'instance Value,
     ) -> Result<(), ErrorIterator<'instance>> {
+        if let Some(true) = self.cached_result(instance) {
+            return Ok(());
+        }
+        // Only the first error is looked for with the memo, the others are collected after the
+        // scope ended
+        let _memo = self.memo_scope();
         let instance_path = InstancePath::new();
-        let mut errors = self.node.validate(instance, &instance_path).peekable();
-        if errors.peek().is_none() {
+        let mut errors = self.errors(instance, &instance_path).peekable();
+        let is_valid = errors.peek().is_none();
+        if let Some(cache) = &self.result_cache {
+            cache.insert(instance, is_valid);
+        }
+        if is_valid {
             Ok(())
         } else {
             Err(Box::new(errors))
         }
     }
 
+    /// Run validation against `instance` and return its errors and warnings separately.
+    ///
+    /// Warnings don't fail validation, they are only reported with some options, e.g.
+    /// [`CompilationOptions::should_warn_about_deprecated_discriminator_branches`]. Warnings of
+    /// subschemas that don't affect the result, e.g. of failed `anyOf` subschemas, are not
+    /// reported.
+    ///
+    /// ```rust
+    /// # use jsonschema::{error::ValidationErrorKind, JSONSchema, Severity};
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+    ///     "discriminator": {
+    ///         "propertyName": "petType",
+    ///         "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+    ///     },
+    ///     "definitions": {
+    ///         "Cat": {"required": ["meows"], "deprecated": true},
+    ///         "Dog": {"required": ["barks"]}
+    ///     }
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .should_warn_about_deprecated_discriminator_branches(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// let instance = json!({"petType": "cat", "meows": true});
+    /// let (errors, warnings) = compiled.validate_with_warnings(&instance);
+    /// assert!(errors.is_empty());
+    /// assert!(matches!(
+    ///     warnings[0].kind,
+    ///     ValidationErrorKind::DeprecatedDiscriminatorValue { .. }
+    /// ));
+    /// assert_eq!(warnings[0].severity, Severity::Warning);
+    /// ```
+    #[must_use]
+    pub fn validate_with_warnings<'instance>(
+        &'instance self,
+        instance: &'instance Value,
+    ) -> (
+        Vec<ValidationError<'instance>>,
+        Vec<ValidationError<'instance>>,
+    ) {
+        let _memo = self.memo_scope();
+        self.node
+            .validate(instance, &InstancePath::new())
+            .partition(ValidationError::is_error)
+    }
+
+    /// Errors of `instance` at `instance_path`, without warnings.
+    fn errors<'instance>(
+        &'instance self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> ErrorIterator<'instance> {
+        let errors = self.node.validate(instance, instance_path);
+        if self.config.reports_warnings() {
+            Box::new(errors.filter(ValidationError::is_error))
+        } else {
+            errors
+        }
+    }
+
+    /// Run validation against `instance`, giving up once `deadline` has passed.
+    ///
+    /// The deadline is checked while iterating over `oneOf` and `anyOf` branches, which is where
+    /// validation of untrusted schemas and instances can take long, and between errors. The clock
+    /// is only read on every 32nd check, so validation may run a few branches past the deadline.
+    /// Neither the memo nor the result cache is used. Errors are collected before they are
+    /// returned.
+    ///
+    /// ```rust
+    /// # use jsonschema::{error::ValidationErrorKind, JSONSchema};
+    /// # use serde_json::json;
+    /// # use std::time::{Duration, Instant};
+    /// let schema = JSONSchema::compile(&json!({"oneOf": [{"type": "string"}, {"type": "integer"}]}))
+    ///     .expect("A valid schema");
+    /// let instance = json!(42);
+    /// let deadline = Instant::now() + Duration::from_secs(1);
+    /// assert!(schema.validate_with_deadline(&instance, deadline).is_ok());
+    /// let mut errors = schema
+    ///     .validate_with_deadline(&instance, Instant::now())
+    ///     .expect_err("No time left");
+    /// assert!(matches!(errors.next().map(|error| error.kind), Some(ValidationErrorKind::Timeout)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// A single [`ValidationErrorKind::Timeout`](crate::error::ValidationErrorKind::Timeout)
+    /// error if the deadline passed, all validation errors otherwise.
+    pub fn validate_with_deadline<'instance>(
+        &'instance self,
+        instance: &'instance Value,
+        deadline: Instant,
+    ) -> Result<(), ErrorIterator<'instance>> {
+        let scope = DeadlineScope::enter(deadline);
+        let mut errors = Vec::new();
+        for error in self.errors(instance, &InstancePath::new()) {
+            if deadline::is_expired() {
+                break;
+            }
+            errors.push(error);
+        }
+        if scope.expired() {
+            Err(error(ValidationError::timeout(
+                JSONPointer::default(),
+                instance,
+            )))
+        } else if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Box::new(errors.into_iter()))
+        }
+    }
+
+    /// Run validation against `instance` and return only the first error.
+    ///
+    /// Validation stops at the first error, so this is cheaper than [`JSONSchema::validate`] for
+    /// instances with many errors. Discriminated `oneOf` subschemas are still validated against
+    /// the mapped branch only.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"items": {"type": "integer"}}))
+    ///     .expect("A valid schema");
+    /// let instance = json!([1, "2", "3"]);
+    /// let error = schema.validate_once(&instance).expect_err("Invalid items");
+    /// assert_eq!(error.instance_path.to_string(), "/1");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`ValidationError`] if `instance` is not valid.
+    pub fn validate_once<'instance>(
+        &'instance self,
+        instance: &'instance Value,
+    ) -> Result<(), Box<ValidationError<'instance>>> {
+        if let Some(true) = self.cached_result(instance) {
+            return Ok(());
+        }
+        match self.node.first_error(instance, &InstancePath::new()) {
+            Some(error) => Err(Box::new(error)),
+            None => Ok(()),
+        }
+    }
+
+    /// Run validation against `instance` that is embedded into a larger document at `prefix`.
+    /// The `prefix` segments are prepended to the `instance_path` of every emitted error.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"properties": {"age": {"type": "integer"}}}))
+    ///     .expect("A valid schema");
+    /// let payload = json!({"users": [{"age": "old"}]});
+    /// let user = &payload["users"][0];
+    /// let errors: Vec<_> = schema
+    ///     .validate_with_prefix(&["users", "0"], user)
+    ///     .expect_err("Invalid age")
+    ///     .collect();
+    /// assert_eq!(errors[0].instance_path.to_string(), "/users/0/age");
+    /// ```
+    #[inline]
+    pub fn validate_with_prefix<'instance>(
+        &'instance self,
+        prefix: &[&str],
+        instance: &'instance Value,
+    ) -> Result<(), ErrorIterator<'instance>> {
+        let prefix = JSONPointer::from(prefix);
+        self.validate(instance)
+            .map_err(|errors| -> ErrorIterator<'instance> {
+                Box::new(errors.map(move |mut error| {
+                    error.instance_path = prefix.extend_with(error.instance_path.as_slice());
+                    error
+                }))
+            })
+    }
+
+    /// Run validation against `instance` and collect at most `max_errors` errors.
+    ///
+    /// Validation stops as soon as one more error than `max_errors` is found, in which case
+    /// `truncated` is set on the returned [`LimitedErrors`].
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"items": {"type": "integer"}}))
+    ///     .expect("A valid schema");
+    /// let instance = json!(["a", "b", "c"]);
+    /// let errors = schema
+    ///     .validate_with_limit(&instance, 2)
+    ///     .expect_err("Invalid items");
+    /// assert_eq!(errors.errors.len(), 2);
+    /// assert!(errors.truncated);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LimitedErrors`] if `instance` is not valid.
+    pub fn validate_with_limit<'instance>(
+        &'instance self,
+        instance: &'instance Value,
+        max_errors: usize,
+    ) -> Result<(), LimitedErrors<'instance>> {
+        let mut errors = self
+            .errors(instance, &InstancePath::new())
+            .take(max_errors.saturating_add(1));
+        let collected: Vec<_> = errors.by_ref().take(max_errors).collect();
+        let truncated = errors.next().is_some();
+        if collected.is_empty() && !truncated {
+            Ok(())
+        } else {
+            Err(LimitedErrors {
+                errors: collected,
+                truncated,
+            })
+        }
+    }
+
+    /// Parse `bytes` as JSON and run validation against the resulting instance.
+    ///
+    /// Errors are returned owned, as the parsed instance does not outlive this call.
+    ///
+    /// ```rust
+    /// # use jsonschema::{BytesValidationError, JSONSchema};
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"required": ["name"]})).expect("A valid schema");
+    /// assert!(schema.validate_bytes(br#"{"name": "Alice"}"#).is_ok());
+    /// assert!(matches!(
+    ///     schema.validate_bytes(br#"{}"#),
+    ///     Err(BytesValidationError::Invalid(_))
+    /// ));
+    /// assert!(matches!(
+    ///     schema.validate_bytes(br#"{"name": "#),
+    ///     Err(BytesValidationError::Parse(_))
+    /// ));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`BytesValidationError::Parse`] if `bytes` is not valid JSON and
+    /// [`BytesValidationError::Invalid`] with all validation errors otherwise.
+    pub fn validate_bytes(&self, bytes: &[u8]) -> Result<(), BytesValidationError> {
+        let instance: Value = serde_json::from_slice(bytes).map_err(BytesValidationError::Parse)?;
+        let errors: Vec<_> = self
+            .errors(&instance, &InstancePath::new())
+            .map(ValidationError::into_owned)
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(BytesValidationError::Invalid(errors))
+        }
+    }
+
+    /// Parse `json` and run validation against the resulting instance, like
+    /// [`JSONSchema::validate_bytes`].
+    ///
+    /// Syntax errors are returned as [`BytesValidationError::Parse`], whose `line` and `column`
+    /// locate the error within `json`.
+    ///
+    /// ```rust
+    /// # use jsonschema::{BytesValidationError, JSONSchema};
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"required": ["name"]})).expect("A valid schema");
+    /// assert!(schema.validate_json_str(r#"{"name": "Alice"}"#).is_ok());
+    /// match schema.validate_json_str("{\n  \"name\": }") {
+    ///     Err(BytesValidationError::Parse(error)) => assert_eq!((error.line(), error.column()), (2, 11)),
+    ///     _ => panic!("Should not parse"),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`BytesValidationError::Parse`] if `json` is not valid JSON and
+    /// [`BytesValidationError::Invalid`] with all validation errors otherwise.
+    pub fn validate_json_str(&self, json: &str) -> Result<(), BytesValidationError> {
+        self.validate_bytes(json.as_bytes())
+    }
+
+    /// Like [`JSONSchema::validate_bytes`], but every error also carries the byte range of the
+    /// invalid value within `bytes`, e.g. for highlighting in an editor.
+    ///
+    /// ```rust
+    /// # use jsonschema::{BytesValidationError, JSONSchema};
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"items": {"type": "integer"}}))
+    ///     .expect("A valid schema");
+    /// match schema.validate_bytes_with_offsets(br#"[1, "2"]"#) {
+    ///     Err(BytesValidationError::Invalid(errors)) => assert_eq!(errors[0].offsets, Some((4, 7))),
+    ///     _ => panic!("Should be invalid"),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`BytesValidationError::Parse`] if `bytes` is not valid JSON and
+    /// [`BytesValidationError::Invalid`] with all located validation errors otherwise.
+    pub fn validate_bytes_with_offsets(
+        &self,
+        bytes: &[u8],
+    ) -> Result<(), BytesValidationError<LocatedValidationError>> {
+        match self.validate_bytes(bytes) {
+            Ok(()) => Ok(()),
+            Err(BytesValidationError::Parse(error)) => Err(BytesValidationError::Parse(error)),
+            Err(BytesValidationError::Invalid(errors)) => {
+                // Positions are only needed for invalid input
+                let offsets = value_offsets(bytes);
+                Err(BytesValidationError::Invalid(
+                    errors
+                        .into_iter()
+                        .map(|error| LocatedValidationError {
+                            offsets: offsets.get(&error.instance_path.to_string()).copied(),
+                            error,
+                        })
+                        .collect(),
+                ))
+            }
+        }
+    }
+
+    /// Validate every element of a top-level JSON array read from `reader` against the schema.
+    ///
+    /// Elements are validated while the input is parsed and dropped afterwards, so arbitrarily
+    /// large documents are validated with the memory of their largest element. The
+    /// `instance_path` of every error starts with the index of its element. Wrap unbuffered
+    /// readers, e.g. a `File`, into a `BufReader`.
+    ///
+    /// ```rust
+    /// # use jsonschema::{BytesValidationError, JSONSchema};
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"type": "integer"})).expect("A valid schema");
+    /// match schema.validate_streaming_json(&br#"[1, 2, "3"]"#[..]) {
+    ///     Err(BytesValidationError::Invalid(errors)) => {
+    ///         assert_eq!(errors[0].instance_path.to_string(), "/2")
+    ///     }
+    ///     _ => panic!("Should be invalid"),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`BytesValidationError::Parse`] if the input is not a valid JSON array and
+    /// [`BytesValidationError::Invalid`] with the validation errors of all elements otherwise.
+    pub fn validate_streaming_json<R: std::io::Read>(
+        &self,
+        reader: R,
+    ) -> Result<(), BytesValidationError> {
+        let mut errors = Vec::new();
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        serde::Deserializer::deserialize_seq(
+            &mut deserializer,
+            streaming::ElementValidator {
+                node: &self.node,
+                errors: &mut errors,
+            },
+        )
+        .and_then(|()| deserializer.end())
+        .map_err(BytesValidationError::Parse)?;
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(BytesValidationError::Invalid(errors))
+        }
+    }
+
+    /// Run validation against `instance` and collect the annotations produced by the schema.
+    ///
+    /// This is a convenience over calling [`JSONSchema::apply`] and [`JSONSchema::validate`],
+    /// not a single-pass validation. The `instance` is first traversed by `apply`, which yields
+    /// the annotations of a valid `instance`. The `apply` output only keeps error messages, so
+    /// for an invalid `instance` the errors are collected with a second, `validate` traversal
+    /// and no annotations are returned.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"type": "string", "title": "Name"}))
+    ///     .expect("A valid schema");
+    /// let valid = json!("Alice");
+    /// let (errors, annotations) = schema.validate_and_collect_annotations(&valid);
+    /// assert!(errors.is_empty());
+    /// assert_eq!(annotations[0].value().into_owned(), json!({"title": "Name"}));
+    /// let invalid = json!(42);
+    /// let (errors, annotations) = schema.validate_and_collect_annotations(&invalid);
+    /// assert_eq!(errors.len(), 1);
+    /// assert!(annotations.is_empty());
+    /// ```
+    #[must_use]
+    pub fn validate_and_collect_annotations<'instance>(
+        &'instance self,
+        instance: &'instance Value,
+    ) -> (
+        Vec<ValidationError<'instance>>,
+        Vec<OutputUnit<Annotations<'instance>>>,
+    ) {
+        match self.apply(instance).basic() {
+            BasicOutput::Valid(annotations) => (Vec::new(), annotations.into()),
+            BasicOutput::Invalid(_) => (
+                self.errors(instance, &InstancePath::new()).collect(),
+                Vec::new(),
+            ),
+        }
+    }
+
     /// Run validation against `instance` but return a boolean result instead of an iterator.
     /// It is useful for cases, where it is important to only know the fact if the data is valid or not.
     /// This approach is much faster, than `validate`.
     #[must_use]
     #[inline]
     pub fn is_valid(&self, instance: &Value) -> bool {
-        self.node.is_valid(instance)
+        if let Some(is_valid) = self.cached_result(instance) {
+            return is_valid;
+        }
+        let _memo = self.memo_scope();
+        let is_valid = self.node.is_valid(instance);
+        if let Some(cache) = &self.result_cache {
+            cache.insert(instance, is_valid);
+        }
+        is_valid
+    }
+
+    fn cached_result(&self, instance: &Value) -> Option<bool> {
+        self.result_cache.as_ref()?.get(instance)
+    }
+
+    fn memo_scope(&self) -> Option<MemoScope<'_>> {
+        if self.config.memoizes_discriminated_instances() {
+            Some(MemoScope::enter(&self.memo_hits))
+        } else {
+            None
+        }
+    }
+
+    /// Number of discriminated sub-instances that were not validated again within a single
+    /// validation call, see [`CompilationOptions::should_memoize_discriminated_instances`].
+    #[must_use]
+    pub fn memo_hits(&self) -> usize {
+        self.memo_hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of `is_valid` and `validate` calls that found their instance in the result cache,
+    /// see [`CompilationOptions::with_result_cache`].
+    #[must_use]
+    pub fn result_cache_hits(&self) -> usize {
+        self.result_cache.as_ref().map_or(0, ResultCache::hits)
+    }
+
+    /// The root `oneOf`, or the `oneOf` of the root `discriminator`.
+    fn root_one_of(&self) -> Option<&OneOfValidator> {
+        match self.node.keyword_validator("oneOf") {
+            Some(validator) => schema_node::downcast_ref(validator),
+            None => self
+                .root_discriminator()
+                .map(DiscriminatorValidator::one_of),
+        }
+    }
+
+    /// The root `discriminator` next to `oneOf`.
+    fn root_discriminator(&self) -> Option<&DiscriminatorValidator> {
+        self.node.keyword_validator_as("discriminator")
+    }
+
+    /// `instance` as the root `oneOf` or `discriminator` sees it, after the pre-validate hook.
+    fn pre_validated<'instance>(&self, instance: &'instance Value) -> Cow<'instance, Value> {
+        match self.config.pre_validate_hook() {
+            Some(hook) => hook(instance),
+            None => Cow::Borrowed(instance),
+        }
+    }
+
+    /// The errors of `instance` against every root `oneOf` subschema.
+    #[allow(clippy::type_complexity)]
+    fn root_branch_errors<'instance>(
+        &self,
+        instance: &'instance Value,
+    ) -> Option<Vec<(usize, Vec<ValidationError<'instance>>)>> {
+        let one_of = self.root_one_of()?;
+        Some(match self.pre_validated(instance) {
+            Cow::Borrowed(instance) => one_of.branch_errors(instance),
+            // Errors can't borrow from the transformed instance as it is dropped here
+            Cow::Owned(instance) => one_of
+                .branch_errors(&instance)
+                .into_iter()
+                .map(|(idx, errors)| {
+                    (
+                        idx,
+                        errors
+                            .into_iter()
+                            .map(ValidationError::into_owned)
+                            .collect(),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// Indices of all root `oneOf` subschemas that `instance` is valid against, regardless of
+    /// whether the `oneOf` as a whole passes. `None` if the root schema has no `oneOf`.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"oneOf": [{"type": "integer"}, {"minimum": 2}]}))
+    ///     .expect("A valid schema");
+    /// assert_eq!(schema.one_of_matching_branches(&json!(5)), Some(vec![0, 1]));
+    /// assert_eq!(schema.one_of_matching_branches(&json!(1.5)), Some(vec![]));
+    /// ```
+    #[must_use]
+    pub fn one_of_matching_branches(&self, instance: &Value) -> Option<Vec<usize>> {
+        Some(
+            self.root_one_of()?
+                .matching_branches(&self.pre_validated(instance)),
+        )
+    }
+
+    /// Index of the root `oneOf` subschema that `instance` matches best, i.e. the one with the
+    /// fewest validation errors. `None` if the root schema has no `oneOf`.
+    ///
+    /// Ties are broken deterministically in favour of the lowest index, so the result only
+    /// depends on the schema and the instance. A discriminator is not consulted, as the tagged
+    /// branch is validated on its own anyway.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({
+    ///     "oneOf": [{"required": ["a", "b"]}, {"required": ["a"]}, {"required": ["b"]}]
+    /// }))
+    /// .expect("A valid schema");
+    /// assert_eq!(schema.one_of_best_match(&json!({"c": 1})), Some(1));
+    /// ```
+    #[must_use]
+    pub fn one_of_best_match(&self, instance: &Value) -> Option<usize> {
+        self.root_branch_errors(instance)?
+            .into_iter()
+            .min_by_key(|(idx, errors)| (errors.len(), *idx))
+            .map(|(idx, _)| idx)
+    }
+
+    /// Validate `instance` against the root `oneOf` subschemas, reporting the best matching one
+    /// if none of them pass. `Ok` holds the lowest index of a passing subschema, while `Err` holds
+    /// the subschema with the fewest errors, picked as in [`JSONSchema::one_of_best_match`],
+    /// together with its errors. `None` if the root schema has no `oneOf`.
+    ///
+    /// Unlike [`JSONSchema::validate`], an instance matching several subschemas is accepted.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({
+    ///     "oneOf": [{"required": ["a", "b"]}, {"required": ["a"]}, {"required": ["b"]}]
+    /// }))
+    /// .expect("A valid schema");
+    /// assert!(matches!(schema.validate_with_best_match(&json!({"a": 1})), Some(Ok(1))));
+    /// let instance = json!({"c": 1});
+    /// let (branch, errors) = schema
+    ///     .validate_with_best_match(&instance)
+    ///     .expect("A oneOf")
+    ///     .expect_err("No matching subschema");
+    /// assert_eq!(branch, 1);
+    /// assert_eq!(errors[0].to_string(), r#""a" is a required property"#);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    #[must_use]
+    pub fn validate_with_best_match<'instance>(
+        &self,
+        instance: &'instance Value,
+    ) -> Option<Result<usize, (usize, Vec<ValidationError<'instance>>)>> {
+        let (idx, errors) = self
+            .root_branch_errors(instance)?
+            .into_iter()
+            .min_by_key(|(idx, errors)| (errors.len(), *idx))?;
+        if errors.is_empty() {
+            Some(Ok(idx))
+        } else {
+            Some(Err((idx, errors)))
+        }
+    }
+
+    /// The branch selected by the discriminator tag of `instance`, without validating `instance`
+    /// against it. `None` if the root schema has no `discriminator` next to `oneOf`.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .compile(&json!({
+    ///         "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+    ///         "discriminator": {
+    ///             "propertyName": "petType",
+    ///             "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+    ///         },
+    ///         "definitions": {"Cat": {"required": ["meows"]}, "Dog": {"required": ["barks"]}}
+    ///     }))
+    ///     .expect("A valid schema");
+    /// let instance = json!({"petType": "dog"});
+    /// let variant = schema
+    ///     .resolve_variant(&instance)
+    ///     .expect("A discriminator")
+    ///     .expect("A mapped tag");
+    /// assert_eq!(variant.reference.as_deref(), Some("#/definitions/Dog"));
+    /// assert_eq!(variant.branch, Some(1));
+    /// ```
+    ///
+    /// The error is the one validation reports for a missing or unmapped tag.
+    #[must_use]
+    pub fn resolve_variant<'instance>(
+        &self,
+        instance: &'instance Value,
+    ) -> Option<Result<VariantInfo, ValidationError<'instance>>> {
+        let discriminator = self.root_discriminator()?;
+        Some(match self.pre_validated(instance) {
+            Cow::Borrowed(instance) => discriminator.resolve_variant(instance).map_err(|e| *e),
+            Cow::Owned(instance) => discriminator
+                .resolve_variant(&instance)
+                .map_err(|error| error.into_owned()),
+        })
+    }
+
+    /// Whether the root `discriminator` accepts `tag`, i.e. it is in the explicit or implicit
+    /// mapping, or the mapping has a `"*"` fallback. A cheap check before building an instance,
+    /// as the selected branch is not evaluated. `None` if the root schema has no `discriminator`
+    /// next to `oneOf`.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .compile(&json!({
+    ///         "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///         "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+    ///         "definitions": {"Cat": {"required": ["meows"]}}
+    ///     }))
+    ///     .expect("A valid schema");
+    /// assert_eq!(schema.validate_discriminator_tag("cat"), Some(true));
+    /// assert_eq!(schema.validate_discriminator_tag("dog"), Some(false));
+    /// ```
+    #[must_use]
+    pub fn validate_discriminator_tag(&self, tag: &str) -> Option<bool> {
+        Some(self.root_discriminator()?.validate_tag(tag))
+    }
+
+    /// The branch the root `discriminator` selects for `tag` and whether it is mapped by the
+    /// `mapping` object, implicitly by the name of a `oneOf` reference, or by the `"*"`
+    /// fallback, to debug why a tag routes unexpectedly. The branch is not evaluated. `None` if
+    /// the tag selects no branch or the root schema has no `discriminator` next to `oneOf`.
+    ///
+    /// ```rust
+    /// # use jsonschema::{JSONSchema, MappingSource};
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .should_resolve_implicit_discriminator_mapping(true)
+    ///     .compile(&json!({
+    ///         "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+    ///         "discriminator": {
+    ///             "propertyName": "petType",
+    ///             "mapping": {"cat": "#/definitions/Cat"}
+    ///         },
+    ///         "definitions": {"Cat": {"required": ["meows"]}, "Dog": {"required": ["barks"]}}
+    ///     }))
+    ///     .expect("A valid schema");
+    /// let (variant, source) = schema.branches_for_tag("Dog").expect("A mapped tag");
+    /// assert_eq!(variant.branch, Some(1));
+    /// assert_eq!(source, MappingSource::Implicit);
+    /// assert!(schema.branches_for_tag("dog").is_none());
+    /// ```
+    #[must_use]
+    pub fn branches_for_tag(&self, tag: &str) -> Option<(VariantInfo, MappingSource)> {
+        self.root_discriminator()?.tag_variant(tag)
+    }
+
+    /// The JSON of every root `oneOf` subschema that was compiled, in order. `None` if the root
+    /// schema has no `oneOf`.
+    #[cfg(feature = "branch-sources")]
+    #[must_use]
+    pub fn one_of_branch_sources(&self) -> Option<&[Value]> {
+        Some(self.root_one_of()?.branch_sources())
+    }
+
+    /// The schema rebuilt from the compiled validators, e.g. to inspect what was compiled.
+    ///
+    /// This is a best-effort approximation: composition keywords, `$ref`, `type`, `required`,
+    /// `const`, `enum`, `properties` and `discriminator` are rebuilt, other validated keywords
+    /// are left out and unknown keywords, like `definitions`, are kept as they are. Implicit
+    /// discriminator mappings are written out explicitly.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({"oneOf": [{"type": "integer"}, {"required": ["foo"]}]});
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// assert_eq!(compiled.to_value(), schema);
+    /// ```
+    #[must_use]
+    pub fn to_value(&self) -> Value {
+        self.node.to_value()
+    }
+
+    /// A standalone schema for the subschema at the JSON pointer `pointer`, e.g. `/oneOf/1` or
+    /// `/discriminator/mapping/cat` for the branch selected by the `cat` tag.
+    ///
+    /// The subschema is compiled with the options of this schema and resolves its references
+    /// within the original document. Error paths are relative to the subschema. `None` if there
+    /// is no valid schema at `pointer`.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = JSONSchema::compile(&json!({"oneOf": [{"type": "integer"}, {"type": "string"}]}))
+    ///     .expect("A valid schema");
+    /// let branch = schema.subschema_at("/oneOf/1").expect("An existing branch");
+    /// assert!(branch.is_valid(&json!("foo")));
+    /// assert!(!branch.is_valid(&json!(42)));
+    /// ```
+    #[must_use]
+    pub fn subschema_at(&self, pointer: &str) -> Option<JSONSchema> {
+        self.compile_subschema(pointer)
+    }
+
+    /// The subschema at `location` in the root document, compiled once and kept.
+    fn compiled_subschema(&self, location: &str) -> Option<Arc<JSONSchema>> {
+        if let Some(subschema) = self.subschemas.read().get(location) {
+            return subschema.clone();
+        }
+        let subschema = self.compile_subschema(location).map(Arc::new);
+        self.subschemas
+            .write()
+            .insert(location.to_string(), subschema.clone());
+        subschema
+    }
+
+    /// The location of the schema that the discriminator of the subschema at `location` maps
+    /// the tag of `instance` to, as the compiled discriminator selects it. `None` if there is no
+    /// discriminator or the tag selects no schema of the root document.
+    pub(crate) fn mapped_location(
+        &self,
+        root: &Value,
+        location: &str,
+        instance: &Value,
+    ) -> Option<String> {
+        // Only subschemas with a discriminator are compiled
+        let schema = root.pointer(location)?.as_object()?;
+        if !schema.contains_key("discriminator") || !schema.contains_key("oneOf") {
+            return None;
+        }
+        let variant = if location.is_empty() {
+            self.root_discriminator()?.resolve_variant(instance).ok()?
+        } else {
+            self.compiled_subschema(location)?
+                .root_discriminator()?
+                .resolve_variant(instance)
+                .ok()?
+        };
+        partial::local_pointer(variant.reference.as_deref()?)
+    }
+
+    fn compile_subschema(&self, pointer: &str) -> Option<JSONSchema> {
+        let mut url = self.scope.clone();
+        url.set_fragment(Some(pointer));
+        let (scope, resolved) = self
+            .resolver
+            .resolve_fragment(self.config.draft(), &url, &format!("#{}", pointer))
+            .ok()?;
+        let is_mapping_value = pointer.rsplit_once('/').map_or(false, |(parent, _)| {
+            parent.ends_with("/discriminator/mapping")
+        });
+        let subschema = match resolved.as_ref() {
+            // Mapping values are references to the mapped branch
+            Value::String(reference) if is_mapping_value => {
+                let mut subschema = serde_json::Map::with_capacity(1);
+                subschema.insert("$ref".to_string(), Value::String(reference.clone()));
+                Value::Object(subschema)
+            }
+            _ => resolved.as_ref().clone(),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = Instant::now();
+        let context = CompilationContext::new(
+            scope.clone().into(),
+            Arc::clone(&self.config),
+            Arc::clone(&self.resolver),
+        );
+        let node = compile_validators(&subschema, &context).ok()?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let duration = start.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        let duration = Duration::ZERO;
+        Some(JSONSchema {
+            node,
+            config: Arc::clone(&self.config),
+            stats: CompilationStats {
+                validators: context.counters.validators(),
+                one_of_sites: context.counters.one_of_sites(),
+                discriminator_sites: context.counters.discriminator_sites(),
+                duration,
+            },
+            diagnostics: context.counters.diagnostics(),
+            result_cache: ResultCache::for_options(&self.config),
+            memo_hits: AtomicUsize::new(0),
+            compiled_references: Default::default(),
+            subschemas: Default::default(),
+            scope,
+            resolver: Arc::clone(&self.resolver),
+        })
+    }
+
+    /// Run validation of `instance` against the schema that `reference` points to, e.g.
+    /// `#/components/schemas/Pet`, as if it were the root schema.
+    ///
+    /// The reference is resolved against the root of this schema. The referenced schema is
+    /// compiled lazily on first use and kept, as are the subschemas it references, so repeated
+    /// calls with the same reference don't compile anything. Up to 1024 distinct references are
+    /// kept, references beyond that are compiled on every call. Error paths are relative to the
+    /// referenced schema. An unresolvable reference is reported as an error.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "components": {
+    ///         "schemas": {
+    ///             "Pet": {"required": ["name"], "properties": {"name": {"type": "string"}}}
+    ///         }
+    ///     }
+    /// });
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// assert!(compiled
+    ///     .validate_ref("#/components/schemas/Pet", &json!({"name": "Rex"}))
+    ///     .is_ok());
+    /// assert!(compiled
+    ///     .validate_ref("#/components/schemas/Pet", &json!({}))
+    ///     .is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an iterator over the errors of `instance`, or the error of an invalid reference.
+    pub fn validate_ref<'instance>(
+        &'instance self,
+        reference: &str,
+        instance: &'instance Value,
+    ) -> Result<(), ErrorIterator<'instance>> {
+        let validator = self
+            .reference_validator(reference)
+            .map_err(|err| error(*err))?;
+        let _memo = self.memo_scope();
+        let mut errors = validator
+            .validate(instance, &InstancePath::new())
+            .filter(ValidationError::is_error);
+        match errors.next() {
+            None => Ok(()),
+            Some(first) => Err(Box::new(std::iter::once(first).chain(errors))),
+        }
+    }
+
+    /// The validator of `reference`, compiled on first use. Validators are kept by the absolute
+    /// URL of the reference, up to `MAX_COMPILED_REFERENCES` of them.
+    fn reference_validator(
+        &self,
+        reference: &str,
+    ) -> Result<Arc<BoxedValidator>, Box<ValidationError<'static>>> {
+        let key = self
+            .scope
+            .join(reference)
+            .map_or_else(|_| reference.to_string(), String::from);
+        if let Some(validator) = self.compiled_references.read().get(&key) {
+            return Ok(Arc::clone(validator));
+        }
+        let context = CompilationContext::new(
+            self.scope.clone().into(),
+            Arc::clone(&self.config),
+            Arc::clone(&self.resolver),
+        );
+        let validator = Arc::new(
+            RefValidator::compile(reference, &context).map_err(ValidationError::into_owned)?,
+        );
+        let mut compiled_references = self.compiled_references.write();
+        if compiled_references.len() >= MAX_COMPILED_REFERENCES {
+            return Ok(validator);
+        }
+        Ok(Arc::clone(
+            compiled_references.entry(key).or_insert(validator),
+        ))
+    }
+
+    /// Resolve and compile every schema reachable through `$ref`, including discriminator
+    /// mapping targets, and report the errors that would otherwise only surface when an instance
+    /// first reaches them, e.g. during a health check at startup.
+    ///
+    /// Every `$ref` of the compiled tree is compiled in place, following the references within
+    /// its target, so validation compiles nothing afterwards. Fetched documents stay cached in
+    /// the resolver.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///     "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+    ///     "definitions": {"Cat": {"required": ["meows"]}}
+    /// });
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// assert!(compiled.warm_up().is_ok());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of all references that can't be resolved or compiled.
+    pub fn warm_up(&self) -> Result<(), Vec<ValidationError<'static>>> {
+        let errors = self.walk(true, |_, _| ());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Call `visit` with every validator of the compiled tree and its location, walking into the
+    /// targets of the `$ref`s along the way. With `resolve`, targets that are not compiled yet
+    /// are resolved and compiled first, otherwise they are skipped.
+    ///
+    /// Validators within a `$ref` target are located like their errors, by the location of the
+    /// `$ref` followed by their path within the target. The target of each URL is only walked
+    /// once, so recursive schemas are walked finitely. Returns the errors of the references that
+    /// can't be resolved or compiled, once per URL.
+    fn walk<'a>(
+        &'a self,
+        resolve: bool,
+        mut visit: impl FnMut(&JSONPointer, &'a BoxedValidator),
+    ) -> Vec<ValidationError<'static>> {
+        let mut pending = vec![(&self.node, JSONPointer::default())];
+        let mut visited = AHashSet::new();
+        let mut errors = Vec::new();
+        while let Some((node, prefix)) = pending.pop() {
+            for validator in node.validators() {
+                visit(&prefix, validator);
+                match schema_node::downcast_ref::<RefValidator>(validator) {
+                    Some(reference) => {
+                        let target = if resolve {
+                            Some(reference.node())
+                        } else {
+                            reference.compiled_node().map(Ok)
+                        };
+                        match target {
+                            Some(target) if visited.insert(reference.url()) => match target {
+                                Ok(target) => pending.push((
+                                    target,
+                                    prefix.extend_with(reference.schema_path().as_slice()),
+                                )),
+                                Err(error) => errors.push(*error),
+                            },
+                            _ => {}
+                        }
+                    }
+                    None => pending.extend(
+                        validator
+                            .child_nodes()
+                            .into_iter()
+                            .map(|node| (node, prefix.clone())),
+                    ),
+                }
+            }
+        }
+        errors
+    }
+
+    /// Validate only the value at the JSON pointer `pointer` within `instance`, e.g. a single
+    /// field that is being edited.
+    ///
+    /// The value is validated against the subschema that applies to it, found by following
+    /// `properties`, `items`, `allOf`, local `$ref`s and the discriminator mappings selected by
+    /// the tags of `instance` along the way, as the compiled discriminators select them. The
+    /// subschemas are compiled on first use and kept. Error paths are relative to the whole
+    /// instance and schema. Values that no subschema constrains are valid. `None` if there is no
+    /// value at `pointer`.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({"properties": {"name": {"type": "string"}}, "required": ["id"]});
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// let instance = json!({"name": 42});
+    /// let errors: Vec<_> = compiled
+    ///     .validate_partial_at("/name", &instance)
+    ///     .expect("An existing value")
+    ///     .expect_err("Not a string")
+    ///     .map(|error| error.instance_path.to_string())
+    ///     .collect();
+    /// // `id` is not checked
+    /// assert_eq!(errors, vec!["/name"]);
+    /// ```
+    #[must_use]
+    pub fn validate_partial_at<'instance>(
+        &self,
+        pointer: &str,
+        instance: &'instance Value,
+    ) -> Option<Result<(), ErrorIterator<'instance>>> {
+        let tokens = partial::tokens(pointer)?;
+        let value = instance.pointer(pointer)?;
+        let (_, root) = self
+            .resolver
+            .resolve_fragment(self.config.draft(), &self.scope, "#")
+            .ok()?;
+        let (location, _) = match partial::locate(&root, instance, &tokens, self) {
+            Some(located) => located,
+            None => return Some(Ok(())),
+        };
+        let subschema = self.compiled_subschema(&location)?;
+        let errors: Vec<_> = match subschema.validate(value) {
+            Ok(()) => return Some(Ok(())),
+            Err(errors) => errors.map(ValidationError::into_owned).collect(),
+        };
+        let instance_prefix = partial::to_json_pointer(instance, &tokens);
+        let schema_prefix = partial::to_json_pointer(&root, &partial::tokens(&location)?);
+        Some(Err(Box::new(errors.into_iter().map(move |mut error| {
+            error.instance_path = instance_prefix.extend_with(error.instance_path.as_slice());
+            error.schema_path = schema_prefix.extend_with(error.schema_path.as_slice());
+            error
+        }))))
+    }
+
+    /// Validate only the values that differ between `old` and `new`, e.g. to check a `PATCH`
+    /// request against an instance that is known to be valid.
+    ///
+    /// Every changed value of `new` is validated as with [`JSONSchema::validate_partial_at`].
+    /// Objects that lost properties and arrays that changed their length are validated as a
+    /// whole, as are objects whose changed discriminator tag selects a different branch. Keywords
+    /// of the unchanged values around a change, e.g. `maxProperties`, are not checked.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+    ///     "required": ["id"]
+    /// });
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// let old = json!({"name": "Alice", "age": 30});
+    /// let new = json!({"name": "Alice", "age": "thirty"});
+    /// let errors: Vec<_> = compiled
+    ///     .diff_validation(&old, &new)
+    ///     .expect_err("Not an integer")
+    ///     .map(|error| error.instance_path.to_string())
+    ///     .collect();
+    /// // The missing `id` did not change
+    /// assert_eq!(errors, vec!["/age"]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The errors of the changed values of `new`.
+    pub fn diff_validation<'instance>(
+        &self,
+        old: &Value,
+        new: &'instance Value,
+    ) -> Result<(), ErrorIterator<'instance>> {
+        let mut changed = Vec::new();
+        partial::changes(old, new, "", &mut changed);
+        let root = self
+            .resolver
+            .resolve_fragment(self.config.draft(), &self.scope, "#")
+            .ok()
+            .map(|(_, root)| root);
+        let mut pointers: Vec<String> = Vec::new();
+        for pointer in changed {
+            let parent = match pointer.rfind('/') {
+                Some(end) => &pointer[..end],
+                None => "",
+            };
+            // A changed tag may select another branch for the whole object
+            let pointer = match (&root, partial::tokens(parent)) {
+                (Some(root), Some(tokens))
+                    if !pointer.is_empty()
+                        && partial::branch(root, old, &tokens, self)
+                            != partial::branch(root, new, &tokens, self) =>
+                {
+                    parent.to_string()
+                }
+                _ => pointer,
+            };
+            if !pointers.contains(&pointer) {
+                pointers.push(pointer);
+            }
+        }
+        let errors: Vec<_> = pointers
+            .iter()
+            .filter(|pointer| {
+                // Values within another changed value are validated along with it
+                !pointers.iter().any(|other| {
+                    other != *pointer
+                        && pointer.starts_with(other.as_str())
+                        && pointer[other.len()..].starts_with('/')
+                })
+            })
+            .filter_map(|pointer| self.validate_partial_at(pointer, new)?.err())
+            .flatten()
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Box::new(errors.into_iter()))
+        }
+    }
+
+    /// Fill the `default` values declared by the schema into a copy of `instance` and validate
+    /// the copy, returning it if it is valid.
+    ///
+    /// Defaults of missing properties are found through `properties`, `items`, `allOf`, local
+    /// `$ref`s and the discriminator mappings selected by the tags of the copy. A `default` of
+    /// the discriminator property therefore selects the branch of an instance without a tag, and
+    /// the defaults of that branch are filled in as well. Present values are never replaced.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "properties": {"name": {"type": "string"}, "role": {"default": "user"}},
+    ///     "required": ["name", "role"]
+    /// });
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// let filled = compiled
+    ///     .validate_with_defaults(&json!({"name": "Alice"}))
+    ///     .expect("A valid instance");
+    /// assert_eq!(filled, json!({"name": "Alice", "role": "user"}));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// All validation errors of the filled copy.
+    pub fn validate_with_defaults(
+        &self,
+        instance: &Value,
+    ) -> Result<Value, Vec<ValidationError<'static>>> {
+        let mut filled = instance.clone();
+        if let Ok((_, root)) = self
+            .resolver
+            .resolve_fragment(self.config.draft(), &self.scope, "#")
+        {
+            defaults::fill(&root, "", &mut filled, self);
+        }
+        let errors: Vec<_> = self
+            .errors(&filled, &InstancePath::new())
+            .map(ValidationError::into_owned)
+            .collect();
+        if errors.is_empty() {
+            Ok(filled)
+        } else {
+            Err(errors)
+        }
     }
 
     /// Apply the schema and return an `Output`. No actual work is done at this point, the
@@ -117,20 +1382,227 @@ impl JSONSchema {
     /// }));
     /// ```
     #[must_use]
-    pub const fn apply<'a, 'b>(&'a self, instance: &'b Value) -> Output<'a, 'b> {
-        Output::new(self, &self.node, instance)
+    pub const fn apply<'a, 'b>(&'a self, instance: &'b Value) -> Output<'a, 'b> {
+        Output::new(self, &self.node, instance)
+    }
+
+    /// The [`Draft`] which this schema was compiled against
+    #[must_use]
+    pub fn draft(&self) -> Draft {
+        self.config.draft()
+    }
+
+    /// The [`CompilationOptions`] that were used to compile this schema
+    #[must_use]
+    pub fn config(&self) -> Arc<CompilationOptions> {
+        Arc::clone(&self.config)
+    }
+
+    /// The [`CompilationStats`] collected while compiling this schema
+    #[must_use]
+    pub const fn compilation_stats(&self) -> &CompilationStats {
+        &self.stats
+    }
+
+    /// Whether the schema has a `discriminator` next to a `oneOf` or an `anyOf`.
+    ///
+    /// Only the validators compiled so far are checked, nothing is resolved or fetched. `$ref`
+    /// targets are compiled when an instance first reaches them, so a discriminator behind a
+    /// `$ref` is only found afterwards, or after [`JSONSchema::warm_up`].
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///     "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+    ///     "definitions": {"Cat": {}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(compiled.uses_discriminator());
+    /// ```
+    #[must_use]
+    pub fn uses_discriminator(&self) -> bool {
+        let mut found = false;
+        self.walk(false, |_, validator| {
+            found |= discriminator_tags(validator).is_some();
+        });
+        found
+    }
+
+    /// Every reference this schema depends on, resolved against its base URI.
+    ///
+    /// Includes `$ref`s inside `oneOf` branches and discriminator mapping values. Like
+    /// [`JSONSchema::uses_discriminator`], only the validators compiled so far are checked,
+    /// nothing is resolved or fetched. The references within a `$ref` target are included once
+    /// an instance reached the target, or after [`JSONSchema::warm_up`].
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///     "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+    ///     "definitions": {"Cat": {}}
+    /// });
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// assert_eq!(compiled.collect_refs(), vec!["json-schema:///#/definitions/Cat"]);
+    /// ```
+    #[must_use]
+    pub fn collect_refs(&self) -> Vec<String> {
+        let mut references = Vec::new();
+        let mut seen = AHashSet::new();
+        self.walk(false, |_, validator| {
+            if let Some(reference) = schema_node::downcast_ref::<RefValidator>(validator) {
+                if seen.insert(reference.url()) {
+                    references.push(reference.url().to_string());
+                }
+            }
+        });
+        references
     }
 
-    /// The [`Draft`] which this schema was compiled against
+    /// Problems found while compiling this schema that don't make it invalid, e.g. by
+    /// [`CompilationOptions::should_check_discriminator_enum`].
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///     "discriminator": {"propertyName": "petType", "mapping": {"kitten": "#/definitions/Cat"}},
+    ///     "definitions": {"Cat": {"properties": {"petType": {"enum": ["cat"]}}}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .should_check_discriminator_enum(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert_eq!(
+    ///     compiled.diagnostics(),
+    ///     [r##"/discriminator/mapping/kitten: "kitten" is not allowed by the "petType" enum of "#/definitions/Cat""##]
+    /// );
+    /// ```
     #[must_use]
-    pub fn draft(&self) -> Draft {
-        self.config.draft()
+    pub fn diagnostics(&self) -> &[String] {
+        &self.diagnostics
     }
 
-    /// The [`CompilationOptions`] that were used to compile this schema
+    /// The location of every `discriminator` in this schema with the tags it accepts, e.g. to
+    /// generate test data, ordered by location. A fallback mapping entry is not listed.
+    ///
+    /// Discriminators within `$ref` targets are included and located like their errors, by the
+    /// location of the `$ref` followed by their path within the target. The targets are compiled
+    /// like by [`JSONSchema::warm_up`], unresolvable references are skipped.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "properties": {
+    ///         "pet": {
+    ///             "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///             "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}}
+    ///         }
+    ///     },
+    ///     "definitions": {"Cat": {}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// let tags = compiled.required_discriminator_tags();
+    /// assert_eq!(tags[0].0.to_string(), "/properties/pet/discriminator");
+    /// assert_eq!(tags[0].1, vec!["cat"]);
+    /// ```
     #[must_use]
-    pub fn config(&self) -> Arc<CompilationOptions> {
-        Arc::clone(&self.config)
+    pub fn required_discriminator_tags(&self) -> Vec<(JSONPointer, Vec<String>)> {
+        let mut found = Vec::new();
+        self.walk(true, |prefix, validator| {
+            if let Some((location, tags)) = discriminator_tags(validator) {
+                found.push((prefix.extend_with(location.as_slice()), tags));
+            }
+        });
+        found.sort_by_cached_key(|(location, _)| location.to_string());
+        found
+    }
+
+    /// Add the mapping and `oneOf` branches of the discriminator at the root of `other` to the
+    /// one at the root of this schema. This lets separately compiled schemas, e.g. of plugins,
+    /// contribute variants to a shared discriminated type.
+    ///
+    /// Other keywords of `other` are dropped, and its branches keep resolving references
+    /// against its own document. The options of this schema apply to the combined discriminator.
+    /// The compilation stats, references and diagnostics of this schema cover the added branches.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let mut compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .compile(&json!({
+    ///         "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///         "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+    ///         "definitions": {"Cat": {"required": ["meows"]}}
+    ///     }))
+    ///     .expect("A valid schema");
+    /// let plugin = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .compile(&json!({
+    ///         "oneOf": [{"$ref": "#/definitions/Dog"}],
+    ///         "discriminator": {"propertyName": "petType", "mapping": {"dog": "#/definitions/Dog"}},
+    ///         "definitions": {"Dog": {"required": ["barks"]}}
+    ///     }))
+    ///     .expect("A valid schema");
+    /// compiled.merge_discriminator(plugin).expect("No conflicting tags");
+    /// assert!(compiled.is_valid(&json!({"petType": "dog", "barks": true})));
+    /// assert!(!compiled.is_valid(&json!({"petType": "dog", "meows": true})));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails without changes if either schema has no discriminator over `oneOf` at its root, if
+    /// the discriminators read different properties, or if both map the same tag.
+    pub fn merge_discriminator(&mut self, other: JSONSchema) -> Result<(), DiscriminatorError> {
+        let diagnostics = other.diagnostics;
+        // A pre-validate hook wraps the discriminator, the downcasts look through it
+        let other = other
+            .node
+            .into_keyword_validator_as::<DiscriminatorValidator>("discriminator")
+            .ok_or(DiscriminatorError::NotDiscriminated)?;
+        let branch_stats = *other.branch_stats();
+        self.node
+            .keyword_validator_as_mut::<DiscriminatorValidator>("discriminator")
+            .ok_or(DiscriminatorError::NotDiscriminated)?
+            .merge(other)?;
+        self.stats.include(&branch_stats);
+        // Only the diagnostics of the merged discriminator apply, the rest of `other` is dropped
+        self.diagnostics.extend(
+            diagnostics
+                .into_iter()
+                .filter(|diagnostic| diagnostic.starts_with("/discriminator/")),
+        );
+        // Results cached before the merge are stale
+        self.result_cache = ResultCache::for_options(&self.config);
+        Ok(())
+    }
+}
+
+/// The location of the `discriminator` `validator` applies, next to `oneOf` or `anyOf`, with the
+/// tags that select a branch. A fallback mapping entry is not listed.
+fn discriminator_tags(validator: &BoxedValidator) -> Option<(&JSONPointer, Vec<String>)> {
+    if let Some(validator) = schema_node::downcast_ref::<DiscriminatorValidator>(validator) {
+        Some(validator.tags())
+    } else if let Some(validator) =
+        schema_node::downcast_ref::<InheritedDiscriminatorValidator>(validator)
+    {
+        Some(validator.tags())
+    } else {
+        schema_node::downcast_ref::<AnyOfValidator>(validator)
+            .and_then(AnyOfValidator::discriminator_tags)
     }
 }
 
@@ -145,13 +1617,16 @@ pub(crate) fn compile_validators<'a>(
     match schema {
         Value::Bool(value) => match value {
             true => Ok(SchemaNode::new_from_boolean(&context, None)),
-            false => Ok(SchemaNode::new_from_boolean(
-                &context,
-                Some(
-                    keywords::boolean::FalseValidator::compile(relative_path)
-                        .expect("Should always compile"),
-                ),
-            )),
+            false => {
+                context.counters.add_validators(1);
+                Ok(SchemaNode::new_from_boolean(
+                    &context,
+                    Some(
+                        keywords::boolean::FalseValidator::compile(relative_path)
+                            .expect("Should always compile"),
+                    ),
+                ))
+            }
         },
         Value::Object(object) => {
             // In Draft 2019-09 and later, `$ref` can be evaluated alongside other attribute aka
@@ -178,6 +1653,7 @@ pub(crate) fn compile_validators<'a>(
                     .expect("should always return Some")?;
 
                 let validators = vec![("$ref".to_string(), validator)];
+                context.counters.add_validators(1);
                 Ok(SchemaNode::new_from_keywords(
                     &context,
                     validators,
@@ -188,10 +1664,14 @@ pub(crate) fn compile_validators<'a>(
                 let mut unmatched_keywords = AHashMap::new();
                 let mut is_if = false;
                 let mut is_props = false;
+                let mut is_discriminator = false;
                 for (keyword, subschema) in object {
                     if keyword == "if" {
                         is_if = true;
                     }
+                    if keyword == "discriminator" {
+                        is_discriminator = true;
+                    }
                     if keyword == "properties"
                         || keyword == "additionalProperties"
                         || keyword == "patternProperties"
@@ -218,6 +1698,10 @@ pub(crate) fn compile_validators<'a>(
                     unmatched_keywords.remove("patternProperties");
                     unmatched_keywords.remove("properties");
                 }
+                if is_discriminator {
+                    unmatched_keywords.remove("oneOf");
+                }
+                context.counters.add_validators(validators.len());
                 let unmatched_keywords = if unmatched_keywords.is_empty() {
                     None
                 } else {
@@ -244,9 +1728,31 @@ pub(crate) fn compile_validators<'a>(
 #[cfg(test)]
 mod tests {
     use super::JSONSchema;
-    use crate::error::ValidationError;
+    use crate::{
+        error::{BytesValidationError, ValidationError, ValidationErrorKind},
+        keywords::ref_::RefValidator,
+        output::BasicOutput,
+        paths::PathChunk,
+        schema_node::{self, SchemaNode},
+        tests_util,
+        validator::Validate,
+    };
     use serde_json::{from_str, json, Value};
-    use std::{fs::File, io::Read, path::Path};
+    use std::{
+        borrow::Cow,
+        collections::hash_map::DefaultHasher,
+        fs::File,
+        hash::BuildHasher,
+        io::Read,
+        path::Path,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+    use test_case::test_case;
 
     fn load(path: &str, idx: usize) -> Value {
         let path = Path::new(path);
@@ -281,6 +1787,61 @@ mod tests {
         assert!(compiled.validate(&value).is_err());
     }
 
+    #[test]
+    fn warm_up_compiles_references_in_place() {
+        let schema = json!({
+            "properties": {"child": {"$ref": "#/definitions/Node"}},
+            "definitions": {"Node": {"items": {"$ref": "#"}}}
+        });
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        let reference = |node: &SchemaNode| {
+            node.validators()
+                .find_map(schema_node::downcast_ref::<RefValidator>)
+                .map(|reference| reference.child_nodes().len())
+        };
+        let child = compiled
+            .node
+            .keyword_validator("properties")
+            .unwrap()
+            .child_nodes()[0];
+        assert_eq!(reference(child), Some(0));
+        // Terminates on the recursive reference
+        assert!(compiled.warm_up().is_ok());
+        assert_eq!(reference(child), Some(1));
+    }
+
+    #[test]
+    fn warm_up_unmapped_branch() {
+        let schema = json!({
+            "oneOf": [
+                {"$ref": "#/definitions/Cat"},
+                {"properties": {"toy": {"$ref": "#/definitions/Toy"}}}
+            ],
+            "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+            "definitions": {"Cat": {}}
+        });
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        let errors = compiled.warm_up().expect_err("Unresolvable toy");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn validate_ref_cache_bound() {
+        let definitions: serde_json::Map<String, Value> = (0..=super::MAX_COMPILED_REFERENCES)
+            .map(|idx| (idx.to_string(), json!({"type": "integer"})))
+            .collect();
+        let compiled = JSONSchema::compile(&json!({ "definitions": definitions })).unwrap();
+        for idx in 0..=super::MAX_COMPILED_REFERENCES {
+            let reference = format!("#/definitions/{}", idx);
+            assert!(compiled.validate_ref(&reference, &json!(1)).is_ok());
+            assert!(compiled.validate_ref(&reference, &json!("1")).is_err());
+        }
+        assert_eq!(
+            compiled.compiled_references.read().len(),
+            super::MAX_COMPILED_REFERENCES
+        );
+    }
+
     #[test]
     fn wrong_schema_type() {
         let schema = json!([1]);
@@ -288,6 +1849,1047 @@ mod tests {
         assert!(compiled.is_err());
     }
 
+    #[test]
+    fn compilation_stats() {
+        let pet = |tag: &str| {
+            json!({
+                "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                "discriminator": {
+                    "propertyName": tag,
+                    "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                }
+            })
+        };
+        let schema = json!({
+            "properties": {"first": pet("kind"), "second": pet("type"), "third": {"oneOf": [{}]}},
+            "definitions": {"Cat": {"type": "object"}, "Dog": {"type": "object"}}
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let stats = compiled.compilation_stats();
+        assert_eq!(stats.discriminator_sites(), 2);
+        assert_eq!(stats.one_of_sites(), 3);
+        // `properties` + 2 x (`discriminator` + 2 oneOf `$ref`s + 2 mapping `$ref`s) + `oneOf`
+        assert_eq!(stats.validators(), 12);
+    }
+
+    #[test]
+    fn collect_refs() {
+        let schema = json!({
+            "$id": "http://example.com/pets.json",
+            "properties": {
+                "pet": {
+                    "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {
+                            "cat": "#/definitions/Cat",
+                            "dog": "#/definitions/Dog",
+                            "fish": "fish.json"
+                        }
+                    }
+                },
+                "owner": {"$ref": "#/definitions/Owner"}
+            },
+            "definitions": {
+                "Cat": {"type": "object"},
+                "Dog": {"type": "object"},
+                "Owner": {"type": "string"}
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let mut references = compiled.collect_refs();
+        references.sort();
+        assert_eq!(
+            references,
+            vec![
+                "http://example.com/fish.json",
+                "http://example.com/pets.json#/definitions/Cat",
+                "http://example.com/pets.json#/definitions/Dog",
+                "http://example.com/pets.json#/definitions/Owner",
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_refs_within_references() {
+        let schema = json!({
+            "properties": {"pet": {"$ref": "#/definitions/Pet"}},
+            "definitions": {
+                "Pet": {"properties": {"owner": {"$ref": "#/definitions/Owner"}}},
+                "Owner": {"type": "string"}
+            }
+        });
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        assert_eq!(
+            compiled.collect_refs(),
+            vec!["json-schema:///#/definitions/Pet"]
+        );
+        // The nested reference is found once its target is compiled
+        compiled.warm_up().unwrap();
+        assert_eq!(
+            compiled.collect_refs(),
+            vec![
+                "json-schema:///#/definitions/Pet",
+                "json-schema:///#/definitions/Owner"
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_discriminator_with_pre_validate_hook() {
+        fn unchanged(instance: &Value) -> Cow<'_, Value> {
+            Cow::Borrowed(instance)
+        }
+        let mut compiled = tests_util::openapi_options()
+            .with_pre_validate_hook(unchanged)
+            .should_check_discriminator_enum(true)
+            .compile(&tests_util::pets(json!({})))
+            .unwrap();
+        let plugin = tests_util::openapi_options()
+            .should_check_discriminator_enum(true)
+            .compile(&json!({
+                "properties": {"owner": {"$ref": "#/definitions/Owner"}},
+                "oneOf": [{"$ref": "#/definitions/Bird"}],
+                "discriminator": {"propertyName": "petType", "mapping": {"bird": "#/definitions/Bird"}},
+                "definitions": {
+                    "Bird": {"properties": {"petType": {"enum": ["parrot"]}}},
+                    "Owner": {"type": "string"}
+                }
+            }))
+            .unwrap();
+        assert_eq!(plugin.diagnostics().len(), 1);
+        let stats = *compiled.compilation_stats();
+        compiled.merge_discriminator(plugin).unwrap();
+        assert_eq!(
+            compiled.required_discriminator_tags()[0].1,
+            vec!["cat", "dog", "bird"]
+        );
+        let mut references = compiled.collect_refs();
+        references.sort();
+        assert_eq!(
+            references,
+            vec![
+                "json-schema:///#/definitions/Bird",
+                "json-schema:///#/definitions/Cat",
+                "json-schema:///#/definitions/Dog",
+            ]
+        );
+        let merged = compiled.compilation_stats();
+        // The `oneOf` `$ref` and the mapping `$ref` of the plugin
+        assert_eq!(merged.validators(), stats.validators() + 2);
+        assert_eq!(merged.one_of_sites(), stats.one_of_sites());
+        assert_eq!(merged.discriminator_sites(), stats.discriminator_sites());
+        assert_eq!(
+            compiled.diagnostics(),
+            [
+                r##"/discriminator/mapping/bird: "bird" is not allowed by the "petType" enum of "#/definitions/Bird""##
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_with_prefix() {
+        let schema = json!({
+            "properties": {
+                "pet": {
+                    "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                    }
+                }
+            },
+            "definitions": {
+                "Cat": {"properties": {"lives": {"type": "integer"}}},
+                "Dog": {"properties": {"barks": {"type": "boolean"}}}
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let instance = json!({"pet": {"petType": "cat", "lives": "nine"}});
+        let errors: Vec<_> = compiled
+            .validate_with_prefix(&["owners", "0"], &instance)
+            .expect_err("Should be an error")
+            .map(|error| error.instance_path.to_string())
+            .collect();
+        assert_eq!(errors, vec!["/owners/0/pet/lives"]);
+        assert!(compiled
+            .validate_with_prefix(&["owners", "0"], &json!({"pet": {"petType": "dog"}}))
+            .is_ok());
+    }
+
+    #[test]
+    fn validate_and_collect_annotations() {
+        let mut schema = tests_util::pets(json!({}));
+        schema["definitions"]["Cat"]["title"] = json!("Cat");
+        schema["definitions"]["Dog"]["title"] = json!("Dog");
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        for instance in [
+            json!({"petType": "cat", "meows": true}),
+            json!({"petType": "cat", "meows": "loudly"}),
+            json!({"petType": "fish"}),
+        ] {
+            let (errors, annotations) = compiled.validate_and_collect_annotations(&instance);
+            let expected_errors = compiled
+                .validate(&instance)
+                .err()
+                .map(|errors| errors.map(|error| error.to_string()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            assert_eq!(
+                errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                expected_errors
+            );
+            let expected_annotations = match compiled.apply(&instance).basic() {
+                BasicOutput::Valid(annotations) => annotations.into_iter().collect(),
+                BasicOutput::Invalid(_) => Vec::new(),
+            };
+            assert_eq!(annotations, expected_annotations);
+        }
+    }
+
+    #[test_case(2, 2, true)]
+    #[test_case(3, 3, false)]
+    #[test_case(5, 3, false)]
+    #[test_case(0, 0, true)]
+    fn validate_with_limit(max_errors: usize, expected: usize, truncated: bool) {
+        let schema = json!({
+            "items": {
+                "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                "discriminator": {
+                    "propertyName": "petType",
+                    "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                }
+            },
+            "definitions": {
+                "Cat": {"properties": {"lives": {"type": "integer"}}},
+                "Dog": {"properties": {"barks": {"type": "boolean"}}}
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let instance = json!([
+            {"petType": "cat", "lives": "nine"},
+            {"petType": "dog", "barks": "loudly"},
+            {"petType": "cat", "lives": 9},
+            {"petType": "fish"}
+        ]);
+        let errors = compiled
+            .validate_with_limit(&instance, max_errors)
+            .expect_err("Should be an error");
+        assert_eq!(errors.errors.len(), expected);
+        assert_eq!(errors.truncated, truncated);
+        let instance_paths: Vec<_> = errors
+            .errors
+            .iter()
+            .map(|error| error.instance_path.to_string())
+            .collect();
+        assert_eq!(instance_paths, ["/0/lives", "/1/barks", "/3"][..expected]);
+        assert!(compiled
+            .validate_with_limit(&json!([{"petType": "dog"}]), 0)
+            .is_ok());
+    }
+
+    #[test]
+    fn validate_json_str() {
+        let compiled = tests_util::openapi_options()
+            .compile(&tests_util::pets(json!({})))
+            .unwrap();
+        assert!(compiled
+            .validate_json_str(r#"{"petType": "dog", "barks": true}"#)
+            .is_ok());
+        match compiled.validate_json_str("{\n  \"petType\": \"cat\",\n  \"meows\": nope\n}") {
+            Err(BytesValidationError::Parse(error)) => {
+                assert!(error.is_syntax());
+                assert_eq!((error.line(), error.column()), (3, 13));
+            }
+            _ => panic!("Should not parse"),
+        };
+        match compiled.validate_json_str(r#"{"petType": "cat", "meows": "loudly"}"#) {
+            Err(BytesValidationError::Invalid(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].instance_path.to_string(), "/meows");
+                assert_eq!(
+                    errors[0].schema_path.to_string(),
+                    "/discriminator/mapping/cat/properties/meows/type"
+                );
+            }
+            _ => panic!("Should be invalid"),
+        };
+    }
+
+    #[test]
+    fn validate_bytes_with_offsets() {
+        let schema = json!({
+            "properties": {
+                "pet": {
+                    "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                    }
+                }
+            },
+            "definitions": {
+                "Cat": {"properties": {"lives": {"type": "integer"}}},
+                "Dog": {"required": ["barks"]}
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let bytes = br#"{"pet": {"petType": "cat", "lives": "nine"}}"#;
+        assert!(compiled.validate_bytes_with_offsets(bytes).is_err());
+        let errors = match compiled.validate_bytes_with_offsets(bytes) {
+            Err(BytesValidationError::Invalid(errors)) => errors,
+            _ => panic!("Should be invalid"),
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error.instance_path.to_string(), "/pet/lives");
+        assert_eq!(errors[0].offsets, Some((36, 42)));
+        assert_eq!(&bytes[36..42], br#""nine""#);
+        assert_eq!(
+            errors[0].to_string(),
+            r#""nine" is not of type "integer" (bytes 36..42)"#
+        );
+        let bytes = br#"{"pet": {"petType": "dog"}}"#;
+        match compiled.validate_bytes_with_offsets(bytes) {
+            Err(BytesValidationError::Invalid(errors)) => {
+                assert_eq!(errors[0].offsets, Some((8, 26)))
+            }
+            _ => panic!("Should be invalid"),
+        };
+        assert!(compiled
+            .validate_bytes_with_offsets(br#"{"pet": {"petType": "cat"}}"#)
+            .is_ok());
+        assert!(matches!(
+            compiled.validate_bytes_with_offsets(b"{"),
+            Err(BytesValidationError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn result_cache() {
+        let schema = tests_util::pets(json!({}));
+        let compiled = JSONSchema::options()
+            .with_result_cache(16)
+            .compile(&schema)
+            .unwrap();
+        let cat = json!({"petType": "cat", "meows": true});
+        let dog = json!({"petType": "dog"});
+        assert!(compiled.is_valid(&cat));
+        assert!(!compiled.is_valid(&dog));
+        assert_eq!(compiled.result_cache_hits(), 0);
+        assert!(compiled.is_valid(&cat));
+        assert!(compiled.validate(&cat).is_ok());
+        assert!(!compiled.is_valid(&dog));
+        assert_eq!(compiled.result_cache_hits(), 3);
+        // Invalid instances are validated again to report errors
+        assert_eq!(compiled.validate(&dog).expect_err("Not a dog").count(), 1);
+        assert_eq!(compiled.result_cache_hits(), 4);
+        assert!(compiled.is_valid(&json!({"petType": "cat", "meows": false})));
+        assert_eq!(compiled.result_cache_hits(), 4);
+        assert_eq!(JSONSchema::compile(&schema).unwrap().result_cache_hits(), 0);
+    }
+
+    #[test]
+    fn result_cache_hasher() {
+        // Counts the hashers it builds
+        struct Counting(Arc<AtomicUsize>);
+        impl BuildHasher for Counting {
+            type Hasher = DefaultHasher;
+            fn build_hasher(&self) -> DefaultHasher {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                DefaultHasher::new()
+            }
+        }
+        let built = Arc::new(AtomicUsize::new(0));
+        let compiled = JSONSchema::options()
+            .with_result_cache(16)
+            .with_result_cache_hasher(Counting(Arc::clone(&built)))
+            .compile(&json!({"type": "integer"}))
+            .unwrap();
+        assert!(compiled.is_valid(&json!(1)));
+        assert!(compiled.is_valid(&json!(1)));
+        assert_eq!(compiled.result_cache_hits(), 1);
+        // A lookup and an insertion, then a lookup
+        assert_eq!(built.load(Ordering::Relaxed), 3);
+    }
+
+    #[test_case("cat", "/pet/meows", &["/definitions/Cat/properties/meows/type"])]
+    #[test_case("cat", "/pet/owner/name", &["/definitions/Cat/properties/owner/properties/name/type"])]
+    #[test_case("cat", "/pet/owner", &["/definitions/Cat/properties/owner/properties/name/type"])]
+    #[test_case("dog", "/pet/meows", &[])]
+    #[test_case("dog", "/pet/barks", &["/definitions/Dog/properties/barks/type"])]
+    #[test_case("cat", "/pet/petType", &[])]
+    fn validate_partial_at(tag: &str, pointer: &str, expected: &[&str]) {
+        let schema = json!({
+            "properties": {
+                "pet": {
+                    "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                    }
+                }
+            },
+            "required": ["id"],
+            "definitions": {
+                "Cat": {
+                    "properties": {
+                        "meows": {"type": "boolean"},
+                        "owner": {"properties": {"name": {"type": "string"}}}
+                    },
+                    "required": ["meows"]
+                },
+                "Dog": {"properties": {"barks": {"type": "boolean"}}, "required": ["barks"]}
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let instance = json!({
+            "pet": {"petType": tag, "meows": "yes", "barks": "no", "owner": {"name": 1}}
+        });
+        let errors: Vec<_> = match compiled
+            .validate_partial_at(pointer, &instance)
+            .expect("An existing value")
+        {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .map(|error| {
+                    assert!(error.instance_path.to_string().starts_with(pointer));
+                    error.schema_path.to_string()
+                })
+                .collect(),
+        };
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn validate_partial_at_implicit_mapping() {
+        let schema = json!({
+            "properties": {
+                "pet": {
+                    "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                    "discriminator": {"propertyName": "petType"}
+                }
+            },
+            "definitions": {
+                "Cat": {"properties": {"meows": {"type": "boolean"}}},
+                "Dog": {"properties": {"barks": {"type": "boolean"}}}
+            }
+        });
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        let instance = json!({"pet": {"petType": "Cat", "meows": "yes"}});
+        let errors: Vec<_> = compiled
+            .validate_partial_at("/pet/meows", &instance)
+            .expect("An existing value")
+            .expect_err("Not a boolean")
+            .map(|error| error.schema_path.to_string())
+            .collect();
+        assert_eq!(errors, vec!["/definitions/Cat/properties/meows/type"]);
+    }
+
+    #[test]
+    fn validate_partial_at_array_item() {
+        let schema = json!({
+            "properties": {"pets": {"items": {"properties": {"age": {"type": "integer"}}}}}
+        });
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        let instance = json!({"pets": [{"age": 1}, {"age": "two"}]});
+        for _ in 0..2 {
+            let errors: Vec<_> = compiled
+                .validate_partial_at("/pets/1/age", &instance)
+                .expect("An existing value")
+                .expect_err("Not an integer")
+                .collect();
+            assert_eq!(errors.len(), 1);
+            assert!(matches!(
+                errors[0].instance_path.as_slice(),
+                [
+                    PathChunk::Property(_),
+                    PathChunk::Index(1),
+                    PathChunk::Property(_)
+                ]
+            ));
+            assert_eq!(
+                errors[0].schema_path.to_string(),
+                "/properties/pets/items/properties/age/type"
+            );
+            // The subschema is compiled once
+            assert_eq!(compiled.subschemas.read().len(), 1);
+        }
+    }
+
+    #[test]
+    fn validate_partial_at_missing() {
+        let compiled = JSONSchema::compile(&json!({"properties": {"a": {}}})).unwrap();
+        let instance = json!({"a": [1]});
+        assert!(compiled.validate_partial_at("/b", &instance).is_none());
+        assert!(compiled.validate_partial_at("a", &instance).is_none());
+        assert!(matches!(
+            compiled.validate_partial_at("/a/0", &instance),
+            Some(Ok(()))
+        ));
+    }
+
+    #[test_case(&json!({"petType": "cat", "meows": "no", "name": 1}), &[]; "unchanged")]
+    #[test_case(&json!({"petType": "cat", "meows": "yes", "name": 1}), &["/definitions/Cat/properties/meows/type"]; "changed field")]
+    #[test_case(&json!({"petType": "cat", "meows": "no"}), &["/properties/pet/discriminator/mapping/cat/properties/meows/type"]; "removed field")]
+    #[test_case(&json!({"petType": "dog", "meows": "no", "name": 1}), &["/properties/pet/discriminator/mapping/dog/required"]; "changed tag")]
+    fn diff_validation(pet: &Value, expected: &[&str]) {
+        let schema = json!({
+            "properties": {
+                "pet": {
+                    "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                    }
+                }
+            },
+            "required": ["id"],
+            "definitions": {
+                "Cat": {
+                    "properties": {"meows": {"type": "boolean"}, "name": {"type": "string"}},
+                    "required": ["meows"]
+                },
+                "Dog": {
+                    "properties": {"barks": {"type": "boolean"}, "name": {"type": "integer"}},
+                    "required": ["barks"]
+                }
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        // The missing `id` is never reported, the invalid `meows` only along with the whole pet
+        let old = json!({"pet": {"petType": "cat", "meows": "no", "name": 1}});
+        let new = json!({ "pet": pet });
+        let mut errors: Vec<_> = match compiled.diff_validation(&old, &new) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.map(|error| error.schema_path.to_string()).collect(),
+        };
+        errors.sort();
+        assert_eq!(errors, expected);
+        // Repeated diffs reuse the compiled subschemas
+        let compiled_subschemas = compiled.subschemas.read().len();
+        let _ = compiled.diff_validation(&old, &new);
+        assert_eq!(compiled.subschemas.read().len(), compiled_subschemas);
+    }
+
+    #[test_case(&json!({"pets": [{"name": "Tom"}]}), &json!({"pets": [{"name": "Tom", "petType": "dog", "barks": true}]}); "defaulted tag")]
+    #[test_case(&json!({"pets": [{"petType": "cat"}]}), &json!({"pets": [{"petType": "cat", "meows": true}]}); "present tag")]
+    fn validate_with_defaults(instance: &Value, expected: &Value) {
+        let schema = json!({
+            "properties": {
+                "pets": {
+                    "items": {
+                        "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                        "discriminator": {
+                            "propertyName": "petType",
+                            "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                        },
+                        "properties": {"petType": {"default": "dog"}},
+                        "required": ["petType"]
+                    }
+                }
+            },
+            "definitions": {
+                "Cat": {
+                    "properties": {"meows": {"default": true}},
+                    "required": ["meows"]
+                },
+                "Dog": {
+                    "properties": {"barks": {"$ref": "#/definitions/Barks"}},
+                    "required": ["barks"]
+                },
+                "Barks": {"type": "boolean", "default": true}
+            }
+        });
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        // Both branches accept an instance without the tag
+        assert!(!compiled.is_valid(instance));
+        assert_eq!(
+            compiled
+                .validate_with_defaults(instance)
+                .expect("Valid instance"),
+            *expected
+        );
+    }
+
+    #[test]
+    fn validate_with_defaults_invalid() {
+        let schema = json!({"properties": {"name": {"type": "string", "default": 42}}});
+        let compiled = JSONSchema::compile(&schema).expect("Valid schema");
+        let errors = compiled
+            .validate_with_defaults(&json!({}))
+            .expect_err("Invalid default");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].instance_path.to_string(), "/name");
+        // Present values are kept
+        assert_eq!(
+            compiled
+                .validate_with_defaults(&json!({"name": "Alice"}))
+                .expect("Valid instance"),
+            json!({"name": "Alice"})
+        );
+    }
+
+    #[test_case("/discriminator/mapping/cat")]
+    #[test_case("/oneOf/0")]
+    #[test_case("/definitions/Cat")]
+    fn subschema_at(pointer: &str) {
+        let compiled = JSONSchema::compile(&tests_util::pets(json!({}))).unwrap();
+        let cat = compiled.subschema_at(pointer).expect("Existing subschema");
+        // No discriminator is involved, the tag is not needed
+        assert!(cat.is_valid(&json!({"meows": true})));
+        assert!(!cat.is_valid(&json!({"petType": "dog", "barks": true})));
+        let instance = json!({"meows": 1});
+        let errors: Vec<_> = cat.validate(&instance).expect_err("Invalid").collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].instance_path.to_string(), "/meows");
+    }
+
+    #[test_case("/definitions/Fish")]
+    #[test_case("/oneOf/2")]
+    #[test_case("definitions")]
+    fn subschema_at_missing(pointer: &str) {
+        let schema = json!({"oneOf": [{"type": "integer"}], "definitions": {}});
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        assert!(compiled.subschema_at(pointer).is_none());
+    }
+
+    #[test]
+    fn validate_component_ref() {
+        let schema = json!({
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "oneOf": [
+                            {"$ref": "#/components/schemas/Cat"},
+                            {"$ref": "#/components/schemas/Dog"}
+                        ],
+                        "discriminator": {
+                            "propertyName": "petType",
+                            "mapping": {
+                                "cat": "#/components/schemas/Cat",
+                                "dog": "#/components/schemas/Dog"
+                            }
+                        }
+                    },
+                    "Cat": {"properties": {"meows": {"type": "boolean"}}, "required": ["meows"]},
+                    "Dog": {"required": ["barks"]}
+                }
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let pet = "#/components/schemas/Pet";
+        assert!(compiled
+            .validate_ref(pet, &json!({"petType": "cat", "meows": true}))
+            .is_ok());
+        let instance = json!({"petType": "cat", "meows": 1});
+        let errors: Vec<_> = compiled
+            .validate_ref(pet, &instance)
+            .expect_err("Invalid cat")
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].instance_path.to_string(), "/meows");
+        assert!(compiled
+            .validate_ref("#/components/schemas/Dog", &json!({"barks": true}))
+            .is_ok());
+        // Compiled once per reference, however it is spelled
+        assert!(compiled
+            .validate_ref(
+                "json-schema:///#/components/schemas/Dog",
+                &json!({"barks": true})
+            )
+            .is_ok());
+        assert_eq!(compiled.compiled_references.read().len(), 2);
+        assert!(compiled
+            .validate_ref("#/components/schemas/Fish", &json!({}))
+            .is_err());
+    }
+
+    #[test]
+    fn validate_streaming_json() {
+        let compiled = tests_util::openapi_options()
+            .compile(&tests_util::pets(json!({})))
+            .unwrap();
+        let input = br#"[
+            {"petType": "cat", "meows": true},
+            {"petType": "dog", "barks": true},
+            {"petType": "dog", "barks": "loudly"},
+            {"petType": "cat", "meows": false}
+        ]"#;
+        match compiled.validate_streaming_json(&input[..]) {
+            Err(BytesValidationError::Invalid(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].instance_path.to_string(), "/2/barks");
+                assert_eq!(
+                    errors[0].schema_path.to_string(),
+                    "/discriminator/mapping/dog/properties/barks/type"
+                );
+            }
+            _ => panic!("Should be invalid"),
+        }
+        assert!(compiled
+            .validate_streaming_json(&br#"[{"petType": "cat", "meows": true}]"#[..])
+            .is_ok());
+    }
+
+    #[test]
+    fn validate_once() {
+        let schema = json!({
+            "items": {
+                "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+                "discriminator": {
+                    "propertyName": "petType",
+                    "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                }
+            },
+            "definitions": {
+                "Cat": {"properties": {"meows": {"type": "boolean"}}, "required": ["meows"]},
+                "Dog": {"properties": {"barks": {"type": "boolean"}}, "required": ["barks"]}
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let instance = json!([
+            {"petType": "cat", "meows": true},
+            {"petType": "dog", "barks": "loudly"},
+            {"petType": "cat"}
+        ]);
+        let first = compiled.validate(&instance).expect_err("Invalid").next();
+        let error = compiled.validate_once(&instance).expect_err("Invalid");
+        assert_eq!(
+            Some(error.to_string()),
+            first.map(|error| error.to_string())
+        );
+        assert_eq!(error.instance_path.to_string(), "/1/barks");
+        assert!(compiled
+            .validate_once(&json!([{"petType": "dog", "barks": true}]))
+            .is_ok());
+    }
+
+    thread_local! {
+        static FORMAT_CHECKS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+    }
+
+    fn counted_format(_: &str) -> bool {
+        FORMAT_CHECKS.with(|checks| checks.set(checks.get() + 1));
+        false
+    }
+
+    #[test_case(json!({"petType": "cat", "names": []}); "discriminated")]
+    #[test_case(json!({"names": []}); "untagged")]
+    fn validate_once_stops_at_first_error(mut instance: Value) {
+        let schema = json!({
+            "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+            },
+            "definitions": {
+                "Cat": {"properties": {"names": {"items": {"format": "counted"}}}},
+                "Dog": {"required": ["barks"]}
+            }
+        });
+        let compiled = JSONSchema::options()
+            .with_format("counted", counted_format)
+            .should_validate_formats(true)
+            .should_collect_all_one_of_errors(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        instance["names"] = Value::Array(vec![json!("Tom"); 100]);
+        FORMAT_CHECKS.with(|checks| checks.set(0));
+        let error = compiled
+            .validate_once(&instance)
+            .expect_err("Invalid names");
+        assert_eq!(error.instance_path.to_string(), "/names/0");
+        // Every name is checked once if all errors of the branch are collected
+        assert!(FORMAT_CHECKS.with(std::cell::Cell::get) < 10);
+    }
+
+    #[test_case(&json!({"oneOf": [{"$ref": "#/definitions/Cat"}], "discriminator": {"propertyName": "petType"}, "definitions": {"Cat": {}}}), true)]
+    #[test_case(&json!({"properties": {"pet": {"anyOf": [{"$ref": "#/definitions/Cat"}], "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}}}}, "definitions": {"Cat": {}}}), true)]
+    #[test_case(&json!({"oneOf": [{"$ref": "#/definitions/Cat"}], "definitions": {"Cat": {}}}), false)]
+    // Without `oneOf` or `anyOf`, the discriminator is just an annotation
+    #[test_case(&json!({"discriminator": {"propertyName": "petType"}}), false)]
+    fn uses_discriminator(schema: &Value, expected: bool) {
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(schema)
+            .unwrap();
+        assert_eq!(compiled.uses_discriminator(), expected);
+    }
+
+    #[test_case(&json!({"$ref": "#/components/schemas/Pet", "components": {"schemas": {"Pet": {"oneOf": [{"$ref": "#/components/schemas/Cat"}], "discriminator": {"propertyName": "petType"}}, "Cat": {}}}}); "behind a reference")]
+    #[test_case(&json!({"items": {"$ref": "#/definitions/Pets"}, "definitions": {"Pets": {"properties": {"pet": {"anyOf": [{"$ref": "#/definitions/Cat"}], "discriminator": {"propertyName": "petType"}}}}, "Cat": {}}}); "behind a nested reference")]
+    fn uses_discriminator_behind_references(schema: &Value) {
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(schema)
+            .unwrap();
+        // The targets are not compiled yet
+        assert!(!compiled.uses_discriminator());
+        assert!(compiled.warm_up().is_ok());
+        assert!(compiled.uses_discriminator());
+    }
+
+    #[test]
+    fn uses_discriminator_does_not_resolve() {
+        let schema = json!({
+            "oneOf": [{"$ref": "#/definitions/Cat"}],
+            "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+            "definitions": {"Cat": {}},
+            "properties": {"owner": {"$ref": "http://example.invalid/owner.json"}}
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        assert!(compiled.uses_discriminator());
+        assert!(compiled.warm_up().is_err());
+    }
+
+    #[test]
+    fn required_discriminator_tags() {
+        let schema = json!({
+            "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {
+                    "cat": "#/definitions/Cat",
+                    "dog": "#/definitions/Dog",
+                    "*": "#/definitions/Dog"
+                }
+            },
+            "properties": {
+                "toy": {
+                    "anyOf": [{"$ref": "#/definitions/Ball"}, {"$ref": "#/definitions/Bone"}],
+                    "discriminator": {"propertyName": "kind"}
+                }
+            },
+            "definitions": {
+                "Cat": {},
+                "Dog": {},
+                "Ball": {},
+                "Bone": {}
+            }
+        });
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        let tags: Vec<_> = compiled
+            .required_discriminator_tags()
+            .into_iter()
+            .map(|(location, tags)| (location.to_string(), tags))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                (
+                    "/discriminator".to_string(),
+                    // Along with the implicit mapping
+                    vec!["cat", "dog", "Cat", "Dog"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect()
+                ),
+                (
+                    "/properties/toy/discriminator".to_string(),
+                    vec!["Ball".to_string(), "Bone".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn required_discriminator_tags_behind_references() {
+        let schema = json!({
+            "properties": {"pet": {"$ref": "#/components/schemas/Pet"}},
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "oneOf": [{"$ref": "#/components/schemas/Cat"}],
+                        "discriminator": {
+                            "propertyName": "petType",
+                            "mapping": {"cat": "#/components/schemas/Cat"}
+                        }
+                    },
+                    "Cat": {"properties": {"kitten": {"$ref": "#/components/schemas/Pet"}}}
+                }
+            }
+        });
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        let tags: Vec<_> = compiled
+            .required_discriminator_tags()
+            .into_iter()
+            .map(|(location, tags)| (location.to_string(), tags))
+            .collect();
+        // The recursive reference is walked once
+        assert_eq!(
+            tags,
+            vec![(
+                "/properties/pet/discriminator".to_string(),
+                vec!["cat".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn validate_bytes() {
+        let schema = json!({
+            "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+            },
+            "definitions": {
+                "Cat": {"properties": {"lives": {"type": "integer"}}},
+                "Dog": {"properties": {"barks": {"type": "boolean"}}}
+            }
+        });
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        assert!(compiled
+            .validate_bytes(br#"{"petType": "cat", "lives": 9}"#)
+            .is_ok());
+        match compiled.validate_bytes(br#"{"petType": "cat", "lives": "nine"}"#) {
+            Err(BytesValidationError::Invalid(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].instance_path.to_string(), "/lives");
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            compiled.validate_bytes(br#"{"petType": "#),
+            Err(BytesValidationError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn one_of_matching_branches() {
+        let schema = json!({"oneOf": [{"type": "string"}, {"maxLength": 3}, {"minLength": 2}]});
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        assert_eq!(
+            compiled.one_of_matching_branches(&json!("foo")),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(
+            compiled.one_of_matching_branches(&json!(42)),
+            Some(vec![1, 2])
+        );
+        let compiled = JSONSchema::compile(&json!({"type": "string"})).unwrap();
+        assert_eq!(compiled.one_of_matching_branches(&json!("foo")), None);
+    }
+
+    #[test_case(&json!({"petType": "bird", "name": 1}), Some(0))]
+    #[test_case(&json!({"petType": "bird", "barks": true}), Some(1))]
+    #[test_case(&json!({"petType": "bird", "barks": 1, "meows": 1}), Some(0))]
+    fn one_of_best_match(instance: &Value, expected: Option<usize>) {
+        // Both branches yield a single error for the first instance, the lower index wins
+        let compiled = JSONSchema::compile(&tests_util::pets(json!({}))).unwrap();
+        for _ in 0..3 {
+            assert_eq!(compiled.one_of_best_match(instance), expected);
+        }
+        let compiled = JSONSchema::compile(&json!({"type": "string"})).unwrap();
+        assert_eq!(compiled.one_of_best_match(instance), None);
+    }
+
+    #[test]
+    fn validate_with_best_match() {
+        let schema = json!({
+            "oneOf": [
+                {"required": ["a", "b", "c"]},
+                {"required": ["a", "b"]},
+                {"properties": {"a": {"type": "string"}}, "required": ["b"]}
+            ]
+        });
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        let instance = json!({"a": 1});
+        let (branch, errors) = compiled
+            .validate_with_best_match(&instance)
+            .unwrap()
+            .unwrap_err();
+        // One error for the middle branch, two for the others
+        assert_eq!(branch, 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].schema_path.to_string(), "/oneOf/1/required");
+        assert_eq!(errors[0].to_string(), r#""b" is a required property"#);
+        assert!(matches!(
+            compiled.validate_with_best_match(&json!({"a": "x", "b": 1})),
+            Some(Ok(1))
+        ));
+        let compiled = JSONSchema::compile(&json!({"type": "string"})).unwrap();
+        assert!(compiled.validate_with_best_match(&instance).is_none());
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<JSONSchema>();
+
+        let schema = json!({
+            "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+            },
+            "definitions": {
+                "Cat": {"properties": {"lives": {"type": "integer"}}},
+                "Dog": {"properties": {"barks": {"type": "boolean"}}}
+            }
+        });
+        let compiled = Arc::new(tests_util::openapi_options().compile(&schema).unwrap());
+        let handles: Vec<_> = (0..8)
+            .map(|idx| {
+                let compiled = Arc::clone(&compiled);
+                thread::spawn(move || {
+                    let valid = json!({"petType": "cat", "lives": idx});
+                    let invalid = json!({"petType": "dog", "barks": idx});
+                    compiled.is_valid(&valid) && !compiled.is_valid(&invalid)
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().expect("Thread panicked"));
+        }
+    }
+
+    fn slow(_: &str) -> bool {
+        thread::sleep(Duration::from_millis(2));
+        false
+    }
+
+    #[test]
+    fn validate_with_deadline() {
+        let branches: Vec<_> = (0..64).map(|_| json!({"format": "slow"})).collect();
+        let compiled = JSONSchema::options()
+            .with_format("slow", slow)
+            .should_validate_formats(true)
+            .compile(&json!({"oneOf": branches}))
+            .expect("Valid schema");
+        let instance = json!("tortoise");
+        let started = Instant::now();
+        let errors: Vec<_> = compiled
+            .validate_with_deadline(&instance, started + Duration::from_millis(5))
+            .expect_err("Should time out")
+            .collect();
+        assert!(started.elapsed() < Duration::from_millis(128));
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, ValidationErrorKind::Timeout));
+        assert_eq!(
+            errors[0].to_string(),
+            "Validation did not finish before its deadline"
+        );
+        // Enough time for all branches
+        let errors: Vec<_> = compiled
+            .validate_with_deadline(&instance, Instant::now() + Duration::from_secs(60))
+            .expect_err("Invalid instance")
+            .collect();
+        assert!(matches!(errors[0].kind, ValidationErrorKind::OneOfNotValid));
+        // The deadline doesn't outlive the call
+        assert!(!compiled.is_valid(&instance));
+        assert!(!super::deadline::is_expired());
+    }
+
     #[test]
     fn multiple_errors() {
         let schema = json!({"minProperties": 2, "propertyNames": {"minLength": 3}});
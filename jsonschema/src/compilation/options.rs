@@ -1,16 +1,45 @@
 use crate::{
-    compilation::{compile_validators, context::CompilationContext, JSONSchema, DEFAULT_SCOPE},
+    compilation::{
+        cache::ResultCache, compile_validators, context::CompilationContext, CompilationStats,
+        JSONSchema, DEFAULT_SCOPE,
+    },
     content_encoding::{
         ContentEncodingCheckType, ContentEncodingConverterType,
         DEFAULT_CONTENT_ENCODING_CHECKS_AND_CONVERTERS,
     },
     content_media_type::{ContentMediaTypeCheckType, DEFAULT_CONTENT_MEDIA_TYPE_CHECKS},
+    error::ValidationErrors,
+    keywords::discriminator::{discriminator_meta_errors, fold_case, NumericTagFormat},
     resolver::{DefaultResolver, Resolver, SchemaResolver},
-    schemas, ValidationError,
+    schemas,
+    spans::SourceSpans,
+    ValidationError,
 };
 use ahash::AHashMap;
 use once_cell::sync::Lazy;
-use std::{fmt, sync::Arc};
+use std::{
+    borrow::Cow,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::{atomic::AtomicUsize, Arc},
+};
+
+/// Normalizes discriminator tags before they are matched against the mapping.
+pub(crate) type TagNormalizer = Arc<dyn Fn(&str) -> Cow<'_, str> + Send + Sync>;
+
+// Coerces a closure into a `TagNormalizer` with the higher-ranked signature
+fn tag_normalizer(
+    normalizer: impl Fn(&str) -> Cow<'_, str> + Send + Sync + 'static,
+) -> TagNormalizer {
+    Arc::new(normalizer)
+}
+
+/// Builds the hashers of the result cache, see `CompilationOptions::with_result_cache_hasher`.
+pub(crate) type ResultCacheHasher = Arc<dyn Fn() -> Box<dyn Hasher> + Send + Sync>;
+
+/// Transforms instances before `oneOf` branches are selected and validated.
+pub(crate) type PreValidateHook =
+    Arc<dyn for<'a> Fn(&'a serde_json::Value) -> Cow<'a, serde_json::Value> + Send + Sync>;
 
 macro_rules! schema {
     ($name:ident, $path:expr) => {
@@ -161,6 +190,7 @@ static META_SCHEMAS: Lazy<AHashMap<String, Arc<serde_json::Value>>> = Lazy::new(
 });
 
 const EXPECT_MESSAGE: &str = "Invalid meta-schema";
+const DEFAULT_MAX_DISCRIMINATOR_MAPPING_SIZE: usize = 10_000;
 static META_SCHEMA_VALIDATORS: Lazy<AHashMap<schemas::Draft, JSONSchema>> = Lazy::new(|| {
     let mut store = AHashMap::with_capacity(3);
     store.insert(
@@ -271,10 +301,42 @@ pub struct CompilationOptions {
     content_encoding_checks_and_converters:
         AHashMap<&'static str, Option<(ContentEncodingCheckType, ContentEncodingConverterType)>>,
     store: AHashMap<String, Arc<serde_json::Value>>,
-    formats: AHashMap<&'static str, fn(&str) -> bool>,
+    formats: Formats,
+    branch_formats: AHashMap<String, Formats>,
     validate_formats: Option<bool>,
     validate_schema: bool,
     ignore_unknown_formats: bool,
+    max_one_of_branches: Option<usize>,
+    max_discriminator_mapping_size: usize,
+    // The custom normalizer combined with the numeric tag format
+    tag_normalizer: Option<TagNormalizer>,
+    custom_tag_normalizer: Option<TagNormalizer>,
+    numeric_tag_format: Option<NumericTagFormat>,
+    loose_numeric_tags: bool,
+    case_insensitive_tags: bool,
+    discriminator: bool,
+    implicit_discriminator_mapping: bool,
+    partial_discriminated_instances: bool,
+    strict_discriminator: bool,
+    prune_unused_discriminator_branches: bool,
+    lenient_discriminator_mapping: bool,
+    permit_discriminator_property: bool,
+    pass_through_unknown_discriminator_values: bool,
+    reject_null_discriminator_values: bool,
+    warn_about_deprecated_discriminator_branches: bool,
+    check_discriminator_enum: bool,
+    check_discriminator_mapping_types: bool,
+    include_instance_in_errors: bool,
+    compress_error_locations: bool,
+    check_multiple_of_exactly: bool,
+    collect_all_one_of_errors: bool,
+    short_circuit_exclusive_one_of: bool,
+    coerce_types: bool,
+    result_cache_capacity: Option<usize>,
+    result_cache_hasher: Option<ResultCacheHasher>,
+    memoize_discriminated_instances: bool,
+    pre_validate_hook: Option<PreValidateHook>,
+    source_spans: Option<Arc<SourceSpans>>,
 }
 
 impl Default for CompilationOptions {
@@ -287,8 +349,39 @@ impl Default for CompilationOptions {
             content_encoding_checks_and_converters: AHashMap::default(),
             store: AHashMap::default(),
             formats: AHashMap::default(),
+            branch_formats: AHashMap::default(),
             validate_formats: None,
             ignore_unknown_formats: true,
+            max_one_of_branches: None,
+            max_discriminator_mapping_size: DEFAULT_MAX_DISCRIMINATOR_MAPPING_SIZE,
+            tag_normalizer: None,
+            custom_tag_normalizer: None,
+            numeric_tag_format: None,
+            loose_numeric_tags: false,
+            case_insensitive_tags: false,
+            discriminator: false,
+            implicit_discriminator_mapping: false,
+            partial_discriminated_instances: false,
+            strict_discriminator: false,
+            prune_unused_discriminator_branches: false,
+            lenient_discriminator_mapping: false,
+            permit_discriminator_property: true,
+            pass_through_unknown_discriminator_values: false,
+            reject_null_discriminator_values: false,
+            warn_about_deprecated_discriminator_branches: false,
+            check_discriminator_enum: false,
+            check_discriminator_mapping_types: false,
+            include_instance_in_errors: false,
+            compress_error_locations: false,
+            check_multiple_of_exactly: false,
+            collect_all_one_of_errors: false,
+            short_circuit_exclusive_one_of: false,
+            coerce_types: false,
+            result_cache_capacity: None,
+            result_cache_hasher: None,
+            memoize_discriminated_instances: false,
+            pre_validate_hook: None,
+            source_spans: None,
         }
     }
 }
@@ -303,6 +396,8 @@ impl CompilationOptions {
         &self,
         schema: &'a serde_json::Value,
     ) -> Result<JSONSchema, ValidationError<'a>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = std::time::Instant::now();
         // Draft is detected in the following precedence order:
         //   - Explicitly specified;
         //   - $schema field in the document;
@@ -336,7 +431,12 @@ impl CompilationOptions {
             schema_json,
             self.store.clone(),
         )?);
-        let context = CompilationContext::new(scope.into(), Arc::clone(&config), resolver);
+        let context = CompilationContext::new(
+            scope.clone().into(),
+            Arc::clone(&config),
+            Arc::clone(&resolver),
+        )
+        .with_source_spans(config.source_spans.clone());
 
         if self.validate_schema {
             if let Some(mut errors) = META_SCHEMA_VALIDATORS
@@ -351,7 +451,81 @@ impl CompilationOptions {
 
         let node = compile_validators(schema, &context)?;
 
-        Ok(JSONSchema { node, config })
+        #[cfg(not(target_arch = "wasm32"))]
+        let duration = start.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        let duration = std::time::Duration::ZERO;
+        let stats = CompilationStats {
+            validators: context.counters.validators(),
+            one_of_sites: context.counters.one_of_sites(),
+            discriminator_sites: context.counters.discriminator_sites(),
+            duration,
+        };
+
+        Ok(JSONSchema {
+            node,
+            config,
+            stats,
+            diagnostics: context.counters.diagnostics(),
+            result_cache: ResultCache::for_options(self),
+            memo_hits: AtomicUsize::new(0),
+            compiled_references: Default::default(),
+            subschemas: Default::default(),
+            scope,
+            resolver,
+        })
+    }
+
+    /// Validate `schema` against the meta-schema of its draft and every `discriminator` in it
+    /// against the OpenAPI discriminator meta-schema, then compile it.
+    ///
+    /// Unlike [`Self::compile`], all problems of a malformed schema are reported at once, and
+    /// the `instance_path` of each error locates the problem within `schema`.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///     "discriminator": {"propertyName": 42},
+    ///     "definitions": {"Cat": {"type": "object"}}
+    /// });
+    /// let errors = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .compile_with_meta_validation(&schema)
+    ///     .expect_err("Invalid discriminator");
+    /// assert_eq!(errors.errors[0].instance_path.to_string(), "/discriminator/propertyName");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// All meta-schema violations, or the compilation error if there are none.
+    pub fn compile_with_meta_validation(
+        &self,
+        schema: &serde_json::Value,
+    ) -> Result<JSONSchema, ValidationErrors> {
+        let draft = self
+            .draft
+            .or_else(|| schemas::draft_from_schema(schema))
+            .unwrap_or_default();
+        let mut errors: Vec<_> = META_SCHEMA_VALIDATORS
+            .get(&draft)
+            .expect("Existing draft")
+            .validate(schema)
+            .err()
+            .into_iter()
+            .flatten()
+            .map(ValidationError::into_owned)
+            .collect();
+        if self.discriminator {
+            errors.extend(discriminator_meta_errors(schema));
+        }
+        if !errors.is_empty() {
+            return Err(ValidationErrors { errors });
+        }
+        self.compile(schema).map_err(|error| ValidationErrors {
+            errors: vec![error.into_owned()],
+        })
     }
 
     /// Ensure that the schema is going to be compiled using the defined Draft.
@@ -408,11 +582,18 @@ impl CompilationOptions {
     }
 
     /// Use a custom resolver for resolving external schema references.
+    ///
+    /// The resolver serves every external `$ref`, including discriminator mapping values, so
+    /// custom schemes like `db://` can be supported.
     pub fn with_resolver(&mut self, resolver: impl SchemaResolver + 'static) -> &mut Self {
         self.external_resolver = Arc::new(resolver);
         self
     }
 
+    pub(crate) const fn external_resolver(&self) -> &Arc<dyn SchemaResolver> {
+        &self.external_resolver
+    }
+
     /// Ensure that compiled schema is not supporting the provided content media type.
     ///
     /// ```rust
@@ -601,6 +782,49 @@ impl CompilationOptions {
     pub(crate) fn format(&self, format: &str) -> FormatKV<'_> {
         self.formats.get_key_value(format)
     }
+    /// Register a custom "format" validator that is only active within the `oneOf` branch or
+    /// `discriminator` mapping value referencing `reference`, e.g. `#/definitions/Cat`.
+    ///
+    /// The format applies to everything compiled for the branch, including the schemas it
+    /// references, and takes precedence over a format of the same name set by
+    /// [`Self::with_format`].
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// fn cat_name(s: &str) -> bool {
+    ///     s.ends_with("cat")
+    /// }
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///     "definitions": {"Cat": {"properties": {"name": {"format": "name"}}}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .with_branch_format("#/definitions/Cat", "name", cat_name)
+    ///     .compile(&schema)
+    ///     .expect("Valid schema");
+    /// assert!(compiled.is_valid(&json!({"name": "Tomcat"})));
+    /// assert!(!compiled.is_valid(&json!({"name": "Rex"})));
+    /// ```
+    pub fn with_branch_format(
+        &mut self,
+        reference: impl Into<String>,
+        name: &'static str,
+        format: fn(&str) -> bool,
+    ) -> &mut Self {
+        self.branch_formats
+            .entry(reference.into())
+            .or_default()
+            .insert(name, format);
+        self
+    }
+    /// These options with the formats scoped to the branch referencing `reference`, if any.
+    pub(crate) fn for_branch(&self, reference: &str) -> Option<CompilationOptions> {
+        let formats = self.branch_formats.get(reference)?;
+        let mut config = self.clone();
+        config.formats.extend(formats);
+        Some(config)
+    }
     /// Do not perform schema validation during compilation.
     /// This method is only used to disable meta-schema validation for meta-schemas itself to avoid
     /// infinite recursion.
@@ -637,8 +861,726 @@ impl CompilationOptions {
     pub(crate) const fn are_unknown_formats_ignored(&self) -> bool {
         self.ignore_unknown_formats
     }
+
+    /// Limit the number of `oneOf` subschemas.
+    ///
+    /// Schemas with a larger `oneOf` fail to compile, which bounds the storage of every `oneOf`
+    /// and the number of branch outputs collected during `apply`. Useful when validating small,
+    /// fixed-shape documents on memory-constrained targets.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({"oneOf": [{"type": "string"}, {"type": "integer"}, {"type": "null"}]});
+    /// assert!(JSONSchema::options()
+    ///     .with_max_one_of_branches(2)
+    ///     .compile(&schema)
+    ///     .is_err());
+    /// ```
+    pub fn with_max_one_of_branches(&mut self, limit: usize) -> &mut Self {
+        self.max_one_of_branches = Some(limit);
+        self
+    }
+
+    pub(crate) const fn max_one_of_branches(&self) -> Option<usize> {
+        self.max_one_of_branches
+    }
+
+    /// Limit the number of `mapping` entries of a discriminator, and of the subschemas of the
+    /// `oneOf` it applies to. Defaults to 10000.
+    ///
+    /// Schemas that exceed it fail to compile before any branch is compiled, which protects
+    /// against untrusted schemas with enormous mappings.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+    ///     "discriminator": {
+    ///         "propertyName": "petType",
+    ///         "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+    ///     },
+    ///     "definitions": {"Cat": {}, "Dog": {}}
+    /// });
+    /// assert!(JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .with_max_discriminator_mapping_size(1)
+    ///     .compile(&schema)
+    ///     .is_err());
+    /// ```
+    pub fn with_max_discriminator_mapping_size(&mut self, limit: usize) -> &mut Self {
+        self.max_discriminator_mapping_size = limit;
+        self
+    }
+
+    pub(crate) const fn max_discriminator_mapping_size(&self) -> usize {
+        self.max_discriminator_mapping_size
+    }
+
+    /// Normalize discriminator tags before matching them against the `mapping`.
+    ///
+    /// The normalizer is applied to the `mapping` keys during compilation and to the tag found in
+    /// the instance during validation. By default tags are matched as is.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// # use std::borrow::Cow;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///     "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+    ///     "definitions": {"Cat": {"required": ["meows"]}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .with_discriminator_tag_normalizer(|tag| Cow::Borrowed(tag.trim()))
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(compiled.is_valid(&json!({"petType": " cat ", "meows": true})));
+    /// ```
+    pub fn with_discriminator_tag_normalizer(
+        &mut self,
+        normalizer: impl Fn(&str) -> Cow<'_, str> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.custom_tag_normalizer = Some(Arc::new(normalizer));
+        self.combine_tag_normalizers();
+        self
+    }
+
+    /// Write numeric discriminator tags canonically before matching them against the `mapping`,
+    /// so that `1`, `1.0` and `1.00` select the same entry.
+    ///
+    /// Numbers are written as `serde_json` writes them by default, i.e. `1` as `1` and both `1.0`
+    /// and `1.00` as `1.0`, while `mapping` keys are matched as they are spelled. The format is
+    /// applied after the normalizer set with `with_discriminator_tag_normalizer`.
+    ///
+    /// ```rust
+    /// # use jsonschema::{JSONSchema, NumericTagFormat};
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/V1"}],
+    ///     "discriminator": {"propertyName": "version", "mapping": {"1": "#/definitions/V1"}},
+    ///     "definitions": {"V1": {"required": ["name"]}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .with_discriminator_numeric_tags(NumericTagFormat::Integer)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(compiled.is_valid(&json!({"version": 1.0, "name": "v1"})));
+    /// ```
+    pub fn with_discriminator_numeric_tags(&mut self, format: NumericTagFormat) -> &mut Self {
+        self.numeric_tag_format = Some(format);
+        self.combine_tag_normalizers();
+        self
+    }
+
+    /// Set to `true` to match numeric-looking discriminator tags by their value, whether an
+    /// instance has them as numbers or as strings, e.g. as gRPC-gateway writes 64-bit integers.
+    ///
+    /// Tags and `mapping` keys are then written canonically in the format set with
+    /// `with_discriminator_numeric_tags`, or as [`NumericTagFormat::Integer`] if there is none,
+    /// so that `2`, `"2"`, `2.0` and `"2.0"` all select the `mapping` key `2`. By default tags
+    /// are matched as they are spelled, with numbers written as `serde_json` writes them.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/V2"}],
+    ///     "discriminator": {"propertyName": "version", "mapping": {"2": "#/definitions/V2"}},
+    ///     "definitions": {"V2": {"required": ["name"]}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .should_use_loose_numeric_tags(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(compiled.is_valid(&json!({"version": "2.0", "name": "v2"})));
+    /// ```
+    pub fn should_use_loose_numeric_tags(&mut self, loose: bool) -> &mut Self {
+        self.loose_numeric_tags = loose;
+        self.combine_tag_normalizers();
+        self
+    }
+
+    /// Set to `true` to match discriminator tags against the `mapping` regardless of case.
+    ///
+    /// Both `mapping` keys and tags are compared by their Unicode case folding rather than by
+    /// lowercasing, so that e.g. `Straße`, `STRASSE` and `strasse` select the same entry, as do
+    /// `ΟΔΟΣ` and `οδος`. Folding doesn't depend on the locale, so the Turkish dotless `ı` and
+    /// `i` remain distinct. It is applied after the normalizer set with
+    /// `with_discriminator_tag_normalizer` and before the numeric tag format.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Street"}],
+    ///     "discriminator": {"propertyName": "kind", "mapping": {"Straße": "#/definitions/Street"}},
+    ///     "definitions": {"Street": {"required": ["name"]}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .should_match_discriminator_tags_case_insensitively(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(compiled.is_valid(&json!({"kind": "strasse", "name": "Unter den Linden"})));
+    /// ```
+    pub fn should_match_discriminator_tags_case_insensitively(
+        &mut self,
+        case_insensitive: bool,
+    ) -> &mut Self {
+        self.case_insensitive_tags = case_insensitive;
+        self.combine_tag_normalizers();
+        self
+    }
+
+    fn combine_tag_normalizers(&mut self) {
+        let normalizer = self.custom_tag_normalizer.clone();
+        let fold = self.case_insensitive_tags;
+        let format = match self.numeric_tag_format {
+            None if self.loose_numeric_tags => Some(NumericTagFormat::Integer),
+            format => format,
+        };
+        self.tag_normalizer = if !fold && format.is_none() {
+            normalizer
+        } else {
+            Some(tag_normalizer(move |tag| {
+                let mut tag = match &normalizer {
+                    Some(normalize) => normalize(tag),
+                    None => Cow::Borrowed(tag),
+                };
+                if fold {
+                    tag = Cow::Owned(fold_case(&tag));
+                }
+                match format.and_then(|format| format.canonicalize(&tag)) {
+                    Some(canonical) => Cow::Owned(canonical),
+                    None => tag,
+                }
+            }))
+        };
+    }
+
+    pub(crate) const fn tag_normalizer(&self) -> Option<&TagNormalizer> {
+        self.tag_normalizer.as_ref()
+    }
+
+    /// Set to `true` to map `oneOf` branches that are `$ref`s by the last segment of their
+    /// reference, as OpenAPI does for schemas under `#/components/schemas`, in addition to the
+    /// explicit `mapping`.
+    ///
+    /// Without implicit mapping a `discriminator` with an empty or missing `mapping` fails to
+    /// compile, as no tag can select a branch.
+    pub fn should_resolve_implicit_discriminator_mapping(&mut self, resolve: bool) -> &mut Self {
+        self.implicit_discriminator_mapping = resolve;
+        self
+    }
+
+    pub(crate) const fn resolves_implicit_discriminator_mapping(&self) -> bool {
+        self.implicit_discriminator_mapping
+    }
+
+    /// Set to `true` to accept partial objects, e.g. PATCH request bodies, in discriminated
+    /// schemas.
+    ///
+    /// An object without the discriminator property is then valid if it satisfies any mapped
+    /// branch apart from the branch's `required` properties. Tagged objects are validated as
+    /// usual.
+    pub fn should_allow_partial_discriminated_instances(&mut self, allow: bool) -> &mut Self {
+        self.partial_discriminated_instances = allow;
+        self
+    }
+
+    pub(crate) const fn allows_partial_discriminated_instances(&self) -> bool {
+        self.partial_discriminated_instances
+    }
+
+    /// Set to `true` to validate the OpenAPI `discriminator` keyword.
+    ///
+    /// It is not part of JSON Schema, so by default it is kept as an annotation and a
+    /// `discriminator` next to `oneOf` or `anyOf` does not change how the subschemas apply.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "oneOf": [{"required": ["meows"]}, {"required": ["barks"]}],
+    ///     "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/oneOf/0"}}
+    /// });
+    /// let instance = json!({"petType": "dog", "meows": true});
+    /// assert!(JSONSchema::compile(&schema).expect("A valid schema").is_valid(&instance));
+    /// let compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(!compiled.is_valid(&instance));
+    /// ```
+    pub fn should_use_discriminator(&mut self, use_discriminator: bool) -> &mut Self {
+        self.discriminator = use_discriminator;
+        self
+    }
+
+    pub(crate) const fn uses_discriminator(&self) -> bool {
+        self.discriminator
+    }
+
+    /// Set to `true` to reject instances of discriminated schemas that have no usable tag.
+    ///
+    /// By default such instances are validated against all `oneOf` subschemas instead. Mapping
+    /// references to missing locations of the same document also fail the compilation, instead
+    /// of failing on first use.
+    pub fn should_use_strict_discriminator(&mut self, strict: bool) -> &mut Self {
+        self.strict_discriminator = strict;
+        self
+    }
+
+    pub(crate) const fn uses_strict_discriminator(&self) -> bool {
+        self.strict_discriminator
+    }
+
+    /// Set to `false` to reject the discriminator property in discriminated branches with
+    /// `additionalProperties: false` that don't list it under `properties`.
+    ///
+    /// By default the property is implicitly permitted in the branch selected by its tag, as
+    /// OpenAPI documents commonly omit it from the branches.
+    pub fn should_permit_discriminator_property(&mut self, permit: bool) -> &mut Self {
+        self.permit_discriminator_property = permit;
+        self
+    }
+
+    pub(crate) const fn permits_discriminator_property(&self) -> bool {
+        self.permit_discriminator_property
+    }
+
+    /// Set to `true` to validate instances with a tag missing from the `discriminator` mapping
+    /// against all `oneOf` subschemas, as if they had no tag.
+    ///
+    /// The unknown tag is reported as a warning by [`JSONSchema::validate_with_warnings`]. By
+    /// default such instances are not valid, even if they are valid against some subschema, as
+    /// the tag shows which variant was intended. A `"*"` fallback mapping entry still takes
+    /// precedence.
+    pub fn should_pass_through_unknown_discriminator_values(
+        &mut self,
+        pass_through: bool,
+    ) -> &mut Self {
+        self.pass_through_unknown_discriminator_values = pass_through;
+        self
+    }
+
+    pub(crate) const fn passes_through_unknown_discriminator_values(&self) -> bool {
+        self.pass_through_unknown_discriminator_values
+    }
+
+    /// Set to `true` to reject instances whose discriminator property is `null`, unless the
+    /// `discriminator` mapping has a `"null"` key, which then selects the branch.
+    ///
+    /// By default a `null` tag is treated like a missing one, i.e. the instance is validated
+    /// against all `oneOf` subschemas, or rejected by
+    /// [`should_use_strict_discriminator`](Self::should_use_strict_discriminator). Only the first
+    /// property of a composite key is checked for `null`.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .should_reject_null_discriminator_values(true)
+    ///     .compile(&json!({
+    ///         "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///         "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+    ///         "definitions": {"Cat": {"properties": {"meows": {"type": "boolean"}}}}
+    ///     }))
+    ///     .expect("A valid schema");
+    /// let instance = json!({"petType": null, "meows": true});
+    /// let error = compiled
+    ///     .validate(&instance)
+    ///     .expect_err("A null tag")
+    ///     .next()
+    ///     .expect("An error");
+    /// assert_eq!(error.to_string(), r#"The "petType" discriminator must not be null"#);
+    /// ```
+    pub fn should_reject_null_discriminator_values(&mut self, reject: bool) -> &mut Self {
+        self.reject_null_discriminator_values = reject;
+        self
+    }
+
+    pub(crate) const fn rejects_null_discriminator_values(&self) -> bool {
+        self.reject_null_discriminator_values
+    }
+
+    /// Set to `true` to report a warning by [`JSONSchema::validate_with_warnings`] when a tag
+    /// selects a `discriminator` branch marked with `deprecated: true`.
+    pub fn should_warn_about_deprecated_discriminator_branches(&mut self, warn: bool) -> &mut Self {
+        self.warn_about_deprecated_discriminator_branches = warn;
+        self
+    }
+
+    pub(crate) const fn warns_about_deprecated_discriminator_branches(&self) -> bool {
+        self.warn_about_deprecated_discriminator_branches
+    }
+
+    /// Whether validation may report warnings along with errors.
+    pub(crate) const fn reports_warnings(&self) -> bool {
+        self.discriminator
+            && (self.pass_through_unknown_discriminator_values
+                || self.warn_about_deprecated_discriminator_branches)
+    }
+
+    /// Set to `true` to check that every `discriminator` mapping key is allowed by the `enum` of
+    /// the discriminator property in the mapped schema.
+    ///
+    /// Such a tag can never select a valid branch. Mismatches don't fail the compilation, they
+    /// are reported by [`JSONSchema::diagnostics`] instead.
+    pub fn should_check_discriminator_enum(&mut self, check: bool) -> &mut Self {
+        self.check_discriminator_enum = check;
+        self
+    }
+
+    pub(crate) const fn checks_discriminator_enum(&self) -> bool {
+        self.check_discriminator_enum
+    }
+
+    /// Set to `true` to check that every schema in a `discriminator` mapping is an object schema,
+    /// i.e. its `type` is `object`, includes `object`, or is not constrained at all.
+    ///
+    /// Such a schema can never match an instance that carries a tag property. Mismatches are
+    /// reported by [`JSONSchema::diagnostics`] like the ones found by
+    /// [`CompilationOptions::should_check_discriminator_enum`].
+    pub fn should_check_discriminator_mapping_types(&mut self, check: bool) -> &mut Self {
+        self.check_discriminator_mapping_types = check;
+        self
+    }
+
+    pub(crate) const fn checks_discriminator_mapping_types(&self) -> bool {
+        self.check_discriminator_mapping_types
+    }
+
+    /// Set to `true` to include a copy of the invalid value in every error of the "basic" output,
+    /// e.g. for logging. Values longer than 1024 bytes as JSON are cut to a string of that size.
+    ///
+    /// Disabled by default, as instances may contain personal data.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let compiled = JSONSchema::options()
+    ///     .should_include_instance_in_errors(true)
+    ///     .compile(&json!({"properties": {"age": {"type": "integer"}}}))
+    ///     .expect("A valid schema");
+    /// let output = serde_json::to_value(compiled.apply(&json!({"age": "old"})).basic())
+    ///     .expect("Serializable output");
+    /// assert_eq!(output["errors"][0]["instance"], json!("old"));
+    /// ```
+    pub fn should_include_instance_in_errors(&mut self, include: bool) -> &mut Self {
+        self.include_instance_in_errors = include;
+        self
+    }
+
+    pub(crate) const fn includes_instance_in_errors(&self) -> bool {
+        self.include_instance_in_errors
+    }
+
+    /// Set to `true` to serialize the locations of errors in the "basic" output in the shorter
+    /// form of [`JSONPointer::compress`](crate::paths::JSONPointer::compress), which writes
+    /// repeated chunks once, e.g. for deeply nested recursive schemas. The locations returned by
+    /// [`OutputUnit`](crate::output::OutputUnit) are not changed.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let compiled = JSONSchema::options()
+    ///     .should_compress_error_locations(true)
+    ///     .compile(&json!({"items": {"items": {"items": {"type": "integer"}}}}))
+    ///     .expect("A valid schema");
+    /// let output = serde_json::to_value(compiled.apply(&json!([[["a"]]])).basic())
+    ///     .expect("Serializable output");
+    /// assert_eq!(output["errors"][0]["keywordLocation"], json!("/items{3}/type"));
+    /// assert_eq!(output["errors"][0]["instanceLocation"], json!("/0{3}"));
+    /// ```
+    pub fn should_compress_error_locations(&mut self, compress: bool) -> &mut Self {
+        self.compress_error_locations = compress;
+        self
+    }
+
+    pub(crate) const fn compresses_error_locations(&self) -> bool {
+        self.compress_error_locations
+    }
+
+    /// Set to `true` to check `multipleOf` with exact arithmetic instead of `f64`.
+    ///
+    /// Integers beyond 2^53, e.g. in a branch selected by a discriminator, are otherwise rounded
+    /// before the check, which may accept or reject them wrongly. The option applies to every
+    /// `multipleOf` of the schema, not only to those within discriminated branches.
+    ///
+    /// Integers are compared exactly as long as `serde_json` stores them as `i64` or `u64`, and
+    /// other numbers by the exact value of their `f64` representation. Integers beyond the `u64`
+    /// range are still rounded, even with the `arbitrary_precision` feature of `serde_json`.
+    /// Disabled by default, as it is slower for fractional divisors.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({"multipleOf": 2});
+    /// let odd = json!(9_007_199_254_740_993_u64);
+    /// assert!(JSONSchema::compile(&schema).expect("A valid schema").is_valid(&odd));
+    /// let compiled = JSONSchema::options()
+    ///     .should_check_multiple_of_exactly(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(!compiled.is_valid(&odd));
+    /// ```
+    pub fn should_check_multiple_of_exactly(&mut self, exact: bool) -> &mut Self {
+        self.check_multiple_of_exactly = exact;
+        self
+    }
+
+    pub(crate) const fn checks_multiple_of_exactly(&self) -> bool {
+        self.check_multiple_of_exactly
+    }
+
+    /// Set to `true` to report the errors of every `oneOf` subschema if none of them matches,
+    /// e.g. to show all problems in a form.
+    ///
+    /// By default a single error reports that no subschema matched.
+    pub fn should_collect_all_one_of_errors(&mut self, collect: bool) -> &mut Self {
+        self.collect_all_one_of_errors = collect;
+        self
+    }
+
+    pub(crate) const fn collects_all_one_of_errors(&self) -> bool {
+        self.collect_all_one_of_errors
+    }
+
+    /// Set to `true` to stop evaluating a `oneOf` at the first matching subschema if its
+    /// subschemas are mutually exclusive, i.e. they either declare disjoint `type`s or all require
+    /// a property with distinct `const` values. Such a `oneOf` behaves like an `anyOf`, and checking
+    /// that no other subschema matches is redundant.
+    ///
+    /// The results are the same either way.
+    pub fn should_short_circuit_exclusive_one_of(&mut self, short_circuit: bool) -> &mut Self {
+        self.short_circuit_exclusive_one_of = short_circuit;
+        self
+    }
+
+    pub(crate) const fn short_circuits_exclusive_one_of(&self) -> bool {
+        self.short_circuit_exclusive_one_of
+    }
+
+    /// Set to `true` to coerce strings to the types expected by `oneOf` and `discriminator`
+    /// branches, e.g. for query string or form data where every value is a string.
+    ///
+    /// If a branch rejects an instance, strings within it that the branch expects to be of
+    /// another type are replaced by the integer, number, boolean or `null` they spell out, and the
+    /// branch is validated again. The other keywords of the branch, e.g. `minimum`, then apply to
+    /// the coerced values. The instance itself is not modified.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({"oneOf": [{"type": "integer", "minimum": 10}, {"type": "boolean"}]});
+    /// let compiled = JSONSchema::options()
+    ///     .should_coerce_types(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(compiled.is_valid(&json!("42")));
+    /// assert!(compiled.is_valid(&json!("true")));
+    /// assert!(!compiled.is_valid(&json!("5")));
+    /// ```
+    pub fn should_coerce_types(&mut self, coerce: bool) -> &mut Self {
+        self.coerce_types = coerce;
+        self
+    }
+
+    pub(crate) const fn coerces_types(&self) -> bool {
+        self.coerce_types
+    }
+
+    /// Set to `true` to skip compiling `oneOf` subschemas that are not referenced from the
+    /// `discriminator` mapping. Such subschemas are unreachable only with a strict
+    /// discriminator (see [`Self::should_use_strict_discriminator`]), otherwise this has no effect.
+    pub fn should_prune_unused_discriminator_branches(&mut self, prune: bool) -> &mut Self {
+        self.prune_unused_discriminator_branches = prune;
+        self
+    }
+
+    pub(crate) const fn prunes_unused_discriminator_branches(&self) -> bool {
+        self.prune_unused_discriminator_branches
+    }
+
+    /// Set to `true` to accept arrays of references as `discriminator` mapping values.
+    ///
+    /// Such a tag selects a union of the listed schemas, i.e. the instance has to be valid
+    /// against any of them. By default mapping values have to be strings.
+    pub fn should_use_lenient_discriminator_mapping(&mut self, lenient: bool) -> &mut Self {
+        self.lenient_discriminator_mapping = lenient;
+        self
+    }
+
+    pub(crate) const fn uses_lenient_discriminator_mapping(&self) -> bool {
+        self.lenient_discriminator_mapping
+    }
+
+    /// Cache the results of up to `capacity` distinct instances in the compiled schema.
+    ///
+    /// Repeated validation of an identical instance then skips the validators, which pays off
+    /// for expensive schemas such as large discriminated `oneOf`s. The least recently used result
+    /// is evicted when the cache is full. Cached instances are compared by value, and
+    /// `validate` only uses the cache to answer for valid instances, as errors are not stored.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let compiled = JSONSchema::options()
+    ///     .with_result_cache(128)
+    ///     .compile(&json!({"type": "integer"}))
+    ///     .expect("A valid schema");
+    /// assert!(compiled.is_valid(&json!(1)));
+    /// assert!(compiled.is_valid(&json!(1)));
+    /// assert_eq!(compiled.result_cache_hits(), 1);
+    /// ```
+    pub fn with_result_cache(&mut self, capacity: usize) -> &mut Self {
+        self.result_cache_capacity = Some(capacity);
+        self
+    }
+    pub(crate) const fn result_cache_capacity(&self) -> Option<usize> {
+        self.result_cache_capacity
+    }
+
+    /// Hash the instances of the result cache with hashers built by `hash_builder`. Instances are
+    /// hashed with `ahash` by default, which is fast but not keyed with a secret. A keyed hasher
+    /// such as the one of `std`'s `HashMap` makes cache collisions harder to provoke with
+    /// untrusted instances, a collision only costs a cache miss though.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let compiled = JSONSchema::options()
+    ///     .with_result_cache(128)
+    ///     .with_result_cache_hasher(RandomState::new())
+    ///     .compile(&json!({"type": "integer"}))
+    ///     .expect("A valid schema");
+    /// assert!(compiled.is_valid(&json!(1)));
+    /// assert!(compiled.is_valid(&json!(1)));
+    /// assert_eq!(compiled.result_cache_hits(), 1);
+    /// ```
+    pub fn with_result_cache_hasher<S>(&mut self, hash_builder: S) -> &mut Self
+    where
+        S: BuildHasher + Send + Sync + 'static,
+        S::Hasher: 'static,
+    {
+        self.result_cache_hasher = Some(Arc::new(move || Box::new(hash_builder.build_hasher())));
+        self
+    }
+    pub(crate) const fn result_cache_hasher(&self) -> Option<&ResultCacheHasher> {
+        self.result_cache_hasher.as_ref()
+    }
+
+    /// Set to `true` to validate equal sub-instances against the same discriminated `oneOf`
+    /// only once per [`JSONSchema::is_valid`] or [`JSONSchema::validate`] call, e.g. for large
+    /// arrays of identical tagged objects.
+    ///
+    /// Results are kept until the call returns, along with a copy of every distinct
+    /// sub-instance. Errors of invalid instances are always collected without the memo.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::json;
+    /// let schema = json!({
+    ///     "items": {
+    ///         "oneOf": [{"$ref": "#/definitions/Cat"}],
+    ///         "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}}
+    ///     },
+    ///     "definitions": {"Cat": {"required": ["meows"]}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .should_memoize_discriminated_instances(true)
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// let cat = json!({"petType": "cat", "meows": true});
+    /// assert!(compiled.is_valid(&json!([cat, cat, cat])));
+    /// assert_eq!(compiled.memo_hits(), 2);
+    /// ```
+    pub fn should_memoize_discriminated_instances(&mut self, memoize: bool) -> &mut Self {
+        self.memoize_discriminated_instances = memoize;
+        self
+    }
+
+    pub(crate) const fn memoizes_discriminated_instances(&self) -> bool {
+        self.memoize_discriminated_instances
+    }
+
+    /// Transform instances before a `oneOf` or a discriminator selects and validates a branch.
+    ///
+    /// Useful to normalize instances, e.g. to fill in a default discriminator tag. Return
+    /// `Cow::Borrowed` to validate the instance as is. Errors reported for a transformed instance
+    /// refer to the transformed value.
+    ///
+    /// ```rust
+    /// # use jsonschema::JSONSchema;
+    /// # use serde_json::{json, Value};
+    /// # use std::borrow::Cow;
+    /// let schema = json!({
+    ///     "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+    ///     "discriminator": {
+    ///         "propertyName": "petType",
+    ///         "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+    ///     },
+    ///     "definitions": {"Cat": {"required": ["meows"]}, "Dog": {"required": ["barks"]}}
+    /// });
+    /// let compiled = JSONSchema::options()
+    ///     .with_pre_validate_hook(|instance| match instance {
+    ///         Value::Object(object) if !object.contains_key("petType") => {
+    ///             let mut object = object.clone();
+    ///             object.insert("petType".to_string(), json!("cat"));
+    ///             Cow::Owned(Value::Object(object))
+    ///         }
+    ///         _ => Cow::Borrowed(instance),
+    ///     })
+    ///     .compile(&schema)
+    ///     .expect("A valid schema");
+    /// assert!(compiled.is_valid(&json!({"meows": true})));
+    /// ```
+    pub fn with_pre_validate_hook(
+        &mut self,
+        hook: impl for<'a> Fn(&'a serde_json::Value) -> Cow<'a, serde_json::Value>
+            + Send
+            + Sync
+            + 'static,
+    ) -> &mut Self {
+        self.pre_validate_hook = Some(Arc::new(hook));
+        self
+    }
+
+    pub(crate) const fn pre_validate_hook(&self) -> Option<&PreValidateHook> {
+        self.pre_validate_hook.as_ref()
+    }
+
+    /// Use `spans` to locate the invalid values in errors of the `discriminator` and `oneOf`
+    /// keywords, see [`ValidationError::source_span`](crate::ValidationError::source_span).
+    /// Values reached through `$ref` are compiled separately and are not located.
+    ///
+    /// ```rust
+    /// # use jsonschema::{spans::{SourceSpan, SourceSpans}, JSONSchema};
+    /// # use serde_json::json;
+    /// let schema = json!({"oneOf": [{}], "discriminator": {"propertyName": 1}});
+    /// let mut spans = SourceSpans::default();
+    /// spans.insert("/discriminator/propertyName", SourceSpan { line: 3, column: 3 });
+    /// let error = JSONSchema::options()
+    ///     .should_use_discriminator(true)
+    ///     .with_source_spans(spans)
+    ///     .compile(&schema)
+    ///     .expect_err("Invalid discriminator");
+    /// assert_eq!(error.source_span, Some(SourceSpan { line: 3, column: 3 }));
+    /// ```
+    pub fn with_source_spans(&mut self, spans: SourceSpans) -> &mut Self {
+        self.source_spans = Some(Arc::new(spans));
+        self
+    }
 }
 // format name & a pointer to a check function
+type Formats = AHashMap<&'static str, fn(&str) -> bool>;
 type FormatKV<'a> = Option<(&'a &'static str, &'a fn(&str) -> bool)>;
 
 impl fmt::Debug for CompilationOptions {
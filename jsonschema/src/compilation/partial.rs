@@ -0,0 +1,219 @@
+//! Locating the subschema that applies to a single value within an instance.
+use crate::{
+    paths::{JSONPointer, PathChunk},
+    JSONSchema,
+};
+use serde_json::{Map, Value};
+
+/// How many `$ref`s, `allOf` items and discriminator mappings are followed at a single location.
+pub(crate) const MAX_DEPTH: usize = 16;
+
+/// The JSON pointer into `root` of the subschema that applies to the value at `tokens` within
+/// `instance`, along with that value.
+///
+/// Locations are followed through `properties`, `additionalProperties`, `items`, `prefixItems`,
+/// `additionalItems`, `allOf`, local `$ref`s and the discriminator mappings selected by the tags
+/// found along the way. `None` if the value doesn't exist or no subschema constrains it.
+pub(crate) fn locate<'i>(
+    root: &Value,
+    instance: &'i Value,
+    tokens: &[String],
+    compiled: &JSONSchema,
+) -> Option<(String, &'i Value)> {
+    let mut location = String::new();
+    let mut instance = instance;
+    for token in tokens {
+        let (child_location, child) =
+            child_location(root, &location, instance, token, compiled, 0)?;
+        location = child_location;
+        instance = child;
+    }
+    Some((location, instance))
+}
+
+/// The location of the subschema that applies to the value at `tokens` within `instance`, after
+/// following the discriminator mappings selected by the tags of that value.
+pub(crate) fn branch(
+    root: &Value,
+    instance: &Value,
+    tokens: &[String],
+    compiled: &JSONSchema,
+) -> Option<String> {
+    let (location, value) = locate(root, instance, tokens, compiled)?;
+    Some(discriminated(root, location, value, compiled))
+}
+
+/// Push the JSON pointers of the values of `new` at `pointer` that differ from `old`.
+///
+/// Objects and arrays of the same length are compared item by item, while an object that lost
+/// properties or an array that changed its length is reported as a whole.
+pub(crate) fn changes(old: &Value, new: &Value, pointer: &str, changed: &mut Vec<String>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Object(old_object), Value::Object(new_object))
+            if old_object.keys().all(|key| new_object.contains_key(key)) =>
+        {
+            for (key, value) in new_object {
+                let child = format!("{}/{}", pointer, escape(key));
+                match old_object.get(key) {
+                    Some(old_value) => changes(old_value, value, &child, changed),
+                    None => changed.push(child),
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items))
+            if old_items.len() == new_items.len() =>
+        {
+            for (idx, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate() {
+                changes(old_item, new_item, &format!("{}/{}", pointer, idx), changed);
+            }
+        }
+        _ => changed.push(pointer.to_string()),
+    }
+}
+
+/// The location of the subschema for the `token` item of `instance`, along with that item.
+fn child_location<'i>(
+    root: &Value,
+    location: &str,
+    instance: &'i Value,
+    token: &str,
+    compiled: &JSONSchema,
+    depth: usize,
+) -> Option<(String, &'i Value)> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let location = discriminated(root, location.to_string(), instance, compiled);
+    let schema = root.pointer(&location)?.as_object()?;
+    let own = match instance {
+        Value::Object(object) => {
+            let child = object.get(token)?;
+            schema
+                .get("properties")
+                .and_then(|properties| properties.get(token))
+                .map(|_| (format!("{}/properties/{}", location, escape(token)), child))
+        }
+        Value::Array(items) => {
+            let idx = token.parse::<usize>().ok()?;
+            let child = items.get(idx)?;
+            item_location(schema, &location, idx).map(|location| (location, child))
+        }
+        _ => return None,
+    };
+    if own.is_some() {
+        return own;
+    }
+    // Subschemas that apply to the same value
+    let reference = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(local_pointer);
+    let all_of = schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .map_or(0, Vec::len);
+    let found = reference
+        .into_iter()
+        .chain((0..all_of).map(|idx| format!("{}/allOf/{}", location, idx)))
+        .find_map(|location| child_location(root, &location, instance, token, compiled, depth + 1));
+    if found.is_some() {
+        return found;
+    }
+    match instance {
+        Value::Object(object) => schema
+            .get("additionalProperties")
+            .map(|_| (format!("{}/additionalProperties", location), &object[token])),
+        _ => None,
+    }
+}
+
+/// The location of the subschema for the array item at `idx`, if declared by `schema` itself.
+pub(crate) fn item_location(
+    schema: &Map<String, Value>,
+    location: &str,
+    idx: usize,
+) -> Option<String> {
+    match (schema.get("prefixItems"), schema.get("items")) {
+        (Some(Value::Array(prefix)), _) if idx < prefix.len() => {
+            Some(format!("{}/prefixItems/{}", location, idx))
+        }
+        (None, Some(Value::Array(items))) if idx < items.len() => {
+            Some(format!("{}/items/{}", location, idx))
+        }
+        (None, Some(Value::Array(_))) if schema.contains_key("additionalItems") => {
+            Some(format!("{}/additionalItems", location))
+        }
+        (Some(Value::Array(_)), Some(_)) | (None, Some(Value::Object(_) | Value::Bool(_))) => {
+            Some(format!("{}/items", location))
+        }
+        _ => None,
+    }
+}
+
+/// Follow the discriminator mappings selected by the tags of `instance` from `location`.
+fn discriminated(
+    root: &Value,
+    mut location: String,
+    instance: &Value,
+    compiled: &JSONSchema,
+) -> String {
+    for _ in 0..MAX_DEPTH {
+        match compiled.mapped_location(root, &location, instance) {
+            Some(mapped) => location = mapped,
+            None => break,
+        }
+    }
+    location
+}
+
+/// The JSON pointer of a reference within the same document, e.g. `#/definitions/Cat`.
+pub(crate) fn local_pointer(reference: &str) -> Option<String> {
+    let fragment = reference.strip_prefix('#')?;
+    percent_encoding::percent_decode_str(fragment)
+        .decode_utf8()
+        .ok()
+        .map(|pointer| pointer.into_owned())
+}
+
+/// The unescaped reference tokens of a JSON pointer, `None` if it is neither empty nor starts with
+/// `/`.
+pub(crate) fn tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    Some(
+        pointer
+            .strip_prefix('/')?
+            .split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+/// The pointer to the value at `tokens` within `document`, with array items as indices.
+pub(crate) fn to_json_pointer(document: &Value, tokens: &[String]) -> JSONPointer {
+    let mut value = Some(document);
+    let chunks: Vec<_> = tokens
+        .iter()
+        .map(|token| {
+            let chunk = match (value, token.parse::<usize>()) {
+                (Some(Value::Array(_)), Ok(idx)) => PathChunk::Index(idx),
+                _ => PathChunk::Property(token.as_str().into()),
+            };
+            value = value.and_then(|value| match value {
+                Value::Object(object) => object.get(token),
+                Value::Array(items) => items.get(token.parse::<usize>().ok()?),
+                _ => None,
+            });
+            chunk
+        })
+        .collect();
+    JSONPointer::from(chunks.as_slice())
+}
+
+pub(crate) fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,60 @@
+//! Validation of the elements of a top-level JSON array while it is being parsed.
+use crate::{paths::InstancePath, schema_node::SchemaNode, validator::Validate, ValidationError};
+use serde::de::{SeqAccess, Visitor};
+use serde_json::Value;
+use std::fmt;
+
+/// Validates every array element as soon as it is parsed, so only a single element is kept in
+/// memory at a time.
+pub(crate) struct ElementValidator<'a> {
+    pub(crate) node: &'a SchemaNode,
+    pub(crate) errors: &'a mut Vec<ValidationError<'static>>,
+}
+
+impl<'de> Visitor<'de> for ElementValidator<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a JSON array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut elements: A) -> Result<(), A::Error> {
+        let root = InstancePath::new();
+        let mut idx = 0;
+        while let Some(element) = elements.next_element::<Value>()? {
+            let instance_path = root.push(idx);
+            self.errors.extend(
+                self.node
+                    .validate(&element, &instance_path)
+                    .filter(ValidationError::is_error)
+                    .map(ValidationError::into_owned),
+            );
+            idx += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BytesValidationError, JSONSchema};
+    use serde_json::json;
+
+    #[test]
+    fn not_an_array() {
+        let compiled = JSONSchema::compile(&json!({})).unwrap();
+        assert!(matches!(
+            compiled.validate_streaming_json(&br#"{"petType": "cat"}"#[..]),
+            Err(BytesValidationError::Parse(_))
+        ));
+        assert!(matches!(
+            compiled.validate_streaming_json(&b"[1, 2"[..]),
+            Err(BytesValidationError::Parse(_))
+        ));
+        assert!(matches!(
+            compiled.validate_streaming_json(&b"[1] [2]"[..]),
+            Err(BytesValidationError::Parse(_))
+        ));
+        assert!(compiled.validate_streaming_json(&b" [] "[..]).is_ok());
+    }
+}
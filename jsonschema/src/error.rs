@@ -1,8 +1,10 @@
 //! Error types
 use crate::{
-    paths::JSONPointer,
+    keywords::{discriminator::DiscriminatorError, one_of::BranchLabels},
+    paths::{JSONPointer, PathChunk},
     primitive_type::{PrimitiveType, PrimitiveTypesBitMap},
     resolver::SchemaResolverError,
+    spans::SourceSpan,
 };
 use serde_json::{Map, Number, Value};
 use std::{
@@ -13,6 +15,7 @@ use std::{
     iter::{empty, once},
     str::Utf8Error,
     string::FromUtf8Error,
+    sync::Arc,
 };
 use url::Url;
 
@@ -27,6 +30,24 @@ pub struct ValidationError<'a> {
     pub instance_path: JSONPointer,
     /// Path to the JSON Schema keyword that failed validation.
     pub schema_path: JSONPointer,
+    /// Where the invalid schema value starts in its source document, for compile-time errors.
+    ///
+    /// Set only if the spans of the schema are passed with
+    /// [`CompilationOptions::with_source_spans`](crate::CompilationOptions::with_source_spans).
+    pub source_span: Option<SourceSpan>,
+    /// Whether this error fails validation.
+    pub severity: Severity,
+    one_of_branches: Option<Arc<BranchLabels>>,
+}
+
+/// How serious a [`ValidationError`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The instance is not valid.
+    Error,
+    /// The instance is valid, but something is worth reporting, e.g. usage of a deprecated
+    /// schema. Only reported by [`crate::JSONSchema::validate_with_warnings`].
+    Warning,
 }
 
 /// An iterator over instances of `ValidationError` that represent validation error for the
@@ -80,6 +101,15 @@ pub enum ValidationErrorKind {
     ContentEncoding { content_encoding: String },
     /// The input value does not respect the defined contentMediaType
     ContentMediaType { content_media_type: String },
+    /// The discriminator property selects a branch marked as `deprecated`.
+    DeprecatedDiscriminatorValue {
+        property_name: String,
+        value: String,
+    },
+    /// The `discriminator` keyword is malformed.
+    Discriminator { error: DiscriminatorError },
+    /// Discriminators inherited through `allOf` dispatched the same instance too many times.
+    DiscriminatorDepthExceeded { limit: usize },
     /// The input value doesn't match any of specified options.
     Enum { options: Value },
     /// Value is too large.
@@ -122,6 +152,8 @@ pub enum ValidationErrorKind {
     MultipleOf { multiple_of: f64 },
     /// Negated schema failed validation.
     Not { schema: Value },
+    /// The discriminator property is `null` and the mapping has no `"null"` key.
+    NullDiscriminatorValue { property_name: String },
     /// The given schema is valid under more than one of the schemas listed in the 'oneOf' keyword.
     OneOfMultipleValid,
     /// The given schema is not valid under any of the schemas listed in the 'oneOf' keyword.
@@ -136,12 +168,21 @@ pub enum ValidationErrorKind {
     Required { property: Value },
     /// Resolved schema failed to compile.
     Schema,
+    /// Validation did not finish before its deadline.
+    Timeout,
     /// When the input value doesn't match one or multiple required types.
     Type { kind: TypeKind },
+    /// The discriminated branch selected by a tag does not constrain the discriminator property.
+    UnconstrainedDiscriminatorProperty { property_name: String, tag: String },
     /// Unexpected properties.
     UnevaluatedProperties { unexpected: Vec<String> },
     /// When the input array has non-unique elements.
     UniqueItems,
+    /// The discriminator property holds a value that is not present in the discriminator mapping.
+    UnknownDiscriminatorValue {
+        property_name: String,
+        value: String,
+    },
     /// Reference contains unknown scheme.
     UnknownReferenceScheme { scheme: String },
     /// Error during schema ref resolution.
@@ -160,6 +201,128 @@ pub enum TypeKind {
     Multiple(PrimitiveTypesBitMap),
 }
 
+impl ValidationError<'_> {
+    /// The keyword that failed validation, i.e. the last keyword in `schema_path`, e.g. `required`
+    /// for `/discriminator/mapping/cat/required`.
+    #[must_use]
+    pub fn keyword(&self) -> Option<&'static str> {
+        self.schema_path
+            .as_slice()
+            .iter()
+            .rev()
+            .find_map(|chunk| match chunk {
+                PathChunk::Keyword(keyword) => Some(*keyword),
+                PathChunk::Property(_) | PathChunk::Index(_) => None,
+            })
+    }
+
+    /// The `title` of every `oneOf` subschema, or its index if it has none, for
+    /// [`ValidationErrorKind::OneOfNotValid`] errors. Empty for other errors.
+    ///
+    /// ```rust
+    /// use jsonschema::JSONSchema;
+    /// use serde_json::json;
+    ///
+    /// let schema = json!({
+    ///     "oneOf": [{"title": "Cat", "required": ["meows"]}, {"required": ["barks"]}]
+    /// });
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// let instance = json!({});
+    /// let mut errors = compiled.validate(&instance).expect_err("No branch matches");
+    /// let error = errors.next().expect("An error");
+    /// assert_eq!(error.one_of_branches(), ["Cat", "1"]);
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "{} is not valid under any of the schemas listed in the 'oneOf' keyword \
+    ///      (expected one of: Cat, 1)"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn one_of_branches(&self) -> &[String] {
+        self.one_of_branches
+            .as_deref()
+            .map_or(&[], |branches| branches.labels.as_slice())
+    }
+}
+
+/// Adapters over [`ErrorIterator`].
+pub trait ErrorIteratorExt<'a> {
+    /// Keep only the errors of `keyword`, see [`ValidationError::keyword`].
+    ///
+    /// ```rust
+    /// use jsonschema::{ErrorIteratorExt, JSONSchema};
+    /// use serde_json::json;
+    ///
+    /// let schema = json!({"properties": {"name": {"type": "string"}}, "required": ["id"]});
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// let instance = json!({"name": 42});
+    /// let errors: Vec<_> = compiled
+    ///     .validate(&instance)
+    ///     .expect_err("Invalid instance")
+    ///     .filter_keyword("required")
+    ///     .map(|error| error.to_string())
+    ///     .collect();
+    /// assert_eq!(errors, vec![r#""id" is a required property"#]);
+    /// ```
+    #[must_use]
+    fn filter_keyword(self, keyword: &'a str) -> ErrorIterator<'a>;
+
+    /// An RFC 7807 `application/problem+json` document for a `422 Unprocessable Entity`
+    /// response. Every error is listed under `errors` with the JSON pointer to the invalid value,
+    /// its message, the failed keyword and the pointer into the schema.
+    ///
+    /// ```rust
+    /// use jsonschema::{ErrorIteratorExt, JSONSchema};
+    /// use serde_json::json;
+    ///
+    /// let schema = json!({"properties": {"name": {"type": "string"}}});
+    /// let compiled = JSONSchema::compile(&schema).expect("A valid schema");
+    /// let instance = json!({"name": 42});
+    /// let problem = compiled
+    ///     .validate(&instance)
+    ///     .expect_err("Invalid instance")
+    ///     .into_problem_details();
+    /// assert_eq!(problem["status"], 422);
+    /// assert_eq!(
+    ///     problem["errors"],
+    ///     json!([{
+    ///         "pointer": "/name",
+    ///         "message": "42 is not of type \"string\"",
+    ///         "keyword": "type",
+    ///         "schemaPointer": "/properties/name/type"
+    ///     }])
+    /// );
+    /// ```
+    #[must_use]
+    fn into_problem_details(self) -> Value;
+}
+
+impl<'a> ErrorIteratorExt<'a> for ErrorIterator<'a> {
+    fn filter_keyword(self, keyword: &'a str) -> ErrorIterator<'a> {
+        Box::new(self.filter(move |error| error.keyword() == Some(keyword)))
+    }
+
+    fn into_problem_details(self) -> Value {
+        let errors: Vec<_> = self
+            .map(|error| {
+                serde_json::json!({
+                    "pointer": error.instance_path.to_string(),
+                    "message": error.to_string(),
+                    "keyword": error.keyword(),
+                    "schemaPointer": error.schema_path.to_string(),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "type": "about:blank",
+            "title": "Unprocessable Entity",
+            "status": 422,
+            "detail": "The instance is not valid under the schema",
+            "errors": errors,
+        })
+    }
+}
+
 /// Shortcuts for creation of specific error kinds.
 impl<'a> ValidationError<'a> {
     pub(crate) fn into_owned(self) -> ValidationError<'static> {
@@ -168,9 +331,23 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(self.instance.into_owned()),
             kind: self.kind,
             schema_path: self.schema_path,
+            source_span: self.source_span,
+            one_of_branches: self.one_of_branches,
+            severity: self.severity,
         }
     }
 
+    /// Report this error as a warning that doesn't fail validation.
+    pub(crate) const fn into_warning(mut self) -> ValidationError<'a> {
+        self.severity = Severity::Warning;
+        self
+    }
+
+    /// Whether this error fails validation.
+    pub(crate) fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
     pub(crate) const fn additional_items(
         schema_path: JSONPointer,
         instance_path: JSONPointer,
@@ -182,6 +359,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::AdditionalItems { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn additional_properties(
@@ -195,6 +375,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::AdditionalProperties { unexpected },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn any_of(
@@ -207,6 +390,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::AnyOf,
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn backtrack_limit(
@@ -220,6 +406,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::BacktrackLimitExceeded { error },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn constant_array(
@@ -235,6 +424,9 @@ impl<'a> ValidationError<'a> {
                 expected_value: Value::Array(expected_value.to_vec()),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn constant_boolean(
@@ -250,6 +442,9 @@ impl<'a> ValidationError<'a> {
                 expected_value: Value::Bool(expected_value),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn constant_null(
@@ -264,6 +459,9 @@ impl<'a> ValidationError<'a> {
                 expected_value: Value::Null,
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn constant_number(
@@ -279,6 +477,9 @@ impl<'a> ValidationError<'a> {
                 expected_value: Value::Number(expected_value.clone()),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn constant_object(
@@ -294,6 +495,9 @@ impl<'a> ValidationError<'a> {
                 expected_value: Value::Object(expected_value.clone()),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn constant_string(
@@ -309,6 +513,9 @@ impl<'a> ValidationError<'a> {
                 expected_value: Value::String(expected_value.to_string()),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn contains(
@@ -321,6 +528,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::Contains,
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn content_encoding(
@@ -336,6 +546,9 @@ impl<'a> ValidationError<'a> {
                 content_encoding: encoding.to_string(),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn content_media_type(
@@ -351,6 +564,9 @@ impl<'a> ValidationError<'a> {
                 content_media_type: media_type.to_string(),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn enumeration(
@@ -366,6 +582,9 @@ impl<'a> ValidationError<'a> {
                 options: options.clone(),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn exclusive_maximum(
@@ -379,6 +598,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::ExclusiveMaximum { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn exclusive_minimum(
@@ -392,6 +614,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::ExclusiveMinimum { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn false_schema(
@@ -404,6 +629,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::FalseSchema,
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn file_not_found(error: io::Error) -> ValidationError<'a> {
@@ -412,6 +640,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(Value::Null),
             kind: ValidationErrorKind::FileNotFound { error },
             schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn format(
@@ -425,6 +656,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::Format { format },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn from_utf8(error: FromUtf8Error) -> ValidationError<'a> {
@@ -433,6 +667,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(Value::Null),
             kind: ValidationErrorKind::FromUtf8 { error },
             schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn json_parse(error: serde_json::Error) -> ValidationError<'a> {
@@ -441,6 +678,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(Value::Null),
             kind: ValidationErrorKind::JSONParse { error },
             schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn invalid_reference(reference: String) -> ValidationError<'a> {
@@ -449,6 +689,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(Value::Null),
             kind: ValidationErrorKind::InvalidReference { reference },
             schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn invalid_url(error: url::ParseError) -> ValidationError<'a> {
@@ -457,6 +700,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(Value::Null),
             kind: ValidationErrorKind::InvalidURL { error },
             schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn max_items(
@@ -470,6 +716,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::MaxItems { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn maximum(
@@ -483,6 +732,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::Maximum { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn max_length(
@@ -496,6 +748,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::MaxLength { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn max_properties(
@@ -509,6 +764,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::MaxProperties { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn min_items(
@@ -522,6 +780,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::MinItems { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn minimum(
@@ -535,6 +796,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::Minimum { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn min_length(
@@ -548,6 +812,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::MinLength { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn min_properties(
@@ -561,6 +828,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::MinProperties { limit },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn multiple_of(
@@ -574,6 +844,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::MultipleOf { multiple_of },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn not(
@@ -587,6 +860,25 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::Not { schema },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
+        }
+    }
+    pub(crate) const fn null_discriminator_value(
+        schema_path: JSONPointer,
+        instance_path: JSONPointer,
+        instance: &'a Value,
+        property_name: String,
+    ) -> ValidationError<'a> {
+        ValidationError {
+            instance_path,
+            instance: Cow::Borrowed(instance),
+            kind: ValidationErrorKind::NullDiscriminatorValue { property_name },
+            schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn one_of_multiple_valid(
@@ -599,18 +891,25 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::OneOfMultipleValid,
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn one_of_not_valid(
         schema_path: JSONPointer,
         instance_path: JSONPointer,
         instance: &'a Value,
+        branches: Arc<BranchLabels>,
     ) -> ValidationError<'a> {
         ValidationError {
             instance_path,
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::OneOfNotValid,
             schema_path,
+            source_span: None,
+            one_of_branches: Some(branches),
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn pattern(
@@ -624,6 +923,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::Pattern { pattern },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn property_names(
@@ -639,6 +941,9 @@ impl<'a> ValidationError<'a> {
                 error: Box::new(error.into_owned()),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn required(
@@ -652,6 +957,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::Required { property },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
 
@@ -661,6 +969,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(Value::Null),
             kind: ValidationErrorKind::Schema,
             schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
 
@@ -677,6 +988,9 @@ impl<'a> ValidationError<'a> {
                 kind: TypeKind::Single(type_name),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn multiple_type_error(
@@ -692,6 +1006,9 @@ impl<'a> ValidationError<'a> {
                 kind: TypeKind::Multiple(types),
             },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn unevaluated_properties(
@@ -705,6 +1022,89 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::UnevaluatedProperties { unexpected },
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
+        }
+    }
+    pub(crate) const fn deprecated_discriminator_value(
+        schema_path: JSONPointer,
+        instance_path: JSONPointer,
+        instance: &'a Value,
+        property_name: String,
+        value: String,
+    ) -> ValidationError<'a> {
+        ValidationError {
+            instance_path,
+            instance: Cow::Borrowed(instance),
+            kind: ValidationErrorKind::DeprecatedDiscriminatorValue {
+                property_name,
+                value,
+            },
+            schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
+        }
+    }
+    pub(crate) const fn discriminator(
+        schema_path: JSONPointer,
+        instance_path: JSONPointer,
+        instance: &'a Value,
+        error: DiscriminatorError,
+    ) -> ValidationError<'a> {
+        ValidationError {
+            instance_path,
+            instance: Cow::Borrowed(instance),
+            kind: ValidationErrorKind::Discriminator { error },
+            schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
+        }
+    }
+    pub(crate) const fn discriminator_depth_exceeded(
+        schema_path: JSONPointer,
+        instance_path: JSONPointer,
+        instance: &'a Value,
+        limit: usize,
+    ) -> ValidationError<'a> {
+        ValidationError {
+            instance_path,
+            instance: Cow::Borrowed(instance),
+            kind: ValidationErrorKind::DiscriminatorDepthExceeded { limit },
+            schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
+        }
+    }
+    pub(crate) const fn unconstrained_discriminator_property(
+        schema_path: JSONPointer,
+        instance_path: JSONPointer,
+        instance: &'a Value,
+        property_name: String,
+        tag: String,
+    ) -> ValidationError<'a> {
+        ValidationError {
+            instance_path,
+            instance: Cow::Borrowed(instance),
+            kind: ValidationErrorKind::UnconstrainedDiscriminatorProperty { property_name, tag },
+            schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
+        }
+    }
+    pub(crate) fn timeout(instance_path: JSONPointer, instance: &'a Value) -> ValidationError<'a> {
+        ValidationError {
+            instance_path,
+            instance: Cow::Borrowed(instance),
+            kind: ValidationErrorKind::Timeout,
+            schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) const fn unique_items(
@@ -717,6 +1117,29 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Borrowed(instance),
             kind: ValidationErrorKind::UniqueItems,
             schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
+        }
+    }
+    pub(crate) const fn unknown_discriminator_value(
+        schema_path: JSONPointer,
+        instance_path: JSONPointer,
+        instance: &'a Value,
+        property_name: String,
+        value: String,
+    ) -> ValidationError<'a> {
+        ValidationError {
+            instance_path,
+            instance: Cow::Borrowed(instance),
+            kind: ValidationErrorKind::UnknownDiscriminatorValue {
+                property_name,
+                value,
+            },
+            schema_path,
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn utf8(error: Utf8Error) -> ValidationError<'a> {
@@ -725,6 +1148,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(Value::Null),
             kind: ValidationErrorKind::Utf8 { error },
             schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
     pub(crate) fn resolver(url: Url, error: SchemaResolverError) -> ValidationError<'a> {
@@ -733,6 +1159,9 @@ impl<'a> ValidationError<'a> {
             instance: Cow::Owned(Value::Null),
             kind: ValidationErrorKind::Resolver { url, error },
             schema_path: JSONPointer::default(),
+            source_span: None,
+            one_of_branches: None,
+            severity: Severity::Error,
         }
     }
 }
@@ -783,9 +1212,38 @@ impl fmt::Display for ValidationError<'_> {
             ValidationErrorKind::FileNotFound { error } => error.fmt(f),
             ValidationErrorKind::InvalidURL { error } => error.fmt(f),
             ValidationErrorKind::BacktrackLimitExceeded { error } => error.fmt(f),
+            ValidationErrorKind::Discriminator { error } => error.fmt(f),
+            ValidationErrorKind::DiscriminatorDepthExceeded { limit } => write!(
+                f,
+                "{} was dispatched by more than {} inherited discriminators",
+                self.instance, limit
+            ),
+            ValidationErrorKind::DeprecatedDiscriminatorValue {
+                property_name,
+                value,
+            } => write!(
+                f,
+                r#""{}" selects a deprecated branch of the "{}" discriminator"#,
+                value, property_name
+            ),
             ValidationErrorKind::UnknownReferenceScheme { scheme } => {
                 write!(f, "Unknown scheme: {}", scheme)
             }
+            ValidationErrorKind::UnconstrainedDiscriminatorProperty { property_name, tag } => {
+                write!(
+                    f,
+                    r#"The "{}" branch does not constrain the "{}" discriminator property"#,
+                    tag, property_name
+                )
+            }
+            ValidationErrorKind::UnknownDiscriminatorValue {
+                property_name,
+                value,
+            } => write!(
+                f,
+                r#""{}" is not a valid value for the "{}" discriminator"#,
+                value, property_name
+            ),
             ValidationErrorKind::Format { format } => {
                 write!(f, r#"{} is not a "{}""#, self.instance, format)
             }
@@ -841,11 +1299,17 @@ impl fmt::Display for ValidationError<'_> {
                 "{} is not valid under any of the schemas listed in the 'anyOf' keyword",
                 self.instance
             ),
-            ValidationErrorKind::OneOfNotValid => write!(
-                f,
-                "{} is not valid under any of the schemas listed in the 'oneOf' keyword",
-                self.instance
-            ),
+            ValidationErrorKind::OneOfNotValid => {
+                write!(
+                    f,
+                    "{} is not valid under any of the schemas listed in the 'oneOf' keyword",
+                    self.instance
+                )?;
+                if let Some(branches) = self.one_of_branches.as_deref().filter(|b| b.titled) {
+                    write!(f, " (expected one of: {})", branches.labels.join(", "))?;
+                }
+                Ok(())
+            }
             ValidationErrorKind::Contains => write!(
                 f,
                 "None of {} are valid under the given schema",
@@ -942,6 +1406,11 @@ impl fmt::Display for ValidationError<'_> {
             ValidationErrorKind::Not { schema } => {
                 write!(f, "{} is not allowed for {}", schema, self.instance)
             }
+            ValidationErrorKind::NullDiscriminatorValue { property_name } => write!(
+                f,
+                r#"The "{}" discriminator must not be null"#,
+                property_name
+            ),
             ValidationErrorKind::OneOfMultipleValid => write!(
                 f,
                 "{} is valid under more than one of the schemas listed in the 'oneOf' keyword",
@@ -979,6 +1448,9 @@ impl fmt::Display for ValidationError<'_> {
             ValidationErrorKind::UniqueItems => {
                 write!(f, "{} has non-unique elements", self.instance)
             }
+            ValidationErrorKind::Timeout => {
+                f.write_str("Validation did not finish before its deadline")
+            }
             ValidationErrorKind::Type {
                 kind: TypeKind::Single(type_),
             } => write!(f, r#"{} is not of type "{}""#, self.instance, type_),
@@ -998,13 +1470,199 @@ impl fmt::Display for ValidationError<'_> {
     }
 }
 
+/// Errors returned by [`JSONSchema::validate_with_limit`](crate::JSONSchema::validate_with_limit).
+#[derive(Debug)]
+pub struct LimitedErrors<'a> {
+    /// Collected errors, at most the requested limit.
+    pub errors: Vec<ValidationError<'a>>,
+    /// Whether more errors were left uncollected.
+    pub truncated: bool,
+}
+
+/// All errors of a failed validation as a single error, e.g. to propagate them with `?`.
+///
+/// ```rust
+/// # use jsonschema::{JSONSchema, ValidationErrors};
+/// # use serde_json::json;
+/// fn check(schema: &JSONSchema, instance: &serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+///     schema.validate(instance).map_err(ValidationErrors::from)?;
+///     Ok(())
+/// }
+/// let schema = JSONSchema::compile(&json!({"minimum": 5})).expect("A valid schema");
+/// let error = check(&schema, &json!(3)).expect_err("Too small");
+/// assert_eq!(error.to_string(), "3 is less than the minimum of 5");
+/// ```
+#[derive(Debug)]
+pub struct ValidationErrors {
+    /// Errors in the order they were reported.
+    pub errors: Vec<ValidationError<'static>>,
+}
+
+impl<'a> From<ErrorIterator<'a>> for ValidationErrors {
+    fn from(errors: ErrorIterator<'a>) -> Self {
+        ValidationErrors {
+            errors: errors.map(ValidationError::into_owned).collect(),
+        }
+    }
+}
+
+/// An error returned by [`JSONSchema::validate_bytes`](crate::JSONSchema::validate_bytes),
+/// [`JSONSchema::validate_json_str`](crate::JSONSchema::validate_json_str) and
+/// [`JSONSchema::validate_bytes_with_offsets`](crate::JSONSchema::validate_bytes_with_offsets).
+#[derive(Debug)]
+pub enum BytesValidationError<E = ValidationError<'static>> {
+    /// The input is not a valid JSON document.
+    Parse(serde_json::Error),
+    /// The parsed instance is not valid against the schema.
+    Invalid(Vec<E>),
+}
+
+/// A validation error along with the location of the invalid value in the raw JSON input.
+#[derive(Debug)]
+pub struct LocatedValidationError {
+    /// The validation error.
+    pub error: ValidationError<'static>,
+    /// Byte range of the value at the error's `instance_path`, end exclusive.
+    pub offsets: Option<(usize, usize)>,
+}
+
+impl fmt::Display for LocatedValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)?;
+        if let Some((start, end)) = self.offsets {
+            write!(f, " (bytes {}..{})", start, end)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (idx, error) in self.errors.iter().enumerate() {
+            if idx > 0 {
+                f.write_str("\n")?;
+            }
+            error.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ValidationErrors {}
+
+impl<E: fmt::Display> fmt::Display for BytesValidationError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BytesValidationError::Parse(error) => error.fmt(f),
+            BytesValidationError::Invalid(errors) => {
+                for (idx, error) in errors.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str("\n")?;
+                    }
+                    error.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> error::Error for BytesValidationError<E> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BytesValidationError::Parse(error) => Some(error),
+            BytesValidationError::Invalid(_) => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{paths::PathChunk, JSONSchema};
+    use crate::{tests_util, JSONSchema};
     use serde_json::json;
     use test_case::test_case;
 
+    #[test]
+    fn validation_errors() {
+        let mut schema = tests_util::pets(json!({}));
+        schema["definitions"]["Cat"]["required"] = json!(["meows", "name"]);
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let instance = json!({"petType": "cat", "meows": 1});
+        let errors = ValidationErrors::from(compiled.validate(&instance).expect_err("Invalid cat"));
+        assert_eq!(errors.errors.len(), 2);
+        assert_eq!(
+            errors.to_string(),
+            "1 is not of type \"boolean\"\n\"name\" is a required property"
+        );
+        let error: Box<dyn error::Error> = Box::new(errors);
+        assert!(error.source().is_none());
+    }
+
+    #[test]
+    fn problem_details() {
+        let mut schema = tests_util::pets(json!({}));
+        schema["definitions"]["Cat"]["required"] = json!(["meows", "name"]);
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let instance = json!({"petType": "cat", "meows": 1});
+        let problem = compiled
+            .validate(&instance)
+            .expect_err("Invalid cat")
+            .into_problem_details();
+        assert_eq!(problem["type"], "about:blank");
+        assert_eq!(problem["status"], 422);
+        assert_eq!(
+            problem["errors"],
+            json!([
+                {
+                    "pointer": "/meows",
+                    "message": "1 is not of type \"boolean\"",
+                    "keyword": "type",
+                    "schemaPointer": "/discriminator/mapping/cat/properties/meows/type"
+                },
+                {
+                    "pointer": "",
+                    "message": "\"name\" is a required property",
+                    "keyword": "required",
+                    "schemaPointer": "/discriminator/mapping/cat/required"
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn filter_keyword() {
+        let mut schema = tests_util::pets(json!({}));
+        schema["definitions"]["Cat"]["properties"]["lives"] = json!({"maximum": 9});
+        schema["definitions"]["Cat"]["required"] = json!(["meows", "name"]);
+        let compiled = tests_util::openapi_options().compile(&schema).unwrap();
+        let instance = json!({"petType": "cat", "meows": 1, "lives": 10});
+        let keywords: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Invalid cat")
+            .map(|error| error.keyword())
+            .collect();
+        assert_eq!(
+            keywords,
+            vec![Some("maximum"), Some("type"), Some("required")]
+        );
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Invalid cat")
+            .filter_keyword("required")
+            .map(|error| error.schema_path.to_string())
+            .collect();
+        assert_eq!(errors, vec!["/discriminator/mapping/cat/required"]);
+        assert_eq!(
+            compiled
+                .validate(&instance)
+                .expect_err("Invalid cat")
+                .filter_keyword("enum")
+                .count(),
+            0
+        );
+    }
+
     #[test]
     fn single_type_error() {
         let instance = json!(42);
@@ -57,6 +57,10 @@ impl Validate for AdditionalItemsObjectValidator {
             no_error()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for AdditionalItemsObjectValidator {
@@ -136,6 +136,10 @@ impl Validate for AdditionalPropertiesValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for AdditionalPropertiesValidator {
@@ -321,6 +325,10 @@ impl<M: PropertiesValidatorsMap> Validate for AdditionalPropertiesNotEmptyFalseV
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.properties.nodes()
+    }
 }
 
 impl<M: PropertiesValidatorsMap> core::fmt::Display
@@ -443,6 +451,12 @@ impl<M: PropertiesValidatorsMap> Validate for AdditionalPropertiesNotEmptyValida
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        let mut nodes = self.properties.nodes();
+        nodes.push(&self.node);
+        nodes
+    }
 }
 
 impl<M: PropertiesValidatorsMap> core::fmt::Display for AdditionalPropertiesNotEmptyValidator<M> {
@@ -593,6 +607,12 @@ impl Validate for AdditionalPropertiesWithPatternsValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        std::iter::once(&self.node)
+            .chain(self.patterns.iter().map(|(_, node)| node))
+            .collect()
+    }
 }
 
 impl core::fmt::Display for AdditionalPropertiesWithPatternsValidator {
@@ -746,6 +766,10 @@ impl Validate for AdditionalPropertiesWithPatternsFalseValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.patterns.iter().map(|(_, node)| node).collect()
+    }
 }
 
 impl core::fmt::Display for AdditionalPropertiesWithPatternsFalseValidator {
@@ -934,6 +958,13 @@ impl<M: PropertiesValidatorsMap> Validate for AdditionalPropertiesWithPatternsNo
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        let mut nodes = self.properties.nodes();
+        nodes.push(&self.node);
+        nodes.extend(self.patterns.iter().map(|(_, node)| node));
+        nodes
+    }
 }
 impl<M: PropertiesValidatorsMap> core::fmt::Display
     for AdditionalPropertiesWithPatternsNotEmptyValidator<M>
@@ -1135,6 +1166,12 @@ impl<M: PropertiesValidatorsMap> Validate
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        let mut nodes = self.properties.nodes();
+        nodes.extend(self.patterns.iter().map(|(_, node)| node));
+        nodes
+    }
 }
 
 impl<M: PropertiesValidatorsMap> core::fmt::Display
@@ -51,6 +51,16 @@ impl Validate for AllOfValidator {
         Box::new(errors.into_iter())
     }
 
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        self.schemas
+            .iter()
+            .find_map(|node| node.first_error(instance, instance_path))
+    }
+
     fn apply<'a>(
         &'a self,
         instance: &Value,
@@ -62,6 +72,17 @@ impl Validate for AllOfValidator {
             .sum::<BasicOutput<'_>>()
             .into()
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.schemas.iter().collect()
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "allOf".to_string(),
+            Value::Array(self.schemas.iter().map(SchemaNode::to_value).collect()),
+        );
+    }
 }
 
 impl core::fmt::Display for AllOfValidator {
@@ -103,6 +124,14 @@ impl Validate for SingleValueAllOfValidator {
         self.node.validate(instance, instance_path)
     }
 
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        self.node.first_error(instance, instance_path)
+    }
+
     fn apply<'a>(
         &'a self,
         instance: &Value,
@@ -110,6 +139,17 @@ impl Validate for SingleValueAllOfValidator {
     ) -> PartialApplication<'a> {
         self.node.apply_rooted(instance, instance_path).into()
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "allOf".to_string(),
+            Value::Array(vec![self.node.to_value()]),
+        );
+    }
 }
 
 impl core::fmt::Display for SingleValueAllOfValidator {
@@ -1,6 +1,7 @@
 use crate::{
-    compilation::{compile_validators, context::CompilationContext},
+    compilation::{compile_validators, context::CompilationContext, deadline},
     error::{error, no_error, ErrorIterator, ValidationError},
+    keywords::discriminator::{branch_reference, Discriminator, TagSelection, TagSelector},
     paths::InstancePath,
     primitive_type::PrimitiveType,
     schema_node::SchemaNode,
@@ -13,12 +14,24 @@ use crate::paths::JSONPointer;
 
 pub(crate) struct AnyOfValidator {
     schemas: Vec<SchemaNode>,
+    discriminator: Option<AnyOfDiscriminator>,
+    schema_path: JSONPointer,
+}
+
+/// Branches selected by the tags of a `discriminator` next to `anyOf`.
+struct AnyOfDiscriminator {
+    selector: TagSelector,
+    // The `anyOf` subschema of every mapping entry, if one references the mapped schema
+    branches: Vec<Option<usize>>,
+    // The normalized tags that select a branch
+    tags: Vec<String>,
     schema_path: JSONPointer,
 }
 
 impl AnyOfValidator {
     #[inline]
     pub(crate) fn compile<'a>(
+        parent: &'a Map<String, Value>,
         schema: &'a Value,
         context: &CompilationContext,
     ) -> CompilationResult<'a> {
@@ -30,8 +43,21 @@ impl AnyOfValidator {
                 let node = compile_validators(item, &item_context)?;
                 schemas.push(node)
             }
+            let discriminator = match parent.get("discriminator") {
+                // A discriminator next to `oneOf` is handled by `DiscriminatorValidator`
+                Some(discriminator)
+                    if !parent.contains_key("oneOf") && context.config.uses_discriminator() =>
+                {
+                    let discriminator = AnyOfDiscriminator::compile(discriminator, schema, context)
+                        .map_err(|error| *error)?;
+                    context.counters.add_discriminator_site();
+                    Some(discriminator)
+                }
+                _ => None,
+            };
             Ok(Box::new(AnyOfValidator {
                 schemas,
+                discriminator,
                 schema_path: keyword_context.into_pointer(),
             }))
         } else {
@@ -43,11 +69,85 @@ impl AnyOfValidator {
             ))
         }
     }
+
+    /// The location of the `discriminator` next to this `anyOf` with the tags that select a
+    /// branch, if there is one.
+    pub(crate) fn discriminator_tags(&self) -> Option<(&JSONPointer, Vec<String>)> {
+        self.discriminator
+            .as_ref()
+            .map(|discriminator| (&discriminator.schema_path, discriminator.tags.clone()))
+    }
+}
+
+impl AnyOfDiscriminator {
+    fn compile<'a>(
+        schema: &'a Value,
+        any_of: &Value,
+        context: &CompilationContext,
+    ) -> Result<AnyOfDiscriminator, Box<ValidationError<'a>>> {
+        let mut discriminator = Discriminator::try_from_value(schema).map_err(|error| {
+            error.into_validation_error(schema, &context.with_path("discriminator"))
+        })?;
+        let draft = context.config.draft();
+        if context.config.resolves_implicit_discriminator_mapping() {
+            discriminator.add_implicit_mapping(any_of, draft);
+        }
+        let references: Vec<_> = any_of
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|branch| branch_reference(branch, draft).map(|(_, reference)| reference))
+            .collect();
+        let branches: Vec<_> = discriminator
+            .mapping()
+            .map(|(_, mapped)| {
+                references
+                    .iter()
+                    .position(|reference| *reference == Some(mapped))
+            })
+            .collect();
+        let tags: Vec<_> = discriminator
+            .mapping()
+            .zip(&branches)
+            .filter(|(_, branch)| branch.is_some())
+            .map(|((tag, _), _)| match context.config.tag_normalizer() {
+                Some(normalize) => normalize(tag).into_owned(),
+                None => tag.to_string(),
+            })
+            .collect();
+        Ok(AnyOfDiscriminator {
+            selector: TagSelector::from_discriminator(&discriminator, &context.config),
+            branches,
+            tags,
+            schema_path: context.as_pointer_with("discriminator"),
+        })
+    }
+}
+
+impl AnyOfValidator {
+    /// The branch named by the instance's discriminator tag.
+    ///
+    /// `None` if there is no discriminator, or the tag is absent or not mapped to any branch. In
+    /// that case all branches are scanned.
+    fn discriminated(&self, instance: &Value) -> Option<&SchemaNode> {
+        let discriminator = self.discriminator.as_ref()?;
+        match discriminator.selector.select(instance) {
+            TagSelection::Mapped { position, .. } => {
+                discriminator.branches[position].map(|position| &self.schemas[position])
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Validate for AnyOfValidator {
     fn is_valid(&self, instance: &Value) -> bool {
-        self.schemas.iter().any(|s| s.is_valid(instance))
+        if let Some(node) = self.discriminated(instance) {
+            return node.is_valid(instance);
+        }
+        self.schemas
+            .iter()
+            .any(|s| !deadline::is_expired() && s.is_valid(instance))
     }
 
     fn validate<'instance>(
@@ -66,11 +166,24 @@ impl Validate for AnyOfValidator {
         }
     }
 
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        (!self.is_valid(instance)).then(|| {
+            ValidationError::any_of(self.schema_path.clone(), instance_path.into(), instance)
+        })
+    }
+
     fn apply<'a>(
         &'a self,
         instance: &Value,
         instance_path: &InstancePath,
     ) -> PartialApplication<'a> {
+        if let Some(node) = self.discriminated(instance) {
+            return node.apply_rooted(instance, instance_path).into();
+        }
         let mut successes = Vec::new();
         let mut failures = Vec::new();
         for node in &self.schemas {
@@ -87,6 +200,17 @@ impl Validate for AnyOfValidator {
             successes.into_iter().collect()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.schemas.iter().collect()
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "anyOf".to_string(),
+            Value::Array(self.schemas.iter().map(SchemaNode::to_value).collect()),
+        );
+    }
 }
 
 impl core::fmt::Display for AnyOfValidator {
@@ -100,11 +224,11 @@ impl core::fmt::Display for AnyOfValidator {
 }
 #[inline]
 pub(crate) fn compile<'a>(
-    _: &'a Map<String, Value>,
+    parent: &'a Map<String, Value>,
     schema: &'a Value,
     context: &CompilationContext,
 ) -> Option<CompilationResult<'a>> {
-    Some(AnyOfValidator::compile(schema, context))
+    Some(AnyOfValidator::compile(parent, schema, context).map_err(|error| context.locate(error)))
 }
 
 #[cfg(test)]
@@ -118,4 +242,53 @@ mod tests {
     fn schema_path(schema: &Value, instance: &Value, expected: &str) {
         tests_util::assert_schema_path(schema, instance, expected)
     }
+
+    fn pets() -> Value {
+        json!({
+            "anyOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+            },
+            "definitions": {
+                "Cat": {"required": ["meows"]},
+                "Dog": {"required": ["barks"]}
+            }
+        })
+    }
+
+    // Only the tagged branch is checked
+    #[test_case(&json!({"petType": "cat", "meows": true}), true)]
+    #[test_case(&json!({"petType": "cat", "barks": true}), false)]
+    #[test_case(&json!({"petType": "dog", "barks": true}), true)]
+    // Without a mapped tag all branches are scanned
+    #[test_case(&json!({"barks": true}), true)]
+    #[test_case(&json!({"petType": "fish", "meows": true}), true)]
+    #[test_case(&json!({"petType": 1}), false)]
+    fn discriminated(instance: &Value, expected: bool) {
+        if expected {
+            tests_util::openapi_is_valid(&pets(), instance);
+        } else {
+            tests_util::openapi_is_not_valid(&pets(), instance);
+        }
+    }
+
+    #[test]
+    fn discriminated_schema_path() {
+        tests_util::openapi_assert_schema_path(
+            &pets(),
+            &json!({"petType": "cat", "barks": true}),
+            "/anyOf",
+        );
+    }
+
+    #[test]
+    fn invalid_discriminator() {
+        let mut schema = pets();
+        schema["discriminator"] = json!({"mapping": {}});
+        let error = tests_util::openapi_options()
+            .compile(&schema)
+            .expect_err("Invalid discriminator");
+        assert_eq!(error.instance_path.to_string(), "/discriminator");
+    }
 }
@@ -49,6 +49,10 @@ impl Validate for ConstArrayValidator {
             false
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("const".to_string(), Value::Array(self.value.clone()));
+    }
 }
 impl core::fmt::Display for ConstArrayValidator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -101,6 +105,10 @@ impl Validate for ConstBooleanValidator {
             false
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("const".to_string(), Value::Bool(self.value));
+    }
 }
 impl core::fmt::Display for ConstBooleanValidator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -139,6 +147,10 @@ impl Validate for ConstNullValidator {
     fn is_valid(&self, instance: &Value) -> bool {
         instance.is_null()
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("const".to_string(), Value::Null);
+    }
 }
 impl core::fmt::Display for ConstNullValidator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -191,6 +203,13 @@ impl Validate for ConstNumberValidator {
             false
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "const".to_string(),
+            Value::Number(self.original_value.clone()),
+        );
+    }
 }
 
 impl core::fmt::Display for ConstNumberValidator {
@@ -242,6 +261,10 @@ impl Validate for ConstObjectValidator {
             false
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("const".to_string(), Value::Object(self.value.clone()));
+    }
 }
 
 impl core::fmt::Display for ConstObjectValidator {
@@ -298,6 +321,10 @@ impl Validate for ConstStringValidator {
             false
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("const".to_string(), Value::String(self.value.clone()));
+    }
 }
 
 impl core::fmt::Display for ConstStringValidator {
@@ -95,6 +95,10 @@ impl Validate for ContainsValidator {
             result
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for ContainsValidator {
@@ -188,6 +192,10 @@ impl Validate for MinContainsValidator {
             true
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for MinContainsValidator {
@@ -292,6 +300,10 @@ impl Validate for MaxContainsValidator {
             true
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for MaxContainsValidator {
@@ -394,6 +406,10 @@ impl Validate for MinMaxContainsValidator {
             true
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for MinMaxContainsValidator {
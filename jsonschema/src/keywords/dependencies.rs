@@ -80,6 +80,10 @@ impl Validate for DependenciesValidator {
             no_error()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.dependencies.iter().map(|(_, node)| node).collect()
+    }
 }
 
 impl core::fmt::Display for DependenciesValidator {
@@ -172,6 +176,10 @@ impl Validate for DependentRequiredValidator {
             no_error()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.dependencies.iter().map(|(_, node)| node).collect()
+    }
 }
 impl core::fmt::Display for DependentRequiredValidator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -239,6 +247,10 @@ impl Validate for DependentSchemasValidator {
             no_error()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.dependencies.iter().map(|(_, node)| node).collect()
+    }
 }
 
 impl core::fmt::Display for DependentSchemasValidator {
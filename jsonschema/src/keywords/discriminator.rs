@@ -0,0 +1,4612 @@
+//! Support for the OpenAPI `discriminator` keyword.
+//!
+//! A discriminator accompanies `oneOf` and names an instance property whose value selects the
+//! branch to validate against, so only a single subschema has to be checked:
+//!
+//! ```json
+//! {
+//!     "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+//!     "discriminator": {
+//!         "propertyName": "petType",
+//!         "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+//!     }
+//! }
+//! ```
+//!
+//! Instances without a usable tag fall back to the regular `oneOf` semantics. Tags missing from
+//! the mapping are rejected, unless the special `"*"` mapping key names a fallback branch for them.
+//! A present but unmapped tag never falls back to `oneOf` on its own, as the tag shows which
+//! variant was intended; `CompilationOptions::should_pass_through_unknown_discriminator_values`
+//! opts into that.
+use crate::{
+    compilation::{
+        cache,
+        context::CompilationContext,
+        options::{CompilationOptions, TagNormalizer},
+        CompilationStats,
+    },
+    error::{error, no_error, ErrorIterator, ValidationError, ValidationErrorKind},
+    keywords::{
+        any_of::AnyOfValidator,
+        helpers::coerced,
+        one_of::OneOfValidator,
+        pre_validate::PreValidateValidator,
+        ref_::{self, reference_cost},
+        BoxedValidator, CompilationResult,
+    },
+    output::BasicOutput,
+    paths::{InstancePath, JSONPointer, PathChunk},
+    primitive_type::PrimitiveType,
+    schema_node::SchemaNode,
+    validator::{format_key_value_validators, PartialApplication, Validate},
+    Draft, JSONSchema,
+};
+use ahash::{AHashMap, AHashSet};
+use once_cell::sync::Lazy;
+use serde_json::{Map, Number, Value};
+use std::{borrow::Cow, cell::RefCell, iter::once, sync::Arc};
+
+/// The mapping key of the branch used for tags that are not mapped otherwise.
+const FALLBACK_TAG: &str = "*";
+/// The mapping key of the branch for a `null` tag, see
+/// `CompilationOptions::should_reject_null_discriminator_values`.
+const NULL_TAG: &str = "null";
+/// Joins the values of a composite key into a tag.
+const COMPOSITE_TAG_SEPARATOR: char = ',';
+
+static DISCRIMINATOR_META_SCHEMA: Lazy<JSONSchema> = Lazy::new(|| {
+    let schema: Value = serde_json::from_str(include_str!(
+        "../../meta_schemas/openapi/discriminator.json"
+    ))
+    .expect("Invalid schema");
+    JSONSchema::options()
+        .without_schema_validation()
+        .compile(&schema)
+        .expect("Valid meta-schema")
+});
+
+/// Validate a `discriminator` object against the OpenAPI meta-schema.
+///
+/// Unlike [`Discriminator::try_from_value`], all violations are reported along with their
+/// locations, which suits authoring tools that check discriminators before compiling schemas.
+///
+/// ```rust
+/// use jsonschema::validate_discriminator_object;
+/// use serde_json::json;
+///
+/// assert!(validate_discriminator_object(&json!({"propertyName": "petType"})).is_ok());
+/// let discriminator = json!({"mapping": {"cat": 1}});
+/// let errors: Vec<_> = validate_discriminator_object(&discriminator)
+///     .expect_err("Not a discriminator")
+///     .map(|error| error.instance_path.to_string())
+///     .collect();
+/// assert_eq!(errors, vec!["/mapping/cat", ""]);
+/// ```
+///
+/// # Errors
+///
+/// Returns an iterator over all meta-schema violations if `discriminator` is not conformant.
+pub fn validate_discriminator_object(discriminator: &Value) -> Result<(), ErrorIterator<'_>> {
+    DISCRIMINATOR_META_SCHEMA.validate(discriminator)
+}
+
+/// Violations of the OpenAPI meta-schema by every `discriminator` next to a `oneOf` or an `anyOf`
+/// in `schema`, located within `schema`.
+pub(crate) fn discriminator_meta_errors(schema: &Value) -> Vec<ValidationError<'static>> {
+    let mut errors = Vec::new();
+    collect_discriminator_meta_errors(schema, &mut Vec::new(), &mut errors);
+    errors
+}
+
+fn collect_discriminator_meta_errors(
+    value: &Value,
+    location: &mut Vec<PathChunk>,
+    errors: &mut Vec<ValidationError<'static>>,
+) {
+    match value {
+        Value::Object(object) => {
+            if let Some(discriminator) = object.get("discriminator") {
+                if object.contains_key("oneOf") || object.contains_key("anyOf") {
+                    if let Err(violations) = validate_discriminator_object(discriminator) {
+                        let prefix = JSONPointer::default()
+                            .extend_with(location)
+                            .clone_with("discriminator");
+                        errors.extend(violations.map(|error| {
+                            let mut error = error.into_owned();
+                            error.instance_path =
+                                prefix.extend_with(error.instance_path.as_slice());
+                            error
+                        }));
+                    }
+                }
+            }
+            for (key, value) in object {
+                location.push(PathChunk::Property(key.as_str().into()));
+                collect_discriminator_meta_errors(value, location, errors);
+                location.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                location.push(PathChunk::Index(idx));
+                collect_discriminator_meta_errors(item, location, errors);
+                location.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A parsed OpenAPI `discriminator` object.
+///
+/// Parsing is independent of schema compilation, so it can be used to check discriminators in
+/// any OpenAPI document:
+///
+/// ```rust
+/// use jsonschema::Discriminator;
+/// use serde_json::json;
+///
+/// let discriminator = Discriminator::try_from_value(&json!({
+///     "propertyName": "petType",
+///     "mapping": {"cat": "#/components/schemas/Cat"}
+/// }))
+/// .expect("A valid discriminator");
+/// assert_eq!(discriminator.property_name(), "petType");
+/// assert_eq!(
+///     discriminator.mapping().collect::<Vec<_>>(),
+///     vec![("cat", "#/components/schemas/Cat")]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discriminator {
+    property_name: String,
+    // Further properties of a composite key, see `property_names`
+    key_properties: Vec<String>,
+    mapping: Vec<(String, String)>,
+}
+
+impl Discriminator {
+    /// Parse a `discriminator` object.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DiscriminatorError`] if `value` is not an object, `propertyName` is missing or
+    /// neither a string nor a non-empty array of strings, or `mapping` is not an object of
+    /// strings.
+    pub fn try_from_value(value: &Value) -> Result<Discriminator, DiscriminatorError> {
+        let discriminator = value.as_object().ok_or(DiscriminatorError::NotAnObject)?;
+        let (property_name, key_properties) = match discriminator.get("propertyName") {
+            Some(Value::String(property_name)) => (property_name.clone(), Vec::new()),
+            Some(Value::Array(property_names)) => {
+                let mut property_names = property_names
+                    .iter()
+                    .map(|name| name.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|names| !names.is_empty())
+                    .ok_or(DiscriminatorError::InvalidPropertyName)?;
+                let property_name = property_names.remove(0);
+                (property_name, property_names)
+            }
+            Some(_) => return Err(DiscriminatorError::InvalidPropertyName),
+            None => return Err(DiscriminatorError::MissingPropertyName),
+        };
+        let mapping = match discriminator.get("mapping") {
+            Some(Value::Object(mapping)) => mapping
+                .iter()
+                .map(|(tag, reference)| match reference {
+                    Value::String(reference) => Ok((tag.clone(), reference.clone())),
+                    _ => Err(DiscriminatorError::InvalidMappingValue { tag: tag.clone() }),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => return Err(DiscriminatorError::InvalidMapping),
+            None => Vec::new(),
+        };
+        Ok(Discriminator {
+            property_name,
+            key_properties,
+            mapping,
+        })
+    }
+
+    /// Map the `oneOf` branches that are plain references by the last segment of their reference,
+    /// e.g. `Cat` for `#/components/schemas/Cat`. Explicitly mapped tags take precedence.
+    pub(crate) fn add_implicit_mapping(&mut self, one_of: &Value, draft: Draft) {
+        let branches = one_of.as_array().map_or(&[][..], Vec::as_slice);
+        for (_, reference) in branches
+            .iter()
+            .filter_map(|branch| branch_reference(branch, draft))
+        {
+            let tag = reference.rsplit('/').next().unwrap_or(reference);
+            if !self.mapping.iter().any(|(mapped, _)| mapped == tag) {
+                self.mapping.push((tag.to_string(), reference.to_string()));
+            }
+        }
+    }
+
+    /// The name of the instance property holding the discriminator tag, the first one of a
+    /// composite key.
+    #[must_use]
+    pub fn property_name(&self) -> &str {
+        &self.property_name
+    }
+
+    /// The names of all instance properties forming the discriminator tag.
+    ///
+    /// `propertyName` may be an array of property names instead of a single one. The tag of an
+    /// instance is then composed of the values of these properties joined by `,`, e.g.
+    /// `circle,2` for `{"kind": "circle", "version": 2}` with `["kind", "version"]`. Composite
+    /// keys are an extension of OpenAPI and are rejected by
+    /// [`validate_discriminator_object`](crate::validate_discriminator_object).
+    ///
+    /// ```rust
+    /// use jsonschema::Discriminator;
+    /// use serde_json::json;
+    ///
+    /// let discriminator = Discriminator::try_from_value(&json!({
+    ///     "propertyName": ["kind", "version"],
+    ///     "mapping": {"circle,2": "#/components/schemas/CircleV2"}
+    /// }))
+    /// .expect("A valid discriminator");
+    /// assert_eq!(
+    ///     discriminator.property_names().collect::<Vec<_>>(),
+    ///     vec!["kind", "version"]
+    /// );
+    /// ```
+    pub fn property_names(&self) -> impl Iterator<Item = &str> {
+        once(self.property_name.as_str()).chain(self.key_properties.iter().map(String::as_str))
+    }
+
+    /// Explicit tag to schema reference pairs.
+    pub fn mapping(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.mapping
+            .iter()
+            .map(|(tag, reference)| (tag.as_str(), reference.as_str()))
+    }
+
+    /// The explicitly mapped tags, e.g. to present the accepted tag values.
+    pub fn mapping_keys(&self) -> impl Iterator<Item = &str> {
+        self.mapping.iter().map(|(tag, _)| tag.as_str())
+    }
+
+    /// The values among `enum_values` that have no `mapping` entry of their own, e.g. to check
+    /// that generated code handles every case of the enum of the discriminator property. Empty
+    /// if the mapping is exhaustive. The `"*"` fallback entry doesn't name a variant, so it covers
+    /// no other value here, and `"*"` itself is only missing if there is no fallback entry.
+    ///
+    /// ```rust
+    /// use jsonschema::Discriminator;
+    /// use serde_json::json;
+    ///
+    /// let discriminator = Discriminator::try_from_value(&json!({
+    ///     "propertyName": "petType",
+    ///     "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+    /// }))
+    /// .expect("A valid discriminator");
+    /// assert_eq!(discriminator.is_exhaustive(["cat", "dog", "bird"]), vec!["bird"]);
+    /// assert!(discriminator.is_exhaustive(["cat", "dog"]).is_empty());
+    /// ```
+    #[must_use]
+    pub fn is_exhaustive<'a>(
+        &self,
+        enum_values: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<&'a str> {
+        enum_values
+            .into_iter()
+            .filter(|value| self.reference(value).is_none())
+            .collect()
+    }
+
+    /// The mapping entries that differ in `other`, e.g. a newer version of this discriminator.
+    ///
+    /// ```rust
+    /// use jsonschema::Discriminator;
+    /// use serde_json::json;
+    ///
+    /// let old = Discriminator::try_from_value(&json!({
+    ///     "propertyName": "petType",
+    ///     "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+    /// }))
+    /// .expect("A valid discriminator");
+    /// let new = Discriminator::try_from_value(&json!({
+    ///     "propertyName": "petType",
+    ///     "mapping": {"cat": "#/definitions/Kitten", "bird": "#/definitions/Bird"}
+    /// }))
+    /// .expect("A valid discriminator");
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.added, vec![("bird".to_string(), "#/definitions/Bird".to_string())]);
+    /// assert_eq!(diff.removed, vec![("dog".to_string(), "#/definitions/Dog".to_string())]);
+    /// assert_eq!(
+    ///     diff.changed,
+    ///     vec![(
+    ///         "cat".to_string(),
+    ///         "#/definitions/Cat".to_string(),
+    ///         "#/definitions/Kitten".to_string()
+    ///     )]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn diff(&self, other: &Discriminator) -> DiscriminatorDiff {
+        let mut diff = DiscriminatorDiff::default();
+        for (tag, reference) in &self.mapping {
+            match other.reference(tag) {
+                None => diff.removed.push((tag.clone(), reference.clone())),
+                Some(new) if new != reference => {
+                    diff.changed
+                        .push((tag.clone(), reference.clone(), new.to_string()));
+                }
+                Some(_) => {}
+            }
+        }
+        for (tag, reference) in &other.mapping {
+            if self.reference(tag).is_none() {
+                diff.added.push((tag.clone(), reference.clone()));
+            }
+        }
+        diff
+    }
+
+    fn reference(&self, tag: &str) -> Option<&str> {
+        self.mapping
+            .iter()
+            .find(|(mapped, _)| mapped == tag)
+            .map(|(_, reference)| reference.as_str())
+    }
+}
+
+/// Mapping entries that differ between two discriminators, see [`Discriminator::diff`].
+///
+/// Entries are listed in the order of the mapping they come from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscriminatorDiff {
+    /// Tags and references only present in the newer discriminator.
+    pub added: Vec<(String, String)>,
+    /// Tags and references only present in the older discriminator.
+    pub removed: Vec<(String, String)>,
+    /// Tags mapped to different references, along with the older and the newer reference.
+    pub changed: Vec<(String, String, String)>,
+}
+
+impl DiscriminatorDiff {
+    /// Whether the mappings are the same.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// An error that can occur while parsing a `discriminator` object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscriminatorError {
+    /// The discriminator is not an object.
+    NotAnObject,
+    /// `propertyName` is missing.
+    MissingPropertyName,
+    /// `propertyName` is neither a string nor a non-empty array of strings.
+    InvalidPropertyName,
+    /// `mapping` is not an object.
+    InvalidMapping,
+    /// The `mapping` value for `tag` is not a string.
+    InvalidMappingValue {
+        /// Mapping key with the invalid value.
+        tag: String,
+    },
+    /// The `mapping` value for `tag` points to a location that doesn't exist in the document.
+    /// Only reported with [`crate::CompilationOptions::should_use_strict_discriminator`].
+    FragmentUnresolvable {
+        /// Mapping key with the unresolvable value.
+        tag: String,
+        /// The unresolvable reference.
+        reference: String,
+    },
+    /// A merged schema has no discriminator over `oneOf` at its root.
+    NotDiscriminated,
+    /// Merged discriminators read different properties.
+    PropertyNameMismatch,
+    /// Merged discriminators both map `tag`.
+    ConflictingTag {
+        /// Mapping key present in both discriminators.
+        tag: String,
+    },
+}
+
+impl DiscriminatorError {
+    /// Convert into a compilation error pointing into the `discriminator` keyword.
+    pub(crate) fn into_validation_error<'a>(
+        self,
+        schema: &'a Value,
+        context: &CompilationContext,
+    ) -> ValidationError<'a> {
+        let (schema_path, instance) = match &self {
+            DiscriminatorError::NotAnObject
+            | DiscriminatorError::MissingPropertyName
+            | DiscriminatorError::NotDiscriminated
+            | DiscriminatorError::PropertyNameMismatch => (context.clone().into_pointer(), schema),
+            DiscriminatorError::InvalidPropertyName => (
+                context.as_pointer_with("propertyName"),
+                &schema["propertyName"],
+            ),
+            DiscriminatorError::InvalidMapping => {
+                (context.as_pointer_with("mapping"), &schema["mapping"])
+            }
+            DiscriminatorError::InvalidMappingValue { tag }
+            | DiscriminatorError::FragmentUnresolvable { tag, .. }
+            | DiscriminatorError::ConflictingTag { tag } => (
+                context.with_path("mapping").as_pointer_with(tag.clone()),
+                &schema["mapping"][tag],
+            ),
+        };
+        ValidationError::discriminator(JSONPointer::default(), schema_path, instance, self)
+    }
+}
+
+impl core::fmt::Display for DiscriminatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscriminatorError::NotAnObject => f.write_str("Discriminator is not an object"),
+            DiscriminatorError::MissingPropertyName => {
+                f.write_str("Discriminator is missing \"propertyName\"")
+            }
+            DiscriminatorError::InvalidPropertyName => {
+                f.write_str("Discriminator \"propertyName\" is not a string or an array of strings")
+            }
+            DiscriminatorError::InvalidMapping => {
+                f.write_str("Discriminator \"mapping\" is not an object")
+            }
+            DiscriminatorError::InvalidMappingValue { tag } => write!(
+                f,
+                "Discriminator \"mapping\" value for \"{}\" is not a string",
+                tag
+            ),
+            DiscriminatorError::FragmentUnresolvable { tag, reference } => write!(
+                f,
+                "Discriminator \"mapping\" value \"{}\" for \"{}\" can't be resolved",
+                reference, tag
+            ),
+            DiscriminatorError::NotDiscriminated => {
+                f.write_str("Merged schema has no discriminator over \"oneOf\" at its root")
+            }
+            DiscriminatorError::PropertyNameMismatch => {
+                f.write_str("Merged discriminators have a different \"propertyName\"")
+            }
+            DiscriminatorError::ConflictingTag { tag } => {
+                write!(f, "Merged discriminators both map \"{}\"", tag)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiscriminatorError {}
+
+/// The canonical spelling of numeric discriminator tags, so that `1`, `1.0` and `1.00` select the
+/// same `mapping` entry.
+///
+/// Applies to numeric tags in instances, to numeric strings and to numeric `mapping` keys alike.
+/// Non-integral numbers are written as `serde_json` writes them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericTagFormat {
+    /// Integral numbers are written without a fraction, e.g. `1`.
+    Integer,
+    /// Integral numbers are written with a single zero fraction digit, e.g. `1.0`.
+    Decimal,
+}
+
+impl NumericTagFormat {
+    /// `tag` with every numeric component rewritten canonically. `None` if nothing changes.
+    pub(crate) fn canonicalize(self, tag: &str) -> Option<String> {
+        let mut changed = false;
+        let components: Vec<Cow<'_, str>> = tag
+            .split(COMPOSITE_TAG_SEPARATOR)
+            .map(|component| match self.canonical_number(component) {
+                Some(number) if number != component => {
+                    changed = true;
+                    Cow::Owned(number)
+                }
+                _ => Cow::Borrowed(component),
+            })
+            .collect();
+        if changed {
+            Some(components.join(&COMPOSITE_TAG_SEPARATOR.to_string()))
+        } else {
+            None
+        }
+    }
+
+    fn canonical_number(self, value: &str) -> Option<String> {
+        if value.trim() != value {
+            return None;
+        }
+        let number: Number = serde_json::from_str(value).ok()?;
+        let integral = if let Some(number) = number.as_i64() {
+            Some(number.to_string())
+        } else if let Some(number) = number.as_u64() {
+            Some(number.to_string())
+        } else {
+            // Beyond 2^53 not every integer is representable, so such floats are left as is
+            number
+                .as_f64()
+                .filter(|number| number.fract() == 0. && number.abs() < 9_007_199_254_740_992.)
+                .map(|number| format!("{:.0}", number))
+        };
+        Some(match (self, integral) {
+            (NumericTagFormat::Integer, Some(integral)) => integral,
+            (NumericTagFormat::Decimal, Some(integral)) => format!("{}.0", integral),
+            (_, None) => number.to_string(),
+        })
+    }
+}
+
+/// The Unicode case folding of `tag`, see
+/// `CompilationOptions::should_match_discriminator_tags_case_insensitively`.
+///
+/// Full folding is approximated by uppercasing before lowercasing, which expands e.g. `ß` to
+/// `ss`. The final sigma `ς`, which lowercasing produces at the end of words, folds to `σ`.
+pub(crate) fn fold_case(tag: &str) -> String {
+    if tag.is_ascii() {
+        return tag.to_ascii_lowercase();
+    }
+    let mut upper = String::with_capacity(tag.len());
+    for character in tag.chars() {
+        // The dotless `ı` folds to itself, while its uppercase `I` folds to `i`
+        if character == 'ı' {
+            upper.push(character);
+        } else {
+            upper.extend(character.to_uppercase());
+        }
+    }
+    upper.to_lowercase().replace('ς', "σ")
+}
+
+/// The branch selected by the tag of an instance, see
+/// [`JSONSchema::resolve_variant`](crate::JSONSchema::resolve_variant).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantInfo {
+    /// The normalized tag of the instance.
+    pub tag: String,
+    /// The `mapping` reference the tag selects, possibly of the `"*"` fallback branch. `None` if
+    /// the tag maps to an array of references.
+    pub reference: Option<String>,
+    /// Index of the `oneOf` subschema that references the mapped schema, if there is one.
+    pub branch: Option<usize>,
+}
+
+/// The part of a discriminator that maps a tag to its branch, see
+/// [`JSONSchema::branches_for_tag`](crate::JSONSchema::branches_for_tag).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingSource {
+    /// An entry of the `mapping` object.
+    Explicit,
+    /// The name of a `oneOf` reference, see
+    /// [`CompilationOptions::should_resolve_implicit_discriminator_mapping`](crate::CompilationOptions::should_resolve_implicit_discriminator_mapping).
+    Implicit,
+    /// The `"*"` entry of the `mapping` object for otherwise unmapped tags.
+    Fallback,
+}
+
+/// The outcome of selecting a branch by the discriminator tag of an instance.
+#[derive(Debug)]
+pub(crate) enum DiscriminationResult<'a, 'i> {
+    /// The tag, or the fallback key for an unmapped tag, selects the branch under `key`.
+    Selected { key: &'a str, node: &'a SchemaNode },
+    /// The instance is an object without a usable value of a key property.
+    MissingProperty,
+    /// The tag is neither mapped nor covered by a fallback branch.
+    UnknownTag(Cow<'i, str>),
+    /// The tag is `null` and there is no `"null"` mapping key. Only reported if `null` tags are
+    /// rejected.
+    NullTag,
+    /// The instance is not an object and can't carry a tag.
+    NonObjectInstance,
+}
+
+/// The outcome of looking up the discriminator tag of an instance in a mapping.
+#[derive(Debug)]
+pub(crate) enum TagSelection<'i> {
+    /// The tag, or the fallback key for an unmapped tag, selects the mapping entry at `position`.
+    Mapped { tag: Cow<'i, str>, position: usize },
+    /// The instance is an object without a usable value of a key property.
+    MissingProperty,
+    /// The tag is neither mapped nor covered by a fallback entry.
+    UnknownTag(Cow<'i, str>),
+    /// The tag is `null` and there is no `"null"` mapping key. Only reported if `null` tags are
+    /// rejected.
+    NullTag,
+    /// The instance is not an object and can't carry a tag.
+    NonObjectInstance,
+}
+
+/// Looks up the tags of instances in a discriminator mapping.
+///
+/// Every kind of discriminator selects its mapping entry through `select`, so they agree on
+/// normalized, composite, `null` and unmapped tags.
+pub(crate) struct TagSelector {
+    property_name: String,
+    // Further properties of a composite key
+    key_properties: Vec<String>,
+    // Position of every normalized tag in the mapping
+    positions: AHashMap<String, usize>,
+    tag_normalizer: Option<TagNormalizer>,
+    // A `null` tag only selects the `"null"` mapping key instead of counting as missing
+    reject_null: bool,
+}
+
+impl TagSelector {
+    fn new(
+        property_name: String,
+        key_properties: Vec<String>,
+        positions: AHashMap<String, usize>,
+        config: &CompilationOptions,
+    ) -> TagSelector {
+        TagSelector {
+            property_name,
+            key_properties,
+            positions,
+            tag_normalizer: config.tag_normalizer().cloned(),
+            reject_null: config.rejects_null_discriminator_values(),
+        }
+    }
+
+    /// A selector over the entries of `discriminator.mapping()`, including its implicit mapping
+    /// if `config` resolves it.
+    pub(crate) fn from_discriminator(
+        discriminator: &Discriminator,
+        config: &CompilationOptions,
+    ) -> TagSelector {
+        let mut positions = AHashMap::with_capacity(discriminator.mapping.len());
+        for (position, (tag, _)) in discriminator.mapping.iter().enumerate() {
+            // Tags that become equal after normalization are mapped to the last entry
+            positions.insert(normalize_with(tag, config.tag_normalizer()), position);
+        }
+        TagSelector::new(
+            discriminator.property_name.clone(),
+            discriminator.key_properties.clone(),
+            positions,
+            config,
+        )
+    }
+
+    /// Select the mapping entry named by the tag of `instance`.
+    pub(crate) fn select<'i>(&self, instance: &'i Value) -> TagSelection<'i> {
+        let object = match instance {
+            Value::Object(object) => object,
+            _ => return TagSelection::NonObjectInstance,
+        };
+        let tag = match composite_tag(
+            instance,
+            &self.property_name,
+            &self.key_properties,
+            self.tag_normalizer.as_ref(),
+        ) {
+            Some(tag) => tag,
+            None if self.reject_null && object.get(&self.property_name) == Some(&Value::Null) => {
+                let tag = self.normalize(NULL_TAG);
+                return match self.positions.get(tag.as_ref()) {
+                    Some(&position) => TagSelection::Mapped { tag, position },
+                    None => TagSelection::NullTag,
+                };
+            }
+            None => return TagSelection::MissingProperty,
+        };
+        match self.position(&tag) {
+            Some(position) => TagSelection::Mapped { tag, position },
+            None => TagSelection::UnknownTag(tag),
+        }
+    }
+
+    /// The position of the normalized `tag`, or of the fallback key if `tag` is not mapped.
+    fn position(&self, tag: &str) -> Option<usize> {
+        self.positions
+            .get(tag)
+            .or_else(|| self.positions.get(FALLBACK_TAG))
+            .copied()
+    }
+
+    fn normalize<'t>(&self, tag: &'t str) -> Cow<'t, str> {
+        match &self.tag_normalizer {
+            Some(normalize) => normalize(tag),
+            None => Cow::Borrowed(tag),
+        }
+    }
+}
+
+pub(crate) struct DiscriminatorValidator {
+    // Positions of the tags in `mapping`
+    selector: TagSelector,
+    // Mapped branches in the order of the `mapping` object, as shown by `Display`
+    mapping: Vec<(String, SchemaNode)>,
+    // Tags that are only mapped implicitly by the names of the `oneOf` references
+    implicit: AHashSet<String>,
+    one_of: OneOfValidator,
+    // References of the `oneOf` subschemas, to find the index of a mapped branch
+    branch_references: Vec<Option<String>>,
+    // `null` is accepted without discrimination, see `is_nullable`
+    nullable: bool,
+    // Objects without the tag only have to satisfy some branch apart from its `required`
+    partial: bool,
+    // Instances without a usable tag are rejected instead of scanning all `oneOf` branches
+    strict: bool,
+    // Tags of branches that don't declare the discriminator property, rejected in strict mode
+    unconstrained: AHashSet<String>,
+    // Properties of the tag, not reported as additional properties of the selected branch
+    permitted: Option<Arc<[String]>>,
+    // Instances with an unmapped tag are validated as if they had no tag, with a warning
+    pass_through: bool,
+    // Tags of branches marked as `deprecated`, reported as warnings
+    deprecated: AHashSet<String>,
+    // `is_valid` results are kept in the memo of the current validation call
+    memoize: bool,
+    // Warnings may be reported, which the memo doesn't keep
+    warns: bool,
+    // Strings are coerced to the types expected by the selected branch if it rejects them
+    coerce: bool,
+    // Estimated cost of the most expensive mapped schema, see `mapping_cost`
+    cost: u32,
+    // What was compiled for the mapped and `oneOf` branches, added to the stats of the schema
+    // that a merged discriminator joins
+    branch_stats: CompilationStats,
+    schema_path: JSONPointer,
+}
+
+impl DiscriminatorValidator {
+    #[inline]
+    pub(crate) fn compile<'a>(
+        parent: &'a Map<String, Value>,
+        schema: &'a Value,
+        one_of: &'a Value,
+        context: &CompilationContext,
+    ) -> Result<BoxedValidator, Box<ValidationError<'a>>> {
+        Ok(Box::new(DiscriminatorValidator::new(
+            parent, schema, one_of, context,
+        )?))
+    }
+
+    fn new<'a>(
+        parent: &'a Map<String, Value>,
+        schema: &'a Value,
+        one_of: &'a Value,
+        context: &CompilationContext,
+    ) -> Result<DiscriminatorValidator, Box<ValidationError<'a>>> {
+        let keyword_context = context.with_path("discriminator");
+        let compiled_before = context.counters.stats();
+        check_size(schema, one_of, context)?;
+        let (strict_schema, unions) = if context.config.uses_lenient_discriminator_mapping() {
+            split_union_mapping(schema)
+        } else {
+            (Cow::Borrowed(schema), Vec::new())
+        };
+        let mut discriminator = Discriminator::try_from_value(&strict_schema)
+            .map_err(|error| error.into_validation_error(schema, &keyword_context))?;
+        let explicit = discriminator.mapping.len();
+        if context.config.resolves_implicit_discriminator_mapping() {
+            discriminator.add_implicit_mapping(one_of, context.config.draft());
+        }
+        let implicit: AHashSet<_> = discriminator.mapping[explicit..]
+            .iter()
+            .map(|(tag, _)| normalize_tag(tag, context))
+            .collect();
+        if discriminator.mapping.is_empty() && unions.is_empty() {
+            // No tag could ever select a branch
+            return Err(Box::new(match schema.get("mapping") {
+                Some(mapping) => ValidationError::min_properties(
+                    JSONPointer::default(),
+                    keyword_context.as_pointer_with("mapping"),
+                    mapping,
+                    1,
+                ),
+                None => ValidationError::required(
+                    JSONPointer::default(),
+                    keyword_context.into_pointer(),
+                    schema,
+                    Value::String("mapping".to_string()),
+                ),
+            }));
+        }
+        check_references(&discriminator, &keyword_context)
+            .map_err(|error| error.into_validation_error(schema, &keyword_context))?;
+        let (mapping, positions) =
+            compile_mapping(&discriminator, &unions, one_of, &keyword_context)?;
+        let strict = context.config.uses_strict_discriminator();
+        if context.config.checks_discriminator_enum() {
+            check_enum(&discriminator, &keyword_context);
+        }
+        if context.config.checks_discriminator_mapping_types() {
+            check_types(&discriminator, &keyword_context);
+        }
+        let unconstrained = if strict {
+            unconstrained_tags(&discriminator, &keyword_context)
+        } else {
+            AHashSet::new()
+        };
+        let cost = mapping_cost(&discriminator, &keyword_context);
+        let deprecated = if context
+            .config
+            .warns_about_deprecated_discriminator_branches()
+        {
+            deprecated_tags(&discriminator, &keyword_context)
+        } else {
+            AHashSet::new()
+        };
+        let branch_references = one_of
+            .as_array()
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .map(|branch| {
+                branch_reference(branch, context.config.draft())
+                    .map(|(_, reference)| reference.to_string())
+            })
+            .collect();
+        let one_of = if strict && context.config.prunes_unused_discriminator_branches() {
+            // Without the `oneOf` fallback, branches outside of the mapping are unreachable
+            OneOfValidator::new_pruned(one_of, context, |branch| {
+                branch_reference(branch, context.config.draft()).map_or(false, |(_, reference)| {
+                    discriminator
+                        .mapping()
+                        .any(|(_, mapped)| mapped == reference)
+                        || unions
+                            .iter()
+                            .any(|(_, references)| references.iter().any(|r| r == reference))
+                })
+            })?
+        } else {
+            OneOfValidator::new(one_of, context)?
+        };
+        let mut branch_stats = context.counters.stats().since(&compiled_before);
+        // The `oneOf` itself joins the one of the discriminator it is merged into
+        branch_stats.one_of_sites -= 1;
+        context.counters.add_discriminator_site();
+        let permitted = context.config.permits_discriminator_property().then(|| {
+            once(&discriminator.property_name)
+                .chain(&discriminator.key_properties)
+                .cloned()
+                .collect()
+        });
+        Ok(DiscriminatorValidator {
+            selector: TagSelector::new(
+                discriminator.property_name,
+                discriminator.key_properties,
+                positions,
+                &context.config,
+            ),
+            mapping,
+            implicit,
+            one_of,
+            branch_references,
+            nullable: is_nullable(parent, context.config.draft()),
+            partial: context.config.allows_partial_discriminated_instances(),
+            strict,
+            unconstrained,
+            permitted,
+            pass_through: context.config.passes_through_unknown_discriminator_values(),
+            deprecated,
+            memoize: context.config.memoizes_discriminated_instances(),
+            warns: context.config.reports_warnings(),
+            coerce: context.config.coerces_types(),
+            cost,
+            branch_stats,
+            schema_path: keyword_context.into_pointer(),
+        })
+    }
+
+    /// What was compiled for the branches of this discriminator, apart from the discriminator
+    /// and its `oneOf` themselves.
+    pub(crate) const fn branch_stats(&self) -> &CompilationStats {
+        &self.branch_stats
+    }
+
+    /// Add the mapping and `oneOf` branches of a discriminator over the same property, e.g. one
+    /// contributed by a plugin. The options of `self` are kept, and branches of `other` follow
+    /// those of `self` in the combined `oneOf`.
+    ///
+    /// Fails without changes if the discriminators read different properties or map the same
+    /// tag, including the fallback key.
+    pub(crate) fn merge(
+        &mut self,
+        other: DiscriminatorValidator,
+    ) -> Result<(), DiscriminatorError> {
+        if self.selector.property_name != other.selector.property_name
+            || self.selector.key_properties != other.selector.key_properties
+        {
+            return Err(DiscriminatorError::PropertyNameMismatch);
+        }
+        if let Some((tag, _)) = other
+            .mapping
+            .iter()
+            .find(|(tag, _)| self.selector.positions.contains_key(tag))
+        {
+            return Err(DiscriminatorError::ConflictingTag { tag: tag.clone() });
+        }
+        for (tag, node) in other.mapping {
+            self.selector
+                .positions
+                .insert(tag.clone(), self.mapping.len());
+            self.mapping.push((tag, node));
+        }
+        self.one_of
+            .merge(other.one_of, self.branch_references.len());
+        self.branch_references.extend(other.branch_references);
+        self.implicit.extend(other.implicit);
+        self.unconstrained.extend(other.unconstrained);
+        self.deprecated.extend(other.deprecated);
+        self.cost = self.cost.max(other.cost);
+        self.branch_stats.include(&other.branch_stats);
+        Ok(())
+    }
+
+    /// Select the branch named by the instance's discriminator tag.
+    ///
+    /// All of `is_valid`, `validate` and `apply` go through this, so they agree on the branch.
+    /// Instances without a usable tag are left to the caller, which either scans all `oneOf`
+    /// branches, validates them partially or rejects them in strict mode.
+    fn discriminate<'i>(&self, instance: &'i Value) -> DiscriminationResult<'_, 'i> {
+        match self.selector.select(instance) {
+            TagSelection::Mapped { position, .. } => {
+                let (key, node) = &self.mapping[position];
+                DiscriminationResult::Selected { key, node }
+            }
+            TagSelection::MissingProperty => DiscriminationResult::MissingProperty,
+            TagSelection::UnknownTag(tag) => DiscriminationResult::UnknownTag(tag),
+            TagSelection::NullTag => DiscriminationResult::NullTag,
+            TagSelection::NonObjectInstance => DiscriminationResult::NonObjectInstance,
+        }
+    }
+
+    /// Whether `tag` selects a branch, either by the explicit or implicit mapping or by the
+    /// fallback key. The branch itself is not evaluated. Composite tags are joined by `,`.
+    pub(crate) fn validate_tag(&self, tag: &str) -> bool {
+        self.selector
+            .position(&self.selector.normalize(tag))
+            .is_some()
+    }
+
+    /// The branch that `tag` selects along with the part of the discriminator that maps it.
+    /// Composite tags are joined by `,`.
+    pub(crate) fn tag_variant(&self, tag: &str) -> Option<(VariantInfo, MappingSource)> {
+        let tag = self.selector.normalize(tag);
+        let (&position, source) = match self.selector.positions.get(tag.as_ref()) {
+            Some(position) if self.implicit.contains(tag.as_ref()) => {
+                (position, MappingSource::Implicit)
+            }
+            Some(position) => (position, MappingSource::Explicit),
+            None => (
+                self.selector.positions.get(FALLBACK_TAG)?,
+                MappingSource::Fallback,
+            ),
+        };
+        Some((self.variant(tag.into_owned(), position), source))
+    }
+
+    /// The branch selected by the tag of `instance`, without validating it.
+    pub(crate) fn resolve_variant<'i>(
+        &self,
+        instance: &'i Value,
+    ) -> Result<VariantInfo, Box<ValidationError<'i>>> {
+        let instance_path = InstancePath::new();
+        let error = match self.selector.select(instance) {
+            TagSelection::Mapped { tag, position } => {
+                return Ok(self.variant(tag.into_owned(), position))
+            }
+            TagSelection::UnknownTag(tag) => ValidationError::unknown_discriminator_value(
+                self.schema_path.clone(),
+                JSONPointer::default(),
+                instance,
+                self.selector.property_name.clone(),
+                tag.into_owned(),
+            ),
+            TagSelection::NullTag => self.null_tag(instance, &instance_path),
+            TagSelection::MissingProperty | TagSelection::NonObjectInstance => {
+                self.missing_tag(instance, &instance_path)
+            }
+        };
+        Err(Box::new(error))
+    }
+
+    /// The `oneOf` the discriminator chooses from.
+    pub(crate) const fn one_of(&self) -> &OneOfValidator {
+        &self.one_of
+    }
+
+    /// The keyword this validator implements.
+    pub(crate) const fn name(&self) -> &'static str {
+        "discriminator"
+    }
+
+    /// The location of the `discriminator` with the tags that select a branch. A fallback
+    /// mapping entry is not listed.
+    pub(crate) fn tags(&self) -> (&JSONPointer, Vec<String>) {
+        (&self.schema_path, mapped_tags(&self.mapping))
+    }
+
+    /// The branch at `position` in the mapping, selected by `tag`.
+    fn variant(&self, tag: String, position: usize) -> VariantInfo {
+        let reference = match mapping_value(&self.mapping[position].1) {
+            Value::String(reference) => Some(reference),
+            _ => None,
+        };
+        let branch = reference.as_ref().and_then(|reference| {
+            self.branch_references
+                .iter()
+                .position(|branch| branch.as_deref() == Some(reference.as_str()))
+        });
+        VariantInfo {
+            tag,
+            reference,
+            branch,
+        }
+    }
+
+    /// The error for an instance without a usable tag in strict mode.
+    fn missing_tag<'i>(
+        &self,
+        instance: &'i Value,
+        instance_path: &InstancePath,
+    ) -> ValidationError<'i> {
+        match instance {
+            Value::Object(object) => {
+                // The first property of the key without a usable value
+                let property_name = self
+                    .key_property_names()
+                    .find(|name| {
+                        !matches!(object.get(*name), Some(Value::String(_) | Value::Number(_)))
+                    })
+                    .unwrap_or(&self.selector.property_name);
+                match object.get(property_name) {
+                    Some(tag) => ValidationError::single_type_error(
+                        self.schema_path.clone(),
+                        instance_path.push(property_name.to_string()).into(),
+                        tag,
+                        PrimitiveType::String,
+                    ),
+                    None => ValidationError::required(
+                        self.schema_path.clone(),
+                        instance_path.into(),
+                        instance,
+                        Value::String(property_name.to_string()),
+                    ),
+                }
+            }
+            _ => ValidationError::single_type_error(
+                self.schema_path.clone(),
+                instance_path.into(),
+                instance,
+                PrimitiveType::Object,
+            ),
+        }
+    }
+
+    /// Whether `instance` is valid, without consulting the memo of the current validation call.
+    fn is_valid_unmemoized(&self, instance: &Value) -> bool {
+        if self.nullable && instance.is_null() {
+            return true;
+        }
+        match self.discriminate(instance) {
+            DiscriminationResult::Selected { key: tag, node } => {
+                self.is_valid_branch(node, instance) && !self.unconstrained.contains(tag)
+            }
+            DiscriminationResult::UnknownTag(_) if self.pass_through => {
+                self.one_of.is_valid(instance)
+            }
+            DiscriminationResult::UnknownTag(_) | DiscriminationResult::NullTag => false,
+            DiscriminationResult::MissingProperty if self.is_partial(instance) => {
+                self.is_valid_partial(instance)
+            }
+            DiscriminationResult::MissingProperty | DiscriminationResult::NonObjectInstance
+                if self.strict =>
+            {
+                false
+            }
+            DiscriminationResult::MissingProperty | DiscriminationResult::NonObjectInstance => {
+                self.one_of.is_valid(instance)
+            }
+        }
+    }
+
+    /// Whether `instance` is valid against the branch selected by its tag.
+    fn is_valid_branch(&self, node: &SchemaNode, instance: &Value) -> bool {
+        // Errors are only inspected for the rare instances that are not valid as they are
+        node.is_valid(instance)
+            || ((self.permitted.is_some() || self.coerce)
+                && !self
+                    .branch_errors(node, instance, &InstancePath::new())
+                    .iter()
+                    .any(ValidationError::is_error))
+    }
+
+    /// Errors of the branch selected by the tag of `instance`.
+    ///
+    /// If the tag is permitted, errors that only report it as an additional property of the
+    /// instance are left out. If types are coerced and the coerced instance passes, only its
+    /// warnings are reported.
+    fn branch_errors<'i>(
+        &self,
+        node: &SchemaNode,
+        instance: &'i Value,
+        instance_path: &InstancePath,
+    ) -> Vec<ValidationError<'i>> {
+        if self.coerce && !node.is_valid(instance) {
+            if let Some(coerced) = coerced(node, instance) {
+                let errors = self.branch_errors(node, &coerced, instance_path);
+                if !errors.iter().any(ValidationError::is_error) {
+                    return errors
+                        .into_iter()
+                        .map(ValidationError::into_owned)
+                        .collect();
+                }
+            }
+        }
+        self.permitted_errors(node, instance, instance_path)
+            .collect()
+    }
+
+    /// Errors of `node`, apart from those that only report the permitted tag.
+    fn permitted_errors<'i>(
+        &self,
+        node: &SchemaNode,
+        instance: &'i Value,
+        instance_path: &InstancePath,
+    ) -> ErrorIterator<'i> {
+        let errors = node.validate(instance, instance_path);
+        match &self.permitted {
+            Some(permitted) => {
+                let permitted = Arc::clone(permitted);
+                let location = JSONPointer::from(instance_path);
+                Box::new(errors.filter_map(move |error| {
+                    unless_permitted(&permitted, error, instance, &location)
+                }))
+            }
+            None => errors,
+        }
+    }
+
+    /// The error for an instance with a `null` tag that no mapping key selects.
+    fn null_tag<'i>(
+        &self,
+        instance: &'i Value,
+        instance_path: &InstancePath,
+    ) -> ValidationError<'i> {
+        ValidationError::null_discriminator_value(
+            self.schema_path.clone(),
+            instance_path.into(),
+            instance,
+            self.selector.property_name.clone(),
+        )
+    }
+
+    /// The error for an instance that is only valid because its branch ignores the tag.
+    fn unconstrained_property<'i>(
+        &self,
+        instance: &'i Value,
+        instance_path: &InstancePath,
+        tag: &str,
+    ) -> ValidationError<'i> {
+        ValidationError::unconstrained_discriminator_property(
+            self.schema_path.clone(),
+            instance_path.into(),
+            instance,
+            self.selector.property_name.clone(),
+            tag.to_string(),
+        )
+    }
+
+    /// Whether `instance` is an object without the tag that should be validated partially.
+    fn is_partial(&self, instance: &Value) -> bool {
+        self.partial
+            && instance.as_object().map_or(false, |object| {
+                self.key_property_names()
+                    .any(|name| !object.contains_key(name))
+            })
+    }
+
+    /// The names of the properties forming the tag.
+    fn key_property_names(&self) -> impl Iterator<Item = &str> {
+        once(self.selector.property_name.as_str())
+            .chain(self.selector.key_properties.iter().map(String::as_str))
+    }
+
+    /// Whether `instance` is valid against any mapped branch when the branch's own `required`
+    /// properties are ignored.
+    fn is_valid_partial(&self, instance: &Value) -> bool {
+        let instance_path = InstancePath::new();
+        self.mapping.iter().any(|(_, node)| {
+            node.validate(instance, &instance_path).all(|error| {
+                !error.is_error()
+                    || (matches!(error.kind, ValidationErrorKind::Required { .. })
+                        && error.instance_path.as_slice().is_empty())
+            })
+        })
+    }
+}
+
+/// The normalized value of the `property_name` tag of `instance`, if it is a string or a number.
+fn instance_tag<'i>(
+    instance: &'i Value,
+    property_name: &str,
+    tag_normalizer: Option<&TagNormalizer>,
+) -> Option<Cow<'i, str>> {
+    let tag = match instance.as_object()?.get(property_name)? {
+        Value::String(tag) => Cow::Borrowed(tag.as_str()),
+        Value::Number(tag) => Cow::Owned(tag.to_string()),
+        _ => return None,
+    };
+    Some(match tag_normalizer {
+        Some(normalize) => Cow::Owned(normalize(&tag).into_owned()),
+        None => tag,
+    })
+}
+
+/// The normalized tag of `instance`, the values of `property_name` and `key_properties` joined
+/// by `,` for a composite key. `None` if any of them is absent or not a string or a number.
+pub(crate) fn composite_tag<'i>(
+    instance: &'i Value,
+    property_name: &str,
+    key_properties: &[String],
+    tag_normalizer: Option<&TagNormalizer>,
+) -> Option<Cow<'i, str>> {
+    let tag = instance_tag(instance, property_name, tag_normalizer)?;
+    if key_properties.is_empty() {
+        return Some(tag);
+    }
+    let mut tag = tag.into_owned();
+    for property_name in key_properties {
+        tag.push(COMPOSITE_TAG_SEPARATOR);
+        tag.push_str(&instance_tag(instance, property_name, tag_normalizer)?);
+    }
+    Some(Cow::Owned(tag))
+}
+
+/// The tags of a compiled mapping, without the fallback entry.
+fn mapped_tags(mapping: &[(String, SchemaNode)]) -> Vec<String> {
+    mapping
+        .iter()
+        .map(|(tag, _)| tag)
+        .filter(|tag| *tag != FALLBACK_TAG)
+        .cloned()
+        .collect()
+}
+
+/// Whether the discriminated schema accepts `null`.
+///
+/// OpenAPI 3.0 builds on the older drafts and marks such schemas with `nullable: true`, while
+/// OpenAPI 3.1 uses 2020-12 where `null` is listed in `type` instead.
+fn is_nullable(parent: &Map<String, Value>, draft: Draft) -> bool {
+    match draft {
+        Draft::Draft4 | Draft::Draft6 | Draft::Draft7 => parent
+            .get("nullable")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        #[cfg(any(feature = "draft201909", feature = "draft202012"))]
+        _ => match parent.get("type") {
+            Some(Value::String(type_)) => type_ == "null",
+            Some(Value::Array(types)) => types.iter().any(|type_| type_ == "null"),
+            _ => false,
+        },
+    }
+}
+
+/// Mapped branches in mapping order, along with the position of every tag.
+type CompiledMapping = (Vec<(String, SchemaNode)>, AHashMap<String, usize>);
+
+/// The reference keywords a `oneOf` branch may use to name a mapped schema in `draft`.
+const fn reference_keywords(draft: Draft) -> &'static [&'static str] {
+    match draft {
+        #[cfg(feature = "draft201909")]
+        Draft::Draft201909 => &["$ref", "$recursiveRef"],
+        #[cfg(feature = "draft202012")]
+        Draft::Draft202012 => &["$ref", "$dynamicRef"],
+        _ => &["$ref"],
+    }
+}
+
+/// The reference keyword and reference of a `oneOf` branch, e.g. `{"$dynamicRef": "#/$defs/Cat"}`.
+pub(crate) fn branch_reference(branch: &Value, draft: Draft) -> Option<(&'static str, &str)> {
+    let branch = branch.as_object()?;
+    reference_keywords(draft).iter().find_map(|keyword| {
+        branch
+            .get(*keyword)
+            .and_then(Value::as_str)
+            .map(|reference| (*keyword, reference))
+    })
+}
+
+/// Remove the mapping values that are arrays of references from a `discriminator`.
+///
+/// Returns the remaining discriminator along with the tags of the removed values and their
+/// references. Arrays with non-string items are kept, so they are reported as invalid.
+fn split_union_mapping(schema: &Value) -> (Cow<'_, Value>, Vec<(String, Vec<String>)>) {
+    let unions: Vec<_> = schema
+        .get("mapping")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter_map(|(tag, value)| {
+            let references = value
+                .as_array()?
+                .iter()
+                .map(|reference| reference.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()?;
+            Some((tag.clone(), references))
+        })
+        .collect();
+    if unions.is_empty() {
+        return (Cow::Borrowed(schema), unions);
+    }
+    let mut strict = schema.clone();
+    if let Some(mapping) = strict.get_mut("mapping").and_then(Value::as_object_mut) {
+        for (tag, _) in &unions {
+            mapping.remove(tag);
+        }
+    }
+    (Cow::Owned(strict), unions)
+}
+
+/// Compile every mapping value as a reference to the branch it names.
+///
+/// The reference is compiled under the keyword used by the `oneOf` branch with the same
+/// reference, so `$dynamicRef` and `$recursiveRef` branches are recognized in the drafts that
+/// define them, and resolved against the anchors of the root schema like those keywords.
+fn compile_mapping<'a>(
+    discriminator: &Discriminator,
+    unions: &[(String, Vec<String>)],
+    one_of: &Value,
+    context: &CompilationContext,
+) -> Result<CompiledMapping, Box<ValidationError<'a>>> {
+    let branches = one_of.as_array().map_or(&[][..], Vec::as_slice);
+    let mapping_context = context.with_path("mapping");
+    let mut nodes = Vec::with_capacity(discriminator.mapping.len());
+    let mut positions = AHashMap::with_capacity(discriminator.mapping.len());
+    for (tag, reference) in discriminator.mapping() {
+        let tag_context = mapping_context.with_path(tag.to_string());
+        let tag_context = tag_context.for_branch(reference);
+        let keyword = branches
+            .iter()
+            .filter_map(|branch| branch_reference(branch, context.config.draft()))
+            .find(|(_, branch_reference)| *branch_reference == reference)
+            .map_or("$ref", |(keyword, _)| keyword);
+        let validator = ref_::compile_reference(keyword, reference, &tag_context)?;
+        context.counters.add_validators(1);
+        let tag = normalize_tag(tag, context);
+        let node = SchemaNode::new_from_keywords(
+            &tag_context,
+            vec![(keyword.to_string(), validator)],
+            None,
+        );
+        insert_mapped(&mut nodes, &mut positions, tag, node);
+    }
+    for (tag, references) in unions {
+        let tag_context = mapping_context.with_path(tag.clone());
+        let any_of = Value::Array(
+            references
+                .iter()
+                .map(|reference| {
+                    let mut branch = Map::with_capacity(1);
+                    branch.insert("$ref".to_string(), Value::String(reference.clone()));
+                    Value::Object(branch)
+                })
+                .collect(),
+        );
+        let validator = AnyOfValidator::compile(&Map::new(), &any_of, &tag_context)
+            .map_err(ValidationError::into_owned)?;
+        context.counters.add_validators(1);
+        let tag = normalize_tag(tag, context);
+        let node = SchemaNode::new_from_keywords(
+            &tag_context,
+            vec![("anyOf".to_string(), validator)],
+            None,
+        );
+        insert_mapped(&mut nodes, &mut positions, tag, node);
+    }
+    Ok((nodes, positions))
+}
+
+/// Ensure that neither the `mapping` nor the `oneOf` exceed
+/// `CompilationOptions::with_max_discriminator_mapping_size`, before any of them is compiled.
+fn check_size<'a>(
+    schema: &'a Value,
+    one_of: &'a Value,
+    context: &CompilationContext,
+) -> Result<(), Box<ValidationError<'a>>> {
+    let limit = context.config.max_discriminator_mapping_size();
+    match schema.get("mapping") {
+        Some(mapping @ Value::Object(entries)) if entries.len() > limit => {
+            return Err(Box::new(ValidationError::max_properties(
+                JSONPointer::default(),
+                context
+                    .with_path("discriminator")
+                    .as_pointer_with("mapping"),
+                mapping,
+                limit as u64,
+            )));
+        }
+        _ => {}
+    }
+    match one_of {
+        Value::Array(branches) if branches.len() > limit => {
+            Err(Box::new(ValidationError::max_items(
+                JSONPointer::default(),
+                context.as_pointer_with("oneOf"),
+                one_of,
+                limit as u64,
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// With a strict discriminator, ensure that mapping references into the same document point to
+/// existing locations. Other references are resolved on first use, like any `$ref`.
+fn check_references(
+    discriminator: &Discriminator,
+    context: &CompilationContext,
+) -> Result<(), DiscriminatorError> {
+    if !context.config.uses_strict_discriminator() {
+        return Ok(());
+    }
+    for (tag, reference) in discriminator.mapping() {
+        if !reference.starts_with('#') {
+            continue;
+        }
+        let resolved = context.build_url(reference).map_or(false, |url| {
+            context
+                .resolver
+                .resolve_fragment(context.config.draft(), &url, reference)
+                .is_ok()
+        });
+        if !resolved {
+            return Err(DiscriminatorError::FragmentUnresolvable {
+                tag: tag.to_string(),
+                reference: reference.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The mapping key `tag` as it is looked up, the fallback key is never normalized.
+fn normalize_tag(tag: &str, context: &CompilationContext) -> String {
+    normalize_with(tag, context.config.tag_normalizer())
+}
+
+/// The mapping key `tag` normalized by `tag_normalizer`, the fallback key is never normalized.
+fn normalize_with(tag: &str, tag_normalizer: Option<&TagNormalizer>) -> String {
+    match tag_normalizer {
+        Some(normalize) if tag != FALLBACK_TAG => normalize(tag).into_owned(),
+        _ => tag.to_string(),
+    }
+}
+
+/// Tags of the mapped schemas that don't list the discriminator property under `properties`.
+///
+/// Such a branch accepts any tag value, which usually means that the mapping points to the
+/// wrong schema. Schemas that can't be resolved are left to fail during validation instead.
+fn unconstrained_tags(
+    discriminator: &Discriminator,
+    context: &CompilationContext,
+) -> AHashSet<String> {
+    discriminator
+        .mapping()
+        .filter(|(tag, reference)| {
+            *tag != FALLBACK_TAG
+                && property_declarations(reference, &discriminator.property_name, context)
+                    .map_or(false, |declarations| declarations.is_empty())
+        })
+        .map(|(tag, _)| normalize_tag(tag, context))
+        .collect()
+}
+
+/// Tags of the mapped schemas marked with `deprecated: true`. Schemas that can't be resolved are
+/// left to fail during validation instead.
+fn deprecated_tags(
+    discriminator: &Discriminator,
+    context: &CompilationContext,
+) -> AHashSet<String> {
+    discriminator
+        .mapping()
+        .filter(|(_, reference)| {
+            context.build_url(reference).map_or(false, |url| {
+                context
+                    .resolver
+                    .resolve_fragment(context.config.draft(), &url, reference)
+                    .map_or(false, |(_, schema)| {
+                        schema.get("deprecated") == Some(&Value::Bool(true))
+                    })
+            })
+        })
+        .map(|(tag, _)| normalize_tag(tag, context))
+        .collect()
+}
+
+/// The cost of checking an instance against its mapped schema, measured by the size of the
+/// largest mapped schema. Mapped schemas are behind `$ref` and compiled lazily, so their
+/// validators can't report it yet.
+fn mapping_cost(discriminator: &Discriminator, context: &CompilationContext) -> u32 {
+    discriminator
+        .mapping()
+        .map(|(_, reference)| {
+            context.build_url(reference).map_or(1, |url| {
+                reference_cost(&context.resolver, context.config.draft(), &url, reference)
+            })
+        })
+        .max()
+        .unwrap_or(0)
+        .saturating_add(1)
+}
+
+/// Report mapping keys that are not allowed by an `enum` of the discriminator property in the
+/// mapped schema.
+fn check_enum(discriminator: &Discriminator, context: &CompilationContext) {
+    if !discriminator.key_properties.is_empty() {
+        // Composite tags are not values of a single property
+        return;
+    }
+    let mapping_context = context.with_path("mapping");
+    for (tag, reference) in discriminator.mapping() {
+        if tag == FALLBACK_TAG {
+            continue;
+        }
+        let declarations = property_declarations(reference, &discriminator.property_name, context)
+            .unwrap_or_default();
+        let rejected = declarations.iter().any(|declaration| {
+            declaration
+                .get("enum")
+                .and_then(Value::as_array)
+                .map_or(false, |values| {
+                    !values.iter().any(|value| match value {
+                        Value::String(value) => value == tag,
+                        Value::Number(value) => value.to_string() == tag,
+                        _ => false,
+                    })
+                })
+        });
+        if rejected {
+            context.counters.add_diagnostic(format!(
+                r#"{}: "{}" is not allowed by the "{}" enum of "{}""#,
+                mapping_context.as_pointer_with(tag.to_string()),
+                tag,
+                discriminator.property_name,
+                reference
+            ));
+        }
+    }
+}
+
+/// Report mapping references to schemas that don't accept objects by their `type`. References
+/// that can't be resolved are reported by `check_references` or on first use instead.
+fn check_types(discriminator: &Discriminator, context: &CompilationContext) {
+    let mapping_context = context.with_path("mapping");
+    for (tag, reference) in discriminator.mapping() {
+        let declared = context.build_url(reference).ok().and_then(|url| {
+            context
+                .resolver
+                .resolve_fragment(context.config.draft(), &url, reference)
+                .ok()
+                .and_then(|(_, schema)| schema.get("type").cloned())
+        });
+        let accepts_objects = match &declared {
+            None => true,
+            Some(Value::String(name)) => name == "object",
+            Some(Value::Array(names)) => names.iter().any(|name| name == "object"),
+            Some(_) => false,
+        };
+        if let Some(declared) = declared.filter(|_| !accepts_objects) {
+            context.counters.add_diagnostic(format!(
+                r#"{}: "{}" is not an object schema, its type is {}"#,
+                mapping_context.as_pointer_with(tag.to_string()),
+                reference,
+                declared
+            ));
+        }
+    }
+}
+
+/// How deep `$ref` is followed when looking for the discriminator property.
+const MAX_DECLARATION_DEPTH: usize = 16;
+
+/// The subschemas of `property_name` in the schema at `reference`, declared directly, in an
+/// `allOf` item, or in a schema referenced by either.
+///
+/// `None` if some of these schemas can't be resolved.
+fn property_declarations(
+    reference: &str,
+    property_name: &str,
+    context: &CompilationContext,
+) -> Option<Vec<Value>> {
+    let mut declarations = Vec::new();
+    collect_declarations(
+        reference,
+        property_name,
+        context,
+        MAX_DECLARATION_DEPTH,
+        &mut declarations,
+    )?;
+    Some(declarations)
+}
+
+fn collect_declarations(
+    reference: &str,
+    property_name: &str,
+    context: &CompilationContext,
+    depth: usize,
+    declarations: &mut Vec<Value>,
+) -> Option<()> {
+    if depth == 0 {
+        return None;
+    }
+    let url = context.build_url(reference).ok()?;
+    let (scope, schema) = context
+        .resolver
+        .resolve_fragment(context.config.draft(), &url, reference)
+        .ok()?;
+    let scope_context = CompilationContext::new(
+        scope.into(),
+        Arc::clone(&context.config),
+        Arc::clone(&context.resolver),
+    );
+    collect_schema_declarations(
+        &schema,
+        property_name,
+        &scope_context,
+        depth - 1,
+        declarations,
+    )
+}
+
+fn collect_schema_declarations(
+    schema: &Value,
+    property_name: &str,
+    context: &CompilationContext,
+    depth: usize,
+    declarations: &mut Vec<Value>,
+) -> Option<()> {
+    if let Some(declaration) = schema
+        .get("properties")
+        .and_then(|properties| properties.get(property_name))
+    {
+        declarations.push(declaration.clone());
+    }
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        collect_declarations(reference, property_name, context, depth, declarations)?;
+    }
+    for item in schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        collect_schema_declarations(item, property_name, context, depth, declarations)?;
+    }
+    Some(())
+}
+
+/// The `discriminator` object a compiled mapping was built from.
+fn discriminator_value(
+    property_name: &str,
+    key_properties: &[String],
+    mapping: &[(String, SchemaNode)],
+) -> Value {
+    let property_name = if key_properties.is_empty() {
+        Value::String(property_name.to_string())
+    } else {
+        Value::Array(
+            once(property_name)
+                .chain(key_properties.iter().map(String::as_str))
+                .map(|name| Value::String(name.to_string()))
+                .collect(),
+        )
+    };
+    let mut discriminator = Map::with_capacity(2);
+    discriminator.insert("propertyName".to_string(), property_name);
+    discriminator.insert(
+        "mapping".to_string(),
+        Value::Object(
+            mapping
+                .iter()
+                .map(|(tag, node)| (tag.clone(), mapping_value(node)))
+                .collect(),
+        ),
+    );
+    Value::Object(discriminator)
+}
+
+/// The mapping value `node` was compiled from, a reference or an array of references.
+fn mapping_value(node: &SchemaNode) -> Value {
+    match node.to_value() {
+        Value::Object(mut schema) => match (schema.remove("$ref"), schema.remove("anyOf")) {
+            (Some(reference), _) => reference,
+            (None, Some(Value::Array(branches))) => Value::Array(
+                branches
+                    .into_iter()
+                    .filter_map(|mut branch| branch.get_mut("$ref").map(Value::take))
+                    .collect(),
+            ),
+            _ => Value::Object(schema),
+        },
+        other => other,
+    }
+}
+
+/// `error` of a discriminated branch at `location`, unless it only reports `permitted` tag
+/// properties as additional properties of `instance`.
+fn unless_permitted<'i>(
+    permitted: &[String],
+    mut error: ValidationError<'i>,
+    instance: &Value,
+    location: &JSONPointer,
+) -> Option<ValidationError<'i>> {
+    if error.instance_path != *location {
+        return Some(error);
+    }
+    match &mut error.kind {
+        ValidationErrorKind::AdditionalProperties { unexpected } => {
+            unexpected.retain(|property| !permitted.iter().any(|name| name == property));
+            if unexpected.is_empty() {
+                return None;
+            }
+        }
+        // `additionalProperties: false` without `properties` rejects any property
+        ValidationErrorKind::FalseSchema
+            if error.schema_path.last() == Some(&PathChunk::Keyword("additionalProperties"))
+                && instance.as_object().map_or(false, |object| {
+                    !object.is_empty()
+                        && object
+                            .keys()
+                            .all(|property| permitted.iter().any(|name| name == property))
+                }) =>
+        {
+            return None;
+        }
+        _ => {}
+    }
+    Some(error)
+}
+
+fn insert_mapped(
+    nodes: &mut Vec<(String, SchemaNode)>,
+    positions: &mut AHashMap<String, usize>,
+    tag: String,
+    node: SchemaNode,
+) {
+    // Tags that become equal after normalization are mapped to the last branch
+    match positions.get(&tag) {
+        Some(&position) => nodes[position] = (tag, node),
+        None => {
+            positions.insert(tag.clone(), nodes.len());
+            nodes.push((tag, node));
+        }
+    }
+}
+
+impl Validate for DiscriminatorValidator {
+    fn is_valid(&self, instance: &Value) -> bool {
+        // Results within a dispatch skip the schemas that are already being dispatched to, so
+        // they are not valid outside of it
+        if self.memoize && !Dispatch::is_dispatching() {
+            let id: *const DiscriminatorValidator = self;
+            cache::memoized(id as usize, instance, || self.is_valid_unmemoized(instance))
+        } else {
+            self.is_valid_unmemoized(instance)
+        }
+    }
+
+    fn validate<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> ErrorIterator<'instance> {
+        if self.nullable && instance.is_null() {
+            return no_error();
+        }
+        // Valid sub-instances are answered by the memo, only invalid ones are validated again.
+        // Valid instances may still have warnings, so they are validated again if there can be any
+        if self.memoize
+            && !self.warns
+            && cache::is_active()
+            && !Dispatch::is_dispatching()
+            && self.is_valid(instance)
+        {
+            return no_error();
+        }
+        match self.discriminate(instance) {
+            DiscriminationResult::Selected { key: tag, node } => {
+                let errors: ErrorIterator<'instance> = if self.coerce {
+                    // Coercion is only attempted for instances that fail the branch
+                    Box::new(
+                        self.branch_errors(node, instance, instance_path)
+                            .into_iter(),
+                    )
+                } else {
+                    self.permitted_errors(node, instance, instance_path)
+                };
+                let errors: ErrorIterator<'instance> = if self.unconstrained.contains(tag) {
+                    let errors: Vec<_> = errors.collect();
+                    if errors.iter().any(ValidationError::is_error) {
+                        Box::new(errors.into_iter())
+                    } else {
+                        let error = self.unconstrained_property(instance, instance_path, tag);
+                        Box::new(once(error).chain(errors))
+                    }
+                } else {
+                    errors
+                };
+                if self.deprecated.contains(tag) {
+                    let warning = ValidationError::deprecated_discriminator_value(
+                        self.schema_path.clone(),
+                        instance_path.into(),
+                        instance,
+                        self.selector.property_name.clone(),
+                        tag.to_string(),
+                    );
+                    Box::new(errors.chain(once(warning.into_warning())))
+                } else {
+                    errors
+                }
+            }
+            DiscriminationResult::UnknownTag(tag) if self.pass_through => {
+                let warning = ValidationError::unknown_discriminator_value(
+                    self.schema_path.clone(),
+                    instance_path.into(),
+                    instance,
+                    self.selector.property_name.clone(),
+                    tag.into_owned(),
+                );
+                Box::new(
+                    once(warning.into_warning())
+                        .chain(self.one_of.validate(instance, instance_path)),
+                )
+            }
+            DiscriminationResult::UnknownTag(tag) => {
+                error(ValidationError::unknown_discriminator_value(
+                    self.schema_path.clone(),
+                    instance_path.into(),
+                    instance,
+                    self.selector.property_name.clone(),
+                    tag.into_owned(),
+                ))
+            }
+            DiscriminationResult::NullTag => error(self.null_tag(instance, instance_path)),
+            DiscriminationResult::MissingProperty
+                if self.is_partial(instance) && self.is_valid_partial(instance) =>
+            {
+                no_error()
+            }
+            DiscriminationResult::MissingProperty | DiscriminationResult::NonObjectInstance
+                if self.strict =>
+            {
+                error(self.missing_tag(instance, instance_path))
+            }
+            DiscriminationResult::MissingProperty | DiscriminationResult::NonObjectInstance => {
+                self.one_of.validate(instance, instance_path)
+            }
+        }
+    }
+
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        if self.nullable && instance.is_null() {
+            return None;
+        }
+        match self.discriminate(instance) {
+            // A coerced instance needs all errors of the branch
+            DiscriminationResult::Selected { key: tag, node } if !self.coerce => {
+                let error = match node.first_error(instance, instance_path) {
+                    Some(error) => error,
+                    None => {
+                        return self
+                            .unconstrained
+                            .contains(tag)
+                            .then(|| self.unconstrained_property(instance, instance_path, tag))
+                    }
+                };
+                let permitted = match &self.permitted {
+                    Some(permitted) => permitted,
+                    None => return Some(error),
+                };
+                let location = JSONPointer::from(instance_path);
+                match unless_permitted(permitted, error, instance, &location) {
+                    Some(error) => Some(error),
+                    // Rare, the permitted tag was reported before other errors of the branch
+                    None => self
+                        .validate(instance, instance_path)
+                        .find(ValidationError::is_error),
+                }
+            }
+            DiscriminationResult::MissingProperty | DiscriminationResult::NonObjectInstance
+                if !self.strict && !self.is_partial(instance) =>
+            {
+                self.one_of.first_error(instance, instance_path)
+            }
+            _ => self
+                .validate(instance, instance_path)
+                .find(ValidationError::is_error),
+        }
+    }
+
+    fn apply<'a>(
+        &'a self,
+        instance: &Value,
+        instance_path: &InstancePath,
+    ) -> PartialApplication<'a> {
+        if self.nullable && instance.is_null() {
+            return PartialApplication::valid_empty();
+        }
+        match self.discriminate(instance) {
+            DiscriminationResult::Selected { key: tag, node } => {
+                let coerced_instance = if self.coerce && !node.is_valid(instance) {
+                    coerced(node, instance)
+                } else {
+                    None
+                };
+                let branch_instance = coerced_instance.as_ref().unwrap_or(instance);
+                match node.apply_rooted(branch_instance, instance_path) {
+                    BasicOutput::Valid(..) if self.unconstrained.contains(tag) => {
+                        PartialApplication::invalid_empty(vec![self
+                            .unconstrained_property(instance, instance_path, tag)
+                            .into()])
+                    }
+                    output @ BasicOutput::Valid(..) => output.into(),
+                    // Only the permitted tag is rejected, annotations of the branch are not collected
+                    BasicOutput::Invalid(..)
+                        if self.permitted.is_some()
+                            && !self
+                                .branch_errors(node, instance, instance_path)
+                                .iter()
+                                .any(ValidationError::is_error) =>
+                    {
+                        if self.unconstrained.contains(tag) {
+                            PartialApplication::invalid_empty(vec![self
+                                .unconstrained_property(instance, instance_path, tag)
+                                .into()])
+                        } else {
+                            PartialApplication::valid_empty()
+                        }
+                    }
+                    // Record the attempted branch, so the failure can be attributed to it
+                    BasicOutput::Invalid(child_results) => PartialApplication::Invalid {
+                        errors: vec![format!(
+                            r#"The "{}" branch selected by the "{}" discriminator is not valid"#,
+                            tag, self.selector.property_name
+                        )
+                        .as_str()
+                        .into()],
+                        child_results,
+                    },
+                }
+            }
+            DiscriminationResult::UnknownTag(_) if self.pass_through => {
+                self.one_of.apply(instance, instance_path)
+            }
+            DiscriminationResult::UnknownTag(tag) => PartialApplication::invalid_empty(vec![
+                ValidationError::unknown_discriminator_value(
+                    self.schema_path.clone(),
+                    instance_path.into(),
+                    instance,
+                    self.selector.property_name.clone(),
+                    tag.into_owned(),
+                )
+                .into(),
+            ]),
+            DiscriminationResult::NullTag => PartialApplication::invalid_empty(vec![self
+                .null_tag(instance, instance_path)
+                .into()]),
+            DiscriminationResult::MissingProperty
+                if self.is_partial(instance) && self.is_valid_partial(instance) =>
+            {
+                PartialApplication::valid_empty()
+            }
+            DiscriminationResult::MissingProperty | DiscriminationResult::NonObjectInstance
+                if self.strict =>
+            {
+                PartialApplication::invalid_empty(vec![self
+                    .missing_tag(instance, instance_path)
+                    .into()])
+            }
+            DiscriminationResult::MissingProperty | DiscriminationResult::NonObjectInstance => {
+                self.one_of.apply(instance, instance_path)
+            }
+        }
+    }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        // Unmapped `oneOf` branches are still checked for instances without a usable tag
+        self.mapping
+            .iter()
+            .map(|(_, node)| node)
+            .chain(self.one_of.child_nodes())
+            .collect()
+    }
+
+    fn estimated_cost(&self) -> u32 {
+        // A tagged instance is checked against its mapped branch only
+        self.cost
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        self.one_of.write_schema(schema);
+        schema.insert(
+            "discriminator".to_string(),
+            discriminator_value(
+                &self.selector.property_name,
+                &self.selector.key_properties,
+                &self.mapping,
+            ),
+        );
+    }
+}
+
+impl core::fmt::Display for DiscriminatorValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {{propertyName: {}, mapping: {{{}}}}}, {}",
+            self.name(),
+            self.key_property_names().collect::<Vec<_>>().join(", "),
+            format_key_value_validators(&self.mapping),
+            self.one_of
+        )
+    }
+}
+
+thread_local! {
+    // Instances that are being validated against their mapped schema on this thread, as instance
+    // addresses along with the discriminator property and tag. The base schema is compiled anew
+    // for every `$ref` to it, so validators can't be told apart by their addresses
+    static DISPATCHING: RefCell<Vec<(usize, String, String)>> = RefCell::new(Vec::new());
+}
+
+/// The schema mapped to a tag along with the tag, or the unmapped tag.
+type MappedTag<'a, 'i> = Result<(Cow<'i, str>, &'a SchemaNode), Cow<'i, str>>;
+
+/// How many inherited discriminators may dispatch the same instance at once.
+const MAX_DISPATCH_DEPTH: usize = 64;
+
+/// Marks an instance as being validated against its mapped schema until dropped.
+struct Dispatch;
+
+/// The outcome of entering a dispatch.
+enum DispatchEntry {
+    Entered(Dispatch),
+    /// `instance` is already being validated against the schema mapped to the tag, e.g. because
+    /// the mapped schema includes the base schema with `allOf`. That validation covers it.
+    Active,
+    /// Too many discriminators dispatch `instance` already.
+    TooDeep,
+}
+
+impl Dispatch {
+    fn enter(instance: &Value, property_name: &str, tag: &str) -> DispatchEntry {
+        let instance: *const Value = instance;
+        let instance = instance as usize;
+        DISPATCHING.with(|dispatching| {
+            let mut dispatching = dispatching.borrow_mut();
+            // Nested instances have their own depth, only the same instance can loop
+            let mut depth = 0;
+            for (active, active_property, active_tag) in dispatching.iter() {
+                if *active == instance {
+                    if active_property == property_name && active_tag == tag {
+                        return DispatchEntry::Active;
+                    }
+                    depth += 1;
+                }
+            }
+            if depth >= MAX_DISPATCH_DEPTH {
+                DispatchEntry::TooDeep
+            } else {
+                dispatching.push((instance, property_name.to_string(), tag.to_string()));
+                DispatchEntry::Entered(Dispatch)
+            }
+        })
+    }
+
+    /// Whether any instance is being validated against its mapped schema on this thread.
+    fn is_dispatching() -> bool {
+        DISPATCHING.with(|dispatching| !dispatching.borrow().is_empty())
+    }
+}
+
+impl Drop for Dispatch {
+    fn drop(&mut self) {
+        DISPATCHING.with(|dispatching| {
+            dispatching.borrow_mut().pop();
+        });
+    }
+}
+
+/// A `discriminator` on a base schema without `oneOf` or `anyOf`, as in OpenAPI inheritance:
+/// the mapped schemas include the base with `allOf`, and instances of the base are validated
+/// against the schema mapped to their tag.
+///
+/// Instances without a tag are only validated against the base.
+pub(crate) struct InheritedDiscriminatorValidator {
+    selector: TagSelector,
+    mapping: Vec<(String, SchemaNode)>,
+    schema_path: JSONPointer,
+}
+
+impl InheritedDiscriminatorValidator {
+    #[inline]
+    pub(crate) fn compile<'a>(
+        schema: &'a Value,
+        discriminator: Discriminator,
+        context: &CompilationContext,
+    ) -> Result<BoxedValidator, Box<ValidationError<'a>>> {
+        let keyword_context = context.with_path("discriminator");
+        check_references(&discriminator, &keyword_context)
+            .map_err(|error| error.into_validation_error(schema, &keyword_context))?;
+        let (mapping, positions) =
+            compile_mapping(&discriminator, &[], &Value::Null, &keyword_context)?;
+        context.counters.add_discriminator_site();
+        Ok(Box::new(InheritedDiscriminatorValidator {
+            selector: TagSelector::new(
+                discriminator.property_name,
+                discriminator.key_properties,
+                positions,
+                &context.config,
+            ),
+            mapping,
+            schema_path: keyword_context.into_pointer(),
+        }))
+    }
+
+    /// The keyword this validator implements.
+    pub(crate) const fn name(&self) -> &'static str {
+        "discriminator"
+    }
+
+    /// The location of the `discriminator` with the tags that select a branch, see
+    /// `DiscriminatorValidator::tags`.
+    pub(crate) fn tags(&self) -> (&JSONPointer, Vec<String>) {
+        (&self.schema_path, mapped_tags(&self.mapping))
+    }
+
+    /// The schema mapped to the tag of `instance` along with the tag, or the unmapped tag. A
+    /// rejected `null` tag counts as unmapped. `None` without a tag.
+    fn mapped<'i>(&self, instance: &'i Value) -> Option<MappedTag<'_, 'i>> {
+        match self.selector.select(instance) {
+            TagSelection::Mapped { tag, position } => Some(Ok((tag, &self.mapping[position].1))),
+            TagSelection::UnknownTag(tag) => Some(Err(tag)),
+            TagSelection::NullTag => Some(Err(Cow::Borrowed(NULL_TAG))),
+            TagSelection::MissingProperty | TagSelection::NonObjectInstance => None,
+        }
+    }
+
+    fn unknown_value<'i>(
+        &self,
+        instance: &'i Value,
+        instance_path: &InstancePath,
+        tag: Cow<'_, str>,
+    ) -> ValidationError<'i> {
+        ValidationError::unknown_discriminator_value(
+            self.schema_path.clone(),
+            instance_path.into(),
+            instance,
+            self.selector.property_name.clone(),
+            tag.into_owned(),
+        )
+    }
+
+    fn too_deep<'i>(
+        &self,
+        instance: &'i Value,
+        instance_path: &InstancePath,
+    ) -> ValidationError<'i> {
+        ValidationError::discriminator_depth_exceeded(
+            self.schema_path.clone(),
+            instance_path.into(),
+            instance,
+            MAX_DISPATCH_DEPTH,
+        )
+    }
+}
+
+impl Validate for InheritedDiscriminatorValidator {
+    fn is_valid(&self, instance: &Value) -> bool {
+        match self.mapped(instance) {
+            Some(Ok((tag, node))) => {
+                match Dispatch::enter(instance, &self.selector.property_name, &tag) {
+                    DispatchEntry::Entered(_dispatch) => node.is_valid(instance),
+                    DispatchEntry::Active => true,
+                    DispatchEntry::TooDeep => false,
+                }
+            }
+            Some(Err(_)) => false,
+            None => true,
+        }
+    }
+
+    fn validate<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> ErrorIterator<'instance> {
+        match self.mapped(instance) {
+            Some(Ok((tag, node))) => {
+                match Dispatch::enter(instance, &self.selector.property_name, &tag) {
+                    // Collected while the dispatch is active, as the mapped schema includes this one
+                    DispatchEntry::Entered(_dispatch) => Box::new(
+                        node.validate(instance, instance_path)
+                            .collect::<Vec<_>>()
+                            .into_iter(),
+                    ),
+                    DispatchEntry::Active => no_error(),
+                    DispatchEntry::TooDeep => error(self.too_deep(instance, instance_path)),
+                }
+            }
+            Some(Err(tag)) => error(self.unknown_value(instance, instance_path, tag)),
+            None => no_error(),
+        }
+    }
+
+    fn apply<'a>(
+        &'a self,
+        instance: &Value,
+        instance_path: &InstancePath,
+    ) -> PartialApplication<'a> {
+        match self.mapped(instance) {
+            Some(Ok((tag, node))) => {
+                match Dispatch::enter(instance, &self.selector.property_name, &tag) {
+                    DispatchEntry::Entered(_dispatch) => {
+                        node.apply_rooted(instance, instance_path).into()
+                    }
+                    DispatchEntry::Active => PartialApplication::valid_empty(),
+                    DispatchEntry::TooDeep => PartialApplication::invalid_empty(vec![self
+                        .too_deep(instance, instance_path)
+                        .into()]),
+                }
+            }
+            Some(Err(tag)) => PartialApplication::invalid_empty(vec![self
+                .unknown_value(instance, instance_path, tag)
+                .into()]),
+            None => PartialApplication::valid_empty(),
+        }
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "discriminator".to_string(),
+            discriminator_value(
+                &self.selector.property_name,
+                &self.selector.key_properties,
+                &self.mapping,
+            ),
+        );
+    }
+}
+
+impl core::fmt::Display for InheritedDiscriminatorValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {{propertyName: {}, mapping: {{{}}}}}",
+            self.name(),
+            once(self.selector.property_name.as_str())
+                .chain(self.selector.key_properties.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(", "),
+            format_key_value_validators(&self.mapping),
+        )
+    }
+}
+
+#[inline]
+pub(crate) fn compile<'a>(
+    parent: &'a Map<String, Value>,
+    schema: &'a Value,
+    context: &CompilationContext,
+) -> Option<CompilationResult<'a>> {
+    if !context.config.uses_discriminator() {
+        return None;
+    }
+    let result = match parent.get("oneOf") {
+        Some(one_of) => Some(
+            DiscriminatorValidator::compile(parent, schema, one_of, context)
+                .map(|validator| {
+                    PreValidateValidator::wrap(context.config.pre_validate_hook(), validator)
+                })
+                .map_err(|error| *error),
+        ),
+        // `anyOf` handles the discriminator itself
+        None if parent.contains_key("anyOf") => None,
+        None => {
+            // A base schema of the mapped schemas. Without a mapping there is nothing to
+            // dispatch to, the keyword is kept as an annotation
+            let discriminator = Discriminator::try_from_value(schema).ok()?;
+            if discriminator.mapping.is_empty() {
+                return None;
+            }
+            Some(
+                InheritedDiscriminatorValidator::compile(schema, discriminator, context)
+                    .map_err(|error| *error),
+            )
+        }
+    };
+    match result {
+        Some(Err(error)) => Some(Err(context.locate(error))),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        validate_discriminator_object, DiscriminationResult, Discriminator, DiscriminatorError,
+        DiscriminatorValidator, MappingSource, NumericTagFormat, VariantInfo, MAX_DISPATCH_DEPTH,
+    };
+    use crate::{
+        compilation::{
+            context::{BaseUri, CompilationContext},
+            DEFAULT_SCOPE,
+        },
+        error::{Severity, ValidationError, ValidationErrorKind},
+        resolver::{DefaultResolver, Resolver},
+        schema_node::SchemaNode,
+        tests_util::{self, pets},
+        validator::Validate,
+        CompilationOptions, Draft, SchemaResolver, SchemaResolverError,
+    };
+    use ahash::AHashMap;
+    use serde_json::{json, Value};
+    use std::{
+        borrow::Cow,
+        iter::once,
+        sync::{Arc, Mutex},
+    };
+    use test_case::test_case;
+    use url::Url;
+
+    #[test_case(&json!({"petType": "cat", "meows": true}))]
+    #[test_case(&json!({"petType": "dog", "barks": false}))]
+    // No tag - regular `oneOf` semantics
+    #[test_case(&json!({"meows": true}))]
+    fn valid(instance: &Value) {
+        tests_util::openapi_is_valid(&pets(json!({})), instance)
+    }
+
+    #[test_case(&json!({"petType": "cat", "barks": true}))]
+    #[test_case(&json!({"petType": "fish"}))]
+    #[test_case(&json!({"barks": "no"}))]
+    #[test_case(&json!(null))]
+    fn invalid(instance: &Value) {
+        tests_util::openapi_is_not_valid(&pets(json!({})), instance)
+    }
+
+    #[test]
+    fn nullable() {
+        let schema = pets(json!({"nullable": true}));
+        tests_util::openapi_is_valid(&schema, &json!(null));
+        tests_util::openapi_is_valid(&schema, &json!({"petType": "cat", "meows": true}));
+        tests_util::openapi_is_not_valid(&schema, &json!({"petType": "cat"}));
+    }
+
+    #[test_case(Draft::Draft4)]
+    #[test_case(Draft::Draft6)]
+    #[test_case(Draft::Draft7)]
+    fn nullable_keyword(draft: Draft) {
+        let is_valid = |schema: &Value| {
+            tests_util::openapi_options()
+                .with_draft(draft)
+                .compile(schema)
+                .expect("Valid schema")
+                .is_valid(&json!(null))
+        };
+        assert!(is_valid(&pets(json!({"nullable": true}))));
+        assert!(!is_valid(&pets(json!({"type": ["object", "null"]}))));
+    }
+
+    #[cfg(feature = "draft202012")]
+    #[test]
+    fn nullable_type() {
+        let schema = pets(json!({"type": ["object", "null"]}));
+        tests_util::openapi_is_valid_with_draft(Draft::Draft202012, &schema, &json!(null));
+        tests_util::openapi_is_not_valid_with_draft(
+            Draft::Draft202012,
+            &schema,
+            &json!({"petType": "cat"}),
+        );
+        let schema = pets(json!({"nullable": true}));
+        tests_util::openapi_is_not_valid_with_draft(Draft::Draft202012, &schema, &json!(null));
+    }
+
+    #[test_case(&json!({"nullable": false}))]
+    #[test_case(&json!({}))]
+    fn not_nullable(extra: &Value) {
+        tests_util::openapi_is_not_valid(&pets(extra.clone()), &json!(null))
+    }
+
+    #[test_case(&json!({"petType": "fish"}), "/discriminator")]
+    #[test_case(&json!({"petType": "cat"}), "/discriminator/mapping/cat/required")]
+    #[test_case(&json!({"barks": "no"}), "/oneOf")]
+    fn schema_path(instance: &Value, expected: &str) {
+        tests_util::openapi_assert_schema_path(&pets(json!({})), instance, expected)
+    }
+
+    #[test]
+    fn parse() {
+        let discriminator = Discriminator::try_from_value(&json!({
+            "propertyName": "petType",
+            "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+        }))
+        .expect("Valid discriminator");
+        assert_eq!(discriminator.property_name(), "petType");
+        assert_eq!(
+            discriminator.mapping().collect::<Vec<_>>(),
+            vec![("cat", "#/definitions/Cat"), ("dog", "#/definitions/Dog")]
+        );
+        let discriminator = Discriminator::try_from_value(&json!({"propertyName": "petType"}))
+            .expect("Valid discriminator");
+        assert_eq!(discriminator.mapping().count(), 0);
+    }
+
+    #[test]
+    fn mapping_keys() {
+        let discriminator = Discriminator::try_from_value(&pets(json!({}))["discriminator"])
+            .expect("Valid discriminator");
+        assert_eq!(
+            discriminator.mapping_keys().collect::<Vec<_>>(),
+            vec!["cat", "dog"]
+        );
+    }
+
+    #[test_case(&json!("petType"), &DiscriminatorError::NotAnObject)]
+    #[test_case(&json!({}), &DiscriminatorError::MissingPropertyName)]
+    #[test_case(&json!({"propertyName": 42}), &DiscriminatorError::InvalidPropertyName)]
+    #[test_case(&json!({"propertyName": "petType", "mapping": []}), &DiscriminatorError::InvalidMapping)]
+    #[test_case(
+        &json!({"propertyName": "petType", "mapping": {"cat": 1}}),
+        &DiscriminatorError::InvalidMappingValue { tag: "cat".to_string() }
+    )]
+    fn parse_malformed(value: &Value, expected: &DiscriminatorError) {
+        assert_eq!(
+            &Discriminator::try_from_value(value).expect_err("Malformed discriminator"),
+            expected
+        );
+    }
+
+    #[test_case(&json!([]), "/discriminator", "Discriminator is not an object")]
+    #[test_case(&json!({}), "/discriminator", "Discriminator is missing \"propertyName\"")]
+    #[test_case(
+        &json!({"propertyName": 42}),
+        "/discriminator/propertyName",
+        "Discriminator \"propertyName\" is not a string or an array of strings"
+    )]
+    #[test_case(
+        &json!({"propertyName": "petType", "mapping": []}),
+        "/discriminator/mapping",
+        "Discriminator \"mapping\" is not an object"
+    )]
+    #[test_case(
+        &json!({"propertyName": "petType", "mapping": {"cat": 1}}),
+        "/discriminator/mapping/cat",
+        "Discriminator \"mapping\" value for \"cat\" is not a string"
+    )]
+    fn compile_malformed(discriminator: &Value, expected: &str, message: &str) {
+        let schema = pets(json!({ "discriminator": discriminator }));
+        let error = tests_util::openapi_options()
+            .compile(&schema)
+            .expect_err("Malformed discriminator");
+        assert!(matches!(
+            error.kind,
+            ValidationErrorKind::Discriminator { .. }
+        ));
+        assert_eq!(error.instance_path.to_string(), expected);
+        assert_eq!(error.to_string(), message);
+    }
+
+    #[cfg(feature = "yaml-spans")]
+    #[test_case("propertyName: 1\n", 10, 3; "property name")]
+    #[test_case("propertyName: petType\n  mapping:\n    cat: 1\n", 12, 5; "mapping value")]
+    fn compile_malformed_yaml(discriminator: &str, line: usize, column: usize) {
+        let yaml = format!(
+            "oneOf:
+  - $ref: '#/definitions/Cat'
+  - $ref: '#/definitions/Dog'
+definitions:
+  Cat:
+    required: [meows]
+  Dog:
+    required: [barks]
+discriminator:
+  {}",
+            discriminator
+        );
+        let (schema, spans) = crate::spans::from_yaml_str(&yaml).expect("Valid YAML");
+        let error = tests_util::openapi_options()
+            .with_source_spans(spans)
+            .compile(&schema)
+            .expect_err("Malformed discriminator");
+        assert_eq!(
+            error.source_span,
+            Some(crate::spans::SourceSpan { line, column })
+        );
+    }
+
+    #[test]
+    fn compile_malformed_without_spans() {
+        let schema = pets(json!({ "discriminator": {"propertyName": 42} }));
+        let error = tests_util::openapi_options()
+            .compile(&schema)
+            .expect_err("Malformed discriminator");
+        assert_eq!(error.source_span, None);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        // Kept as an annotation, like any unknown keyword
+        let schema = pets(json!({ "discriminator": {"propertyName": 42} }));
+        let compiled = crate::JSONSchema::compile(&schema).expect("Valid schema");
+        assert!(!compiled.uses_discriminator());
+        assert!(compiled.is_valid(&json!({"petType": "dog", "meows": true})));
+    }
+
+    #[test]
+    fn unresolvable_mapping() {
+        let mut schema = pets(json!({}));
+        schema["discriminator"]["mapping"]["cat"] = json!("#/definitions/Kitten");
+        // Resolved on first use, like any `$ref`
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"petType": "dog", "barks": true})));
+        let error = tests_util::openapi_options()
+            .should_use_strict_discriminator(true)
+            .compile(&schema)
+            .expect_err("Unresolvable mapping");
+        assert!(matches!(
+            error.kind,
+            ValidationErrorKind::Discriminator {
+                error: DiscriminatorError::FragmentUnresolvable { .. }
+            }
+        ));
+        assert_eq!(
+            error.instance_path.to_string(),
+            "/discriminator/mapping/cat"
+        );
+        assert_eq!(
+            error.to_string(),
+            "Discriminator \"mapping\" value \"#/definitions/Kitten\" for \"cat\" can't be resolved"
+        );
+    }
+
+    #[test_case(&json!({"petType": "fish"}), "/properties/pet/discriminator"; "unknown value")]
+    #[test_case(&json!({"meows": "no"}), "/properties/pet/oneOf"; "no branch")]
+    #[test_case(&json!({"meows": true, "barks": true}), "/properties/pet/oneOf"; "multiple branches")]
+    fn schema_path_accessor(pet: &Value, expected: &str) {
+        let schema = json!({
+            "properties": {"pet": pets(json!({}))},
+            "definitions": pets(json!({}))["definitions"]
+        });
+        let error = tests_util::openapi_validate(&schema, &json!({ "pet": pet }));
+        assert_eq!(error.schema_path.to_string(), expected);
+        assert_eq!(error.instance_path.to_string(), "/pet");
+    }
+
+    #[test]
+    fn attempted_branch() {
+        let schema = tests_util::openapi_options()
+            .compile(&pets(json!({})))
+            .expect("Valid schema");
+        let instance = json!({"petType": "cat", "barks": true});
+        let output = serde_json::to_value(schema.apply(&instance).basic()).unwrap();
+        assert_eq!(
+            output,
+            json!({
+                "valid": false,
+                "errors": [
+                    {
+                        "keywordLocation": "/discriminator/mapping/cat/required",
+                        "instanceLocation": "",
+                        "error": "\"meows\" is a required property"
+                    },
+                    {
+                        "keywordLocation": "/discriminator",
+                        "instanceLocation": "",
+                        "error": "The \"cat\" branch selected by the \"petType\" discriminator is not valid"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test_case(&json!({"propertyName": "petType", "mapping": {}}), "/discriminator/mapping")]
+    #[test_case(&json!({"propertyName": "petType"}), "/discriminator")]
+    fn compile_without_mapping(discriminator: &Value, expected: &str) {
+        let schema = pets(json!({ "discriminator": discriminator }));
+        let error = tests_util::openapi_options()
+            .compile(&schema)
+            .expect_err("Nothing to discriminate");
+        assert_eq!(error.instance_path.to_string(), expected);
+    }
+
+    #[test_case(&json!({"propertyName": "petType", "mapping": {}}))]
+    #[test_case(&json!({"propertyName": "petType"}))]
+    fn implicit_mapping(discriminator: &Value) {
+        let schema = pets(json!({ "discriminator": discriminator }));
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"petType": "Cat", "meows": true})));
+        assert!(compiled.is_valid(&json!({"petType": "Dog", "barks": true})));
+        assert!(!compiled.is_valid(&json!({"petType": "Cat", "barks": true})));
+        assert!(!compiled.is_valid(&json!({"petType": "cat", "meows": true})));
+    }
+
+    #[test]
+    fn implicit_mapping_with_explicit_entries() {
+        let schema = pets(json!({
+            "discriminator": {"propertyName": "petType", "mapping": {"Cat": "#/definitions/Dog"}}
+        }));
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"petType": "Cat", "barks": true})));
+        assert!(compiled.is_valid(&json!({"petType": "Dog", "barks": true})));
+    }
+
+    #[test]
+    fn partial() {
+        let schema = pets(json!({}));
+        let compiled = tests_util::openapi_options()
+            .should_allow_partial_discriminated_instances(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        // `meows` / `barks` are required by the branches
+        assert!(compiled.is_valid(&json!({})));
+        assert!(compiled.validate(&json!({})).is_ok());
+        assert!(compiled.apply(&json!({})).basic().is_valid());
+        assert!(compiled.is_valid(&json!({"meows": false})));
+        assert!(!compiled.is_valid(&json!({"meows": "no", "barks": "no"})));
+        // Tagged instances are still complete
+        assert!(!compiled.is_valid(&json!({"petType": "cat"})));
+        tests_util::openapi_is_not_valid(&schema, &json!({}));
+    }
+
+    #[test_case(&json!({"meows": true}), "", "\"petType\" is a required property")]
+    #[test_case(&json!({"petType": true}), "/petType", "true is not of type \"string\"")]
+    #[test_case(&json!(null), "", "null is not of type \"object\"")]
+    fn strict(instance: &Value, instance_path: &str, message: &str) {
+        let schema = pets(json!({}));
+        let compiled = tests_util::openapi_options()
+            .should_use_strict_discriminator(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(!compiled.is_valid(instance));
+        assert!(!compiled.apply(instance).basic().is_valid());
+        let errors: Vec<_> = compiled.validate(instance).expect_err("No tag").collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].schema_path.to_string(), "/discriminator");
+        assert_eq!(errors[0].instance_path.to_string(), instance_path);
+        assert_eq!(errors[0].to_string(), message);
+        assert!(compiled.is_valid(&json!({"petType": "cat", "meows": true})));
+    }
+
+    #[test]
+    fn strict_unconstrained_property() {
+        let mut schema = pets(json!({}));
+        // `Dog` only declares the tag through `allOf`, `Cat` doesn't declare it at all
+        schema["definitions"]["Cat"] = json!({"required": ["meows"]});
+        schema["definitions"]["Dog"] = json!({
+            "allOf": [{"$ref": "#/definitions/Tagged"}],
+            "required": ["barks"]
+        });
+        schema["definitions"]["Tagged"] = json!({"properties": {"petType": {"type": "string"}}});
+        let cat = json!({"petType": "cat", "meows": true});
+        let dog = json!({"petType": "dog", "barks": true});
+        let compiled = tests_util::openapi_options()
+            .should_use_strict_discriminator(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(!compiled.is_valid(&cat));
+        assert!(!compiled.apply(&cat).basic().is_valid());
+        let errors: Vec<_> = compiled
+            .validate(&cat)
+            .expect_err("Unconstrained")
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].schema_path.to_string(), "/discriminator");
+        assert_eq!(
+            errors[0].to_string(),
+            r#"The "cat" branch does not constrain the "petType" discriminator property"#
+        );
+        // Invalid instances report the branch errors
+        let incomplete = json!({"petType": "cat"});
+        let errors: Vec<_> = compiled
+            .validate(&incomplete)
+            .expect_err("Missing `meows`")
+            .collect();
+        assert_eq!(errors[0].to_string(), r#""meows" is a required property"#);
+        assert!(compiled.is_valid(&dog));
+        // Not checked outside of strict mode
+        tests_util::openapi_is_valid(&schema, &cat);
+    }
+
+    #[test_case(true)]
+    #[test_case(false)]
+    fn permit_discriminator_property(permit: bool) {
+        let mut schema = pets(json!({}));
+        schema["definitions"]["Cat"] = json!({
+            "properties": {"meows": {"type": "boolean"}},
+            "additionalProperties": false
+        });
+        schema["definitions"]["Dog"] = json!({"additionalProperties": false});
+        let compiled = tests_util::openapi_options()
+            .should_permit_discriminator_property(permit)
+            .compile(&schema)
+            .expect("Valid schema");
+        for instance in [
+            json!({"petType": "cat", "meows": true}),
+            json!({"petType": "dog"}),
+        ] {
+            assert_eq!(compiled.is_valid(&instance), permit);
+            assert_eq!(compiled.validate(&instance).is_ok(), permit);
+            assert_eq!(compiled.apply(&instance).basic().is_valid(), permit);
+        }
+        // Other additional properties are still reported
+        let instance = json!({"petType": "cat", "purrs": true});
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Unexpected")
+            .collect();
+        assert_eq!(errors.len(), 1);
+        let expected = if permit {
+            r#"Additional properties are not allowed ('purrs' was unexpected)"#
+        } else {
+            r#"Additional properties are not allowed ('petType', 'purrs' were unexpected)"#
+        };
+        assert_eq!(errors[0].to_string(), expected);
+    }
+
+    #[test]
+    fn check_enum() {
+        let mut schema = pets(json!({}));
+        schema["discriminator"]["mapping"]["bird"] = json!("#/definitions/Bird");
+        schema["definitions"]["Cat"]["properties"]["petType"] = json!({"enum": ["cat"]});
+        // Declared through `allOf` and `$ref`
+        schema["definitions"]["Dog"]["allOf"] = json!([{"$ref": "#/definitions/Tagged"}]);
+        schema["definitions"]["Tagged"] =
+            json!({"properties": {"petType": {"enum": ["cat", "dog"]}}});
+        schema["definitions"]["Bird"] = json!({"$ref": "#/definitions/Tagged"});
+        let compile = |check: bool| {
+            tests_util::openapi_options()
+                .should_check_discriminator_enum(check)
+                .compile(&schema)
+                .expect("Mismatches don't fail the compilation")
+        };
+        assert_eq!(
+            compile(true).diagnostics(),
+            [
+                r##"/discriminator/mapping/bird: "bird" is not allowed by the "petType" enum of "#/definitions/Bird""##
+            ]
+        );
+        assert!(compile(false).diagnostics().is_empty());
+    }
+
+    #[test]
+    fn check_types() {
+        let mut schema = pets(json!({}));
+        schema["discriminator"]["mapping"]["name"] = json!("#/definitions/Name");
+        schema["discriminator"]["mapping"]["nullable"] = json!("#/definitions/NullableDog");
+        schema["definitions"]["Name"] = json!({"type": "string"});
+        schema["definitions"]["NullableDog"] = json!({"type": ["object", "null"]});
+        // Unconstrained
+        schema["discriminator"]["mapping"]["any"] = json!("#/definitions/Any");
+        schema["definitions"]["Any"] = json!({});
+        let compile = |check: bool| {
+            tests_util::openapi_options()
+                .should_check_discriminator_mapping_types(check)
+                .compile(&schema)
+                .expect("Mismatches don't fail the compilation")
+        };
+        assert_eq!(
+            compile(true).diagnostics(),
+            [
+                r##"/discriminator/mapping/name: "#/definitions/Name" is not an object schema, its type is "string""##
+            ]
+        );
+        assert!(compile(false).diagnostics().is_empty());
+    }
+
+    #[test]
+    fn branch_format() {
+        fn cat_name(value: &str) -> bool {
+            value.ends_with("cat")
+        }
+        let name = json!({"properties": {"name": {"type": "string", "format": "pet-name"}}});
+        let mut schema = pets(json!({"allOf": [name]}));
+        schema["definitions"]["Cat"]["allOf"] = json!([name]);
+        schema["definitions"]["Dog"]["allOf"] = json!([name]);
+        let compiled = tests_util::openapi_options()
+            .with_branch_format("#/definitions/Cat", "pet-name", cat_name)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"petType": "cat", "meows": true, "name": "Tomcat"})));
+        let invalid = json!({"petType": "cat", "meows": true, "name": "Rex"});
+        let errors: Vec<_> = compiled.validate(&invalid).expect_err("Rex").collect();
+        assert_eq!(
+            errors[0].schema_path.to_string(),
+            "/discriminator/mapping/cat/allOf/0/properties/name/format"
+        );
+        assert!(!compiled.is_valid(&invalid));
+        // The format is unknown outside of the `Cat` branch
+        assert!(compiled.is_valid(&json!({"petType": "dog", "barks": true, "name": "Rex"})));
+        // Also when the branch is selected by `oneOf`
+        let mut schema = schema;
+        schema.as_object_mut().unwrap().remove("discriminator");
+        let compiled = tests_util::openapi_options()
+            .with_branch_format("#/definitions/Cat", "pet-name", cat_name)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(!compiled.is_valid(&json!({"meows": true, "name": "Rex"})));
+        assert!(compiled.is_valid(&json!({"meows": true, "name": "Tomcat"})));
+    }
+
+    #[test]
+    fn diff() {
+        let parse = |mapping: Value| {
+            Discriminator::try_from_value(&json!({"propertyName": "petType", "mapping": mapping}))
+                .expect("Valid discriminator")
+        };
+        let old = parse(json!({"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}));
+        let new = parse(json!({"cat": "#/definitions/Cat", "bird": "#/definitions/Bird"}));
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.added,
+            vec![("bird".to_string(), "#/definitions/Bird".to_string())]
+        );
+        assert_eq!(
+            diff.removed,
+            vec![("dog".to_string(), "#/definitions/Dog".to_string())]
+        );
+        assert!(diff.changed.is_empty());
+        assert_eq!(new.diff(&old).added, diff.removed);
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn is_exhaustive() {
+        let discriminator = Discriminator::try_from_value(&json!({
+            "propertyName": "petType",
+            "mapping": {
+                "cat": "#/definitions/Cat",
+                "dog": "#/definitions/Dog",
+                "*": "#/definitions/Pet"
+            }
+        }))
+        .expect("Valid discriminator");
+        assert_eq!(
+            discriminator.is_exhaustive(["cat", "dog", "bird"]),
+            vec!["bird"]
+        );
+        assert!(discriminator.is_exhaustive(["dog", "cat"]).is_empty());
+        assert!(discriminator.is_exhaustive(Vec::new()).is_empty());
+    }
+
+    #[test_case(&json!({"cat": "#/definitions/Cat", "*": "#/definitions/Pet"}), &[]; "mapped")]
+    #[test_case(&json!({"cat": "#/definitions/Cat"}), &["*"]; "unmapped")]
+    fn is_exhaustive_fallback(mapping: &Value, expected: &[&str]) {
+        let discriminator = Discriminator::try_from_value(&json!({
+            "propertyName": "petType",
+            "mapping": mapping
+        }))
+        .expect("Valid discriminator");
+        assert_eq!(discriminator.is_exhaustive(["cat", "*"]), expected);
+    }
+
+    #[test_case("urn:cat")]
+    #[test_case("urn:cat#")]
+    #[test_case("urn:cat#/")]
+    fn mapping_to_id(reference: &str) {
+        let mut schema = pets(json!({}));
+        schema["oneOf"][0] = json!({"$ref": reference});
+        schema["discriminator"]["mapping"]["cat"] = json!(reference);
+        schema["definitions"]["Cat"]["$id"] = json!("urn:cat");
+        tests_util::openapi_is_valid(&schema, &json!({"petType": "cat", "meows": true}));
+        tests_util::openapi_is_not_valid(&schema, &json!({"petType": "cat", "barks": true}));
+        tests_util::openapi_assert_schema_path(
+            &schema,
+            &json!({"petType": "cat", "meows": 1}),
+            "/discriminator/mapping/cat/properties/meows/type",
+        );
+    }
+
+    #[test_case(&json!({"petType": "cat", "meows": true, "toy": {"squeaks": true}, "owner": {"name": "Jon"}}), true)]
+    #[test_case(&json!({"petType": "cat", "meows": true, "toy": {"squeaks": "no"}}), false)]
+    #[test_case(&json!({"petType": "cat", "meows": true, "owner": {}}), false)]
+    // No tag, the branch is found by `oneOf`
+    #[test_case(&json!({"meows": true, "toy": {"squeaks": true}, "owner": {"name": "Jon"}}), true)]
+    #[test_case(&json!({"meows": true, "toy": {"squeaks": "no"}}), false)]
+    fn mapped_branch_with_id(instance: &Value, expected: bool) {
+        let mut schema = pets(json!({}));
+        schema["definitions"]["Cat"] = json!({
+            "$id": "https://example.com/schemas/cat.json",
+            "properties": {
+                "meows": {"type": "boolean"},
+                "toy": {"$ref": "toy.json"},
+                "owner": {"$ref": "#/definitions/Owner"}
+            },
+            "required": ["meows"],
+            "definitions": {"Owner": {"required": ["name"]}}
+        });
+        schema["definitions"]["Toy"] = json!({
+            "$id": "https://example.com/schemas/toy.json",
+            "properties": {"squeaks": {"type": "boolean"}}
+        });
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(instance), expected);
+        assert_eq!(compiled.validate(instance).is_ok(), expected);
+        assert_eq!(compiled.apply(instance).basic().is_valid(), expected);
+    }
+
+    #[test]
+    fn compile_with_meta_validation() {
+        let mut schema = pets(json!({}));
+        schema["discriminator"]["mapping"]["bird"] = json!(1);
+        schema["definitions"]["Cat"]["required"] = json!("meows");
+        schema["definitions"]["Nested"] = json!({
+            "anyOf": [{"$ref": "#/definitions/Cat"}],
+            "discriminator": {"mapping": {}}
+        });
+        let errors = tests_util::openapi_options()
+            .compile_with_meta_validation(&schema)
+            .expect_err("Invalid schema");
+        let locations: Vec<_> = errors
+            .errors
+            .iter()
+            .map(|error| error.instance_path.to_string())
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                "/definitions/Cat/required",
+                "/discriminator/mapping/bird",
+                "/definitions/Nested/discriminator"
+            ]
+        );
+        assert!(tests_util::openapi_options()
+            .compile_with_meta_validation(&pets(json!({})))
+            .is_ok());
+    }
+
+    fn nested() -> Value {
+        let pets = pets(json!({}));
+        json!({
+            "oneOf": [{"$ref": "#/definitions/Pet"}, {"$ref": "#/definitions/Plant"}],
+            "discriminator": {
+                "propertyName": "kind",
+                "mapping": {"pet": "#/definitions/Pet", "plant": "#/definitions/Plant"}
+            },
+            "definitions": {
+                "Pet": {
+                    "oneOf": pets["oneOf"],
+                    "discriminator": pets["discriminator"]
+                },
+                "Plant": {"required": ["leaves"]},
+                "Cat": pets["definitions"]["Cat"],
+                "Dog": pets["definitions"]["Dog"]
+            }
+        })
+    }
+
+    #[test_case(&json!({"kind": "pet", "petType": "cat", "meows": true}))]
+    #[test_case(&json!({"kind": "pet", "petType": "dog", "barks": true}))]
+    #[test_case(&json!({"kind": "plant", "leaves": 3}))]
+    fn nested_valid(instance: &Value) {
+        tests_util::openapi_is_valid(&nested(), instance)
+    }
+
+    #[test_case(&json!({"kind": "pet", "petType": "cat", "barks": true}), "/discriminator/mapping/pet/discriminator/mapping/cat/required")]
+    #[test_case(&json!({"kind": "pet", "petType": "fish"}), "/discriminator/mapping/pet/discriminator")]
+    #[test_case(&json!({"kind": "tree", "leaves": 3}), "/discriminator")]
+    #[test_case(&json!({"kind": "plant", "petType": "cat", "meows": true}), "/discriminator/mapping/plant/required")]
+    fn nested_schema_path(instance: &Value, expected: &str) {
+        tests_util::openapi_is_not_valid(&nested(), instance);
+        tests_util::openapi_assert_schema_path(&nested(), instance, expected)
+    }
+
+    #[test]
+    fn nested_attempted_branch() {
+        let schema = tests_util::openapi_options()
+            .compile(&nested())
+            .expect("Valid schema");
+        let instance = json!({"kind": "pet", "petType": "dog", "meows": true});
+        let output = serde_json::to_value(schema.apply(&instance).basic()).unwrap();
+        assert_eq!(
+            output,
+            json!({
+                "valid": false,
+                "errors": [
+                    {
+                        "keywordLocation": "/discriminator/mapping/pet/discriminator/mapping/dog/required",
+                        "instanceLocation": "",
+                        "error": "\"barks\" is a required property"
+                    },
+                    {
+                        "keywordLocation": "/discriminator/mapping/pet/discriminator",
+                        "instanceLocation": "",
+                        "error": "The \"dog\" branch selected by the \"petType\" discriminator is not valid"
+                    },
+                    {
+                        "keywordLocation": "/discriminator",
+                        "instanceLocation": "",
+                        "error": "The \"pet\" branch selected by the \"kind\" discriminator is not valid"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn name() {
+        let compiled = tests_util::openapi_options()
+            .compile(&pets(json!({})))
+            .expect("Valid schema");
+        let validator = compiled
+            .node
+            .keyword_validator_as::<DiscriminatorValidator>("discriminator")
+            .expect("Compiled");
+        assert_eq!(validator.name(), "discriminator");
+        assert!(validator.to_string().starts_with("discriminator: {"));
+    }
+
+    #[test]
+    fn child_nodes() {
+        fn count(node: &SchemaNode) -> usize {
+            1 + node
+                .validators()
+                .flat_map(|validator| validator.child_nodes())
+                .map(count)
+                .sum::<usize>()
+        }
+
+        let compiled = tests_util::openapi_options()
+            .compile(&pets(json!({})))
+            .expect("Valid schema");
+        // The root, and the `$ref` of every mapped branch and `oneOf` subschema, whose targets
+        // are compiled on first use
+        assert_eq!(count(&compiled.node), 5);
+        assert!(compiled.is_valid(&json!({"petType": "cat", "meows": true})));
+        // The `Cat` schema and its two properties
+        assert_eq!(count(&compiled.node), 8);
+    }
+
+    #[test]
+    fn estimated_cost() {
+        let cost = |branch: Value| {
+            let compiled = tests_util::openapi_options()
+                .compile(&json!({
+                    "oneOf": [{"$ref": "#/definitions/a"}, {"$ref": "#/definitions/b"}],
+                    "discriminator": {
+                        "propertyName": "kind",
+                        "mapping": {"a": "#/definitions/a", "b": "#/definitions/b"}
+                    },
+                    "definitions": {"a": {"type": "object"}, "b": branch}
+                }))
+                .expect("Valid schema");
+            let validator = compiled
+                .node
+                .keyword_validator("discriminator")
+                .expect("Compiled");
+            validator.estimated_cost()
+        };
+        let flat = cost(json!({"type": "object"}));
+        let nested = cost(json!({"properties": {"a": {"properties": {"b": {"type": "string"}}}}}));
+        assert!(nested > flat, "{} > {}", nested, flat);
+    }
+
+    #[test_case(&json!({"allOf": [{"required": ["name"]}]}); "sibling")]
+    #[test_case(&json!({"required": ["name"]}); "sibling keyword")]
+    fn sibling_constraints(extra: &Value) {
+        let schema = pets(extra.clone());
+        tests_util::openapi_is_valid(
+            &schema,
+            &json!({"petType": "cat", "meows": true, "name": "Tom"}),
+        );
+        tests_util::openapi_is_not_valid(&schema, &json!({"petType": "cat", "meows": true}));
+        tests_util::openapi_is_not_valid(&schema, &json!({"petType": "cat", "name": "Tom"}));
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        let instance = json!({"petType": "cat"});
+        let mut errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Invalid")
+            .map(|error| error.schema_path.to_string())
+            .collect();
+        errors.sort();
+        let mut expected = vec![
+            "/discriminator/mapping/cat/required".to_string(),
+            if extra.get("allOf").is_some() {
+                "/allOf/0/required".to_string()
+            } else {
+                "/required".to_string()
+            },
+        ];
+        expected.sort();
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn items() {
+        let mut schema = pets(json!({}));
+        let definitions = schema
+            .as_object_mut()
+            .and_then(|schema| schema.remove("definitions"))
+            .expect("Definitions");
+        let schema = json!({"items": schema, "definitions": definitions});
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        let valid = json!([
+            {"petType": "cat", "meows": true},
+            {"petType": "dog", "barks": true},
+            {"petType": "cat", "meows": false}
+        ]);
+        assert!(compiled.is_valid(&valid));
+        assert!(compiled.validate(&valid).is_ok());
+        // Every element selects its own branch, only the failing one is reported
+        let invalid = json!([
+            {"petType": "cat", "meows": true},
+            {"petType": "dog", "barks": true},
+            {"petType": "cat", "barks": true, "meows": "loudly"},
+            {"petType": "fish"}
+        ]);
+        assert!(!compiled.is_valid(&invalid));
+        let errors: Vec<_> = compiled
+            .validate(&invalid)
+            .expect_err("Invalid")
+            .map(|error| {
+                (
+                    error.instance_path.to_string(),
+                    error.schema_path.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "/2/meows".to_string(),
+                    "/items/discriminator/mapping/cat/properties/meows/type".to_string()
+                ),
+                ("/3".to_string(), "/items/discriminator".to_string()),
+            ]
+        );
+    }
+
+    #[test_case(false, &json!({}), true; "fallback to oneOf by default")]
+    #[test_case(true, &json!({}), false; "rejected")]
+    #[test_case(true, &json!({"null": "#/definitions/Dog"}), false; "mapped to another branch")]
+    #[test_case(true, &json!({"null": "#/definitions/Cat"}), true; "mapped")]
+    fn null_tag(reject: bool, extra_mapping: &Value, expected: bool) {
+        let mut schema = pets(json!({}));
+        for name in ["Cat", "Dog"] {
+            schema["definitions"][name]["properties"]["petType"] =
+                json!({"type": ["string", "null"]});
+        }
+        for (tag, reference) in extra_mapping.as_object().expect("An object") {
+            schema["discriminator"]["mapping"][tag] = reference.clone();
+        }
+        let compiled = tests_util::openapi_options()
+            .should_reject_null_discriminator_values(reject)
+            .compile(&schema)
+            .expect("Valid schema");
+        let instance = json!({"petType": null, "meows": true});
+        assert_eq!(compiled.is_valid(&instance), expected);
+        assert_eq!(compiled.validate(&instance).is_ok(), expected);
+        assert_eq!(compiled.apply(&instance).basic().is_valid(), expected);
+    }
+
+    #[test]
+    fn null_tag_error() {
+        let compiled = tests_util::openapi_options()
+            .should_reject_null_discriminator_values(true)
+            .compile(&pets(json!({})))
+            .expect("Valid schema");
+        let instance = json!({"petType": null, "meows": true});
+        let errors: Vec<_> = compiled.validate(&instance).expect_err("Invalid").collect();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].kind,
+            ValidationErrorKind::NullDiscriminatorValue { property_name } if property_name == "petType"
+        ));
+        assert_eq!(errors[0].schema_path.to_string(), "/discriminator");
+        // Missing tags are not affected
+        assert!(compiled.is_valid(&json!({"meows": true})));
+    }
+
+    #[test]
+    fn sibling_not() {
+        let schema = pets(json!({
+            "not": {"properties": {"meows": {"const": false}}, "required": ["meows"]}
+        }));
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        tests_util::openapi_is_valid(&schema, &json!({"petType": "cat", "meows": true}));
+        tests_util::openapi_is_valid(&schema, &json!({"petType": "dog", "barks": false}));
+        // Accepted by the selected branch, but forbidden by `not`
+        let instance = json!({"petType": "cat", "meows": false});
+        tests_util::openapi_is_not_valid(&schema, &instance);
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Invalid")
+            .map(|error| error.schema_path.to_string())
+            .collect();
+        assert_eq!(errors, vec!["/not"]);
+        assert!(!compiled.apply(&instance).basic().is_valid());
+    }
+
+    #[test]
+    fn wrapped_in_all_of() {
+        let pets = pets(json!({}));
+        let schema = json!({
+            "allOf": [
+                {"properties": {"name": {"type": "string"}}, "required": ["name"]},
+                {"oneOf": pets["oneOf"], "discriminator": pets["discriminator"]}
+            ],
+            "definitions": pets["definitions"]
+        });
+        tests_util::openapi_is_valid(
+            &schema,
+            &json!({"petType": "dog", "barks": true, "name": "Rex"}),
+        );
+        tests_util::openapi_is_not_valid(&schema, &json!({"petType": "dog", "barks": true}));
+        tests_util::openapi_is_not_valid(
+            &schema,
+            &json!({"petType": "dog", "barks": true, "name": 1}),
+        );
+        tests_util::openapi_is_not_valid(&schema, &json!({"petType": "dog", "name": "Rex"}));
+        tests_util::openapi_assert_schema_path(
+            &schema,
+            &json!({"petType": "dog", "name": "Rex"}),
+            "/allOf/1/discriminator/mapping/dog/required",
+        );
+    }
+
+    #[test]
+    fn prune_unused_branches() {
+        let mut schema = pets(json!({}));
+        schema["oneOf"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({"$ref": "#/definitions/Fish"}));
+        schema["definitions"]["Fish"] = json!({"required": ["swims"]});
+        let compile = |prune: bool| {
+            tests_util::openapi_options()
+                .should_use_strict_discriminator(true)
+                .should_prune_unused_discriminator_branches(prune)
+                .compile(&schema)
+                .expect("Valid schema")
+        };
+        let (full, pruned) = (compile(false), compile(true));
+        assert_eq!(
+            full.compilation_stats().validators() - pruned.compilation_stats().validators(),
+            1
+        );
+        let instance = json!({"meows": true, "barks": true, "swims": true});
+        assert_eq!(
+            full.one_of_matching_branches(&instance),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(pruned.one_of_matching_branches(&instance), Some(vec![0, 1]));
+        for compiled in [full, pruned] {
+            assert!(compiled.is_valid(&json!({"petType": "cat", "meows": true})));
+            assert!(!compiled.is_valid(&json!({"petType": "dog", "meows": true})));
+            assert!(!compiled.is_valid(&json!({"swims": true})));
+        }
+    }
+
+    #[test_case(&json!({"propertyName": "petType"}))]
+    #[test_case(&json!({"propertyName": "petType", "mapping": {}}))]
+    #[test_case(&json!({"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}}))]
+    #[test_case(&json!({"propertyName": "petType", "x-extension": true}))]
+    fn conformant_discriminator_object(discriminator: &Value) {
+        assert!(validate_discriminator_object(discriminator).is_ok());
+    }
+
+    #[test_case(&json!([]), &[""])]
+    #[test_case(&json!({}), &[""])]
+    #[test_case(&json!({"propertyName": 1}), &["/propertyName"])]
+    #[test_case(&json!({"propertyName": "petType", "mapping": []}), &["/mapping"])]
+    #[test_case(&json!({"propertyName": "petType", "mapping": {"cat": 1, "dog": null}}), &["/mapping/cat", "/mapping/dog"])]
+    fn non_conformant_discriminator_object(discriminator: &Value, expected: &[&str]) {
+        let errors: Vec<_> = validate_discriminator_object(discriminator)
+            .expect_err("Not conformant")
+            .map(|error| error.instance_path.to_string())
+            .collect();
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn pre_validate_hook_injects_tag() {
+        let schema = pets(json!({}));
+        fn default_tag(instance: &Value) -> Cow<'_, Value> {
+            match instance {
+                Value::Object(object) if !object.contains_key("petType") => {
+                    let mut object = object.clone();
+                    object.insert("petType".to_string(), json!("cat"));
+                    Cow::Owned(Value::Object(object))
+                }
+                _ => Cow::Borrowed(instance),
+            }
+        }
+        let compiled = tests_util::openapi_options()
+            .with_pre_validate_hook(default_tag)
+            .compile(&schema)
+            .expect("Valid schema");
+        // Both branches match without a tag
+        let instance = json!({"meows": true, "barks": true});
+        assert!(!tests_util::openapi_options()
+            .compile(&schema)
+            .unwrap()
+            .is_valid(&instance));
+        assert!(compiled.is_valid(&instance));
+        assert!(compiled.validate(&instance).is_ok());
+        assert!(compiled.apply(&instance).basic().is_valid());
+        let dog = json!({"barks": true});
+        let errors: Vec<_> = compiled.validate(&dog).expect_err("Not a cat").collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].schema_path.to_string(),
+            "/discriminator/mapping/cat/required"
+        );
+        assert_eq!(
+            errors[0].instance,
+            Cow::Owned::<Value>(json!({"barks": true, "petType": "cat"}))
+        );
+        // Tagged instances are validated as is
+        assert!(!compiled.is_valid(&json!({"petType": "dog", "meows": true})));
+    }
+
+    #[test]
+    fn custom_resolver() {
+        struct InMemoryResolver(AHashMap<String, Arc<Value>>);
+
+        impl SchemaResolver for InMemoryResolver {
+            fn resolve(
+                &self,
+                _: &Value,
+                url: &Url,
+                _: &str,
+            ) -> Result<Arc<Value>, SchemaResolverError> {
+                self.0
+                    .get(url.as_str())
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Unknown schema: {}", url))
+            }
+        }
+
+        let mut documents = AHashMap::new();
+        documents.insert(
+            "db://schemas/cat".to_string(),
+            Arc::new(json!({"required": ["meows"]})),
+        );
+        documents.insert(
+            "db://schemas/dog".to_string(),
+            Arc::new(json!({"required": ["barks"]})),
+        );
+        let schema = json!({
+            "oneOf": [{"$ref": "db://schemas/cat"}, {"$ref": "db://schemas/dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "db://schemas/cat", "dog": "db://schemas/dog"}
+            }
+        });
+        let compiled = tests_util::openapi_options()
+            .with_resolver(InMemoryResolver(documents))
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"petType": "cat", "meows": true})));
+        assert!(!compiled.is_valid(&json!({"petType": "cat", "barks": true})));
+        let instance = json!({"petType": "dog"});
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Not a dog")
+            .collect();
+        assert_eq!(
+            errors[0].schema_path.to_string(),
+            "/discriminator/mapping/dog/required"
+        );
+    }
+
+    #[test]
+    fn lazy_branches() {
+        struct RecordingResolver(Arc<Mutex<Vec<String>>>);
+
+        impl SchemaResolver for RecordingResolver {
+            fn resolve(
+                &self,
+                _: &Value,
+                url: &Url,
+                _: &str,
+            ) -> Result<Arc<Value>, SchemaResolverError> {
+                self.0.lock().unwrap().push(url.to_string());
+                Ok(Arc::new(json!({"required": [url.path()]})))
+            }
+        }
+
+        let resolved = Arc::new(Mutex::new(Vec::new()));
+        let schema = json!({
+            "oneOf": [{"$ref": "db://schemas/cat"}, {"$ref": "db://schemas/dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "db://schemas/cat", "dog": "db://schemas/dog"}
+            }
+        });
+        let compiled = tests_util::openapi_options()
+            .with_resolver(RecordingResolver(Arc::clone(&resolved)))
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(resolved.lock().unwrap().is_empty());
+        let cat = json!({"petType": "cat", "/cat": true});
+        assert!(compiled.is_valid(&cat));
+        assert!(compiled.is_valid(&cat));
+        assert_eq!(*resolved.lock().unwrap(), vec!["db://schemas/cat"]);
+    }
+
+    #[test]
+    fn warm_up() {
+        struct InMemoryResolver(AHashMap<String, Arc<Value>>);
+
+        impl SchemaResolver for InMemoryResolver {
+            fn resolve(
+                &self,
+                _: &Value,
+                url: &Url,
+                _: &str,
+            ) -> Result<Arc<Value>, SchemaResolverError> {
+                self.0
+                    .get(url.as_str())
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Unknown schema: {}", url))
+            }
+        }
+
+        let mut documents = AHashMap::new();
+        documents.insert(
+            "db://schemas/cat".to_string(),
+            Arc::new(json!({"properties": {"toy": {"$ref": "db://schemas/toy"}}})),
+        );
+        documents.insert(
+            "db://schemas/toy".to_string(),
+            Arc::new(json!({"minLength": "short"})),
+        );
+        documents.insert(
+            "db://schemas/dog".to_string(),
+            Arc::new(json!({"required": ["barks"]})),
+        );
+        let schema = json!({
+            "oneOf": [{"$ref": "db://schemas/cat"}, {"$ref": "db://schemas/dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "db://schemas/cat", "dog": "db://schemas/dog"}
+            }
+        });
+        let compiled = tests_util::openapi_options()
+            .with_resolver(InMemoryResolver(documents))
+            .compile(&schema)
+            .expect("Valid schema");
+        // The malformed schema is only reached through a branch
+        assert!(compiled.is_valid(&json!({"petType": "dog", "barks": true})));
+        let errors = compiled.warm_up().expect_err("Malformed toy");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, ValidationErrorKind::Type { .. }));
+    }
+
+    #[test]
+    fn malformed_unused_branch() {
+        // Branches in the same document are checked by the meta-schema, even if never selected
+        let mut schema = pets(json!({}));
+        schema["discriminator"]["mapping"]["bird"] = json!("#/definitions/Bird");
+        schema["definitions"]["Bird"] = json!({"type": 5});
+        assert!(tests_util::openapi_options().compile(&schema).is_err());
+    }
+
+    #[test_case(&json!({"petType": "cat", "meows": true}), &[], &["/discriminator"])]
+    #[test_case(&json!({"petType": "cat"}), &["/discriminator/mapping/cat/required"], &["/discriminator"])]
+    #[test_case(&json!({"petType": "dog", "barks": true}), &[], &[])]
+    #[test_case(&json!({"petType": "fish", "meows": true}), &[], &["/discriminator"]; "pass through")]
+    #[test_case(&json!({"petType": "fish"}), &["/oneOf"], &["/discriminator"]; "pass through invalid")]
+    fn warnings(instance: &Value, expected_errors: &[&str], expected_warnings: &[&str]) {
+        let mut schema = pets(json!({}));
+        schema["definitions"]["Cat"]["deprecated"] = json!(true);
+        let compiled = tests_util::openapi_options()
+            .should_warn_about_deprecated_discriminator_branches(true)
+            .should_pass_through_unknown_discriminator_values(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        let (errors, warnings) = compiled.validate_with_warnings(instance);
+        let locations = |errors: &[ValidationError<'_>]| {
+            errors
+                .iter()
+                .map(|error| error.schema_path.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(locations(&errors), expected_errors);
+        assert_eq!(locations(&warnings), expected_warnings);
+        assert!(errors.iter().all(|error| error.severity == Severity::Error));
+        assert!(warnings
+            .iter()
+            .all(|warning| warning.severity == Severity::Warning));
+        // Warnings don't fail validation
+        assert_eq!(compiled.is_valid(instance), errors.is_empty());
+        assert_eq!(
+            compiled.validate(instance).err().map_or(0, Iterator::count),
+            errors.len()
+        );
+        assert_eq!(compiled.validate_once(instance).is_ok(), errors.is_empty());
+        assert_eq!(
+            compiled.apply(instance).basic().is_valid(),
+            errors.is_empty()
+        );
+    }
+
+    // Valid against `Cat` without the tag
+    #[test_case(json!({}), false, false)]
+    #[test_case(json!({}), true, true)]
+    // The fallback branch takes precedence over passing the tag through
+    #[test_case(json!({"*": "#/definitions/Dog"}), false, false)]
+    #[test_case(json!({"*": "#/definitions/Dog"}), true, false)]
+    #[test_case(json!({"*": "#/definitions/Cat"}), false, true)]
+    fn unmapped_tag_precedence(fallback: Value, pass_through: bool, expected: bool) {
+        let mut schema = pets(json!({}));
+        if let Value::Object(fallback) = fallback {
+            schema["discriminator"]["mapping"]
+                .as_object_mut()
+                .expect("An object")
+                .extend(fallback);
+        }
+        let compiled = tests_util::openapi_options()
+            .should_pass_through_unknown_discriminator_values(pass_through)
+            .compile(&schema)
+            .expect("Valid schema");
+        let instance = json!({"petType": "fish", "meows": true});
+        assert_eq!(compiled.is_valid(&instance), expected);
+        assert_eq!(compiled.validate(&instance).is_ok(), expected);
+        // Strict mode doesn't change how a present tag is handled
+        let compiled = tests_util::openapi_options()
+            .should_pass_through_unknown_discriminator_values(pass_through)
+            .should_use_strict_discriminator(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(&instance), expected);
+    }
+
+    #[test]
+    fn unknown_value_without_pass_through() {
+        let compiled = tests_util::openapi_options()
+            .compile(&pets(json!({})))
+            .expect("Valid schema");
+        let instance = json!({"petType": "fish", "meows": true});
+        let (errors, warnings) = compiled.validate_with_warnings(&instance);
+        assert_eq!(errors.len(), 1);
+        assert!(warnings.is_empty());
+    }
+
+    fn pet_list() -> Value {
+        let mut schema = pets(json!({}));
+        let schema_object = schema.as_object_mut().expect("An object");
+        let one_of = schema_object.remove("oneOf").expect("oneOf");
+        let discriminator = schema_object
+            .remove("discriminator")
+            .expect("discriminator");
+        schema["items"] = json!({"oneOf": one_of, "discriminator": discriminator});
+        schema
+    }
+
+    #[test]
+    fn memoized() {
+        let compiled = tests_util::openapi_options()
+            .should_memoize_discriminated_instances(true)
+            .compile(&pet_list())
+            .expect("Valid schema");
+        let instance = json!(vec![json!({"petType": "cat", "meows": true}); 1000]);
+        assert!(compiled.is_valid(&instance));
+        assert_eq!(compiled.memo_hits(), 999);
+        // The memo doesn't outlive a single call
+        assert!(compiled.validate(&instance).is_ok());
+        assert_eq!(compiled.memo_hits(), 1998);
+        let mut instance = instance;
+        instance[500] = json!({"petType": "dog", "meows": true});
+        assert!(!compiled.is_valid(&instance));
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Not a dog")
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].instance_path.to_string(), "/500");
+    }
+
+    #[test]
+    fn memoized_warnings() {
+        let mut schema = pet_list();
+        schema["definitions"]["Cat"]["deprecated"] = json!(true);
+        let instance = json!(vec![json!({"petType": "cat", "meows": true}); 3]);
+        let warnings = |memoize: bool| {
+            let compiled = tests_util::openapi_options()
+                .should_memoize_discriminated_instances(memoize)
+                .should_warn_about_deprecated_discriminator_branches(true)
+                .compile(&schema)
+                .expect("Valid schema");
+            let (errors, warnings) = compiled.validate_with_warnings(&instance);
+            assert!(errors.is_empty());
+            warnings
+                .iter()
+                .map(|warning| warning.instance_path.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(warnings(true), vec!["/0", "/1", "/2"]);
+        assert_eq!(warnings(true), warnings(false));
+    }
+
+    #[test]
+    fn not_memoized_by_default() {
+        let compiled = tests_util::openapi_options()
+            .compile(&pet_list())
+            .expect("Valid schema");
+        let instance = json!(vec![json!({"petType": "cat", "meows": true}); 10]);
+        assert!(compiled.is_valid(&instance));
+        assert_eq!(compiled.memo_hits(), 0);
+    }
+
+    #[test]
+    fn to_value() {
+        let schema = pets(json!({}));
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.to_value(), schema);
+        // The rebuilt schema compiles to the same validators
+        let recompiled = tests_util::openapi_options()
+            .compile(&compiled.to_value())
+            .expect("Valid schema");
+        assert_eq!(recompiled.node.to_string(), compiled.node.to_string());
+    }
+
+    #[test]
+    fn to_value_with_implicit_mapping() {
+        let mut schema = pets(json!({}));
+        schema["discriminator"]["mapping"]
+            .as_object_mut()
+            .expect("An object")
+            .remove("dog");
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(
+            compiled.to_value()["discriminator"],
+            json!({
+                "propertyName": "petType",
+                "mapping": {
+                    "cat": "#/definitions/Cat",
+                    "Cat": "#/definitions/Cat",
+                    "Dog": "#/definitions/Dog"
+                }
+            })
+        );
+    }
+
+    fn shapes() -> Value {
+        json!({
+            "oneOf": [
+                {"$ref": "#/definitions/CircleV1"},
+                {"$ref": "#/definitions/CircleV2"},
+                {"$ref": "#/definitions/Square"}
+            ],
+            "discriminator": {
+                "propertyName": ["kind", "version"],
+                "mapping": {
+                    "circle,1": "#/definitions/CircleV1",
+                    "circle,2": "#/definitions/CircleV2",
+                    "square,1": "#/definitions/Square"
+                }
+            },
+            "definitions": {
+                "CircleV1": {"required": ["radius"]},
+                "CircleV2": {"required": ["diameter"]},
+                "Square": {"required": ["side"]}
+            }
+        })
+    }
+
+    #[test_case(&json!({"kind": "circle", "version": 1, "radius": 1}), None)]
+    #[test_case(&json!({"kind": "circle", "version": "2", "diameter": 2}), None)]
+    #[test_case(&json!({"kind": "square", "version": 1, "side": 1}), None)]
+    #[test_case(&json!({"kind": "circle", "version": 2, "radius": 1}), Some("/discriminator/mapping/circle,2/required"))]
+    #[test_case(&json!({"kind": "square", "version": 2, "side": 1}), Some("/discriminator"))]
+    fn composite_key(instance: &Value, expected: Option<&str>) {
+        let compiled = tests_util::openapi_options()
+            .compile(&shapes())
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(instance), expected.is_none());
+        let errors: Vec<_> = compiled
+            .validate(instance)
+            .err()
+            .into_iter()
+            .flatten()
+            .map(|error| error.schema_path.to_string())
+            .collect();
+        assert_eq!(errors.first().map(String::as_str), expected);
+    }
+
+    #[test]
+    fn composite_key_missing_property() {
+        let compiled = tests_util::openapi_options()
+            .should_use_strict_discriminator(true)
+            .compile(&shapes())
+            .expect("Valid schema");
+        let instance = json!({"kind": "circle", "radius": 1});
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("No version")
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), r#""version" is a required property"#);
+    }
+
+    #[test]
+    fn composite_property_names() {
+        let discriminator =
+            Discriminator::try_from_value(&shapes()["discriminator"]).expect("Valid discriminator");
+        assert_eq!(discriminator.property_name(), "kind");
+        assert_eq!(
+            discriminator.property_names().collect::<Vec<_>>(),
+            vec!["kind", "version"]
+        );
+        for property_name in [json!([]), json!(["kind", 1])] {
+            assert_eq!(
+                Discriminator::try_from_value(&json!({"propertyName": property_name})),
+                Err(DiscriminatorError::InvalidPropertyName)
+            );
+        }
+        let compiled = tests_util::openapi_options()
+            .compile(&shapes())
+            .expect("Valid schema");
+        assert_eq!(compiled.to_value(), shapes());
+    }
+
+    fn inherited(root: &str) -> Value {
+        json!({
+            "$ref": root,
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "required": ["petType"],
+                    "properties": {"petType": {"type": "string"}},
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+                    }
+                },
+                "Cat": {"allOf": [{"$ref": "#/definitions/Pet"}, {"required": ["meows"]}]},
+                "Dog": {"allOf": [{"$ref": "#/definitions/Pet"}, {"required": ["barks"]}]}
+            }
+        })
+    }
+
+    #[test_case("#/definitions/Pet", &json!({"petType": "cat", "meows": true}), &[])]
+    #[test_case("#/definitions/Pet", &json!({"petType": "dog", "barks": true}), &[])]
+    #[test_case("#/definitions/Pet", &json!({"petType": "cat", "barks": true}), &["/discriminator/mapping/cat/allOf/1/required"])]
+    #[test_case("#/definitions/Pet", &json!({"petType": "fish"}), &["/discriminator"])]
+    #[test_case("#/definitions/Pet", &json!({"meows": true}), &["/required"])]
+    #[test_case("#/definitions/Pet", &json!({"petType": 1, "meows": true}), &["/discriminator", "/properties/petType/type"])]
+    #[test_case("#/definitions/Cat", &json!({"petType": "cat", "meows": true}), &[])]
+    #[test_case("#/definitions/Cat", &json!({"petType": "dog", "meows": true}), &["/allOf/0/discriminator/mapping/dog/allOf/1/required"])]
+    fn inherited_discriminator(root: &str, instance: &Value, expected: &[&str]) {
+        let compiled = tests_util::openapi_options()
+            .compile(&inherited(root))
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(instance), expected.is_empty());
+        assert_eq!(
+            compiled.apply(instance).basic().is_valid(),
+            expected.is_empty()
+        );
+        let errors: Vec<_> = compiled
+            .validate(instance)
+            .err()
+            .into_iter()
+            .flatten()
+            .map(|error| error.schema_path.to_string())
+            .collect();
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn inherited_discriminator_without_mapping() {
+        // Nothing to dispatch to, the discriminator is only an annotation
+        let schema = json!({"discriminator": {"propertyName": "petType"}});
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"petType": "cat"})));
+        assert!(compiled.node.keyword_validator("discriminator").is_none());
+    }
+
+    #[test]
+    fn inherited_discriminator_depth() {
+        // Every base dispatches the instance to the next one by its own property
+        let definitions: serde_json::Map<_, _> = (0..=MAX_DISPATCH_DEPTH)
+            .map(|idx| {
+                let next = format!("#/definitions/Base{}", idx + 1);
+                let base = json!({
+                    "discriminator": {
+                        "propertyName": format!("kind{}", idx),
+                        "mapping": {"next": next}
+                    }
+                });
+                (format!("Base{}", idx), base)
+            })
+            .chain(once((format!("Base{}", MAX_DISPATCH_DEPTH + 1), json!({}))))
+            .collect();
+        let schema = json!({"$ref": "#/definitions/Base0", "definitions": definitions});
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        let instance: serde_json::Map<_, _> = (0..=MAX_DISPATCH_DEPTH)
+            .map(|idx| (format!("kind{}", idx), json!("next")))
+            .collect();
+        let instance = Value::Object(instance);
+        assert!(!compiled.is_valid(&instance));
+        assert!(!compiled.apply(&instance).basic().is_valid());
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Too many dispatches")
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].kind,
+            ValidationErrorKind::DiscriminatorDepthExceeded { limit } if limit == MAX_DISPATCH_DEPTH
+        ));
+        // One dispatch less is within the limit
+        let mut instance = instance;
+        instance
+            .as_object_mut()
+            .unwrap()
+            .remove(&format!("kind{}", MAX_DISPATCH_DEPTH));
+        assert!(compiled.is_valid(&instance));
+    }
+
+    #[test]
+    fn lenient_union_mapping() {
+        let mut schema = pets(json!({}));
+        schema["definitions"]["Lion"] = json!({"required": ["roars"]});
+        schema["oneOf"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({"$ref": "#/definitions/Lion"}));
+        schema["discriminator"]["mapping"]["feline"] =
+            json!(["#/definitions/Cat", "#/definitions/Lion"]);
+        let error = tests_util::openapi_options()
+            .compile(&schema)
+            .expect_err("Not a string mapping value");
+        assert_eq!(
+            error.instance_path.to_string(),
+            "/discriminator/mapping/feline"
+        );
+        let compiled = tests_util::openapi_options()
+            .should_use_lenient_discriminator_mapping(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"petType": "feline", "meows": true})));
+        assert!(compiled.is_valid(&json!({"petType": "feline", "roars": true})));
+        assert!(!compiled.is_valid(&json!({"petType": "feline", "barks": true})));
+        assert!(compiled.is_valid(&json!({"petType": "cat", "meows": true})));
+        let instance = json!({"petType": "feline"});
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Neither a cat nor a lion")
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].schema_path.to_string(),
+            "/discriminator/mapping/feline/anyOf"
+        );
+        // Arrays with non-string items are still rejected
+        schema["discriminator"]["mapping"]["feline"] = json!(["#/definitions/Cat", 1]);
+        assert!(tests_util::openapi_options()
+            .should_use_lenient_discriminator_mapping(true)
+            .compile(&schema)
+            .is_err());
+    }
+
+    #[test]
+    fn display() {
+        let schema = pets(json!({}));
+        let expected = "discriminator: {propertyName: petType, mapping: {cat: {$ref: json-schema:///#/definitions/Cat}, dog: {$ref: json-schema:///#/definitions/Dog}}}, oneOf: [{$ref: json-schema:///#/definitions/Cat}, {$ref: json-schema:///#/definitions/Dog}]";
+        for _ in 0..10 {
+            let compiled = tests_util::openapi_options()
+                .compile(&schema)
+                .expect("Valid schema");
+            let validator = compiled
+                .node
+                .keyword_validator("discriminator")
+                .expect("A discriminator");
+            assert_eq!(validator.to_string(), expected);
+        }
+    }
+
+    #[test_case(&json!({"petType": "fish", "swims": true}), true)]
+    #[test_case(&json!({"petType": "fish"}), false)]
+    #[test_case(&json!({"petType": "cat", "swims": true}), false)]
+    fn fallback_branch(instance: &Value, expected: bool) {
+        let mut schema = pets(json!({}));
+        schema["oneOf"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({"$ref": "#/definitions/Unknown"}));
+        schema["discriminator"]["mapping"]["*"] = json!("#/definitions/Unknown");
+        schema["definitions"]["Unknown"] = json!({"required": ["swims"]});
+        if expected {
+            tests_util::openapi_is_valid(&schema, instance);
+        } else {
+            tests_util::openapi_is_not_valid(&schema, instance);
+        }
+    }
+
+    #[test]
+    fn fallback_branch_schema_path() {
+        let mut schema = pets(json!({}));
+        schema["discriminator"]["mapping"]["*"] = json!("#/definitions/Cat");
+        tests_util::openapi_assert_schema_path(
+            &schema,
+            &json!({"petType": "fish"}),
+            "/discriminator/mapping/*/required",
+        );
+    }
+
+    #[cfg(feature = "draft202012")]
+    #[test]
+    fn dynamic_ref_mapping() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema#",
+            "oneOf": [
+                {"$dynamicRef": "#/$defs/Leaf"},
+                {"$dynamicRef": "#/$defs/Tree"}
+            ],
+            "discriminator": {"propertyName": "kind"},
+            "$defs": {
+                "Leaf": {"required": ["value"], "properties": {"value": {"type": "integer"}}},
+                "Tree": {
+                    "required": ["children"],
+                    "properties": {"children": {"type": "array", "items": {"$ref": "#"}}}
+                }
+            }
+        });
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        let tree = json!({
+            "kind": "Tree",
+            "children": [
+                {"kind": "Leaf", "value": 1},
+                {"kind": "Tree", "children": [{"kind": "Leaf", "value": 2}]}
+            ]
+        });
+        assert!(compiled.is_valid(&tree));
+        let invalid = json!({
+            "kind": "Tree",
+            "children": [{"kind": "Tree", "children": [{"kind": "Leaf", "value": "2"}]}]
+        });
+        let errors: Vec<_> = compiled
+            .validate(&invalid)
+            .expect_err("Invalid leaf")
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].instance_path.to_string(),
+            "/children/0/children/0/value"
+        );
+    }
+
+    // A `cat` dynamic anchor of the root schema overrides the one of the referenced resource
+    #[cfg(feature = "draft202012")]
+    #[test_case(true, false)]
+    #[test_case(false, true)]
+    fn dynamic_ref_mapping_root_anchor(root_anchor: bool, expected: bool) {
+        let reference = "https://example.com/pets#cat";
+        let mut schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema#",
+            "oneOf": [{"$dynamicRef": reference}, {"$ref": "#/$defs/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": reference, "dog": "#/$defs/Dog"}
+            },
+            "$defs": {
+                "StrictCat": {"required": ["meows"]},
+                "Dog": {"required": ["barks"]},
+                "pets": {
+                    "$id": "https://example.com/pets",
+                    "$defs": {"Cat": {"$dynamicAnchor": "cat", "type": "object"}}
+                }
+            }
+        });
+        if root_anchor {
+            schema["$defs"]["StrictCat"]["$dynamicAnchor"] = json!("cat");
+        }
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(&json!({"petType": "cat"})), expected);
+        assert!(compiled.is_valid(&json!({"petType": "cat", "meows": true})));
+    }
+
+    #[test]
+    fn tag_normalizer() {
+        let schema = pets(json!({}));
+        let compiled = tests_util::openapi_options()
+            .with_discriminator_tag_normalizer(|tag| Cow::Borrowed(tag.trim()))
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"petType": " cat ", "meows": true})));
+        assert!(!compiled.is_valid(&json!({"petType": " cat ", "barks": true})));
+        // Tags are matched as is by default
+        tests_util::openapi_is_not_valid(&schema, &json!({"petType": " cat ", "meows": true}));
+    }
+
+    fn versions(key: &str) -> Value {
+        json!({
+            "oneOf": [{"$ref": "#/definitions/V1"}, {"$ref": "#/definitions/V2"}],
+            "discriminator": {
+                "propertyName": "version",
+                "mapping": {key: "#/definitions/V1", "2.5": "#/definitions/V2"}
+            },
+            "definitions": {
+                "V1": {"required": ["name"]},
+                "V2": {"required": ["title"]}
+            }
+        })
+    }
+
+    #[test_case(NumericTagFormat::Integer, "1")]
+    #[test_case(NumericTagFormat::Integer, "1.0")]
+    #[test_case(NumericTagFormat::Integer, "1.00")]
+    #[test_case(NumericTagFormat::Decimal, "1")]
+    #[test_case(NumericTagFormat::Decimal, "1.0")]
+    #[test_case(NumericTagFormat::Decimal, "1.00")]
+    fn numeric_tags(format: NumericTagFormat, key: &str) {
+        let compiled = tests_util::openapi_options()
+            .with_discriminator_numeric_tags(format)
+            .compile(&versions(key))
+            .expect("Valid schema");
+        for tag in ["1", "1.0", "1.00"] {
+            let instance: Value =
+                serde_json::from_str(&format!(r#"{{"version": {}, "name": "v1"}}"#, tag))
+                    .expect("Valid JSON");
+            assert!(compiled.is_valid(&instance), "{}", tag);
+            assert!(!compiled.is_valid(&json!({"version": tag, "title": "v2"})));
+            assert!(compiled.is_valid(&json!({"version": tag, "name": "v1"})));
+        }
+        assert!(compiled.is_valid(&json!({"version": 2.50, "title": "v2"})));
+        assert!(!compiled.is_valid(&json!({"version": 1, "title": "v2"})));
+    }
+
+    #[test_case(NumericTagFormat::Integer, "1.0" => Some("1".to_string()))]
+    #[test_case(NumericTagFormat::Integer, "1e2" => Some("100".to_string()))]
+    #[test_case(NumericTagFormat::Integer, "-3.00" => Some("-3".to_string()))]
+    #[test_case(NumericTagFormat::Integer, "1" => None)]
+    #[test_case(NumericTagFormat::Integer, "1.5" => None)]
+    #[test_case(NumericTagFormat::Decimal, "1" => Some("1.0".to_string()))]
+    #[test_case(NumericTagFormat::Decimal, "1.00" => Some("1.0".to_string()))]
+    #[test_case(NumericTagFormat::Decimal, "cat,2" => Some("cat,2.0".to_string()))]
+    #[test_case(NumericTagFormat::Decimal, " 1" => None)]
+    #[test_case(NumericTagFormat::Decimal, "cat" => None)]
+    fn canonical_numeric_tag(format: NumericTagFormat, tag: &str) -> Option<String> {
+        format.canonicalize(tag)
+    }
+
+    #[test]
+    fn numeric_tags_after_normalizer() {
+        let compiled = tests_util::openapi_options()
+            .with_discriminator_numeric_tags(NumericTagFormat::Integer)
+            .with_discriminator_tag_normalizer(|tag| Cow::Borrowed(tag.trim()))
+            .compile(&versions("1.0"))
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"version": " 1 ", "name": "v1"})));
+    }
+
+    #[test]
+    fn numeric_tags_as_is_by_default() {
+        let schema = versions("1");
+        tests_util::openapi_is_valid(&schema, &json!({"version": 1, "name": "v1"}));
+        tests_util::openapi_is_not_valid(&schema, &json!({"version": 1.0, "name": "v1"}));
+        tests_util::openapi_is_not_valid(&schema, &json!({"version": "1.0", "name": "v1"}));
+    }
+
+    #[test_case(&json!("2"))]
+    #[test_case(&json!(2))]
+    #[test_case(&json!("2.0"))]
+    #[test_case(&json!(2.0))]
+    fn loose_numeric_tags(tag: &Value) {
+        let mut schema = versions("1");
+        schema["discriminator"]["mapping"] =
+            json!({"1": "#/definitions/V1", "2": "#/definitions/V2"});
+        let compiled = tests_util::openapi_options()
+            .should_use_loose_numeric_tags(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"version": tag, "title": "v2"})));
+        assert!(!compiled.is_valid(&json!({"version": tag, "name": "v1"})));
+        assert!(!compiled.is_valid(&json!({"version": "02x", "title": "v2"})));
+    }
+
+    #[test]
+    fn loose_numeric_tags_keep_format() {
+        let mut schema = versions("1");
+        schema["discriminator"]["mapping"] = json!({"2.0": "#/definitions/V2"});
+        schema["oneOf"] = json!([{"$ref": "#/definitions/V2"}]);
+        let compiled = tests_util::openapi_options()
+            .with_discriminator_numeric_tags(NumericTagFormat::Decimal)
+            .should_use_loose_numeric_tags(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"version": "2", "title": "v2"})));
+    }
+
+    #[test_case(&tests_util::openapi_options(); "default")]
+    #[test_case(
+        tests_util::openapi_options().with_discriminator_tag_normalizer(|tag| Cow::Owned(tag.to_lowercase()));
+        "normalized"
+    )]
+    #[test_case(tests_util::openapi_options().should_use_strict_discriminator(true); "strict")]
+    #[test_case(tests_util::openapi_options().should_allow_partial_discriminated_instances(true); "partial")]
+    #[test_case(
+        tests_util::openapi_options().should_pass_through_unknown_discriminator_values(true);
+        "pass through"
+    )]
+    #[test_case(
+        tests_util::openapi_options().should_memoize_discriminated_instances(true);
+        "memoized"
+    )]
+    fn instance_unchanged(options: &CompilationOptions) {
+        let schema = pets(json!({}));
+        let compiled = options.compile(&schema).expect("Valid schema");
+        for instance in [
+            json!({"petType": "cat", "meows": true}),
+            json!({"petType": "Cat", "meows": true}),
+            json!({"petType": "dog", "meows": true}),
+            json!({"petType": "fish"}),
+            json!({"meows": true}),
+            json!({"petType": null}),
+            json!([{"petType": "cat"}]),
+        ] {
+            let frozen = serde_json::to_vec(&instance).expect("Serializable");
+            let _ = compiled.is_valid(&instance);
+            if let Err(errors) = compiled.validate(&instance) {
+                errors.for_each(drop);
+            }
+            let _ = compiled.apply(&instance).basic();
+            let _ = compiled.validate_with_defaults(&instance);
+            assert_eq!(
+                serde_json::to_vec(&instance).expect("Serializable"),
+                frozen,
+                "{}",
+                instance
+            );
+        }
+    }
+
+    #[test]
+    fn mapping_size_limit() {
+        let schema = pets(json!({}));
+        let error = tests_util::openapi_options()
+            .with_max_discriminator_mapping_size(1)
+            .compile(&schema)
+            .expect_err("Too many mapping entries");
+        assert_eq!(error.instance_path.to_string(), "/discriminator/mapping");
+        assert!(matches!(
+            error.kind,
+            ValidationErrorKind::MaxProperties { limit: 1 }
+        ));
+        assert!(tests_util::openapi_options()
+            .with_max_discriminator_mapping_size(2)
+            .compile(&schema)
+            .is_ok());
+    }
+
+    #[test]
+    fn mapping_size_limit_branches() {
+        let schema = pets(json!({
+            "oneOf": [
+                {"$ref": "#/definitions/Cat"},
+                {"$ref": "#/definitions/Dog"},
+                {"$ref": "#/definitions/Dog"}
+            ]
+        }));
+        let error = tests_util::openapi_options()
+            .with_max_discriminator_mapping_size(2)
+            .compile(&schema)
+            .expect_err("Too many branches");
+        assert_eq!(error.instance_path.to_string(), "/oneOf");
+        assert!(matches!(
+            error.kind,
+            ValidationErrorKind::MaxItems { limit: 2 }
+        ));
+    }
+
+    #[test_case("Straße", "strasse")]
+    #[test_case("Straße", "STRASSE")]
+    #[test_case("strasse", "Straße")]
+    #[test_case("ΟΔΟΣ", "οδος")]
+    #[test_case("Ǆ", "ǆ")]
+    #[test_case("Cat", "cAT")]
+    fn case_insensitive_tags(key: &str, tag: &str) {
+        let schema = json!({
+            "oneOf": [{"$ref": "#/definitions/A"}, {"$ref": "#/definitions/B"}],
+            "discriminator": {
+                "propertyName": "kind",
+                "mapping": {key: "#/definitions/A", "other": "#/definitions/B"}
+            },
+            "definitions": {"A": {"required": ["a"]}, "B": {"required": ["b"]}}
+        });
+        let compiled = tests_util::openapi_options()
+            .should_match_discriminator_tags_case_insensitively(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"kind": tag, "a": 1})));
+        assert!(!compiled.is_valid(&json!({"kind": tag, "b": 1})));
+        // Case matters by default
+        tests_util::openapi_is_not_valid(&schema, &json!({"kind": tag, "a": 1}));
+    }
+
+    #[test]
+    fn coerce_types() {
+        let mut schema = pets(json!({}));
+        schema["definitions"]["Cat"]["properties"]["lives"] =
+            json!({"type": "integer", "maximum": 9});
+        let compiled = tests_util::openapi_options()
+            .should_coerce_types(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        let instance = json!({"petType": "cat", "meows": "true", "lives": "9"});
+        assert!(compiled.is_valid(&instance));
+        assert!(compiled.validate(&instance).is_ok());
+        assert!(compiled.apply(&instance).basic().is_valid());
+        tests_util::openapi_is_not_valid(&schema, &instance);
+        // Coerced values are checked against the other keywords of the branch
+        let instance = json!({"petType": "cat", "meows": "true", "lives": "10"});
+        assert!(!compiled.is_valid(&instance));
+        assert!(!compiled.apply(&instance).basic().is_valid());
+        // Errors report the original instance
+        let instance = json!({"petType": "cat", "meows": "maybe"});
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("Not a boolean")
+            .map(|error| (error.instance_path.to_string(), error.instance.into_owned()))
+            .collect();
+        assert_eq!(errors, vec![("/meows".to_string(), json!("maybe"))]);
+    }
+
+    #[test_case("Straße" => "strasse")]
+    #[test_case("ΟΔΟΣ" => "οδοσ")]
+    #[test_case("İstanbul" => "i̇stanbul")]
+    #[test_case("ıi" => "ıi")]
+    #[test_case("MiXeD" => "mixed")]
+    fn fold_case(tag: &str) -> String {
+        super::fold_case(tag)
+    }
+
+    #[test]
+    fn numeric_string_tags() {
+        // Tags are compared by their spelling, so strings and numbers select the same key
+        let schema = versions("2");
+        tests_util::openapi_is_valid(&schema, &json!({"version": "2", "name": "v1"}));
+        tests_util::openapi_is_valid(&schema, &json!({"version": 2, "name": "v1"}));
+        tests_util::openapi_is_not_valid(&schema, &json!({"version": "2", "title": "v2"}));
+        tests_util::openapi_is_valid(&schema, &json!({"version": "2.5", "title": "v2"}));
+        // Differently spelled numbers need a canonical format
+        let compiled = tests_util::openapi_options()
+            .with_discriminator_numeric_tags(NumericTagFormat::Integer)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!({"version": "2.0", "name": "v1"})));
+    }
+
+    #[test]
+    fn resolve_variant() {
+        let compiled = tests_util::openapi_options()
+            .compile(&pets(json!({
+                "discriminator": {
+                    "propertyName": "petType",
+                    "mapping": {"cat": "#/definitions/Cat", "*": "#/definitions/Dog"}
+                }
+            })))
+            .expect("Valid schema");
+        // Discrimination doesn't validate the branch
+        let instance = json!({"petType": "cat", "meows": "loudly"});
+        assert_eq!(
+            compiled
+                .resolve_variant(&instance)
+                .expect("Discriminator")
+                .expect("Mapped tag"),
+            VariantInfo {
+                tag: "cat".to_string(),
+                reference: Some("#/definitions/Cat".to_string()),
+                branch: Some(0),
+            }
+        );
+        let instance = json!({"petType": "fish"});
+        let variant = compiled
+            .resolve_variant(&instance)
+            .expect("Discriminator")
+            .expect("Fallback");
+        assert_eq!(variant.tag, "fish");
+        assert_eq!(variant.reference.as_deref(), Some("#/definitions/Dog"));
+        assert_eq!(variant.branch, Some(1));
+    }
+
+    #[test_case("cat", true)]
+    #[test_case("dog", true)]
+    #[test_case(" cat ", true; "normalized")]
+    #[test_case("fish", false)]
+    #[test_case("Cat", false)]
+    fn validate_tag(tag: &str, expected: bool) {
+        let compiled = tests_util::openapi_options()
+            .with_discriminator_tag_normalizer(|tag| Cow::Borrowed(tag.trim()))
+            .compile(&pets(json!({})))
+            .expect("Valid schema");
+        assert_eq!(compiled.validate_discriminator_tag(tag), Some(expected));
+    }
+
+    #[test]
+    fn validate_tag_implicit_and_fallback() {
+        let mut schema = pets(json!({}));
+        schema["discriminator"] = json!({"propertyName": "petType"});
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.validate_discriminator_tag("Cat"), Some(true));
+        assert_eq!(compiled.validate_discriminator_tag("cat"), Some(false));
+        schema["discriminator"]["mapping"] = json!({"*": "#/definitions/Dog"});
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.validate_discriminator_tag("fish"), Some(true));
+        let compiled = tests_util::openapi_options()
+            .compile(&json!({"oneOf": [{}]}))
+            .expect("Valid schema");
+        assert_eq!(compiled.validate_discriminator_tag("cat"), None);
+    }
+
+    #[test_case("cat", Some(("#/definitions/Cat", MappingSource::Explicit)))]
+    #[test_case("Dog", Some(("#/definitions/Dog", MappingSource::Implicit)))]
+    // Explicit entries take precedence over implicit ones
+    #[test_case("Cat", Some(("#/definitions/Dog", MappingSource::Explicit)))]
+    #[test_case("fish", Some(("#/definitions/Cat", MappingSource::Fallback)))]
+    fn branches_for_tag(tag: &str, expected: Option<(&str, MappingSource)>) {
+        let compiled = tests_util::openapi_options()
+            .should_resolve_implicit_discriminator_mapping(true)
+            .compile(&pets(json!({
+                "discriminator": {
+                    "propertyName": "petType",
+                    "mapping": {
+                        "cat": "#/definitions/Cat",
+                        "Cat": "#/definitions/Dog",
+                        "*": "#/definitions/Cat"
+                    }
+                }
+            })))
+            .expect("Valid schema");
+        let actual = compiled
+            .branches_for_tag(tag)
+            .map(|(variant, source)| (variant.reference.expect("A single reference"), source));
+        assert_eq!(
+            actual,
+            expected.map(|(reference, source)| (reference.to_string(), source))
+        );
+    }
+
+    #[test]
+    fn branches_for_tag_unmapped() {
+        let compiled = tests_util::openapi_options()
+            .compile(&pets(json!({})))
+            .expect("Valid schema");
+        let (variant, source) = compiled.branches_for_tag("dog").expect("A mapped tag");
+        assert_eq!(variant.branch, Some(1));
+        assert_eq!(source, MappingSource::Explicit);
+        assert!(compiled.branches_for_tag("Dog").is_none());
+        let compiled = tests_util::openapi_options()
+            .compile(&json!({"oneOf": [{}]}))
+            .expect("Valid schema");
+        assert!(compiled.branches_for_tag("dog").is_none());
+    }
+
+    #[test]
+    fn resolve_variant_errors() {
+        let compiled = tests_util::openapi_options()
+            .compile(&pets(json!({})))
+            .expect("Valid schema");
+        let instance = json!({"petType": "fish"});
+        let error = compiled
+            .resolve_variant(&instance)
+            .expect("Discriminator")
+            .expect_err("Unknown tag");
+        assert_eq!(
+            error.to_string(),
+            r#""fish" is not a valid value for the "petType" discriminator"#
+        );
+        assert_eq!(error.schema_path.to_string(), "/discriminator");
+        let instance = json!({"meows": true});
+        let error = compiled
+            .resolve_variant(&instance)
+            .expect("Discriminator")
+            .expect_err("Missing tag");
+        assert_eq!(error.to_string(), r#""petType" is a required property"#);
+        let compiled = tests_util::openapi_options()
+            .compile(&json!({"oneOf": [{"type": "object"}]}))
+            .unwrap();
+        assert!(compiled.resolve_variant(&instance).is_none());
+    }
+
+    #[test]
+    fn resolve_variant_null_tag() {
+        let mut schema = pets(json!({}));
+        let instance = json!({"petType": null});
+        let compile = |schema: &Value| {
+            tests_util::openapi_options()
+                .should_reject_null_discriminator_values(true)
+                .compile(schema)
+                .expect("Valid schema")
+        };
+        let error = compile(&schema)
+            .resolve_variant(&instance)
+            .expect("Discriminator")
+            .expect_err("Rejected null tag");
+        assert!(matches!(
+            error.kind,
+            ValidationErrorKind::NullDiscriminatorValue { .. }
+        ));
+        schema["discriminator"]["mapping"]["null"] = json!("#/definitions/Dog");
+        let variant = compile(&schema)
+            .resolve_variant(&instance)
+            .expect("Discriminator")
+            .expect("Mapped null tag");
+        assert_eq!(variant.branch, Some(1));
+    }
+
+    #[test]
+    fn unknown_value_message() {
+        tests_util::openapi_expect_errors(
+            &pets(json!({})),
+            &json!({"petType": "fish"}),
+            &[r#""fish" is not a valid value for the "petType" discriminator"#],
+        )
+    }
+
+    #[test]
+    fn discriminate_selected() {
+        let validator = discriminator_validator(&pets(json!({})));
+        let instance = json!({"petType": "cat", "meows": "loudly"});
+        match validator.discriminate(&instance) {
+            DiscriminationResult::Selected { key, node } => {
+                assert_eq!(key, "cat");
+                // Selection doesn't validate the branch
+                assert!(!node.is_valid(&instance));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn discriminate_fallback() {
+        let validator = discriminator_validator(&pets(json!({
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/definitions/Cat", "*": "#/definitions/Dog"}
+            }
+        })));
+        let instance = json!({"petType": "fish"});
+        assert!(matches!(
+            validator.discriminate(&instance),
+            DiscriminationResult::Selected { key: "*", .. }
+        ));
+    }
+
+    #[test_case(&json!({"meows": true}); "missing")]
+    #[test_case(&json!({"petType": true}); "not a string")]
+    fn discriminate_missing_property(instance: &Value) {
+        let validator = discriminator_validator(&pets(json!({})));
+        assert!(matches!(
+            validator.discriminate(instance),
+            DiscriminationResult::MissingProperty
+        ));
+    }
+
+    #[test]
+    fn discriminate_unknown_tag() {
+        let validator = discriminator_validator(&pets(json!({})));
+        let instance = json!({"petType": "fish"});
+        match validator.discriminate(&instance) {
+            DiscriminationResult::UnknownTag(tag) => assert_eq!(tag, "fish"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test_case(&json!("cat"))]
+    #[test_case(&json!(["cat"]))]
+    #[test_case(&json!(null))]
+    fn discriminate_non_object(instance: &Value) {
+        let validator = discriminator_validator(&pets(json!({})));
+        assert!(matches!(
+            validator.discriminate(instance),
+            DiscriminationResult::NonObjectInstance
+        ));
+    }
+
+    fn birds(property_name: &str) -> Value {
+        json!({
+            "oneOf": [{"$ref": "#/definitions/Bird"}],
+            "discriminator": {
+                "propertyName": property_name,
+                "mapping": {"bird": "#/definitions/Bird"}
+            },
+            "definitions": {
+                "Bird": {"properties": {"sings": {"type": "boolean"}}, "required": ["sings"]}
+            }
+        })
+    }
+
+    #[test]
+    fn merge() {
+        let mut validator = discriminator_validator(&pets(json!({})));
+        validator
+            .merge(discriminator_validator(&birds("petType")))
+            .expect("No conflicts");
+        assert!(validator.is_valid(&json!({"petType": "cat", "meows": true})));
+        assert!(validator.is_valid(&json!({"petType": "dog", "barks": true})));
+        assert!(validator.is_valid(&json!({"petType": "bird", "sings": true})));
+        assert!(!validator.is_valid(&json!({"petType": "bird", "meows": true})));
+        assert!(!validator.is_valid(&json!({"petType": "fish"})));
+        let (variant, _) = validator.tag_variant("bird").expect("Mapped");
+        assert_eq!(variant.branch, Some(2));
+        assert_eq!(
+            validator
+                .one_of()
+                .matching_branches(&json!({"sings": true})),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn merge_conflicts() {
+        let mut validator = discriminator_validator(&pets(json!({})));
+        assert_eq!(
+            validator.merge(discriminator_validator(&pets(json!({})))),
+            Err(DiscriminatorError::ConflictingTag {
+                tag: "cat".to_string()
+            })
+        );
+        assert_eq!(
+            validator.merge(discriminator_validator(&birds("kind"))),
+            Err(DiscriminatorError::PropertyNameMismatch)
+        );
+        // Failed merges leave the validator unchanged
+        assert!(validator.is_valid(&json!({"petType": "cat", "meows": true})));
+        assert!(!validator.is_valid(&json!({"petType": "bird", "sings": true})));
+        assert_eq!(validator.mapping.len(), 2);
+    }
+
+    fn discriminator_validator(schema: &Value) -> DiscriminatorValidator {
+        let compiled = tests_util::openapi_options()
+            .compile(schema)
+            .expect("Valid schema");
+        let resolver = Arc::new(
+            Resolver::new(
+                Arc::new(DefaultResolver),
+                Default::default(),
+                &DEFAULT_SCOPE,
+                Arc::new(schema.clone()),
+                Default::default(),
+            )
+            .expect("Valid resolver"),
+        );
+        let context = CompilationContext::new(BaseUri::Unknown, compiled.config(), resolver);
+        let parent = schema.as_object().expect("An object");
+        DiscriminatorValidator::new(parent, &schema["discriminator"], &schema["oneOf"], &context)
+            .expect("Valid discriminator")
+    }
+}
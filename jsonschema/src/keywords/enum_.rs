@@ -65,6 +65,10 @@ impl Validate for EnumValidator {
             false
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("enum".to_string(), self.options.clone());
+    }
 }
 
 impl core::fmt::Display for EnumValidator {
@@ -124,6 +128,10 @@ impl Validate for SingleValueEnumValidator {
     fn is_valid(&self, instance: &Value) -> bool {
         helpers::equal(&self.value, instance)
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("enum".to_string(), self.options.clone());
+    }
 }
 
 impl core::fmt::Display for SingleValueEnumValidator {
@@ -1,8 +1,13 @@
 use num_cmp::NumCmp;
-use serde_json::{Map, Value};
+use serde_json::{Map, Number, Value};
 
 use crate::{
-    compilation::context::CompilationContext, paths::JSONPointer, primitive_type::PrimitiveType,
+    compilation::context::CompilationContext,
+    error::{TypeKind, ValidationErrorKind},
+    paths::{InstancePath, JSONPointer},
+    primitive_type::PrimitiveType,
+    schema_node::SchemaNode,
+    validator::Validate,
     ValidationError,
 };
 
@@ -104,6 +109,72 @@ pub(crate) fn fail_on_non_positive_integer(
     }
 }
 
+/// A copy of `instance` where the strings that `node` rejects for their type are replaced by the
+/// value they spell out in an expected type, see `CompilationOptions::should_coerce_types`.
+/// `None` if no string could be coerced.
+pub(crate) fn coerced(node: &SchemaNode, instance: &Value) -> Option<Value> {
+    let mut coerced: Option<Value> = None;
+    loop {
+        let current = coerced.as_ref().unwrap_or(instance);
+        let replacements: Vec<_> = node
+            .validate(current, &InstancePath::new())
+            .filter_map(|error| match (&error.kind, error.instance.as_ref()) {
+                (ValidationErrorKind::Type { kind }, Value::String(string)) => {
+                    coerce_string(string, kind)
+                        .map(|value| (error.instance_path.to_string(), string.clone(), value))
+                }
+                _ => None,
+            })
+            .collect();
+        if replacements.is_empty() {
+            return coerced;
+        }
+        let target = coerced.get_or_insert_with(|| instance.clone());
+        let mut changed = false;
+        for (pointer, string, value) in replacements {
+            // Errors of e.g. `propertyNames` report a string that is not at their location
+            match target.pointer_mut(&pointer) {
+                Some(slot) if slot.as_str() == Some(string.as_str()) => {
+                    *slot = value;
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        // Coerced strings are never coerced again, so this terminates
+        if !changed {
+            return coerced.filter(|coerced| coerced != instance);
+        }
+    }
+}
+
+/// The value `string` spells out in the first of the `expected` types it fits.
+fn coerce_string(string: &str, expected: &TypeKind) -> Option<Value> {
+    let expects = |primitive_type| match expected {
+        TypeKind::Single(single) => *single == primitive_type,
+        TypeKind::Multiple(types) => types.contains_type(primitive_type),
+    };
+    if expects(PrimitiveType::Integer) || expects(PrimitiveType::Number) {
+        if let Ok(integer) = string.parse::<i64>() {
+            return Some(integer.into());
+        }
+        if let Ok(integer) = string.parse::<u64>() {
+            return Some(integer.into());
+        }
+    }
+    if expects(PrimitiveType::Number) {
+        if let Some(number) = string.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Some(Value::Number(number));
+        }
+    }
+    match string {
+        "true" if expects(PrimitiveType::Boolean) => Some(Value::Bool(true)),
+        "false" if expects(PrimitiveType::Boolean) => Some(Value::Bool(false)),
+        "null" if expects(PrimitiveType::Null) => Some(Value::Null),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::equal;
@@ -70,6 +70,10 @@ impl Validate for IfThenValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.schema, &self.then_schema]
+    }
 }
 
 impl core::fmt::Display for IfThenValidator {
@@ -145,6 +149,10 @@ impl Validate for IfElseValidator {
                 .into()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.schema, &self.else_schema]
+    }
 }
 
 impl core::fmt::Display for IfElseValidator {
@@ -228,6 +236,10 @@ impl Validate for IfThenElseValidator {
                 .into()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.schema, &self.then_schema, &self.else_schema]
+    }
 }
 
 impl core::fmt::Display for IfThenElseValidator {
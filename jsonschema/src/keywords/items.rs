@@ -1,6 +1,6 @@
 use crate::{
     compilation::{compile_validators, context::CompilationContext},
-    error::{no_error, ErrorIterator},
+    error::{no_error, ErrorIterator, ValidationError},
     keywords::CompilationResult,
     paths::InstancePath,
     schema_node::SchemaNode,
@@ -57,6 +57,26 @@ impl Validate for ItemsArrayValidator {
             no_error()
         }
     }
+
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        if let Value::Array(items) = instance {
+            items
+                .iter()
+                .zip(self.items.iter())
+                .enumerate()
+                .find_map(|(idx, (item, node))| node.first_error(item, &instance_path.push(idx)))
+        } else {
+            None
+        }
+    }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.items.iter().collect()
+    }
 }
 
 impl core::fmt::Display for ItemsArrayValidator {
@@ -111,6 +131,21 @@ impl Validate for ItemsObjectValidator {
         }
     }
 
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        if let Value::Array(items) = instance {
+            items
+                .iter()
+                .enumerate()
+                .find_map(|(idx, item)| self.node.first_error(item, &instance_path.push(idx)))
+        } else {
+            None
+        }
+    }
+
     fn apply<'a>(
         &'a self,
         instance: &Value,
@@ -135,6 +170,10 @@ impl Validate for ItemsObjectValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for ItemsObjectValidator {
@@ -220,6 +259,10 @@ impl Validate for ItemsObjectSkipPrefixValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for ItemsObjectSkipPrefixValidator {
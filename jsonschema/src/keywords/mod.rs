@@ -7,6 +7,7 @@ pub(crate) mod const_;
 pub(crate) mod contains;
 pub(crate) mod content;
 pub(crate) mod dependencies;
+pub(crate) mod discriminator;
 pub(crate) mod enum_;
 pub(crate) mod exclusive_maximum;
 pub(crate) mod exclusive_minimum;
@@ -28,6 +29,7 @@ pub(crate) mod not;
 pub(crate) mod one_of;
 pub(crate) mod pattern;
 pub(crate) mod pattern_properties;
+pub(crate) mod pre_validate;
 pub(crate) mod prefix_items;
 pub(crate) mod properties;
 pub(crate) mod property_names;
@@ -131,6 +133,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validators_order() {
+        let branch =
+            json!({"type": "object", "required": ["a"], "maxProperties": 3, "minProperties": 1});
+        let schema = json!({"oneOf": [branch, {"type": "null"}]});
+        let expected = "oneOf: [{maxProperties: 3, minProperties: 1, required: [a], type: object}, {type: null}]";
+        for _ in 0..3 {
+            let compiled = JSONSchema::compile(&schema).unwrap();
+            assert_eq!(
+                format!("{:?}", compiled.node.validators().next().unwrap()),
+                expected
+            );
+        }
+        let names: Vec<_> = JSONSchema::compile(&branch)
+            .unwrap()
+            .node
+            .validators()
+            .map(|validator| validator.to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "maxProperties: 3",
+                "minProperties: 1",
+                "required: [a]",
+                "type: object"
+            ]
+        );
+    }
+
     #[test_case(&json!({"items": [{}], "additionalItems": {"type": "integer"}}), &json!([ null, 2, 3, "foo" ]), r#""foo" is not of type "integer""#)]
     #[test_case(&json!({"items": [{}, {}, {}], "additionalItems": false}), &json!([ 1, 2, 3, 4 ]), r#"Additional items are not allowed (4 was unexpected)"#)]
     #[test_case(&json!({"items": [{}, {}, {}], "additionalItems": false}), &json!([ 1, 2, 3, 4, 5 ]), r#"Additional items are not allowed (4, 5 were unexpected)"#)]
@@ -1,13 +1,13 @@
 use crate::{
     compilation::context::CompilationContext,
     error::{error, no_error, ErrorIterator, ValidationError},
-    keywords::CompilationResult,
+    keywords::{BoxedValidator, CompilationResult},
     paths::{InstancePath, JSONPointer},
     primitive_type::PrimitiveType,
     validator::Validate,
 };
 use fraction::{BigFraction, BigUint};
-use serde_json::{Map, Value};
+use serde_json::{Map, Number, Value};
 
 pub(crate) struct MultipleOfFloatValidator {
     multiple_of: f64,
@@ -117,6 +117,84 @@ impl core::fmt::Display for MultipleOfIntegerValidator {
         write!(f, "multipleOf: {}", self.multiple_of)
     }
 }
+/// Checks `multipleOf` without converting integers to `f64`, see
+/// `CompilationOptions::should_check_multiple_of_exactly`.
+pub(crate) struct MultipleOfExactValidator {
+    multiple_of: f64,
+    fraction: BigFraction,
+    // Non-zero integer divisors are checked with integer arithmetic
+    integer: Option<i128>,
+    schema_path: JSONPointer,
+}
+
+impl MultipleOfExactValidator {
+    #[inline]
+    pub(crate) fn compile(multiple_of: &Number, schema_path: JSONPointer) -> BoxedValidator {
+        Box::new(MultipleOfExactValidator {
+            multiple_of: multiple_of.as_f64().expect("Always valid"),
+            fraction: exact_fraction(multiple_of),
+            integer: exact_integer(multiple_of).filter(|value| *value != 0),
+            schema_path,
+        })
+    }
+}
+
+/// The value of `number` as an `i128`, if it is an integer that `serde_json` stores exactly.
+fn exact_integer(number: &Number) -> Option<i128> {
+    number
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| number.as_u64().map(i128::from))
+}
+
+fn exact_fraction(number: &Number) -> BigFraction {
+    match exact_integer(number) {
+        Some(value) => BigFraction::from(value),
+        None => BigFraction::from(number.as_f64().expect("Always valid")),
+    }
+}
+
+impl Validate for MultipleOfExactValidator {
+    fn is_valid(&self, instance: &Value) -> bool {
+        if let Value::Number(item) = instance {
+            if let (Some(multiple_of), Some(item)) = (self.integer, exact_integer(item)) {
+                return item % multiple_of == 0;
+            }
+            // Involves heap allocations via the underlying `BigUint` type
+            let fraction = exact_fraction(item) / self.fraction.clone();
+            if let Some(denom) = fraction.denom() {
+                denom == &BigUint::from(1_u8)
+            } else {
+                true
+            }
+        } else {
+            true
+        }
+    }
+
+    fn validate<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> ErrorIterator<'instance> {
+        if !self.is_valid(instance) {
+            return error(ValidationError::multiple_of(
+                self.schema_path.clone(),
+                instance_path.into(),
+                instance,
+                self.multiple_of,
+            ));
+        }
+        no_error()
+    }
+}
+
+impl core::fmt::Display for MultipleOfExactValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "multipleOf: {}", self.multiple_of)
+    }
+}
+
 #[inline]
 pub(crate) fn compile<'a>(
     _: &'a Map<String, Value>,
@@ -124,8 +202,14 @@ pub(crate) fn compile<'a>(
     context: &CompilationContext,
 ) -> Option<CompilationResult<'a>> {
     if let Value::Number(multiple_of) = schema {
-        let multiple_of = multiple_of.as_f64().expect("Always valid");
         let schema_path = context.as_pointer_with("multipleOf");
+        if context.config.checks_multiple_of_exactly() {
+            return Some(Ok(MultipleOfExactValidator::compile(
+                multiple_of,
+                schema_path,
+            )));
+        }
+        let multiple_of = multiple_of.as_f64().expect("Always valid");
         if multiple_of.fract() == 0. {
             Some(MultipleOfIntegerValidator::compile(
                 multiple_of,
@@ -146,7 +230,7 @@ pub(crate) fn compile<'a>(
 
 #[cfg(test)]
 mod tests {
-    use crate::tests_util;
+    use crate::{tests_util, JSONSchema};
     use serde_json::{json, Value};
     use test_case::test_case;
 
@@ -168,4 +252,69 @@ mod tests {
     fn schema_path(schema: &Value, instance: &Value, expected: &str) {
         tests_util::assert_schema_path(schema, instance, expected)
     }
+
+    // 2^53 + 1 is a multiple of 3 and odd, but rounds to 2^53 as `f64`
+    #[test_case(&json!({"multipleOf": 3}), &json!(9_007_199_254_740_993_u64), true)]
+    #[test_case(&json!({"multipleOf": 2}), &json!(9_007_199_254_740_993_u64), false)]
+    #[test_case(&json!({"multipleOf": 3}), &json!(-9_007_199_254_740_993_i64), true)]
+    #[test_case(&json!({"multipleOf": 0.5}), &json!(9_007_199_254_740_993_u64), true)]
+    #[test_case(&json!({"multipleOf": 1.5}), &json!(4.5), true)]
+    #[test_case(&json!({"multipleOf": 1.5}), &json!(5), false)]
+    fn exact(schema: &Value, instance: &Value, expected: bool) {
+        let compiled = JSONSchema::options()
+            .should_check_multiple_of_exactly(true)
+            .compile(schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(instance), expected);
+        assert_eq!(compiled.validate(instance).is_ok(), expected);
+    }
+
+    #[test_case("cat", 9_007_199_254_740_993_u64, true, false)]
+    #[test_case("cat", 9_007_199_254_740_994_u64, false, false)]
+    #[test_case("dog", 9_007_199_254_740_993_u64, false, true)]
+    fn exact_in_discriminated_branch(tag: &str, count: u64, expected: bool, rounded: bool) {
+        let schema = json!({
+            "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+            },
+            "definitions": {
+                "Cat": {"properties": {"lives": {"multipleOf": 3}}},
+                "Dog": {"properties": {"lives": {"multipleOf": 2}}}
+            }
+        });
+        let instance = json!({"petType": tag, "lives": count});
+        let compiled = tests_util::openapi_options()
+            .should_check_multiple_of_exactly(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(&instance), expected);
+        let compiled = tests_util::openapi_options()
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(&instance), rounded);
+    }
+
+    #[test]
+    fn exact_in_discriminated_branch_beyond_u64() {
+        let schema = json!({
+            "oneOf": [{"$ref": "#/definitions/Cat"}],
+            "discriminator": {"propertyName": "petType", "mapping": {"cat": "#/definitions/Cat"}},
+            "definitions": {"Cat": {"properties": {"lives": {"multipleOf": 3}}}}
+        });
+        let compiled = tests_util::openapi_options()
+            .should_check_multiple_of_exactly(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        // 10^20 + 2 is a multiple of 3, but it is beyond `u64` and checked as its `f64`
+        // rounding, 10^20, which is not
+        let instance: Value =
+            serde_json::from_str(r#"{"petType": "cat", "lives": 100000000000000000002}"#)
+                .expect("Valid JSON");
+        assert!(!compiled.is_valid(&instance));
+        // The largest `u64` is a multiple of 3 and still checked exactly
+        let instance = json!({"petType": "cat", "lives": u64::MAX});
+        assert!(compiled.is_valid(&instance));
+    }
 }
@@ -51,6 +51,14 @@ impl Validate for NotValidator {
             ))
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("not".to_string(), self.node.to_value());
+    }
 }
 
 impl core::fmt::Display for NotValidator {
@@ -1,17 +1,54 @@
 use crate::{
-    compilation::{compile_validators, context::CompilationContext},
+    compilation::{compile_validators, context::CompilationContext, deadline},
     error::{error, no_error, ErrorIterator, ValidationError},
-    keywords::CompilationResult,
+    keywords::{
+        discriminator::branch_reference, helpers::coerced, pre_validate::PreValidateValidator,
+        CompilationResult,
+    },
     output::BasicOutput,
     paths::{InstancePath, JSONPointer},
-    primitive_type::PrimitiveType,
+    primitive_type::{PrimitiveType, PrimitiveTypesBitMap},
     schema_node::SchemaNode,
     validator::{format_iter_of_validators, PartialApplication, Validate},
 };
 use serde_json::{Map, Value};
+use std::{convert::TryFrom, sync::Arc};
 
+/// Labels of the `oneOf` subschemas, see [`ValidationError::one_of_branches`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BranchLabels {
+    // The `title` of every branch or its index in the original `oneOf`
+    pub(crate) labels: Vec<String>,
+    // Labels are only shown in error messages if some branch has a `title`
+    pub(crate) titled: bool,
+}
+
+/// Validator for `oneOf`.
+///
+/// Allocation points:
+///   - Compilation stores one `SchemaNode` per branch in a `Vec` sized up front;
+///   - `is_valid` and `validate` do not allocate, except for the single reported error, or for
+///     the errors of all branches with `CompilationOptions::should_collect_all_one_of_errors`;
+///   - `apply` keeps at most one successful branch output and collects the outputs of failing
+///     branches only while no branch has matched.
+///
+/// The number of branches can be bounded with `CompilationOptions::with_max_one_of_branches`.
 pub(crate) struct OneOfValidator {
     schemas: Vec<SchemaNode>,
+    // Positions of `schemas` in the original `oneOf`, if some branches were pruned
+    branch_indices: Option<Vec<usize>>,
+    // Positions of `schemas` from the cheapest to the most expensive one, the order of evaluation
+    order: Vec<usize>,
+    #[cfg(feature = "branch-sources")]
+    sources: Vec<Value>,
+    // Shared with the errors reported when no branch matches
+    labels: Arc<BranchLabels>,
+    // Report the errors of every branch instead of a single error if no branch matches
+    collect_all: bool,
+    // At most one branch can match, so evaluation stops at the first one that does
+    exclusive: bool,
+    // Strings are coerced to the types expected by a branch that rejects them
+    coerce: bool,
     schema_path: JSONPointer,
 }
 
@@ -21,32 +58,162 @@ impl OneOfValidator {
         schema: &'a Value,
         context: &CompilationContext,
     ) -> CompilationResult<'a> {
+        Ok(Box::new(
+            OneOfValidator::new(schema, context).map_err(|error| *error)?,
+        ))
+    }
+
+    #[inline]
+    pub(crate) fn new<'a>(
+        schema: &'a Value,
+        context: &CompilationContext,
+    ) -> Result<OneOfValidator, Box<ValidationError<'a>>> {
+        let max_branches = context.config.max_one_of_branches().unwrap_or(usize::MAX);
+        OneOfValidator::new_bounded(schema, context, max_branches)
+    }
+
+    /// Compile a `oneOf` with at most `max_branches` subschemas.
+    pub(crate) fn new_bounded<'a>(
+        schema: &'a Value,
+        context: &CompilationContext,
+        max_branches: usize,
+    ) -> Result<OneOfValidator, Box<ValidationError<'a>>> {
+        OneOfValidator::compile_branches(schema, context, max_branches, |_| true)
+    }
+
+    /// Compile a `oneOf` without the subschemas rejected by `keep`. They are never evaluated,
+    /// so the caller must ensure that they can't affect the result.
+    pub(crate) fn new_pruned<'a>(
+        schema: &'a Value,
+        context: &CompilationContext,
+        keep: impl Fn(&Value) -> bool,
+    ) -> Result<OneOfValidator, Box<ValidationError<'a>>> {
+        let max_branches = context.config.max_one_of_branches().unwrap_or(usize::MAX);
+        OneOfValidator::compile_branches(schema, context, max_branches, keep)
+    }
+
+    fn compile_branches<'a>(
+        schema: &'a Value,
+        context: &CompilationContext,
+        max_branches: usize,
+        keep: impl Fn(&Value) -> bool,
+    ) -> Result<OneOfValidator, Box<ValidationError<'a>>> {
         if let Value::Array(items) = schema {
             let keyword_context = context.with_path("oneOf");
+            if items.len() > max_branches {
+                return Err(Box::new(ValidationError::max_items(
+                    JSONPointer::default(),
+                    keyword_context.into_pointer(),
+                    schema,
+                    max_branches as u64,
+                )));
+            }
+            context.counters.add_one_of_site();
             let mut schemas = Vec::with_capacity(items.len());
+            let mut branch_indices = Vec::with_capacity(items.len());
+            let mut labels = BranchLabels {
+                labels: Vec::with_capacity(items.len()),
+                titled: false,
+            };
+            #[cfg(feature = "branch-sources")]
+            let mut sources = Vec::with_capacity(items.len());
             for (idx, item) in items.iter().enumerate() {
+                if !keep(item) {
+                    continue;
+                }
                 let item_context = keyword_context.with_path(idx);
-                let node = compile_validators(item, &item_context)?;
-                schemas.push(node)
+                let node = match branch_reference(item, context.config.draft()) {
+                    Some((_, reference)) => {
+                        compile_validators(item, &item_context.for_branch(reference))?
+                    }
+                    None => compile_validators(item, &item_context)?,
+                };
+                schemas.push(node);
+                branch_indices.push(idx);
+                match branch_title(item, context) {
+                    Some(title) => {
+                        labels.labels.push(title);
+                        labels.titled = true;
+                    }
+                    None => labels.labels.push(idx.to_string()),
+                }
+                #[cfg(feature = "branch-sources")]
+                sources.push(item.clone());
             }
-            Ok(Box::new(OneOfValidator {
+            let branch_indices = if schemas.len() < items.len() {
+                Some(branch_indices)
+            } else {
+                None
+            };
+            Ok(OneOfValidator {
+                order: cost_order(&schemas),
                 schemas,
+                branch_indices,
+                #[cfg(feature = "branch-sources")]
+                sources,
+                labels: Arc::new(labels),
+                collect_all: context.config.collects_all_one_of_errors(),
+                exclusive: context.config.short_circuits_exclusive_one_of()
+                    && are_exclusive(items, context),
+                coerce: context.config.coerces_types(),
                 schema_path: keyword_context.into_pointer(),
-            }))
+            })
         } else {
-            Err(ValidationError::single_type_error(
+            Err(Box::new(ValidationError::single_type_error(
                 JSONPointer::default(),
                 context.clone().into_pointer(),
                 schema,
                 PrimitiveType::Array,
-            ))
+            )))
+        }
+    }
+
+    /// Append the subschemas of `other`. They are reported at their index plus `offset`, the
+    /// length of the `oneOf` that `self` was compiled from.
+    pub(crate) fn merge(&mut self, other: OneOfValidator, offset: usize) {
+        if self.branch_indices.is_some() || other.branch_indices.is_some() {
+            let own_len = self.schemas.len();
+            let other_len = other.schemas.len();
+            let indices = self
+                .branch_indices
+                .get_or_insert_with(|| (0..own_len).collect());
+            indices.extend(
+                other
+                    .branch_indices
+                    .unwrap_or_else(|| (0..other_len).collect())
+                    .into_iter()
+                    .map(|idx| idx.saturating_add(offset)),
+            );
         }
+        self.schemas.extend(other.schemas);
+        let labels = Arc::make_mut(&mut self.labels);
+        labels.labels.extend(other.labels.labels.iter().cloned());
+        labels.titled |= other.labels.titled;
+        #[cfg(feature = "branch-sources")]
+        self.sources.extend(other.sources);
+        self.order = cost_order(&self.schemas);
+        // Branches of different schemas were never checked against each other
+        self.exclusive = false;
+    }
+
+    fn is_valid_branch(&self, node: &SchemaNode, instance: &Value) -> bool {
+        node.is_valid(instance)
+            || (self.coerce
+                && coerced(node, instance).map_or(false, |coerced| node.is_valid(&coerced)))
     }
 
+    /// The position in `order` of the first valid branch.
+    ///
+    /// Once the deadline of the current thread expired, the remaining branches are skipped as if
+    /// they were not valid. The result is meaningless then, which is fine as
+    /// `JSONSchema::validate_with_deadline` reports only a timeout once its deadline expired.
     fn get_first_valid(&self, instance: &Value) -> Option<usize> {
         let mut first_valid_idx = None;
-        for (idx, node) in self.schemas.iter().enumerate() {
-            if node.is_valid(instance) {
+        for (idx, &position) in self.order.iter().enumerate() {
+            if deadline::is_expired() {
+                break;
+            }
+            if self.is_valid_branch(&self.schemas[position], instance) {
                 first_valid_idx = Some(idx);
                 break;
             }
@@ -54,16 +221,202 @@ impl OneOfValidator {
         first_valid_idx
     }
 
+    /// Whether any branch after the position `idx` in `order` is valid as well.
+    ///
+    /// Like in `get_first_valid`, branches are skipped as not valid once the deadline expired, so
+    /// an expired `oneOf` may look valid. Its result is discarded along with all others.
     #[allow(clippy::integer_arithmetic)]
     fn are_others_valid(&self, instance: &Value, idx: usize) -> bool {
+        if self.exclusive {
+            return false;
+        }
         // `idx + 1` will not overflow, because the maximum possible value there is `usize::MAX - 1`
         // For example we have `usize::MAX` schemas and only the last one is valid, then
         // in `get_first_valid` we enumerate from `0`, and on the last index will be `usize::MAX - 1`
+        self.order.iter().skip(idx + 1).any(|&position| {
+            !deadline::is_expired() && self.is_valid_branch(&self.schemas[position], instance)
+        })
+    }
+
+    /// The source of every compiled subschema.
+    #[cfg(feature = "branch-sources")]
+    pub(crate) fn branch_sources(&self) -> &[Value] {
+        &self.sources
+    }
+
+    /// The keyword this validator implements.
+    pub(crate) const fn name(&self) -> &'static str {
+        "oneOf"
+    }
+
+    /// Indices of all subschemas `instance` is valid against.
+    pub(crate) fn matching_branches(&self, instance: &Value) -> Vec<usize> {
+        self.schemas
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| self.is_valid_branch(node, instance))
+            .map(|(idx, _)| self.branch_index(idx))
+            .collect()
+    }
+
+    /// The errors of `instance` against every subschema, along with the subschema's index.
+    pub(crate) fn branch_errors<'instance>(
+        &self,
+        instance: &'instance Value,
+    ) -> Vec<(usize, Vec<ValidationError<'instance>>)> {
+        let instance_path = InstancePath::new();
         self.schemas
             .iter()
-            .skip(idx + 1)
-            .any(|n| n.is_valid(instance))
+            .enumerate()
+            .map(|(idx, node)| {
+                (
+                    self.branch_index(idx),
+                    node.validate(instance, &instance_path)
+                        .filter(ValidationError::is_error)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// The position of `schemas[idx]` in the original `oneOf`.
+    fn branch_index(&self, idx: usize) -> usize {
+        self.branch_indices
+            .as_ref()
+            .map_or(idx, |indices| indices[idx])
+    }
+}
+
+/// Positions of `schemas` ordered by their estimated cost. Branches of the same cost keep their
+/// order, so that ties are checked as written.
+fn cost_order(schemas: &[SchemaNode]) -> Vec<usize> {
+    let mut order: Vec<_> = (0..schemas.len()).collect();
+    order.sort_by_key(|&position| schemas[position].estimated_cost());
+    order
+}
+
+/// How deep `$ref` is followed when looking for the constraints of a subschema.
+const MAX_REFERENCE_DEPTH: usize = 16;
+
+/// Whether an instance can be valid against at most one of `items`. Conservative, `false` if
+/// it can't be proven.
+fn are_exclusive(items: &[Value], context: &CompilationContext) -> bool {
+    let branches: Option<Vec<_>> = items
+        .iter()
+        .map(|item| resolve_branch(item, context, MAX_REFERENCE_DEPTH))
+        .collect();
+    match branches {
+        Some(branches) => have_disjoint_types(&branches) || have_distinct_tags(&branches),
+        None => false,
+    }
+}
+
+/// The subschema at the end of a chain of `$ref`. Keywords next to `$ref` can only further
+/// restrict it.
+fn resolve_branch(schema: &Value, context: &CompilationContext, depth: usize) -> Option<Value> {
+    match schema.get("$ref") {
+        Some(Value::String(reference)) if depth > 0 => {
+            let url = context.build_url(reference).ok()?;
+            let (scope, schema) = context
+                .resolver
+                .resolve_fragment(context.config.draft(), &url, reference)
+                .ok()?;
+            let scope_context = CompilationContext::new(
+                scope.into(),
+                Arc::clone(&context.config),
+                Arc::clone(&context.resolver),
+            );
+            resolve_branch(&schema, &scope_context, depth - 1)
+        }
+        Some(_) => None,
+        None => Some(schema.clone()),
+    }
+}
+
+/// The `title` of a branch, or of the schema at the end of its chain of `$ref`. Only references
+/// within the document are followed, others are resolved lazily on first use.
+fn branch_title(branch: &Value, context: &CompilationContext) -> Option<String> {
+    let title = match branch.get("$ref").and_then(Value::as_str) {
+        Some(reference) if branch.get("title").is_none() && reference.starts_with('#') => {
+            resolve_branch(branch, context, MAX_REFERENCE_DEPTH)?
+                .get("title")
+                .cloned()
+        }
+        _ => branch.get("title").cloned(),
+    };
+    title?.as_str().map(str::to_string)
+}
+
+fn declared_types(schema: &Value) -> Option<PrimitiveTypesBitMap> {
+    let mut types = PrimitiveTypesBitMap::new();
+    match schema.get("type")? {
+        Value::String(name) => types |= PrimitiveType::try_from(name.as_str()).ok()?,
+        Value::Array(names) => {
+            for name in names {
+                types |= PrimitiveType::try_from(name.as_str()?).ok()?;
+            }
+        }
+        _ => return None,
+    }
+    // Integers are numbers
+    if types.contains_type(PrimitiveType::Number) {
+        types |= PrimitiveType::Integer;
+    }
+    Some(types)
+}
+
+fn have_disjoint_types(branches: &[Value]) -> bool {
+    let mut seen = PrimitiveTypesBitMap::new();
+    for branch in branches {
+        match declared_types(branch) {
+            Some(types) => {
+                for primitive_type in types {
+                    if seen.contains_type(primitive_type) {
+                        return false;
+                    }
+                    seen |= primitive_type;
+                }
+            }
+            None => return false,
+        }
     }
+    true
+}
+
+/// Whether all branches require some property to be equal to a different `const`.
+fn have_distinct_tags(branches: &[Value]) -> bool {
+    let first = match branches.first() {
+        Some(first) => first,
+        None => return false,
+    };
+    first
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .any(|property| {
+            let mut seen = Vec::with_capacity(branches.len());
+            branches.iter().all(|branch| {
+                let is_required = branch
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .map_or(false, |required| {
+                        required.iter().any(|name| name.as_str() == Some(property))
+                    });
+                match branch
+                    .get("properties")
+                    .and_then(|properties| properties.get(property))
+                    .and_then(|declaration| declaration.get("const"))
+                {
+                    Some(value) if is_required && !seen.contains(&value) => {
+                        seen.push(value);
+                        true
+                    }
+                    _ => false,
+                }
+            })
+        })
 }
 
 impl Validate for OneOfValidator {
@@ -86,45 +439,110 @@ impl Validate for OneOfValidator {
                 ));
             }
             no_error()
+        } else if self.collect_all && !self.schemas.is_empty() {
+            let errors: Vec<_> = self
+                .schemas
+                .iter()
+                .flat_map(|node| node.validate(instance, instance_path))
+                .filter(ValidationError::is_error)
+                .collect();
+            Box::new(errors.into_iter())
         } else {
             error(ValidationError::one_of_not_valid(
                 self.schema_path.clone(),
                 instance_path.into(),
                 instance,
+                Arc::clone(&self.labels),
             ))
         }
     }
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        match self.get_first_valid(instance) {
+            Some(idx) => self.are_others_valid(instance, idx).then(|| {
+                ValidationError::one_of_multiple_valid(
+                    self.schema_path.clone(),
+                    instance_path.into(),
+                    instance,
+                )
+            }),
+            // None of the branches is valid, the first one reports the first error
+            None if self.collect_all && !self.schemas.is_empty() => self
+                .schemas
+                .iter()
+                .find_map(|node| node.first_error(instance, instance_path)),
+            None => Some(ValidationError::one_of_not_valid(
+                self.schema_path.clone(),
+                instance_path.into(),
+                instance,
+                Arc::clone(&self.labels),
+            )),
+        }
+    }
     fn apply<'a>(
         &'a self,
         instance: &Value,
         instance_path: &InstancePath,
     ) -> PartialApplication<'a> {
         let mut failures = Vec::new();
-        let mut successes = Vec::new();
+        let mut success = None;
         for node in &self.schemas {
-            match node.apply_rooted(instance, instance_path) {
-                output @ BasicOutput::Valid(..) => successes.push(output),
-                output @ BasicOutput::Invalid(..) => failures.push(output),
+            let output = match node.apply_rooted(instance, instance_path) {
+                output @ BasicOutput::Invalid(..) if self.coerce => coerced(node, instance)
+                    .map_or(output, |coerced| node.apply_rooted(&coerced, instance_path)),
+                output => output,
+            };
+            match output {
+                output @ BasicOutput::Valid(..) => {
+                    if success.is_some() {
+                        return PartialApplication::invalid_empty(vec![
+                            "more than one subschema succeeded".into(),
+                        ]);
+                    }
+                    failures.clear();
+                    success = Some(output);
+                    if self.exclusive {
+                        break;
+                    }
+                }
+                output @ BasicOutput::Invalid(..) => {
+                    if success.is_none() {
+                        failures.push(output);
+                    }
+                }
             };
         }
-        if successes.len() == 1 {
-            let success = successes.remove(0);
+        if let Some(success) = success {
             success.into()
-        } else if successes.len() > 1 {
-            PartialApplication::invalid_empty(vec!["more than one subschema succeeded".into()])
         } else if !failures.is_empty() {
             failures.into_iter().sum::<BasicOutput<'_>>().into()
         } else {
-            unreachable!("compilation should fail for oneOf with no subschemas")
+            // Only possible for an empty `oneOf`, which can be compiled without schema validation
+            PartialApplication::invalid_empty(vec!["oneOf matched no branches".into()])
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.schemas.iter().collect()
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "oneOf".to_string(),
+            Value::Array(self.schemas.iter().map(SchemaNode::to_value).collect()),
+        );
+    }
 }
 
 impl core::fmt::Display for OneOfValidator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "oneOf: [{}]",
+            "{}: [{}]",
+            self.name(),
             format_iter_of_validators(self.schemas.iter().map(SchemaNode::validators))
         )
     }
@@ -132,17 +550,36 @@ impl core::fmt::Display for OneOfValidator {
 
 #[inline]
 pub(crate) fn compile<'a>(
-    _: &'a Map<String, Value>,
+    parent: &'a Map<String, Value>,
     schema: &'a Value,
     context: &CompilationContext,
 ) -> Option<CompilationResult<'a>> {
-    Some(OneOfValidator::compile(schema, context))
+    if parent.contains_key("discriminator") && context.config.uses_discriminator() {
+        // `DiscriminatorValidator` handles `oneOf` logic
+        None
+    } else {
+        Some(
+            OneOfValidator::compile(schema, context)
+                .map(|validator| {
+                    PreValidateValidator::wrap(context.config.pre_validate_hook(), validator)
+                })
+                .map_err(|error| context.locate(error)),
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::tests_util;
+    use super::*;
+    use crate::{
+        compilation::{context::BaseUri, DEFAULT_SCOPE},
+        error::ValidationErrorKind,
+        keywords::BoxedValidator,
+        resolver::{DefaultResolver, Resolver},
+        tests_util, JSONSchema,
+    };
     use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
     use test_case::test_case;
 
     #[test_case(&json!({"oneOf": [{"type": "string"}]}), &json!(0), "/oneOf")]
@@ -150,4 +587,311 @@ mod tests {
     fn schema_path(schema: &Value, instance: &Value, expected: &str) {
         tests_util::assert_schema_path(schema, instance, expected)
     }
+
+    #[test]
+    fn name() {
+        let compiled =
+            JSONSchema::compile(&json!({"oneOf": [{"type": "string"}]})).expect("Valid schema");
+        let validator = compiled
+            .node
+            .keyword_validator_as::<OneOfValidator>("oneOf")
+            .expect("Compiled");
+        assert_eq!(validator.name(), "oneOf");
+        assert!(validator.to_string().starts_with("oneOf: ["));
+    }
+
+    #[test_case(&json!([{"type": "string"}, {"type": ["integer", "null"]}]), true)]
+    #[test_case(&json!([{"type": "string"}, {"$ref": "#/definitions/Count"}]), true; "reference")]
+    #[test_case(&json!([{"type": "integer"}, {"type": "number"}]), false)]
+    #[test_case(&json!([{"type": "string"}, {"minimum": 0}]), false)]
+    #[test_case(&json!([
+        {"properties": {"kind": {"const": "a"}}, "required": ["kind"]},
+        {"properties": {"kind": {"const": "b"}}, "required": ["kind", "b"]}
+    ]), true; "distinct tags")]
+    #[test_case(&json!([
+        {"properties": {"kind": {"const": "a"}}, "required": ["kind"]},
+        {"properties": {"kind": {"const": "a"}}, "required": ["kind"]}
+    ]), false; "same tags")]
+    #[test_case(&json!([
+        {"properties": {"kind": {"const": "a"}}, "required": ["kind"]},
+        {"properties": {"kind": {"const": "b"}}}
+    ]), false; "optional tag")]
+    fn exclusive(one_of: &Value, expected: bool) {
+        let schema =
+            Arc::new(json!({"oneOf": one_of, "definitions": {"Count": {"type": "integer"}}}));
+        let compiled = JSONSchema::compile(&schema).expect("Valid schema");
+        let resolver = Arc::new(
+            Resolver::new(
+                Arc::new(DefaultResolver),
+                Default::default(),
+                &DEFAULT_SCOPE,
+                schema,
+                Default::default(),
+            )
+            .expect("Valid resolver"),
+        );
+        let context = CompilationContext::new(BaseUri::Unknown, compiled.config(), resolver);
+        assert_eq!(
+            are_exclusive(one_of.as_array().unwrap(), &context),
+            expected
+        );
+    }
+
+    #[test_case(&json!("abc"))]
+    #[test_case(&json!(42))]
+    #[test_case(&json!(4.2))]
+    #[test_case(&json!(null))]
+    fn short_circuit_exclusive(instance: &Value) {
+        let schema = json!({"oneOf": [{"type": "string", "maxLength": 2}, {"type": "integer"}]});
+        let compile = |short_circuit| {
+            JSONSchema::options()
+                .should_short_circuit_exclusive_one_of(short_circuit)
+                .compile(&schema)
+                .expect("Valid schema")
+        };
+        let (expected, actual) = (compile(false), compile(true));
+        assert_eq!(actual.is_valid(instance), expected.is_valid(instance));
+        let errors = |compiled: &JSONSchema| {
+            compiled
+                .validate(instance)
+                .err()
+                .into_iter()
+                .flatten()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(errors(&actual), errors(&expected));
+        assert_eq!(
+            serde_json::to_value(actual.apply(instance).basic()).unwrap(),
+            serde_json::to_value(expected.apply(instance).basic()).unwrap()
+        );
+    }
+
+    #[test_case(false, &["/oneOf"])]
+    #[test_case(true, &["/oneOf/0/type", "/oneOf/1/properties/age/minimum", "/oneOf/1/required"])]
+    fn collect_all(collect: bool, expected: &[&str]) {
+        let schema = json!({
+            "oneOf": [
+                {"type": "string"},
+                {"required": ["name"], "properties": {"age": {"minimum": 0}}}
+            ]
+        });
+        let compiled = JSONSchema::options()
+            .should_collect_all_one_of_errors(collect)
+            .compile(&schema)
+            .expect("Valid schema");
+        let instance = json!({"age": -1});
+        let errors: Vec<_> = compiled
+            .validate(&instance)
+            .expect_err("No branch matches")
+            .map(|error| error.schema_path.to_string())
+            .collect();
+        assert_eq!(errors, expected);
+        // Multiple matches are still reported as a single error
+        let instance = json!({"name": "Alice"});
+        let schema = json!({"oneOf": [{"required": ["name"]}, {"type": "object"}]});
+        let compiled = JSONSchema::options()
+            .should_collect_all_one_of_errors(collect)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(
+            compiled
+                .validate(&instance)
+                .expect_err("Two matches")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn branch_titles() {
+        let schema = json!({
+            "oneOf": [
+                {"$ref": "#/definitions/Cat"},
+                {"title": "Dog", "required": ["barks"]},
+                {"required": ["swims"]}
+            ],
+            "definitions": {"Cat": {"title": "Cat", "required": ["meows"]}}
+        });
+        tests_util::expect_errors(
+            &schema,
+            &json!({}),
+            &[
+                r#"{} is not valid under any of the schemas listed in the 'oneOf' keyword (expected one of: Cat, Dog, 2)"#,
+            ],
+        );
+        let compiled = JSONSchema::compile(&schema).expect("Valid schema");
+        let instance = json!({});
+        let error = compiled
+            .validate(&instance)
+            .expect_err("No branch matches")
+            .next()
+            .expect("An error");
+        assert!(matches!(error.kind, ValidationErrorKind::OneOfNotValid));
+        assert_eq!(error.one_of_branches(), ["Cat", "Dog", "2"]);
+    }
+
+    #[test]
+    fn apply_empty() {
+        let schema = json!({"oneOf": []});
+        let compiled = JSONSchema::options()
+            .without_schema_validation()
+            .compile(&schema)
+            .expect("Schema validation is disabled");
+        let output = serde_json::to_value(compiled.apply(&json!(1)).basic()).unwrap();
+        assert_eq!(
+            output,
+            json!({
+                "valid": false,
+                "errors": [
+                    {
+                        "keywordLocation": "/oneOf",
+                        "instanceLocation": "",
+                        "error": "oneOf matched no branches"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[cfg(feature = "branch-sources")]
+    #[test]
+    fn branch_sources() {
+        let branches = json!([{"type": "string"}, {"$ref": "#/definitions/int"}]);
+        let schema = json!({"oneOf": branches, "definitions": {"int": {"type": "integer"}}});
+        let compiled = JSONSchema::compile(&schema).expect("Valid schema");
+        assert_eq!(
+            compiled.one_of_branch_sources(),
+            Some(branches.as_array().unwrap().as_slice())
+        );
+    }
+
+    #[test]
+    fn bounded_branches() {
+        let schema = json!({"oneOf": [{"type": "string"}, {"type": "integer"}]});
+        let compiled = JSONSchema::options()
+            .with_max_one_of_branches(2)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert!(compiled.is_valid(&json!("foo")));
+        assert!(!compiled.is_valid(&json!(null)));
+        let error = JSONSchema::options()
+            .with_max_one_of_branches(1)
+            .compile(&schema)
+            .expect_err("Too many branches");
+        assert_eq!(error.instance_path.to_string(), "/oneOf");
+    }
+
+    struct CostlyValidator {
+        cost: u32,
+        checked: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl Validate for CostlyValidator {
+        fn is_valid(&self, _: &Value) -> bool {
+            self.checked.lock().expect("Not poisoned").push(self.cost);
+            false
+        }
+
+        fn validate<'instance>(
+            &self,
+            instance: &'instance Value,
+            instance_path: &InstancePath,
+        ) -> ErrorIterator<'instance> {
+            error(ValidationError::false_schema(
+                JSONPointer::default(),
+                instance_path.into(),
+                instance,
+            ))
+        }
+
+        fn estimated_cost(&self) -> u32 {
+            self.cost
+        }
+    }
+
+    impl core::fmt::Display for CostlyValidator {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "costly: {}", self.cost)
+        }
+    }
+
+    #[test]
+    fn cheapest_first() {
+        let schema = Arc::new(json!({}));
+        let compiled = JSONSchema::compile(&schema).expect("Valid schema");
+        let resolver = Arc::new(
+            Resolver::new(
+                Arc::new(DefaultResolver),
+                Default::default(),
+                &DEFAULT_SCOPE,
+                schema,
+                Default::default(),
+            )
+            .expect("Valid resolver"),
+        );
+        let context = CompilationContext::new(BaseUri::Unknown, compiled.config(), resolver);
+        let checked = Arc::new(Mutex::new(Vec::new()));
+        let schemas: Vec<_> = [3, 1, 2, 1]
+            .iter()
+            .map(|&cost| {
+                let validator: BoxedValidator = Box::new(CostlyValidator {
+                    cost,
+                    checked: Arc::clone(&checked),
+                });
+                SchemaNode::new_from_keywords(
+                    &context,
+                    vec![("costly".to_string(), validator)],
+                    None,
+                )
+            })
+            .collect();
+        let validator = OneOfValidator {
+            order: cost_order(&schemas),
+            labels: Arc::default(),
+            #[cfg(feature = "branch-sources")]
+            sources: vec![json!({}); schemas.len()],
+            schemas,
+            branch_indices: None,
+            collect_all: false,
+            exclusive: false,
+            coerce: false,
+            schema_path: JSONPointer::default(),
+        };
+        assert_eq!(validator.order, vec![1, 3, 2, 0]);
+        assert!(!validator.is_valid(&json!(null)));
+        assert_eq!(*checked.lock().expect("Not poisoned"), vec![1, 1, 2, 3]);
+    }
+
+    #[test_case(&json!("42"), true)]
+    #[test_case(&json!("true"), true)]
+    #[test_case(&json!("false"), true)]
+    #[test_case(&json!("5"), false)]
+    #[test_case(&json!("4.2"), false)]
+    #[test_case(&json!("yes"), false)]
+    fn coerce_types(instance: &Value, expected: bool) {
+        let schema = json!({"oneOf": [{"type": "integer", "minimum": 10}, {"type": "boolean"}]});
+        let compiled = JSONSchema::options()
+            .should_coerce_types(true)
+            .compile(&schema)
+            .expect("Valid schema");
+        assert_eq!(compiled.is_valid(instance), expected);
+        assert_eq!(compiled.validate(instance).is_ok(), expected);
+        assert_eq!(compiled.apply(instance).basic().is_valid(), expected);
+        // Strings are not coerced by default
+        tests_util::is_not_valid(&schema, instance);
+    }
+
+    #[test]
+    fn nested_branches_cost_more() {
+        let compiled = JSONSchema::compile(&json!({
+            "oneOf": [
+                {"properties": {"a": {"properties": {"b": {"type": "string"}}}}},
+                {"type": "object"}
+            ]
+        }))
+        .expect("Valid schema");
+        let validator = compiled.node.keyword_validator("oneOf").expect("Compiled");
+        assert_eq!(validator.estimated_cost(), 1 + 3 + 1);
+    }
 }
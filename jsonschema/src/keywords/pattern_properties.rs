@@ -106,6 +106,10 @@ impl Validate for PatternPropertiesValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.patterns.iter().map(|(_, node)| node).collect()
+    }
 }
 
 impl core::fmt::Display for PatternPropertiesValidator {
@@ -207,6 +211,10 @@ impl Validate for SingleValuePatternPropertiesValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 
 impl core::fmt::Display for SingleValuePatternPropertiesValidator {
@@ -0,0 +1,95 @@
+use crate::{
+    compilation::options::PreValidateHook,
+    error::{ErrorIterator, ValidationError},
+    keywords::BoxedValidator,
+    paths::InstancePath,
+    schema_node::SchemaNode,
+    validator::{PartialApplication, Validate},
+};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+/// Runs `CompilationOptions::with_pre_validate_hook` on the instance before a `oneOf` or a
+/// discriminator selects and validates a branch.
+pub(crate) struct PreValidateValidator {
+    hook: PreValidateHook,
+    inner: BoxedValidator,
+}
+
+impl PreValidateValidator {
+    /// Wrap `inner` if a hook is configured.
+    pub(crate) fn wrap(hook: Option<&PreValidateHook>, inner: BoxedValidator) -> BoxedValidator {
+        match hook {
+            Some(hook) => Box::new(PreValidateValidator {
+                hook: hook.clone(),
+                inner,
+            }),
+            None => inner,
+        }
+    }
+
+    /// The wrapped validator.
+    pub(crate) fn inner(&self) -> &BoxedValidator {
+        &self.inner
+    }
+
+    /// Mutable access to the wrapped validator.
+    pub(crate) fn inner_mut(&mut self) -> &mut BoxedValidator {
+        &mut self.inner
+    }
+
+    /// Take the wrapped validator, dropping the hook.
+    pub(crate) fn into_inner(self) -> BoxedValidator {
+        self.inner
+    }
+}
+
+impl Validate for PreValidateValidator {
+    fn is_valid(&self, instance: &Value) -> bool {
+        self.inner.is_valid(&(self.hook)(instance))
+    }
+
+    fn validate<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> ErrorIterator<'instance> {
+        match (self.hook)(instance) {
+            Cow::Borrowed(instance) => self.inner.validate(instance, instance_path),
+            // Errors can't borrow from the transformed instance as it is dropped here
+            Cow::Owned(instance) => Box::new(
+                self.inner
+                    .validate(&instance, instance_path)
+                    .map(ValidationError::into_owned)
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            ),
+        }
+    }
+
+    fn apply<'a>(
+        &'a self,
+        instance: &Value,
+        instance_path: &InstancePath,
+    ) -> PartialApplication<'a> {
+        self.inner.apply(&(self.hook)(instance), instance_path)
+    }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.inner.child_nodes()
+    }
+
+    fn estimated_cost(&self) -> u32 {
+        self.inner.estimated_cost()
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        self.inner.write_schema(schema);
+    }
+}
+
+impl core::fmt::Display for PreValidateValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
@@ -95,6 +95,10 @@ impl Validate for PrefixItemsValidator {
         }
         PartialApplication::valid_empty()
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.schemas.iter().collect()
+    }
 }
 
 impl core::fmt::Display for PrefixItemsValidator {
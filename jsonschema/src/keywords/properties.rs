@@ -79,6 +79,21 @@ impl Validate for PropertiesValidator {
         }
     }
 
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        if let Value::Object(item) = instance {
+            self.properties.iter().find_map(|(name, node)| {
+                let item = item.get(name)?;
+                node.first_error(item, &instance_path.push(name.clone()))
+            })
+        } else {
+            None
+        }
+    }
+
     fn apply<'a>(
         &'a self,
         instance: &Value,
@@ -101,6 +116,22 @@ impl Validate for PropertiesValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        self.properties.iter().map(|(_, node)| node).collect()
+    }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "properties".to_string(),
+            Value::Object(
+                self.properties
+                    .iter()
+                    .map(|(name, node)| (name.clone(), node.to_value()))
+                    .collect(),
+            ),
+        );
+    }
 }
 
 impl core::fmt::Display for PropertiesValidator {
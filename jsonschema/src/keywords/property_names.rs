@@ -85,6 +85,10 @@ impl Validate for PropertyNamesObjectValidator {
             PartialApplication::valid_empty()
         }
     }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        vec![&self.node]
+    }
 }
 impl core::fmt::Display for PropertyNamesObjectValidator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
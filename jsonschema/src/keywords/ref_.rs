@@ -1,28 +1,29 @@
 use crate::{
     compilation::{compile_validators, context::CompilationContext},
     error::{error, ErrorIterator},
-    keywords::CompilationResult,
+    keywords::{BoxedValidator, CompilationResult},
     paths::{InstancePath, JSONPointer},
     primitive_type::PrimitiveType,
     resolver::Resolver,
     schema_node::SchemaNode,
-    validator::Validate,
+    schemas,
+    validator::{PartialApplication, Validate},
     CompilationOptions, Draft, ValidationError,
 };
-use parking_lot::RwLock;
+use once_cell::sync::OnceCell;
 use serde_json::{Map, Value};
 use std::sync::Arc;
-use url::Url;
+use url::{ParseError, Url};
 
 pub(crate) struct RefValidator {
     original_reference: String,
     reference: Url,
     /// Precomputed validators.
-    /// They are behind a RwLock as is not possible to compute them
+    /// They are behind a OnceCell as is not possible to compute them
     /// at compile time without risking infinite loops of references
     /// and at the same time during validation we iterate over shared
     /// references (&self) and not owned references (&mut self).
-    sub_nodes: RwLock<Option<SchemaNode>>,
+    sub_nodes: OnceCell<SchemaNode>,
     schema_path: JSONPointer,
     config: Arc<CompilationOptions>,
     pub(crate) resolver: Arc<Resolver>,
@@ -34,39 +35,65 @@ impl RefValidator {
         reference: &str,
         context: &CompilationContext,
     ) -> CompilationResult<'a> {
+        RefValidator::compile_url(reference, context.build_url(reference)?, context)
+            .map_err(|error| *error)
+    }
+
+    /// Compile `reference`, already resolved to `url`.
+    fn compile_url<'a>(
+        reference: &str,
+        url: Url,
+        context: &CompilationContext,
+    ) -> Result<BoxedValidator, Box<ValidationError<'a>>> {
         Ok(Box::new(RefValidator {
             original_reference: reference.to_string(),
-            reference: context.build_url(reference)?,
-            sub_nodes: RwLock::new(None),
+            reference: url,
+            sub_nodes: OnceCell::new(),
             schema_path: context.schema_path.clone().into(),
             config: Arc::clone(&context.config),
             resolver: Arc::clone(&context.resolver),
         }))
     }
-}
 
-impl Validate for RefValidator {
-    fn is_valid(&self, instance: &Value) -> bool {
-        if let Some(sub_nodes) = self.sub_nodes.read().as_ref() {
-            return sub_nodes.is_valid(instance);
-        }
-        if let Ok((scope, resolved)) = self.resolver.resolve_fragment(
-            self.config.draft(),
-            &self.reference,
-            &self.original_reference,
-        ) {
+    /// The absolute URL of the referenced schema.
+    pub(crate) const fn url(&self) -> &Url {
+        &self.reference
+    }
+
+    /// The location of the `$ref` within the schema that holds it.
+    pub(crate) const fn schema_path(&self) -> &JSONPointer {
+        &self.schema_path
+    }
+
+    /// The referenced schema if it is already compiled.
+    pub(crate) fn compiled_node(&self) -> Option<&SchemaNode> {
+        self.sub_nodes.get()
+    }
+
+    /// Resolve and compile the referenced schema on first use.
+    pub(crate) fn node(&self) -> Result<&SchemaNode, Box<ValidationError<'static>>> {
+        self.sub_nodes.get_or_try_init(|| {
+            let (scope, resolved) = self
+                .resolver
+                .resolve_fragment(
+                    self.config.draft(),
+                    &self.reference,
+                    &self.original_reference,
+                )
+                .map_err(|error| Box::new(error.into_owned()))?;
             let context = CompilationContext::new(
                 scope.into(),
                 Arc::clone(&self.config),
                 Arc::clone(&self.resolver),
             );
-            if let Ok(node) = compile_validators(&resolved, &context) {
-                let result = node.is_valid(instance);
-                *self.sub_nodes.write() = Some(node);
-                return result;
-            }
-        };
-        false
+            compile_validators(&resolved, &context).map_err(|error| Box::new(error.into_owned()))
+        })
+    }
+}
+
+impl Validate for RefValidator {
+    fn is_valid(&self, instance: &Value) -> bool {
+        self.node().map_or(false, |node| node.is_valid(instance))
     }
 
     fn validate<'instance>(
@@ -74,46 +101,96 @@ impl Validate for RefValidator {
         instance: &'instance Value,
         instance_path: &InstancePath,
     ) -> ErrorIterator<'instance> {
-        if let Some(node) = self.sub_nodes.read().as_ref() {
-            return Box::new(
-                node.validate(instance, instance_path)
+        match self.node() {
+            Ok(node) => Box::new(
+                node.err_iter(instance, instance_path)
+                    .map(move |mut error| {
+                        let schema_path = self.schema_path.clone();
+                        error.schema_path = schema_path.extend_with(error.schema_path.as_slice());
+                        error
+                    })
                     .collect::<Vec<_>>()
                     .into_iter(),
-            );
+            ),
+            Err(err) => error(*err),
         }
-        match self.resolver.resolve_fragment(
-            self.config.draft(),
-            &self.reference,
-            &self.original_reference,
-        ) {
-            Ok((scope, resolved)) => {
-                let context = CompilationContext::new(
-                    scope.into(),
-                    Arc::clone(&self.config),
-                    Arc::clone(&self.resolver),
-                );
-                match compile_validators(&resolved, &context) {
-                    Ok(node) => {
-                        let result = Box::new(
-                            node.err_iter(instance, instance_path)
-                                .map(move |mut error| {
-                                    let schema_path = self.schema_path.clone();
-                                    error.schema_path =
-                                        schema_path.extend_with(error.schema_path.as_slice());
-                                    error
-                                })
-                                .collect::<Vec<_>>()
-                                .into_iter(),
-                        );
-                        *self.sub_nodes.write() = Some(node);
-                        result
-                    }
-                    Err(err) => error(err.into_owned()),
-                }
-            }
-            Err(err) => error(err.into_owned()),
+    }
+
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        match self.node() {
+            Ok(node) => node.first_error(instance, instance_path).map(|mut error| {
+                error.schema_path = self.schema_path.extend_with(error.schema_path.as_slice());
+                error
+            }),
+            Err(err) => Some(*err),
+        }
+    }
+
+    fn apply<'a>(
+        &'a self,
+        instance: &Value,
+        instance_path: &InstancePath,
+    ) -> PartialApplication<'a> {
+        // Annotations of the referenced schema (e.g. `examples`) are kept in the output
+        match self.node() {
+            Ok(node) => node
+                .apply_rooted(instance, instance_path)
+                .with_keyword_prefix(&self.schema_path)
+                .into(),
+            Err(err) => PartialApplication::invalid_empty(vec![(*err).into()]),
+        }
+    }
+
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        // Only once compiled, compiling it here could recurse endlessly
+        self.sub_nodes.get().into_iter().collect()
+    }
+
+    fn estimated_cost(&self) -> u32 {
+        match self.sub_nodes.get() {
+            Some(node) => node.estimated_cost(),
+            None => reference_cost(
+                &self.resolver,
+                self.config.draft(),
+                &self.reference,
+                &self.original_reference,
+            ),
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "$ref".to_string(),
+            Value::String(self.original_reference.clone()),
+        );
+    }
+}
+
+/// The cost of a schema that is not compiled yet, measured by its size. Only references into the
+/// same document are resolved, other schemas count as a single keyword.
+pub(crate) fn reference_cost(resolver: &Resolver, draft: Draft, url: &Url, reference: &str) -> u32 {
+    if !reference.starts_with('#') {
+        return 1;
+    }
+    resolver
+        .resolve_fragment(draft, url, reference)
+        .map_or(1, |(_, schema)| schema_size(&schema).max(1))
+}
+
+/// The number of object members within `schema`, including nested subschemas.
+fn schema_size(schema: &Value) -> u32 {
+    match schema {
+        Value::Object(object) => object
+            .values()
+            .map(|value| schema_size(value).saturating_add(1))
+            .fold(0, u32::saturating_add),
+        Value::Array(items) => items.iter().map(schema_size).fold(0, u32::saturating_add),
+        _ => 0,
+    }
 }
 
 impl core::fmt::Display for RefValidator {
@@ -143,6 +220,108 @@ pub(crate) fn compile<'a>(
     )
 }
 
+/// Compile `reference` as the value of the reference `keyword`, i.e. `$ref`, `$dynamicRef` or
+/// `$recursiveRef`.
+///
+/// Dynamic scopes are not tracked during validation. As validation starts at the root schema,
+/// which is the outermost dynamic scope, a dynamic reference is resolved to the matching anchor
+/// of the root schema resource if it has one, and like `$ref` otherwise.
+pub(crate) fn compile_reference<'a>(
+    keyword: &str,
+    reference: &str,
+    context: &CompilationContext,
+) -> Result<BoxedValidator, Box<ValidationError<'a>>> {
+    let target = match keyword {
+        "$dynamicRef" => dynamic_target(reference, context).map_err(ValidationError::from)?,
+        "$recursiveRef" => recursive_target(reference, context).map_err(ValidationError::from)?,
+        _ => None,
+    };
+    match target {
+        Some(url) => RefValidator::compile_url(reference, url, context),
+        None => RefValidator::compile(reference, context).map_err(Box::new),
+    }
+}
+
+/// The target of a `$dynamicRef` that names a `$dynamicAnchor` of its target resource: the
+/// anchor with the same name in the root schema resource, or the one in the target resource.
+fn dynamic_target(
+    reference: &str,
+    context: &CompilationContext,
+) -> Result<Option<Url>, ParseError> {
+    let mut url = context.build_url(reference)?;
+    let name = match url.fragment() {
+        // JSON pointers are resolved like `$ref`
+        Some(name) if !name.is_empty() && !name.starts_with('/') => name.to_string(),
+        _ => return Ok(None),
+    };
+    url.set_fragment(None);
+    let draft = context.config.draft();
+    let resource = match context.resolver.resolve_fragment(draft, &url, reference) {
+        Ok((_, resource)) => resource,
+        Err(_) => return Ok(None),
+    };
+    let pointer = match anchor_pointer(draft, &resource, "$dynamicAnchor", &name) {
+        Some(pointer) => pointer,
+        None => return Ok(None),
+    };
+    let (root_url, root) = context.resolver.root();
+    let (mut target, pointer) = match anchor_pointer(draft, root, "$dynamicAnchor", &name) {
+        Some(root_pointer) => (root_url.clone(), root_pointer),
+        None => (url, pointer),
+    };
+    target.set_fragment(Some(&pointer));
+    Ok(Some(target))
+}
+
+/// The target of a `$recursiveRef`: the root schema, if both it and the target resource have
+/// `"$recursiveAnchor": true`.
+fn recursive_target(
+    reference: &str,
+    context: &CompilationContext,
+) -> Result<Option<Url>, ParseError> {
+    if reference != "#" {
+        return Ok(None);
+    }
+    let url = context.build_url(reference)?;
+    let is_anchor = |schema: &Value| schema.get("$recursiveAnchor") == Some(&Value::Bool(true));
+    let (root_url, root) = context.resolver.root();
+    match context
+        .resolver
+        .resolve_fragment(context.config.draft(), &url, reference)
+    {
+        Ok((_, target)) if is_anchor(&target) && is_anchor(root) => Ok(Some(root_url.clone())),
+        _ => Ok(None),
+    }
+}
+
+/// The JSON pointer to the subschema of `resource` that declares the anchor `name` with
+/// `keyword`. Anchors of embedded resources are not searched.
+fn anchor_pointer(draft: Draft, resource: &Value, keyword: &str, name: &str) -> Option<String> {
+    let nested = |segment: String, value: &Value| {
+        if schemas::id_of(draft, value).is_some() {
+            return None;
+        }
+        anchor_pointer(draft, value, keyword, name)
+            .map(|pointer| format!("/{}{}", segment, pointer))
+    };
+    match resource {
+        Value::Object(object) => {
+            if object.get(keyword).and_then(Value::as_str) == Some(name) {
+                return Some(String::new());
+            }
+            object
+                .iter()
+                .filter(|(key, _)| *key != "enum" && *key != "const")
+                .find_map(|(key, value)| nested(key.replace('~', "~0").replace('/', "~1"), value))
+        }
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(idx, item)| nested(idx.to_string(), item)),
+        _ => None,
+    }
+}
+
 pub(crate) const fn supports_adjacent_validation(draft: Draft) -> bool {
     match draft {
         #[cfg(feature = "draft201909")]
@@ -155,7 +334,8 @@ pub(crate) const fn supports_adjacent_validation(draft: Draft) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::tests_util;
+    use super::RefValidator;
+    use crate::{schema_node, tests_util, validator::Validate, JSONSchema};
     use serde_json::json;
 
     #[test]
@@ -166,4 +346,27 @@ mod tests {
             "/properties/foo/type",
         )
     }
+
+    #[test]
+    fn estimated_cost() {
+        let cost = |reference: &str| {
+            let compiled = JSONSchema::compile(&json!({
+                "$ref": reference,
+                "definitions": {
+                    "small": {"type": "string"},
+                    "large": {"properties": {"a": {"type": "string"}, "b": {"minimum": 1}}}
+                }
+            }))
+            .unwrap();
+            let validator = compiled
+                .node
+                .validators()
+                .find_map(schema_node::downcast_ref::<RefValidator>)
+                .expect("A reference");
+            validator.estimated_cost()
+        };
+        assert!(cost("#/definitions/small") < cost("#/definitions/large"));
+        // Unresolvable references count as a single keyword
+        assert_eq!(cost("#/definitions/missing"), 1);
+    }
 }
@@ -72,6 +72,13 @@ impl Validate for RequiredValidator {
         }
         no_error()
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "required".to_string(),
+            Value::Array(self.required.iter().cloned().map(Value::String).collect()),
+        );
+    }
 }
 
 impl core::fmt::Display for RequiredValidator {
@@ -120,6 +127,13 @@ impl Validate for SingleItemRequiredValidator {
             true
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "required".to_string(),
+            Value::Array(vec![Value::String(self.value.clone())]),
+        );
+    }
 }
 
 impl core::fmt::Display for SingleItemRequiredValidator {
@@ -2,10 +2,10 @@ use crate::{
     compilation::context::CompilationContext,
     error::{error, no_error, ErrorIterator, ValidationError},
     keywords::CompilationResult,
-    primitive_type::{PrimitiveType, PrimitiveTypesBitMap},
+    primitive_type::{is_integer, PrimitiveType, PrimitiveTypesBitMap},
     validator::Validate,
 };
-use serde_json::{json, Map, Number, Value};
+use serde_json::{json, Map, Value};
 use std::convert::TryFrom;
 
 use crate::paths::{InstancePath, JSONPointer};
@@ -79,6 +79,18 @@ impl Validate for MultipleTypesValidator {
             ))
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert(
+            "type".to_string(),
+            Value::Array(
+                self.types
+                    .into_iter()
+                    .map(|type_| Value::String(type_.to_string()))
+                    .collect(),
+            ),
+        );
+    }
 }
 
 impl core::fmt::Display for MultipleTypesValidator {
@@ -126,6 +138,10 @@ impl Validate for NullTypeValidator {
             ))
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("type".to_string(), Value::String("null".to_string()));
+    }
 }
 
 impl core::fmt::Display for NullTypeValidator {
@@ -165,6 +181,10 @@ impl Validate for BooleanTypeValidator {
             ))
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("type".to_string(), Value::String("boolean".to_string()));
+    }
 }
 
 impl core::fmt::Display for BooleanTypeValidator {
@@ -205,6 +225,10 @@ impl Validate for StringTypeValidator {
             ))
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("type".to_string(), Value::String("string".to_string()));
+    }
 }
 impl core::fmt::Display for StringTypeValidator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -244,6 +268,10 @@ impl Validate for ArrayTypeValidator {
             ))
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("type".to_string(), Value::String("array".to_string()));
+    }
 }
 
 impl core::fmt::Display for ArrayTypeValidator {
@@ -283,6 +311,10 @@ impl Validate for ObjectTypeValidator {
             ))
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("type".to_string(), Value::String("object".to_string()));
+    }
 }
 
 impl core::fmt::Display for ObjectTypeValidator {
@@ -322,6 +354,10 @@ impl Validate for NumberTypeValidator {
             ))
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("type".to_string(), Value::String("number".to_string()));
+    }
 }
 impl core::fmt::Display for NumberTypeValidator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -363,6 +399,10 @@ impl Validate for IntegerTypeValidator {
             ))
         }
     }
+
+    fn write_schema(&self, schema: &mut Map<String, Value>) {
+        schema.insert("type".to_string(), Value::String("integer".to_string()));
+    }
 }
 
 impl core::fmt::Display for IntegerTypeValidator {
@@ -371,10 +411,6 @@ impl core::fmt::Display for IntegerTypeValidator {
     }
 }
 
-fn is_integer(num: &Number) -> bool {
-    num.is_u64() || num.is_i64() || num.as_f64().expect("Always valid").fract() == 0.
-}
-
 #[inline]
 pub(crate) fn compile<'a>(
     _: &'a Map<String, Value>,
@@ -3,7 +3,10 @@ use std::sync::Arc;
 use crate::{
     compilation::{compile_validators, context::CompilationContext},
     error::{no_error, ErrorIterator, ValidationError},
-    keywords::CompilationResult,
+    keywords::{
+        discriminator::{branch_reference, Discriminator, TagSelection, TagSelector},
+        CompilationResult,
+    },
     output::BasicOutput,
     paths::{InstancePath, JSONPointer},
     primitive_type::PrimitiveType,
@@ -111,12 +114,19 @@ impl UnevaluatedPropertiesValidator {
         }
 
         if let Some(Value::Array(subschemas)) = parent.get("oneOf") {
-            let validator = SubschemaSubvalidator::from_values(
+            let mut validator = SubschemaSubvalidator::from_values(
                 schema,
                 subschemas,
                 SubschemaBehavior::One,
                 context,
             )?;
+            if let Some(discriminator) = parent
+                .get("discriminator")
+                .filter(|_| context.config.uses_discriminator())
+            {
+                validator.discriminated =
+                    DiscriminatedBranches::new(discriminator, &parent["oneOf"], context);
+            }
             subschema_validators.push(validator);
         }
 
@@ -591,11 +601,90 @@ impl SubschemaBehavior {
     }
 }
 
+/// The `oneOf` subschema selected by the tag of a discriminated instance.
+///
+/// Only the properties evaluated by the selected subschema are evaluated, even if the instance
+/// is valid against further subschemas, just like the tag selects a single subschema during
+/// validation. Tags are selected like the `discriminator` keyword selects them, with the same
+/// options.
+struct DiscriminatedBranches {
+    selector: TagSelector,
+    // The subvalidator of every mapping entry, if one references the mapped schema
+    branches: Vec<Option<usize>>,
+    // Instances without a usable tag are rejected instead of evaluated by all subschemas
+    strict: bool,
+    // Instances with an unmapped tag are evaluated as if they had no tag
+    pass_through: bool,
+}
+
+impl DiscriminatedBranches {
+    /// `None` if `discriminator` is malformed, which fails the compilation of the `discriminator`
+    /// keyword instead.
+    fn new(
+        discriminator: &Value,
+        one_of: &Value,
+        context: &CompilationContext,
+    ) -> Option<DiscriminatedBranches> {
+        let mut discriminator = Discriminator::try_from_value(discriminator).ok()?;
+        let draft = context.config.draft();
+        if context.config.resolves_implicit_discriminator_mapping() {
+            discriminator.add_implicit_mapping(one_of, draft);
+        }
+        // Aligned with the subvalidators, which are only compiled for object subschemas
+        let references: Vec<_> = one_of
+            .as_array()?
+            .iter()
+            .filter(|branch| branch.is_object())
+            .map(|branch| branch_reference(branch, draft).map(|(_, reference)| reference))
+            .collect();
+        let branches = discriminator
+            .mapping()
+            .map(|(_, mapped)| {
+                references
+                    .iter()
+                    .position(|reference| *reference == Some(mapped))
+            })
+            .collect();
+        Some(DiscriminatedBranches {
+            selector: TagSelector::from_discriminator(&discriminator, &context.config),
+            branches,
+            strict: context.config.uses_strict_discriminator(),
+            pass_through: context.config.passes_through_unknown_discriminator_values(),
+        })
+    }
+
+    /// Position of the subvalidator selected by the tag of `instance`, `Some(None)` if the
+    /// discriminator rejects `instance` regardless of its subschemas. `None` if all subschemas
+    /// are considered, e.g. without a tag.
+    #[allow(clippy::option_option)]
+    fn select(&self, instance: &Value) -> Option<Option<usize>> {
+        match self.selector.select(instance) {
+            TagSelection::Mapped { position, .. } => self.branches[position].map(Some),
+            TagSelection::UnknownTag(_) if self.pass_through => None,
+            TagSelection::UnknownTag(_) | TagSelection::NullTag => Some(None),
+            TagSelection::MissingProperty | TagSelection::NonObjectInstance if self.strict => {
+                Some(None)
+            }
+            TagSelection::MissingProperty | TagSelection::NonObjectInstance => None,
+        }
+    }
+}
+
+impl std::fmt::Debug for DiscriminatedBranches {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiscriminatedBranches")
+            .field("branches", &self.branches)
+            .finish()
+    }
+}
+
 /// A subvalidator for subschema validation such as `allOf`, `oneOf`, and `anyOf`.
 #[derive(Debug)]
 struct SubschemaSubvalidator {
     behavior: SubschemaBehavior,
     subvalidators: Vec<(SchemaNode, UnevaluatedPropertiesValidator)>,
+    // Set for a `oneOf` next to a `discriminator`
+    discriminated: Option<DiscriminatedBranches>,
 }
 
 impl SubschemaSubvalidator {
@@ -626,6 +715,27 @@ impl SubschemaSubvalidator {
         Ok(Self {
             behavior,
             subvalidators,
+            discriminated: None,
+        })
+    }
+
+    /// The subschema selected by the discriminator tag of `instance`, if it is valid.
+    ///
+    /// `None` if there is no discriminator or the tag doesn't select a subschema, `Some(None)` if
+    /// the instance is not valid against the selected subschema.
+    #[allow(clippy::option_option)]
+    fn discriminated(
+        &self,
+        instance: &Value,
+    ) -> Option<Option<&(SchemaNode, UnevaluatedPropertiesValidator)>> {
+        let selected = match self.discriminated.as_ref()?.select(instance)? {
+            Some(position) => &self.subvalidators[position],
+            None => return Some(None),
+        };
+        Some(if selected.0.is_valid(instance) {
+            Some(selected)
+        } else {
+            None
         })
     }
 
@@ -635,6 +745,11 @@ impl SubschemaSubvalidator {
         property_instance: &Value,
         property_name: &str,
     ) -> Option<bool> {
+        if let Some(selected) = self.discriminated(instance) {
+            return selected?
+                .1
+                .is_valid_property(instance, property_instance, property_name);
+        }
         let mapped = self.subvalidators.iter().map(|(node, subvalidator)| {
             (
                 subvalidator.is_valid_property(instance, property_instance, property_name),
@@ -697,6 +812,15 @@ impl SubschemaSubvalidator {
         property_instance: &'instance Value,
         property_name: &str,
     ) -> Option<ErrorIterator<'instance>> {
+        if let Some(selected) = self.discriminated(instance) {
+            return selected?.1.validate_property(
+                instance,
+                instance_path,
+                property_path,
+                property_instance,
+                property_name,
+            );
+        }
         let mapped = self.subvalidators.iter().map(|(node, subvalidator)| {
             let property_result = subvalidator
                 .validate_property(
@@ -708,7 +832,10 @@ impl SubschemaSubvalidator {
                 )
                 .map(|errs| errs.collect::<Vec<_>>());
 
-            let instance_result = node.validate(instance, instance_path).collect::<Vec<_>>();
+            let instance_result = node
+                .validate(instance, instance_path)
+                .filter(ValidationError::is_error)
+                .collect::<Vec<_>>();
 
             (property_result, instance_result)
         });
@@ -779,6 +906,15 @@ impl SubschemaSubvalidator {
         property_instance: &Value,
         property_name: &str,
     ) -> Option<BasicOutput<'a>> {
+        if let Some(selected) = self.discriminated(instance) {
+            return selected?.1.apply_property(
+                instance,
+                instance_path,
+                property_path,
+                property_instance,
+                property_name,
+            );
+        }
         let mapped = self.subvalidators.iter().map(|(node, subvalidator)| {
             let property_result = subvalidator.apply_property(
                 instance,
@@ -1031,7 +1167,11 @@ impl ConditionalSubvalidator {
                 )
             })
             .or_else(|| {
-                if self.condition.validate(instance, instance_path).count() == 0 {
+                if !self
+                    .condition
+                    .validate(instance, instance_path)
+                    .any(|error| error.is_error())
+                {
                     self.success.as_ref().and_then(|success| {
                         success.validate_property(
                             instance,
@@ -1349,7 +1489,7 @@ fn unexpected_type<'a>(
 
 #[cfg(test)]
 mod tests {
-    use crate::{tests_util, Draft};
+    use crate::{tests_util, Draft, JSONSchema};
     use serde_json::json;
 
     #[cfg(all(feature = "draft201909", not(feature = "draft202012")))]
@@ -1382,6 +1522,72 @@ mod tests {
         )
     }
 
+    #[test]
+    fn discriminated_one_of() {
+        // Without the tag, the instances would be valid against both subschemas
+        let schema = json!({
+            "oneOf": [{"$ref": "#/$defs/Cat"}, {"$ref": "#/$defs/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/$defs/Cat", "dog": "#/$defs/Dog"}
+            },
+            "unevaluatedProperties": false,
+            "$defs": {
+                "Cat": {"properties": {"petType": {"type": "string"}, "meows": {"type": "boolean"}}},
+                "Dog": {"properties": {"petType": {"type": "string"}, "barks": {"type": "boolean"}}}
+            }
+        });
+        tests_util::openapi_is_valid_with_draft(
+            get_draft_version(),
+            &schema,
+            &json!({"petType": "cat", "meows": true}),
+        );
+        tests_util::openapi_is_valid_with_draft(
+            get_draft_version(),
+            &schema,
+            &json!({"petType": "dog", "barks": true}),
+        );
+        // Evaluated by the other subschema only
+        tests_util::openapi_is_not_valid_with_draft(
+            get_draft_version(),
+            &schema,
+            &json!({"petType": "cat", "barks": true}),
+        );
+        tests_util::openapi_is_not_valid_with_draft(
+            get_draft_version(),
+            &schema,
+            &json!({"petType": "cat", "meows": true, "purrs": true}),
+        );
+    }
+
+    #[test]
+    fn discriminated_one_of_null_tag() {
+        let schema = json!({
+            "oneOf": [{"$ref": "#/$defs/Cat"}, {"$ref": "#/$defs/Dog"}],
+            "discriminator": {
+                "propertyName": "petType",
+                "mapping": {"cat": "#/$defs/Cat", "dog": "#/$defs/Dog", "null": "#/$defs/Cat"}
+            },
+            "unevaluatedProperties": false,
+            "$defs": {
+                "Cat": {"properties": {"petType": true, "meows": {"type": "boolean"}}},
+                "Dog": {"properties": {"petType": true, "barks": {"type": "boolean"}}}
+            }
+        });
+        let compile = |reject: bool| {
+            tests_util::openapi_options()
+                .with_draft(get_draft_version())
+                .should_reject_null_discriminator_values(reject)
+                .compile(&schema)
+                .expect("Valid schema")
+        };
+        let instance = json!({"petType": null, "meows": true});
+        // The `null` tag selects `Cat` like it does for the discriminator itself
+        assert!(compile(true).is_valid(&instance));
+        assert!(compile(true).validate(&instance).is_ok());
+        assert!(!compile(false).is_valid(&instance));
+    }
+
     #[test]
     fn any_of() {
         tests_util::is_valid_with_draft(
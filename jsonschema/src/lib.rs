@@ -88,17 +88,30 @@ mod content_encoding;
 mod content_media_type;
 pub mod error;
 mod keywords;
+mod offsets;
 pub mod output;
 pub mod paths;
 pub mod primitive_type;
 pub(crate) mod properties;
 mod resolver;
 mod schema_node;
+#[cfg(feature = "schemars-interop")]
+pub mod schemars_interop;
 mod schemas;
+pub mod spans;
 mod validator;
 
-pub use compilation::{options::CompilationOptions, JSONSchema};
-pub use error::{ErrorIterator, ValidationError};
+pub use compilation::{
+    compiler::SchemaCompiler, options::CompilationOptions, CompilationStats, JSONSchema,
+};
+pub use error::{
+    BytesValidationError, ErrorIterator, ErrorIteratorExt, LimitedErrors, LocatedValidationError,
+    Severity, ValidationError, ValidationErrors,
+};
+pub use keywords::discriminator::{
+    validate_discriminator_object, Discriminator, DiscriminatorDiff, DiscriminatorError,
+    MappingSource, NumericTagFormat, VariantInfo,
+};
 pub use resolver::{SchemaResolver, SchemaResolverError};
 pub use schemas::Draft;
 
@@ -125,9 +138,27 @@ pub fn is_valid(schema: &Value, instance: &Value) -> bool {
 #[cfg(test)]
 pub(crate) mod tests_util {
     use super::JSONSchema;
-    use crate::ValidationError;
+    use crate::{CompilationOptions, ValidationError};
     use serde_json::Value;
 
+    /// Options with the OpenAPI `discriminator` keyword enabled, used by the `openapi_*` helpers.
+    pub(crate) fn openapi_options() -> CompilationOptions {
+        let mut options = JSONSchema::options();
+        options.should_use_discriminator(true);
+        options
+    }
+
+    /// The Cat and Dog `oneOf` with a `petType` discriminator, shared with the output tests.
+    /// `extra` keywords are added to its root.
+    pub(crate) fn pets(extra: Value) -> Value {
+        let mut schema: Value =
+            serde_json::from_str(include_str!("../tests/pets.json")).expect("Valid JSON");
+        if let (Value::Object(schema), Value::Object(extra)) = (&mut schema, extra) {
+            schema.extend(extra);
+        }
+        schema
+    }
+
     fn is_not_valid_inner(compiled: &JSONSchema, instance: &Value) {
         assert!(
             !compiled.is_valid(instance),
@@ -151,6 +182,11 @@ pub(crate) mod tests_util {
         is_not_valid_inner(&compiled, instance)
     }
 
+    pub(crate) fn openapi_is_not_valid(schema: &Value, instance: &Value) {
+        let compiled = openapi_options().compile(schema).unwrap();
+        is_not_valid_inner(&compiled, instance)
+    }
+
     #[cfg(any(feature = "draft201909", feature = "draft202012"))]
     pub(crate) fn is_not_valid_with_draft(draft: crate::Draft, schema: &Value, instance: &Value) {
         let compiled = JSONSchema::options()
@@ -160,10 +196,19 @@ pub(crate) mod tests_util {
         is_not_valid_inner(&compiled, instance)
     }
 
-    pub(crate) fn expect_errors(schema: &Value, instance: &Value, errors: &[&str]) {
+    #[cfg(any(feature = "draft201909", feature = "draft202012"))]
+    pub(crate) fn openapi_is_not_valid_with_draft(
+        draft: crate::Draft,
+        schema: &Value,
+        instance: &Value,
+    ) {
+        let compiled = openapi_options().with_draft(draft).compile(schema).unwrap();
+        is_not_valid_inner(&compiled, instance)
+    }
+
+    fn expect_errors_inner(compiled: &JSONSchema, instance: &Value, errors: &[&str]) {
         assert_eq!(
-            JSONSchema::compile(schema)
-                .expect("Should be a valid schema")
+            compiled
                 .validate(instance)
                 .expect_err(format!("{} should not be valid", instance).as_str())
                 .map(|e| e.to_string())
@@ -172,6 +217,18 @@ pub(crate) mod tests_util {
         )
     }
 
+    pub(crate) fn expect_errors(schema: &Value, instance: &Value, errors: &[&str]) {
+        let compiled = JSONSchema::compile(schema).expect("Should be a valid schema");
+        expect_errors_inner(&compiled, instance, errors)
+    }
+
+    pub(crate) fn openapi_expect_errors(schema: &Value, instance: &Value, errors: &[&str]) {
+        let compiled = openapi_options()
+            .compile(schema)
+            .expect("Should be a valid schema");
+        expect_errors_inner(&compiled, instance, errors)
+    }
+
     fn is_valid_inner(compiled: &JSONSchema, instance: &Value) {
         assert!(
             compiled.is_valid(instance),
@@ -195,6 +252,11 @@ pub(crate) mod tests_util {
         is_valid_inner(&compiled, instance);
     }
 
+    pub(crate) fn openapi_is_valid(schema: &Value, instance: &Value) {
+        let compiled = openapi_options().compile(schema).unwrap();
+        is_valid_inner(&compiled, instance);
+    }
+
     #[cfg(any(feature = "draft201909", feature = "draft202012"))]
     pub(crate) fn is_valid_with_draft(draft: crate::Draft, schema: &Value, instance: &Value) {
         let compiled = JSONSchema::options()
@@ -204,15 +266,28 @@ pub(crate) mod tests_util {
         is_valid_inner(&compiled, instance)
     }
 
-    pub(crate) fn validate(schema: &Value, instance: &Value) -> ValidationError<'static> {
-        let compiled = JSONSchema::compile(schema).unwrap();
-        let err = compiled
+    #[cfg(any(feature = "draft201909", feature = "draft202012"))]
+    pub(crate) fn openapi_is_valid_with_draft(
+        draft: crate::Draft,
+        schema: &Value,
+        instance: &Value,
+    ) {
+        let compiled = openapi_options().with_draft(draft).compile(schema).unwrap();
+        is_valid_inner(&compiled, instance)
+    }
+
+    fn first_error(compiled: &JSONSchema, instance: &Value) -> ValidationError<'static> {
+        compiled
             .validate(instance)
             .expect_err("Should be an error")
             .next()
             .expect("Should be an error")
-            .into_owned();
-        err
+            .into_owned()
+    }
+
+    pub(crate) fn validate(schema: &Value, instance: &Value) -> ValidationError<'static> {
+        let compiled = JSONSchema::compile(schema).unwrap();
+        first_error(&compiled, instance)
     }
 
     pub(crate) fn assert_schema_path(schema: &Value, instance: &Value, expected: &str) {
@@ -220,6 +295,16 @@ pub(crate) mod tests_util {
         assert_eq!(error.schema_path.to_string(), expected)
     }
 
+    pub(crate) fn openapi_validate(schema: &Value, instance: &Value) -> ValidationError<'static> {
+        let compiled = openapi_options().compile(schema).unwrap();
+        first_error(&compiled, instance)
+    }
+
+    pub(crate) fn openapi_assert_schema_path(schema: &Value, instance: &Value, expected: &str) {
+        let error = openapi_validate(schema, instance);
+        assert_eq!(error.schema_path.to_string(), expected)
+    }
+
     pub(crate) fn assert_schema_paths(schema: &Value, instance: &Value, expected: &[&str]) {
         let compiled = JSONSchema::compile(schema).unwrap();
         let errors = compiled.validate(instance).expect_err("Should be an error");
@@ -0,0 +1,147 @@
+//! Byte offsets of the values in a raw JSON document.
+//!
+//! `serde_json` does not retain positions, so the document is scanned once more after it was
+//! parsed successfully. The scanner relies on the input being valid JSON.
+use ahash::AHashMap;
+
+/// Map the JSON Pointer of every value in `bytes` to its byte range, end exclusive.
+///
+/// Pointers are escaped the same way as `JSONPointer`'s `Display`, so they can be looked up with
+/// the `instance_path` of an error.
+pub(crate) fn value_offsets(bytes: &[u8]) -> AHashMap<String, (usize, usize)> {
+    let mut scanner = Scanner {
+        bytes,
+        position: 0,
+        offsets: AHashMap::new(),
+    };
+    scanner.value(&mut String::new());
+    scanner.offsets
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    offsets: AHashMap<String, (usize, usize)>,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn value(&mut self, pointer: &mut String) {
+        self.skip_whitespace();
+        let start = self.position;
+        match self.peek() {
+            Some(b'{') => self.object(pointer),
+            Some(b'[') => self.array(pointer),
+            Some(b'"') => self.string(),
+            Some(_) => {
+                while let Some(byte) = self.peek() {
+                    if matches!(byte, b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r') {
+                        break;
+                    }
+                    self.position += 1;
+                }
+            }
+            None => return,
+        }
+        self.offsets.insert(pointer.clone(), (start, self.position));
+    }
+
+    fn object(&mut self, pointer: &mut String) {
+        // Skip `{`
+        self.position += 1;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'"') => {
+                    let key_start = self.position;
+                    self.string();
+                    let key: String = serde_json::from_slice(&self.bytes[key_start..self.position])
+                        .unwrap_or_default();
+                    self.skip_whitespace();
+                    // Skip `:`
+                    self.position += 1;
+                    let len = pointer.len();
+                    pointer.push('/');
+                    for ch in key.chars() {
+                        match ch {
+                            '/' => pointer.push_str("~1"),
+                            '~' => pointer.push_str("~0"),
+                            _ => pointer.push(ch),
+                        }
+                    }
+                    self.value(pointer);
+                    pointer.truncate(len);
+                }
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn array(&mut self, pointer: &mut String) {
+        // Skip `[`
+        self.position += 1;
+        let mut idx = 0_usize;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b']') => {
+                    self.position += 1;
+                    return;
+                }
+                Some(b',') => self.position += 1,
+                Some(_) => {
+                    let len = pointer.len();
+                    pointer.push('/');
+                    pointer.push_str(itoa::Buffer::new().format(idx));
+                    self.value(pointer);
+                    pointer.truncate(len);
+                    idx += 1;
+                }
+                None => return,
+            }
+        }
+    }
+
+    fn string(&mut self) {
+        // Skip the opening quote
+        self.position += 1;
+        while let Some(byte) = self.peek() {
+            self.position += 1;
+            match byte {
+                b'\\' => self.position += 1,
+                b'"' => return,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::value_offsets;
+    use test_case::test_case;
+
+    #[test_case(br#"1"#, "", (0, 1))]
+    #[test_case(br#" {"a": [1, "x\"y"]} "#, "", (1, 19))]
+    #[test_case(br#" {"a": [1, "x\"y"]} "#, "/a", (7, 18))]
+    #[test_case(br#" {"a": [1, "x\"y"]} "#, "/a/1", (11, 17))]
+    #[test_case(br#"{"a/b~": {"c": null}}"#, "/a~1b~0/c", (15, 19))]
+    #[test_case(br#"{"\u00e9": true}"#, "/\u{e9}", (11, 15))]
+    fn offsets(bytes: &[u8], pointer: &str, expected: (usize, usize)) {
+        assert_eq!(value_offsets(bytes)[pointer], expected);
+    }
+}
@@ -18,7 +18,7 @@ use ahash::AHashMap;
 use serde::ser::SerializeMap;
 
 use crate::{
-    paths::{AbsolutePath, InstancePath, JSONPointer},
+    paths::{AbsolutePath, InstancePath, JSONPointer, PathChunk},
     schema_node::SchemaNode,
     JSONSchema,
 };
@@ -106,9 +106,63 @@ impl<'a, 'b> Output<'a, 'b> {
     /// ```
     #[must_use]
     pub fn basic(&self) -> BasicOutput<'a> {
-        self.root_node
-            .apply_rooted(self.instance, &InstancePath::new())
+        let output = self
+            .root_node
+            .apply_rooted(self.instance, &InstancePath::new());
+        match output {
+            BasicOutput::Invalid(units)
+                if self.schema.config().includes_instance_in_errors()
+                    || self.schema.config().compresses_error_locations() =>
+            {
+                let config = self.schema.config();
+                BasicOutput::Invalid(
+                    units
+                        .into_iter()
+                        .map(|mut unit| {
+                            if config.includes_instance_in_errors() {
+                                unit.instance = value_at(self.instance, &unit.instance_location)
+                                    .map(capped_instance);
+                            }
+                            unit.compress_locations = config.compresses_error_locations();
+                            unit
+                        })
+                        .collect(),
+                )
+            }
+            output => output,
+        }
+    }
+}
+
+/// The longest serialized instance included in an error, longer ones are truncated.
+const MAX_ERROR_INSTANCE_LENGTH: usize = 1024;
+
+/// The value at `pointer` within `instance`.
+fn value_at<'v>(
+    instance: &'v serde_json::Value,
+    pointer: &JSONPointer,
+) -> Option<&'v serde_json::Value> {
+    pointer
+        .iter()
+        .try_fold(instance, |value, chunk| match chunk {
+            PathChunk::Property(property) => value.get(&**property),
+            PathChunk::Index(idx) => value.get(idx),
+            PathChunk::Keyword(keyword) => value.get(keyword),
+        })
+}
+
+/// A copy of `instance`, or its serialized JSON cut to `MAX_ERROR_INSTANCE_LENGTH` if it is
+/// longer than that.
+fn capped_instance(instance: &serde_json::Value) -> serde_json::Value {
+    let serialized = instance.to_string();
+    if serialized.len() <= MAX_ERROR_INSTANCE_LENGTH {
+        return instance.clone();
     }
+    let mut end = MAX_ERROR_INSTANCE_LENGTH;
+    while !serialized.is_char_boundary(end) {
+        end -= 1;
+    }
+    serde_json::Value::String(format!("{}...", &serialized[..end]))
 }
 
 /// The "basic" output format. See the documentation for [`Output::basic`] for
@@ -130,6 +184,24 @@ impl<'a> BasicOutput<'a> {
             BasicOutput::Invalid(..) => false,
         }
     }
+
+    /// Prepend `prefix` to the keyword location of every unit in this output.
+    pub(crate) fn with_keyword_prefix(self, prefix: &JSONPointer) -> Self {
+        match self {
+            BasicOutput::Valid(units) => BasicOutput::Valid(
+                units
+                    .into_iter()
+                    .map(|unit| unit.with_keyword_prefix(prefix))
+                    .collect(),
+            ),
+            BasicOutput::Invalid(units) => BasicOutput::Invalid(
+                units
+                    .into_iter()
+                    .map(|unit| unit.with_keyword_prefix(prefix))
+                    .collect(),
+            ),
+        }
+    }
 }
 
 impl<'a> From<OutputUnit<Annotations<'a>>> for BasicOutput<'a> {
@@ -205,6 +277,10 @@ pub struct OutputUnit<T> {
     instance_location: JSONPointer,
     absolute_keyword_location: Option<AbsolutePath>,
     value: T,
+    // The invalid value, see `CompilationOptions::should_include_instance_in_errors`
+    instance: Option<serde_json::Value>,
+    // See `CompilationOptions::should_compress_error_locations`
+    compress_locations: bool,
 }
 
 impl<T> OutputUnit<T> {
@@ -219,6 +295,8 @@ impl<T> OutputUnit<T> {
             instance_location,
             absolute_keyword_location,
             value: annotations,
+            instance: None,
+            compress_locations: false,
         }
     }
 
@@ -233,9 +311,16 @@ impl<T> OutputUnit<T> {
             instance_location,
             absolute_keyword_location,
             value: error,
+            instance: None,
+            compress_locations: false,
         }
     }
 
+    fn with_keyword_prefix(mut self, prefix: &JSONPointer) -> Self {
+        self.keyword_location = prefix.extend_with(self.keyword_location.as_slice());
+        self
+    }
+
     ///  The location in the schema of the keyword
     pub const fn keyword_location(&self) -> &JSONPointer {
         &self.keyword_location
@@ -267,6 +352,13 @@ impl OutputUnit<ErrorDescription> {
     pub const fn error_description(&self) -> &ErrorDescription {
         &self.value
     }
+
+    /// The invalid value, if enabled by
+    /// [`CompilationOptions::should_include_instance_in_errors`](crate::CompilationOptions::should_include_instance_in_errors).
+    #[must_use]
+    pub const fn instance(&self) -> Option<&serde_json::Value> {
+        self.instance.as_ref()
+    }
 }
 
 /// Annotations associated with an output unit.
@@ -391,12 +483,20 @@ impl serde::Serialize for OutputUnit<ErrorDescription> {
         S: serde::Serializer,
     {
         let mut map_ser = serializer.serialize_map(Some(4))?;
-        map_ser.serialize_entry("keywordLocation", &self.keyword_location)?;
-        map_ser.serialize_entry("instanceLocation", &self.instance_location)?;
+        if self.compress_locations {
+            map_ser.serialize_entry("keywordLocation", &self.keyword_location.compress())?;
+            map_ser.serialize_entry("instanceLocation", &self.instance_location.compress())?;
+        } else {
+            map_ser.serialize_entry("keywordLocation", &self.keyword_location)?;
+            map_ser.serialize_entry("instanceLocation", &self.instance_location)?;
+        }
         if let Some(absolute) = &self.absolute_keyword_location {
             map_ser.serialize_entry("absoluteKeywordLocation", &absolute)?;
         }
         map_ser.serialize_entry("error", &self.value)?;
+        if let Some(instance) = &self.instance {
+            map_ser.serialize_entry("instance", instance)?;
+        }
         map_ser.end()
     }
 }
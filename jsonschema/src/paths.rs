@@ -45,6 +45,60 @@ impl JSONPointer {
     pub(crate) fn as_slice(&self) -> &[PathChunk] {
         &self.0
     }
+
+    /// A shorter form of the pointer for display, e.g. of deeply nested recursive schemas.
+    ///
+    /// Consecutive repetitions of the same chunks are written once, followed by the number of
+    /// repetitions in braces. Repeated sequences of several chunks are put in parentheses. Runs
+    /// are taken from left to right, preferring the one covering the most chunks:
+    ///
+    /// ```rust
+    /// # use jsonschema::paths::JSONPointer;
+    /// let pointer = JSONPointer::from(&["items", "items", "items", "type"][..]);
+    /// assert_eq!(pointer.compress(), "/items{3}/type");
+    /// let pointer = JSONPointer::from(&["children", "0", "children", "0", "name"][..]);
+    /// assert_eq!(pointer.compress(), "/(children/0){2}/name");
+    /// ```
+    ///
+    /// Pointers without repetitions are written as usual. The result is not a valid JSON pointer.
+    #[must_use]
+    pub fn compress(&self) -> String {
+        let chunks: Vec<String> = self
+            .0
+            .iter()
+            .map(|chunk| {
+                let mut output = String::new();
+                write_chunk(&mut output, chunk).expect("Writing to a String never fails");
+                output
+            })
+            .collect();
+        let mut output = String::new();
+        let mut start = 0;
+        while start < chunks.len() {
+            // The longest run of repetitions at `start`
+            let (mut period, mut count) = (1, 1);
+            for candidate in 1..=(chunks.len() - start) / 2 {
+                let sequence = &chunks[start..start + candidate];
+                let repetitions = chunks[start..]
+                    .chunks_exact(candidate)
+                    .take_while(|next| *next == sequence)
+                    .count();
+                if repetitions > 1 && candidate * repetitions > period * count {
+                    period = candidate;
+                    count = repetitions;
+                }
+            }
+            let sequence = chunks[start..start + period].join("/");
+            output.push('/');
+            match (period, count) {
+                (_, 1) => output.push_str(&sequence),
+                (1, _) => output.push_str(&format!("{}{{{}}}", sequence, count)),
+                _ => output.push_str(&format!("({}){{{}}}", sequence, count)),
+            }
+            start += period * count;
+        }
+        output
+    }
 }
 
 impl serde::Serialize for JSONPointer {
@@ -58,26 +112,30 @@ impl serde::Serialize for JSONPointer {
 
 impl fmt::Display for JSONPointer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if !self.0.is_empty() {
-            for chunk in &self.0 {
-                f.write_char('/')?;
-                match chunk {
-                    PathChunk::Property(value) => {
-                        for ch in value.chars() {
-                            match ch {
-                                '/' => f.write_str("~1")?,
-                                '~' => f.write_str("~0")?,
-                                _ => f.write_char(ch)?,
-                            }
-                        }
-                    }
-                    PathChunk::Index(idx) => f.write_str(itoa::Buffer::new().format(*idx))?,
-                    PathChunk::Keyword(keyword) => f.write_str(keyword)?,
+        for chunk in &self.0 {
+            f.write_char('/')?;
+            write_chunk(f, chunk)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a single chunk of a JSON pointer, escaping `/` and `~`.
+fn write_chunk(f: &mut impl Write, chunk: &PathChunk) -> fmt::Result {
+    match chunk {
+        PathChunk::Property(value) => {
+            for ch in value.chars() {
+                match ch {
+                    '/' => f.write_str("~1")?,
+                    '~' => f.write_str("~0")?,
+                    _ => f.write_char(ch)?,
                 }
             }
         }
-        Ok(())
+        PathChunk::Index(idx) => f.write_str(itoa::Buffer::new().format(*idx))?,
+        PathChunk::Keyword(keyword) => f.write_str(keyword)?,
     }
+    Ok(())
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -270,6 +328,7 @@ impl From<url::Url> for AbsolutePath {
 mod tests {
     use super::JSONPointer;
     use serde_json::json;
+    use test_case::test_case;
 
     #[test]
     fn json_pointer_to_string() {
@@ -279,4 +338,20 @@ mod tests {
         let data = json!({"/": {"~": 42}});
         assert_eq!(data.pointer(&pointer), Some(&json!(42)))
     }
+
+    #[test_case(&["properties", "name"], "/properties/name"; "no repetitions")]
+    #[test_case(&["items", "items", "items", "type"], "/items{3}/type"; "single chunk")]
+    #[test_case(
+        &["pets", "children", "0", "children", "0", "children", "0", "name"],
+        "/pets/(children/0){3}/name";
+        "several chunks"
+    )]
+    #[test_case(&["a", "a", "b", "a", "b", "c"], "/a{2}/b/a/b/c"; "left to right")]
+    #[test_case(&["a", "a", "a", "a"], "/a{4}"; "shortest period")]
+    #[test_case(&["a/b", "a/b"], "/a~1b{2}"; "escaped")]
+    #[test_case(&[], ""; "empty")]
+    fn compress(chunks: &[&str], expected: &str) {
+        let pointer = JSONPointer::from(chunks);
+        assert_eq!(pointer.compress(), expected);
+    }
 }
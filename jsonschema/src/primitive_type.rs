@@ -1,6 +1,6 @@
 //! Primitive types for property type validators
 
-use serde_json::Value;
+use serde_json::{Number, Value};
 use std::{convert::TryFrom, fmt, ops::BitOrAssign};
 
 /// For faster error handling in "type" keyword validator we have this enum, to match
@@ -49,6 +49,30 @@ impl TryFrom<&str> for PrimitiveType {
     }
 }
 
+impl PrimitiveType {
+    /// The type of `instance`, `Integer` for numbers without a fractional part, e.g. `1.0`.
+    ///
+    /// ```rust
+    /// use jsonschema::primitive_type::PrimitiveType;
+    /// use serde_json::json;
+    ///
+    /// assert_eq!(PrimitiveType::of(&json!(1.0)), PrimitiveType::Integer);
+    /// assert_eq!(PrimitiveType::of(&json!(1.5)), PrimitiveType::Number);
+    /// ```
+    #[must_use]
+    pub fn of(instance: &Value) -> PrimitiveType {
+        match instance {
+            Value::Number(number) if is_integer(number) => PrimitiveType::Integer,
+            _ => PrimitiveType::from(instance),
+        }
+    }
+}
+
+/// Whether `number` has no fractional part.
+pub(crate) fn is_integer(number: &Number) -> bool {
+    number.is_u64() || number.is_i64() || number.as_f64().expect("Always valid").fract() == 0.
+}
+
 impl From<&Value> for PrimitiveType {
     fn from(instance: &Value) -> Self {
         match instance {
@@ -158,6 +182,22 @@ impl Iterator for PrimitiveTypesBitMapIterator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+    use test_case::test_case;
+
+    #[test_case(&json!(null), PrimitiveType::Null)]
+    #[test_case(&json!(true), PrimitiveType::Boolean)]
+    #[test_case(&json!(1), PrimitiveType::Integer)]
+    #[test_case(&json!(-1), PrimitiveType::Integer)]
+    #[test_case(&json!(u64::MAX), PrimitiveType::Integer)]
+    #[test_case(&json!(1.0), PrimitiveType::Integer)]
+    #[test_case(&json!(1.5), PrimitiveType::Number)]
+    #[test_case(&json!("1"), PrimitiveType::String)]
+    #[test_case(&json!([1]), PrimitiveType::Array)]
+    #[test_case(&json!({"a": 1}), PrimitiveType::Object)]
+    fn of(instance: &Value, expected: PrimitiveType) {
+        assert_eq!(PrimitiveType::of(instance), expected);
+    }
 
     #[test]
     fn test_multiple_types() {
@@ -13,7 +13,7 @@ use crate::{
 pub(crate) type PatternedValidators = Vec<(Regex, SchemaNode)>;
 
 /// A value that can look up property validators by name.
-pub(crate) trait PropertiesValidatorsMap: Send + Sync {
+pub(crate) trait PropertiesValidatorsMap: Send + Sync + 'static {
     fn from_map<'a>(
         map: &'a Map<String, Value>,
         context: &CompilationContext,
@@ -23,6 +23,7 @@ pub(crate) trait PropertiesValidatorsMap: Send + Sync {
 
     fn get_validator(&self, property: &str) -> Option<&SchemaNode>;
     fn get_key_validator(&self, property: &str) -> Option<(&String, &SchemaNode)>;
+    fn nodes(&self) -> Vec<&SchemaNode>;
 }
 
 // We're defining two different property validator map implementations, one for small map sizes and
@@ -64,6 +65,9 @@ impl PropertiesValidatorsMap for SmallValidatorsMap {
         }
         None
     }
+    fn nodes(&self) -> Vec<&SchemaNode> {
+        self.iter().map(|(_, node)| node).collect()
+    }
 }
 
 impl PropertiesValidatorsMap for BigValidatorsMap {
@@ -86,6 +90,9 @@ impl PropertiesValidatorsMap for BigValidatorsMap {
     fn get_key_validator(&self, property: &str) -> Option<(&String, &SchemaNode)> {
         self.get_key_value(property)
     }
+    fn nodes(&self) -> Vec<&SchemaNode> {
+        self.values().collect()
+    }
 }
 
 pub(crate) fn compile_small_map<'a>(
@@ -116,6 +116,7 @@ impl SchemaResolver for DefaultResolver {
 pub(crate) struct Resolver {
     external_resolver: Arc<dyn SchemaResolver>,
     root_schema: Arc<Value>,
+    root_scope: Url,
     // canonical_id: sub-schema mapping to resolve documents by their ID
     // canonical_id is composed with the root document id
     // (if not specified, then `DEFAULT_ROOT_URL` is used for this purpose)
@@ -150,11 +151,17 @@ impl Resolver {
         Ok(Resolver {
             external_resolver,
             root_schema: schema,
+            root_scope: scope.clone(),
             schemas,
             store: RwLock::new(store),
         })
     }
 
+    /// The URL and the document of the root schema.
+    pub(crate) fn root(&self) -> (&Url, &Value) {
+        (&self.root_scope, &self.root_schema)
+    }
+
     /// Load a document for the given `url`.
     /// It may be:
     ///   - the root document (`DEFAULT_ROOT_URL`) case;
@@ -215,6 +222,8 @@ impl Resolver {
                 let joined_folders = join_folders(resource, &folders)?;
                 Ok((joined_folders, Arc::new(resolved.clone())))
             }
+            // `urn:cat#/` is commonly meant as the whole document, e.g. in discriminator mappings
+            None if fragment == "/" => Ok((resource, document)),
             None => Err(ValidationError::invalid_reference(url.as_str().to_string())),
         }
     }
@@ -1,12 +1,13 @@
 use crate::{
     compilation::context::CompilationContext,
-    error::ErrorIterator,
-    keywords::BoxedValidator,
+    error::{ErrorIterator, ValidationError},
+    keywords::{pre_validate::PreValidateValidator, BoxedValidator},
     output::{Annotations, BasicOutput, ErrorDescription, OutputUnit},
     paths::{AbsolutePath, InstancePath, JSONPointer},
-    validator::{format_validators, PartialApplication, Validate},
+    validator::{format_validators, AsAny, PartialApplication, Validate},
 };
 use ahash::AHashMap;
+use serde_json::Map;
 use std::{collections::VecDeque, fmt};
 
 /// A node in the schema tree, returned by [`compile_validators`]
@@ -42,8 +43,7 @@ struct KeywordValidators {
     /// The keywords on this node which were not recognized by any vocabularies. These are
     /// stored so we can later produce them as annotations
     unmatched_keywords: Option<AHashMap<String, serde_json::Value>>,
-    // We should probably use AHashMap here but it breaks a bunch of test which assume
-    // validators are in a particular order
+    // In the order of `SchemaNode::validators`, which `Display` and error order rely on
     validators: Vec<(String, BoxedValidator)>,
 }
 
@@ -87,6 +87,10 @@ impl SchemaNode {
         }
     }
 
+    /// Validators of this node, in the order of their keywords in the schema object.
+    ///
+    /// Without the `preserve_order` feature of `serde_json` the keywords of a schema object are
+    /// ordered lexicographically, so the order is the same for every compilation of the schema.
     pub(crate) fn validators(&self) -> impl Iterator<Item = &BoxedValidator> + ExactSizeIterator {
         match &self.validators {
             NodeValidators::Boolean { validator } => {
@@ -105,6 +109,93 @@ impl SchemaNode {
         }
     }
 
+    /// The validator compiled for `keyword`, if this node is composed of keywords.
+    pub(crate) fn keyword_validator(&self, keyword: &str) -> Option<&BoxedValidator> {
+        match &self.validators {
+            NodeValidators::Keyword(kvals) => kvals
+                .validators
+                .iter()
+                .find(|(name, _)| name == keyword)
+                .map(|(_, validator)| validator),
+            NodeValidators::Boolean { .. } | NodeValidators::Array { .. } => None,
+        }
+    }
+
+    /// Mutable access to the validator compiled for `keyword`, see `keyword_validator`.
+    pub(crate) fn keyword_validator_mut(&mut self, keyword: &str) -> Option<&mut BoxedValidator> {
+        match &mut self.validators {
+            NodeValidators::Keyword(kvals) => kvals
+                .validators
+                .iter_mut()
+                .find(|(name, _)| name == keyword)
+                .map(|(_, validator)| validator),
+            NodeValidators::Boolean { .. } | NodeValidators::Array { .. } => None,
+        }
+    }
+
+    /// Take the validator compiled for `keyword`, dropping the rest of the node.
+    pub(crate) fn into_keyword_validator(self, keyword: &str) -> Option<BoxedValidator> {
+        match self.validators {
+            NodeValidators::Keyword(kvals) => kvals
+                .validators
+                .into_iter()
+                .find(|(name, _)| name == keyword)
+                .map(|(_, validator)| validator),
+            NodeValidators::Boolean { .. } | NodeValidators::Array { .. } => None,
+        }
+    }
+
+    /// The validator compiled for `keyword` as a `T`, looking through a pre-validate hook.
+    pub(crate) fn keyword_validator_as<T: Validate>(&self, keyword: &str) -> Option<&T> {
+        downcast_ref(self.keyword_validator(keyword)?)
+    }
+
+    /// Mutable access to the validator compiled for `keyword` as a `T`, see
+    /// `keyword_validator_as`.
+    pub(crate) fn keyword_validator_as_mut<T: Validate>(
+        &mut self,
+        keyword: &str,
+    ) -> Option<&mut T> {
+        downcast_mut(self.keyword_validator_mut(keyword)?)
+    }
+
+    /// Take the validator compiled for `keyword` as a `T`, see `keyword_validator_as`.
+    pub(crate) fn into_keyword_validator_as<T: Validate>(self, keyword: &str) -> Option<T> {
+        downcast(self.into_keyword_validator(keyword)?)
+    }
+
+    /// The schema this node was compiled from, rebuilt from its validators on a best-effort
+    /// basis. Keywords that no validator can rebuild are left out, unknown keywords are kept.
+    pub(crate) fn to_value(&self) -> serde_json::Value {
+        match &self.validators {
+            NodeValidators::Boolean { validator } => serde_json::Value::Bool(validator.is_none()),
+            NodeValidators::Keyword(kvals) => {
+                let mut schema = Map::with_capacity(kvals.validators.len());
+                for (_, validator) in &kvals.validators {
+                    validator.write_schema(&mut schema);
+                }
+                if let Some(unmatched_keywords) = &kvals.unmatched_keywords {
+                    for (keyword, value) in unmatched_keywords {
+                        schema
+                            .entry(keyword.clone())
+                            .or_insert_with(|| value.clone());
+                    }
+                }
+                serde_json::Value::Object(schema)
+            }
+            // The property names of a `dependencies` entry, compiled as `required`
+            NodeValidators::Array { validators } => {
+                let mut schema = Map::new();
+                for validator in validators {
+                    validator.write_schema(&mut schema);
+                }
+                schema
+                    .remove("required")
+                    .unwrap_or_else(|| serde_json::Value::Array(Vec::new()))
+            }
+        }
+    }
+
     fn format_validators(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(&format_validators(self.validators()))
     }
@@ -215,7 +306,7 @@ impl SchemaNode {
         annotations: Option<Annotations<'a>>,
     ) -> PartialApplication<'a>
     where
-        I: Iterator<Item = (P, &'a Box<dyn Validate + Send + Sync + 'a>)> + 'a,
+        I: Iterator<Item = (P, &'a BoxedValidator)> + 'a,
         P: Into<crate::paths::PathChunk> + std::fmt::Display,
     {
         let mut success_results: VecDeque<OutputUnit<Annotations>> = VecDeque::new();
@@ -277,6 +368,33 @@ impl fmt::Display for SchemaNode {
     }
 }
 
+/// `validator` as a `T`, looking through a pre-validate hook around it.
+pub(crate) fn downcast_ref<T: Validate>(validator: &BoxedValidator) -> Option<&T> {
+    let validator = (**validator).as_any();
+    match validator.downcast_ref::<PreValidateValidator>() {
+        Some(wrapper) => downcast_ref(wrapper.inner()),
+        None => validator.downcast_ref(),
+    }
+}
+
+fn downcast_mut<T: Validate>(validator: &mut BoxedValidator) -> Option<&mut T> {
+    let validator = (**validator).as_any_mut();
+    if validator.is::<PreValidateValidator>() {
+        validator
+            .downcast_mut::<PreValidateValidator>()
+            .and_then(|wrapper| downcast_mut(wrapper.inner_mut()))
+    } else {
+        validator.downcast_mut()
+    }
+}
+
+fn downcast<T: Validate>(validator: BoxedValidator) -> Option<T> {
+    match AsAny::into_any(validator).downcast::<PreValidateValidator>() {
+        Ok(wrapper) => downcast(wrapper.into_inner()),
+        Err(validator) => validator.downcast().ok().map(|validator| *validator),
+    }
+}
+
 impl Validate for SchemaNode {
     fn validate<'instance>(
         &self,
@@ -304,6 +422,21 @@ impl Validate for SchemaNode {
         }
     }
 
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance serde_json::Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        self.validators()
+            .find_map(|validator| validator.first_error(instance, instance_path))
+    }
+
+    fn estimated_cost(&self) -> u32 {
+        self.validators()
+            .map(|validator| validator.estimated_cost())
+            .fold(0, u32::saturating_add)
+    }
+
     fn apply<'a>(
         &'a self,
         instance: &serde_json::Value,
@@ -388,11 +521,11 @@ impl<'a> ExactSizeIterator for NodeValidatorsIter<'a> {
 pub(crate) enum NodeValidatorsErrIter<'a> {
     NoErrs,
     Single(ErrorIterator<'a>),
-    Multiple(std::vec::IntoIter<crate::error::ValidationError<'a>>),
+    Multiple(std::vec::IntoIter<ValidationError<'a>>),
 }
 
 impl<'a> Iterator for NodeValidatorsErrIter<'a> {
-    type Item = crate::error::ValidationError<'a>;
+    type Item = ValidationError<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
@@ -0,0 +1,153 @@
+//! Interoperability with schemas generated by [`schemars`](https://docs.rs/schemars).
+//!
+//! `schemars` describes an internally tagged enum (`#[serde(tag = "...")]`) as a plain `oneOf`
+//! where every variant pins the tag property to a single value:
+//!
+//! ```json
+//! {
+//!     "oneOf": [
+//!         {"type": "object", "required": ["type"], "properties": {"type": {"type": "string", "enum": ["Cat"]}}},
+//!         {"type": "object", "required": ["type"], "properties": {"type": {"type": "string", "enum": ["Dog"]}}}
+//!     ]
+//! }
+//! ```
+//!
+//! [`tagged_enum_to_discriminated`] turns such a schema into a discriminated `oneOf`, so only the
+//! variant named by the tag is validated.
+use serde_json::{Map, Value};
+
+/// Add a `discriminator` to the schema of an internally tagged enum generated by `schemars`.
+///
+/// The tag property becomes `propertyName` and every variant is added to `mapping` as a
+/// `#/oneOf/<index>` reference, therefore `schema` is expected to be the root schema.
+/// Returns `None` if `schema` is not a `oneOf` whose variants share a tag property with distinct
+/// values.
+///
+/// ```rust
+/// # use jsonschema::{schemars_interop::tagged_enum_to_discriminated, JSONSchema};
+/// # use serde_json::json;
+/// let schema = json!({
+///     "oneOf": [
+///         {"type": "object", "required": ["type"], "properties": {"type": {"enum": ["Cat"]}}},
+///         {"type": "object", "required": ["type"], "properties": {"type": {"enum": ["Dog"]}}}
+///     ]
+/// });
+/// let schema = tagged_enum_to_discriminated(&schema).expect("A tagged enum");
+/// assert_eq!(
+///     schema["discriminator"],
+///     json!({"propertyName": "type", "mapping": {"Cat": "#/oneOf/0", "Dog": "#/oneOf/1"}})
+/// );
+/// ```
+#[must_use]
+pub fn tagged_enum_to_discriminated(schema: &Value) -> Option<Value> {
+    let variants = schema.get("oneOf")?.as_array()?;
+    let properties = variants.first()?.get("properties")?.as_object()?;
+    let (property_name, mapping) = properties.keys().find_map(|property_name| {
+        tag_mapping(variants, property_name).map(|m| (property_name, m))
+    })?;
+    let mut discriminated = schema.clone();
+    let mut discriminator = Map::with_capacity(2);
+    discriminator.insert(
+        "propertyName".to_string(),
+        Value::String(property_name.clone()),
+    );
+    discriminator.insert("mapping".to_string(), Value::Object(mapping));
+    discriminated
+        .as_object_mut()?
+        .insert("discriminator".to_string(), Value::Object(discriminator));
+    Some(discriminated)
+}
+
+/// Map every variant's tag to its position, if `property_name` is a tag shared by all variants.
+fn tag_mapping(variants: &[Value], property_name: &str) -> Option<Map<String, Value>> {
+    let mut mapping = Map::with_capacity(variants.len());
+    for (idx, variant) in variants.iter().enumerate() {
+        let tag = variant_tag(variant, property_name)?;
+        let reference = Value::String(format!("#/oneOf/{}", idx));
+        if mapping.insert(tag.to_string(), reference).is_some() {
+            // Duplicate tags can't select a single variant
+            return None;
+        }
+    }
+    Some(mapping)
+}
+
+/// The single value a variant allows for `property_name`, via `const` or a one-element `enum`.
+fn variant_tag<'a>(variant: &'a Value, property_name: &str) -> Option<&'a str> {
+    let property = variant.get("properties")?.get(property_name)?;
+    if let Some(tag) = property.get("const") {
+        return tag.as_str();
+    }
+    match property.get("enum")?.as_array()?.as_slice() {
+        [tag] => tag.as_str(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tagged_enum_to_discriminated;
+    use crate::tests_util;
+    use serde_json::json;
+
+    // `schemars` output for:
+    //
+    // #[derive(JsonSchema, Serialize)]
+    // #[serde(tag = "petType")]
+    // enum Pet {
+    //     Cat { meows: bool },
+    //     Dog { barks: bool },
+    // }
+    fn pet() -> serde_json::Value {
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Pet",
+            "oneOf": [
+                {
+                    "type": "object",
+                    "required": ["meows", "petType"],
+                    "properties": {
+                        "meows": {"type": "boolean"},
+                        "petType": {"type": "string", "enum": ["Cat"]}
+                    }
+                },
+                {
+                    "type": "object",
+                    "required": ["barks", "petType"],
+                    "properties": {
+                        "barks": {"type": "boolean"},
+                        "petType": {"type": "string", "enum": ["Dog"]}
+                    }
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn tagged_enum() {
+        let schema = tagged_enum_to_discriminated(&pet()).expect("A tagged enum");
+        assert_eq!(
+            schema["discriminator"],
+            json!({"propertyName": "petType", "mapping": {"Cat": "#/oneOf/0", "Dog": "#/oneOf/1"}})
+        );
+        tests_util::openapi_is_valid(&schema, &json!({"petType": "Cat", "meows": true}));
+        tests_util::openapi_is_not_valid(&schema, &json!({"petType": "Cat", "barks": true}));
+        tests_util::openapi_assert_schema_path(
+            &schema,
+            &json!({"petType": "Dog", "barks": 1}),
+            "/discriminator/mapping/Dog/properties/barks/type",
+        );
+    }
+
+    #[test]
+    fn not_a_tagged_enum() {
+        assert!(tagged_enum_to_discriminated(&json!({"type": "object"})).is_none());
+        assert!(tagged_enum_to_discriminated(&json!({
+            "oneOf": [
+                {"properties": {"kind": {"enum": ["A"]}}},
+                {"properties": {"kind": {"enum": ["A"]}}}
+            ]
+        }))
+        .is_none());
+    }
+}
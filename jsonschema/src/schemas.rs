@@ -90,6 +90,7 @@ impl Draft {
                 Draft::Draft202012 => None,
             },
             "dependencies" => Some(keywords::dependencies::compile),
+            "discriminator" => Some(keywords::discriminator::compile),
             #[cfg(any(feature = "draft201909", feature = "draft202012"))]
             "dependentRequired" => Some(keywords::dependencies::compile_dependent_required),
             #[cfg(any(feature = "draft201909", feature = "draft202012"))]
@@ -0,0 +1,322 @@
+//! Locations of schema values in the source document they were loaded from.
+//!
+//! Compile-time errors of the `discriminator` and `oneOf` keywords carry the
+//! [`SourceSpan`] of the invalid schema value, if the spans of the schema are passed with
+//! [`CompilationOptions::with_source_spans`](crate::CompilationOptions::with_source_spans).
+//! With the `yaml-spans` feature, [`from_yaml_str`] loads a YAML schema along with its spans.
+use ahash::AHashMap;
+
+/// Where a value starts in its source document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceSpan {
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column number, starting at 1.
+    pub column: usize,
+}
+
+/// The source spans of the values of a schema document, by their JSON pointers.
+#[derive(Debug, Clone, Default)]
+pub struct SourceSpans {
+    spans: AHashMap<String, SourceSpan>,
+}
+
+impl SourceSpans {
+    /// Record `span` for the value at `pointer`, e.g. `/discriminator/mapping`.
+    pub fn insert(&mut self, pointer: impl Into<String>, span: SourceSpan) {
+        self.spans.insert(pointer.into(), span);
+    }
+
+    /// The span of the value at `pointer`.
+    #[must_use]
+    pub fn get(&self, pointer: &str) -> Option<SourceSpan> {
+        self.spans.get(pointer).copied()
+    }
+}
+
+#[cfg(feature = "yaml-spans")]
+pub use yaml::{from_yaml_str, YamlError};
+
+#[cfg(feature = "yaml-spans")]
+mod yaml {
+    use super::{SourceSpan, SourceSpans};
+    use ahash::AHashMap;
+    use serde_json::{Map, Number, Value};
+    use std::{error, fmt};
+    use yaml_rust::{
+        parser::{Event, MarkedEventReceiver, Parser},
+        scanner::{Marker, TScalarStyle, TokenType},
+        Yaml,
+    };
+
+    /// A YAML document that can't be loaded as a schema.
+    #[derive(Debug)]
+    pub struct YamlError {
+        /// What went wrong.
+        pub message: String,
+        /// Where it went wrong.
+        pub span: SourceSpan,
+    }
+
+    impl fmt::Display for YamlError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "{} at line {} column {}",
+                self.message, self.span.line, self.span.column
+            )
+        }
+    }
+
+    impl error::Error for YamlError {}
+
+    /// Load the first document of `yaml` as JSON, along with the span of every value.
+    ///
+    /// Object members start at their key, array items at the item.
+    ///
+    /// ```rust
+    /// # use jsonschema::spans::from_yaml_str;
+    /// let (schema, spans) = from_yaml_str("oneOf:\n  - type: string\n").expect("Valid YAML");
+    /// assert_eq!(schema["oneOf"][0]["type"], "string");
+    /// let span = spans.get("/oneOf/0/type").expect("Recorded");
+    /// assert_eq!((span.line, span.column), (2, 5));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`YamlError`] if `yaml` is not valid YAML, uses keys that are not scalars or
+    /// refers to an unknown anchor.
+    pub fn from_yaml_str(yaml: &str) -> Result<(Value, SourceSpans), YamlError> {
+        let mut loader = SpanLoader::default();
+        Parser::new(yaml.chars())
+            .load(&mut loader, false)
+            .map_err(|error| YamlError {
+                // `ScanError` exposes its message only through `Display`, along with its location
+                #[allow(deprecated)]
+                message: error::Error::description(&error).to_string(),
+                span: span(*error.marker()),
+            })?;
+        if let Some(error) = loader.error {
+            return Err(error);
+        }
+        Ok((loader.root.unwrap_or(Value::Null), loader.spans))
+    }
+
+    fn span(marker: Marker) -> SourceSpan {
+        SourceSpan {
+            line: marker.line(),
+            column: marker.col() + 1,
+        }
+    }
+
+    /// An object or array that is being loaded.
+    enum Frame {
+        Array {
+            items: Vec<Value>,
+            pointer: String,
+            anchor: usize,
+        },
+        Object {
+            members: Map<String, Value>,
+            pointer: String,
+            anchor: usize,
+            // The key of the next member, along with where it starts
+            key: Option<(String, Marker)>,
+            // Where the object starts, until its first key is seen
+            start: Option<Marker>,
+        },
+    }
+
+    #[derive(Default)]
+    struct SpanLoader {
+        frames: Vec<Frame>,
+        anchors: AHashMap<usize, Value>,
+        spans: SourceSpans,
+        root: Option<Value>,
+        // The first error, later events are ignored
+        error: Option<YamlError>,
+    }
+
+    impl SpanLoader {
+        /// Record the span of the next value and return its pointer.
+        fn enter_value(&mut self, marker: Marker) -> String {
+            let (pointer, marker) = match self.frames.last() {
+                Some(Frame::Array { items, pointer, .. }) => {
+                    (format!("{}/{}", pointer, items.len()), marker)
+                }
+                Some(Frame::Object {
+                    pointer,
+                    key: Some((key, key_marker)),
+                    ..
+                }) => (format!("{}/{}", pointer, escape(key)), *key_marker),
+                Some(Frame::Object { key: None, .. }) | None => (String::new(), marker),
+            };
+            self.spans.insert(pointer.clone(), span(marker));
+            pointer
+        }
+
+        /// Add a complete value to its parent.
+        fn add_value(&mut self, value: Value, anchor: usize) {
+            if anchor > 0 {
+                self.anchors.insert(anchor, value.clone());
+            }
+            match self.frames.last_mut() {
+                Some(Frame::Array { items, .. }) => items.push(value),
+                Some(Frame::Object { members, key, .. }) => {
+                    if let Some((key, _)) = key.take() {
+                        members.insert(key, value);
+                    }
+                }
+                None => self.root = Some(value),
+            }
+        }
+
+        /// Whether the next scalar is the key of an object member.
+        fn expects_key(&self) -> bool {
+            matches!(self.frames.last(), Some(Frame::Object { key: None, .. }))
+        }
+
+        fn fail(&mut self, message: &str, marker: Marker) {
+            self.error = Some(YamlError {
+                message: message.to_string(),
+                span: span(marker),
+            });
+        }
+    }
+
+    impl MarkedEventReceiver for SpanLoader {
+        fn on_event(&mut self, event: Event, marker: Marker) {
+            if self.error.is_some() {
+                return;
+            }
+            match event {
+                Event::Scalar(value, _, _, _) if self.expects_key() => {
+                    if let Some(Frame::Object {
+                        key,
+                        start,
+                        pointer,
+                        ..
+                    }) = self.frames.last_mut()
+                    {
+                        // Block objects are reported to start after their first key
+                        if let Some(start) = start.take() {
+                            if marker.index() < start.index() {
+                                self.spans.insert(pointer.clone(), span(marker));
+                            }
+                        }
+                        *key = Some((value, marker));
+                    }
+                }
+                Event::SequenceStart(_) | Event::MappingStart(_) | Event::Alias(_)
+                    if self.expects_key() =>
+                {
+                    self.fail("only scalar keys are supported", marker);
+                }
+                Event::Scalar(value, style, anchor, tag) => {
+                    self.enter_value(marker);
+                    self.add_value(scalar(value, style, tag.as_ref()), anchor);
+                }
+                Event::Alias(anchor) => match self.anchors.get(&anchor).cloned() {
+                    Some(value) => {
+                        self.enter_value(marker);
+                        self.add_value(value, 0);
+                    }
+                    None => self.fail("unknown anchor", marker),
+                },
+                Event::SequenceStart(anchor) => {
+                    let pointer = self.enter_value(marker);
+                    self.frames.push(Frame::Array {
+                        items: Vec::new(),
+                        pointer,
+                        anchor,
+                    });
+                }
+                Event::MappingStart(anchor) => {
+                    let pointer = self.enter_value(marker);
+                    self.frames.push(Frame::Object {
+                        members: Map::new(),
+                        pointer,
+                        anchor,
+                        key: None,
+                        start: Some(marker),
+                    });
+                }
+                Event::SequenceEnd | Event::MappingEnd => match self.frames.pop() {
+                    Some(Frame::Array { items, anchor, .. }) => {
+                        self.add_value(Value::Array(items), anchor);
+                    }
+                    Some(Frame::Object {
+                        members, anchor, ..
+                    }) => self.add_value(Value::Object(members), anchor),
+                    None => {}
+                },
+                Event::Nothing
+                | Event::StreamStart
+                | Event::StreamEnd
+                | Event::DocumentStart
+                | Event::DocumentEnd => {}
+            }
+        }
+    }
+
+    /// Convert a YAML scalar into JSON, following the YAML core schema like `yaml_rust`.
+    fn scalar(value: String, style: TScalarStyle, tag: Option<&TokenType>) -> Value {
+        if style != TScalarStyle::Plain {
+            return Value::String(value);
+        }
+        if let Some(TokenType::Tag(handle, suffix)) = tag {
+            if handle == "!!" && suffix == "str" {
+                return Value::String(value);
+            }
+        }
+        match Yaml::from_str(&value) {
+            Yaml::Null => Value::Null,
+            Yaml::Boolean(value) => Value::Bool(value),
+            Yaml::Integer(value) => Value::Number(value.into()),
+            Yaml::Real(real) => real
+                .parse()
+                .ok()
+                .and_then(Number::from_f64)
+                .map_or(Value::String(value), Value::Number),
+            _ => Value::String(value),
+        }
+    }
+
+    /// Escape a key as a JSON pointer segment.
+    fn escape(key: &str) -> String {
+        key.replace('~', "~0").replace('/', "~1")
+    }
+}
+
+#[cfg(all(test, feature = "yaml-spans"))]
+mod tests {
+    use super::{from_yaml_str, SourceSpan};
+    use serde_json::{json, Value};
+    use test_case::test_case;
+
+    #[test_case("a: 1\nb: [x, 2.5, null]\n", &json!({"a": 1, "b": ["x", 2.5, null]}))]
+    #[test_case("a: '1'\nb: !!str 2\nc: true\n", &json!({"a": "1", "b": "2", "c": true}))]
+    #[test_case("a: &x {b: 1}\nc: *x\n", &json!({"a": {"b": 1}, "c": {"b": 1}}))]
+    fn values(yaml: &str, expected: &Value) {
+        let (value, _) = from_yaml_str(yaml).expect("Valid YAML");
+        assert_eq!(&value, expected);
+    }
+
+    #[test_case("", 1, 1)]
+    #[test_case("/a", 1, 1)]
+    #[test_case("/a~1b", 2, 1)]
+    #[test_case("/a~1b/0", 3, 5)]
+    #[test_case("/a~1b/1", 4, 5)]
+    #[test_case("/a~1b/1/c", 4, 5)]
+    fn spans(pointer: &str, line: usize, column: usize) {
+        let (_, spans) = from_yaml_str("a: 1\na/b:\n  - x\n  - c: 2\n").expect("Valid YAML");
+        assert_eq!(spans.get(pointer), Some(SourceSpan { line, column }));
+    }
+
+    #[test_case("a: [1\n")]
+    #[test_case("? [a]\n: 1\n")]
+    #[test_case("a: *x\n")]
+    fn invalid(yaml: &str) {
+        assert!(from_yaml_str(yaml).is_err());
+    }
+}
@@ -1,12 +1,12 @@
 use crate::{
-    error::ErrorIterator,
+    error::{ErrorIterator, ValidationError},
     keywords::BoxedValidator,
     output::{Annotations, ErrorDescription, OutputUnit},
     paths::InstancePath,
     schema_node::SchemaNode,
 };
-use serde_json::Value;
-use std::{collections::VecDeque, fmt};
+use serde_json::{Map, Value};
+use std::{any::Any, collections::VecDeque, fmt};
 
 /// The Validate trait represents a predicate over some JSON value. Some validators are very simple
 /// predicates such as "a value which is a string", whereas others may be much more complex,
@@ -22,7 +22,7 @@ use std::{collections::VecDeque, fmt};
 /// If you are implementing `Validate` it is often sufficient to implement `validate` and
 /// `is_valid`. `apply` is only necessary for validators which compose other validators. See the
 /// documentation for `apply` for more information.
-pub(crate) trait Validate: Send + Sync + core::fmt::Display {
+pub(crate) trait Validate: AsAny + Send + Sync + core::fmt::Display {
     fn validate<'instance>(
         &self,
         instance: &'instance Value,
@@ -33,6 +33,18 @@ pub(crate) trait Validate: Send + Sync + core::fmt::Display {
     // not constructed
     fn is_valid(&self, instance: &Value) -> bool;
 
+    /// The first error of `validate` that is not a warning, without building the others.
+    /// Validators of subschemas override it to descend into their subschemas in order, until one
+    /// of them reports an error.
+    fn first_error<'instance>(
+        &self,
+        instance: &'instance Value,
+        instance_path: &InstancePath,
+    ) -> Option<ValidationError<'instance>> {
+        self.validate(instance, instance_path)
+            .find(ValidationError::is_error)
+    }
+
     /// `apply` applies this validator and any sub-validators it is composed of to the value in
     /// question and collects the resulting annotations or errors. Note that the result of `apply`
     /// is a `PartialApplication`.
@@ -83,6 +95,7 @@ pub(crate) trait Validate: Send + Sync + core::fmt::Display {
     ) -> PartialApplication<'a> {
         let errors: Vec<ErrorDescription> = self
             .validate(instance, instance_path)
+            .filter(ValidationError::is_error)
             .map(ErrorDescription::from)
             .collect();
         if errors.is_empty() {
@@ -91,6 +104,48 @@ pub(crate) trait Validate: Send + Sync + core::fmt::Display {
             PartialApplication::invalid_empty(errors)
         }
     }
+
+    /// The nodes of the subschemas this validator applies, to walk the compiled tree. `$ref`
+    /// only reports its target once it is compiled, and `discriminator` reports its mapping.
+    fn child_nodes(&self) -> Vec<&SchemaNode> {
+        Vec::new()
+    }
+
+    /// A rough cost of evaluating this validator relative to others, used to check cheaper
+    /// `oneOf` branches first. Validators of subschemas add the costs of their subschemas, while
+    /// `$ref` estimates the size of its target until it is compiled.
+    fn estimated_cost(&self) -> u32 {
+        self.child_nodes()
+            .into_iter()
+            .map(SchemaNode::estimated_cost)
+            .fold(1, u32::saturating_add)
+    }
+
+    /// Write the keywords this validator was compiled from into `schema`, see
+    /// `JSONSchema::to_value`. Validators that can't be rebuilt leave `schema` unchanged.
+    fn write_schema(&self, _schema: &mut Map<String, Value>) {}
+}
+
+/// The concrete type of a validator, to inspect particular keywords of a compiled tree through
+/// `SchemaNode::keyword_validator_as` and friends.
+pub(crate) trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
 }
 
 /// The result of applying a validator to an instance. As explained in the documentation for
@@ -580,6 +580,41 @@ use test_case::test_case;
         ]
     }); "valid propertyNames"
 }]
+#[test_case{
+    &json!({
+        "oneOf": [{"$ref": "#/definitions/int"}, {"$ref": "#/definitions/str"}],
+        "definitions": {
+            "int": {"type": "integer", "examples": [1]},
+            "str": {"type": "string", "examples": ["a"]}
+        }
+    }),
+    &json!{"some string"},
+    &json!({
+        "valid": true,
+        "annotations": [
+            {
+                "keywordLocation": "",
+                "instanceLocation": "",
+                "annotations": {
+                    "definitions": {
+                        "int": {"type": "integer", "examples": [1]},
+                        "str": {"type": "string", "examples": ["a"]}
+                    }
+                }
+            },
+            {
+                "keywordLocation": "/oneOf/1",
+                "instanceLocation": "",
+                "annotations": {}
+            },
+            {
+                "keywordLocation": "/oneOf/1",
+                "instanceLocation": "",
+                "annotations": {"examples": ["a"]}
+            }
+        ]
+    }); "valid oneOf with examples in referenced branch"
+}]
 fn test_basic_output(
     schema_json: &serde_json::Value,
     instance: &serde_json::Value,
@@ -590,6 +625,54 @@ fn test_basic_output(
     assert_eq!(&output_json, expected_output);
 }
 
+/// Basic output of schemas compiled with the OpenAPI `discriminator` keyword enabled.
+#[test_case{
+    &json!({
+        "oneOf": [{"$ref": "#/definitions/Cat"}, {"$ref": "#/definitions/Dog"}],
+        "discriminator": {
+            "propertyName": "petType",
+            "mapping": {"cat": "#/definitions/Cat", "dog": "#/definitions/Dog"}
+        },
+        "definitions": {
+            "Cat": {"type": "object", "examples": [{"petType": "cat"}]},
+            "Dog": {"type": "object", "examples": [{"petType": "dog"}]}
+        }
+    }),
+    &json!{{"petType": "dog"}},
+    &json!({
+        "valid": true,
+        "annotations": [
+            {
+                "keywordLocation": "",
+                "instanceLocation": "",
+                "annotations": {
+                    "definitions": {
+                        "Cat": {"type": "object", "examples": [{"petType": "cat"}]},
+                        "Dog": {"type": "object", "examples": [{"petType": "dog"}]}
+                    }
+                }
+            },
+            {
+                "keywordLocation": "/discriminator/mapping/dog",
+                "instanceLocation": "",
+                "annotations": {"examples": [{"petType": "dog"}]}
+            }
+        ]
+    }); "valid discriminator with examples in mapped branch"
+}]
+fn test_discriminator_basic_output(
+    schema_json: &serde_json::Value,
+    instance: &serde_json::Value,
+    expected_output: &serde_json::Value,
+) {
+    let schema = JSONSchema::options()
+        .should_use_discriminator(true)
+        .compile(schema_json)
+        .unwrap();
+    let output_json = serde_json::to_value(schema.apply(instance).basic()).unwrap();
+    assert_eq!(&output_json, expected_output);
+}
+
 /// These tests are separated from the rest of the basic output tests for convenience, there's
 /// nothing different about them but they are all tests of the additionalProperties keyword, which
 /// is complicated by the fact that there are eight different implementations based on the
@@ -1018,3 +1101,76 @@ fn test_additional_properties_basic_output(
         panic!("\nExpected:\n{}\n\nGot:\n{}\n", expected_str, actual_str);
     }
 }
+
+#[test_case(false; "instance excluded")]
+#[test_case(true; "instance included")]
+fn test_instance_in_basic_output(include: bool) {
+    // The discriminated schema the unit tests of the crate share
+    let schema_json: serde_json::Value =
+        serde_json::from_str(include_str!("pets.json")).expect("Valid JSON");
+    let schema = JSONSchema::options()
+        .should_use_discriminator(true)
+        .should_include_instance_in_errors(include)
+        .compile(&schema_json)
+        .unwrap();
+    let instance = json!({"petType": "cat", "meows": "loudly"});
+    let output_json = serde_json::to_value(schema.apply(&instance).basic()).unwrap();
+    let errors = output_json["errors"].as_array().unwrap();
+    let branch = errors
+        .iter()
+        .find(|error| error["instanceLocation"] == "")
+        .unwrap();
+    let meows = errors
+        .iter()
+        .find(|error| error["instanceLocation"] == "/meows")
+        .unwrap();
+    if include {
+        assert_eq!(branch["instance"], instance);
+        assert_eq!(meows["instance"], json!("loudly"));
+    } else {
+        assert!(branch.get("instance").is_none());
+        assert!(meows.get("instance").is_none());
+    }
+}
+
+#[test]
+fn test_long_instance_in_basic_output() {
+    let schema = JSONSchema::options()
+        .should_include_instance_in_errors(true)
+        .compile(&json!({"type": "string"}))
+        .unwrap();
+    let instance = json!(vec![1; 1000]);
+    let output_json = serde_json::to_value(schema.apply(&instance).basic()).unwrap();
+    let capped = output_json["errors"][0]["instance"].as_str().unwrap();
+    assert_eq!(capped.len(), 1024 + "...".len());
+    assert!(capped.starts_with("[1,1,"));
+}
+
+#[test_case(false, "/properties/node/properties/children/items/properties/children/items/properties/children/items/properties/name/type", "/node/children/0/children/1/children/0/name"; "full")]
+#[test_case(true, "/properties/node/(properties/children/items){3}/properties/name/type", "/node/children/0/children/1/children/0/name"; "compressed")]
+fn test_compressed_locations_in_basic_output(
+    compress: bool,
+    keyword_location: &str,
+    instance_location: &str,
+) {
+    let schema = JSONSchema::options()
+        .should_compress_error_locations(compress)
+        .compile(&json!({
+            "properties": {"node": {"$ref": "#/definitions/Node"}},
+            "definitions": {
+                "Node": {
+                    "properties": {
+                        "name": {"type": "string"},
+                        "children": {"items": {"$ref": "#/definitions/Node"}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+    let instance =
+        json!({"node": {"children": [{"children": [{}, {"children": [{"name": 42}]}]}]}});
+    let output_json = serde_json::to_value(schema.apply(&instance).basic()).unwrap();
+    let error = &output_json["errors"][0];
+    assert_eq!(error["keywordLocation"], keyword_location);
+    assert_eq!(error["instanceLocation"], instance_location);
+}